@@ -0,0 +1,185 @@
+// Times the inspection of generated schemas, run with
+// `cargo bench --bench inspect`. Each case reports the best of a few runs, and
+// the allocations it made as counted by the global allocator.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use migi::dbinfo::Dbinfo;
+use migi::inspector::Inspector;
+use migi::{Dialect, Options};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, TokenWithLocation, Tokenizer};
+
+const FILES: usize = 400;
+const TABLES_PER_FILE: usize = 25;
+const RUNS: usize = 5;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The fastest of RUNS runs of `f` given what `setup` returns, with the number
+// of allocations and bytes allocated by one run.
+fn measure<T, R>(mut setup: impl FnMut() -> T, mut f: impl FnMut(T) -> R) -> String {
+    let mut best = Duration::MAX;
+    let mut allocations = (0, 0);
+    for _ in 0..RUNS {
+        let input = setup();
+        let before = (
+            ALLOCATIONS.load(Ordering::Relaxed),
+            ALLOCATED.load(Ordering::Relaxed),
+        );
+        let start = Instant::now();
+        let result = f(input);
+        best = best.min(start.elapsed());
+        allocations = (
+            ALLOCATIONS.load(Ordering::Relaxed) - before.0,
+            ALLOCATED.load(Ordering::Relaxed) - before.1,
+        );
+        drop(result);
+    }
+    format!(
+        "{:?}, {} allocations, {:.1} MB",
+        best,
+        allocations.0,
+        allocations.1 as f64 / 1e6
+    )
+}
+
+fn table_sql(name: &str) -> String {
+    format!(
+        "CREATE TABLE {name} (\n    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,\n    \
+         owner_id BIGINT NOT NULL,\n    title VARCHAR(200) NOT NULL DEFAULT '',\n    \
+         score NUMERIC(10,2) CHECK (score >= 0),\n    created_at TIMESTAMP NOT NULL DEFAULT now()\n);\n\
+         CREATE VIEW {name}_titles AS SELECT id, title FROM {name};\n\
+         CREATE INDEX {name}_owner ON {name} (owner_id, created_at DESC);\n"
+    )
+}
+
+fn sources() -> Vec<(String, String)> {
+    (0..FILES)
+        .map(|file| {
+            let sql: String = (0..TABLES_PER_FILE)
+                .map(|table| table_sql(&format!("t{}_{}", file, table)))
+                .collect();
+            (format!("{:03}.sql", file), sql)
+        })
+        .collect()
+}
+
+fn options() -> Options {
+    Options::builder()
+        .dialect(Dialect::PostgreSql)
+        .database("bench")
+        .build()
+        .unwrap()
+}
+
+fn inspect_all(sources: &[(String, String)], parallel: bool) -> String {
+    measure(
+        || Dbinfo::with_options(options()),
+        |mut dbinfo| {
+            Inspector::new(&mut dbinfo)
+                .with_parallel(parallel)
+                .inspect_all(sources)
+                .unwrap();
+            dbinfo
+        },
+    )
+}
+
+fn tokenize(sql: &str) -> Vec<TokenWithLocation> {
+    Tokenizer::new(&PostgreSqlDialect {}, sql)
+        .tokenize_with_location()
+        .unwrap()
+}
+
+// How the inspector quoted words before quoting them in place.
+fn quote_words_by_cloning(tokens: Vec<TokenWithLocation>) -> Vec<TokenWithLocation> {
+    tokens
+        .into_iter()
+        .map(|tok| match tok.token {
+            Token::Word(ref w) if w.keyword == Keyword::NoKeyword => {
+                let mut w = w.clone();
+                w.quote_style = Some('"');
+                TokenWithLocation {
+                    token: Token::Word(w),
+                    location: tok.location,
+                }
+            }
+            _ => tok,
+        })
+        .collect()
+}
+
+fn quote_words_in_place(mut tokens: Vec<TokenWithLocation>) -> Vec<TokenWithLocation> {
+    for tok in &mut tokens {
+        if let Token::Word(w) = &mut tok.token {
+            if w.keyword == Keyword::NoKeyword {
+                w.quote_style = Some('"');
+            }
+        }
+    }
+    tokens
+}
+
+fn main() {
+    let sources = sources();
+    let lines: usize = sources.iter().map(|(_, sql)| sql.lines().count()).sum();
+    println!("{} files, {} lines", sources.len(), lines);
+    println!("inspect_all serial:   {}", inspect_all(&sources, false));
+    println!("inspect_all parallel: {}", inspect_all(&sources, true));
+
+    // a single file of about 50,000 lines
+    let sql: String = (0..5_556).map(|i| table_sql(&format!("t{}", i))).collect();
+    let tokens = tokenize(&sql);
+    println!(
+        "\n1 file, {} lines, {} tokens",
+        sql.lines().count(),
+        tokens.len()
+    );
+    println!(
+        "quote words by cloning: {}",
+        measure(|| tokens.clone(), quote_words_by_cloning)
+    );
+    println!(
+        "quote words in place:   {}",
+        measure(|| tokens.clone(), quote_words_in_place)
+    );
+    println!(
+        "inspect:                {}",
+        measure(
+            || Dbinfo::with_options(options()),
+            |mut dbinfo| {
+                Inspector::new(&mut dbinfo)
+                    .inspect(&sql, "schema.sql")
+                    .unwrap();
+                dbinfo
+            },
+        )
+    );
+}
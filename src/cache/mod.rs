@@ -0,0 +1,68 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{CaseFolding, Dialect};
+
+pub const DEFAULT_DIR: &str = ".migi/cache";
+
+// Parse results stored on disk by a hash of the parsed file's contents, the
+// dialect and case folding it was parsed with and the migi version, so that a
+// change of any of them misses the cache. A cache that can't be read or written is ignored.
+#[derive(Debug, Clone)]
+pub struct ParseCache {
+    dir: PathBuf,
+}
+
+impl ParseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub(crate) fn get<T: DeserializeOwned>(
+        &self,
+        dialect: Dialect,
+        case_folding: CaseFolding,
+        sql: &str,
+    ) -> Option<T> {
+        let path = self.path(dialect, case_folding, sql);
+        let json = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&json) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::debug!("ignoring cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn put<T: Serialize>(
+        &self,
+        dialect: Dialect,
+        case_folding: CaseFolding,
+        sql: &str,
+        entry: &T,
+    ) {
+        let path = self.path(dialect, case_folding, sql);
+        let written = fs::create_dir_all(&self.dir).and_then(|_| {
+            let json = serde_json::to_string(entry)?;
+            fs::write(&path, json)
+        });
+        if let Err(e) = written {
+            log::debug!("cannot write cache entry {}: {}", path.display(), e);
+        }
+    }
+
+    fn path(&self, dialect: Dialect, case_folding: CaseFolding, sql: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        dialect.hash(&mut hasher);
+        case_folding.hash(&mut hasher);
+        sql.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+}
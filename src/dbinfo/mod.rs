@@ -1,13 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 
 use anyhow::Result;
+use serde_derive::{Deserialize, Serialize};
 use sqlparser::ast::{
-    ColumnOptionDef, DataType, Expr, Ident, ObjectName, OnCommit, Query, SqlOption, TableConstraint,
+    ArgMode, ArrayElemTypeDef, BinaryOperator, ColumnOption, ColumnOptionDef, DataType, Expr,
+    FunctionBehavior, FunctionCalledOnNull, FunctionParallel, Ident, ObjectName, OnCommit,
+    OperateFunctionArg, OrderByExpr, Query, SelectItem, SequenceOptions, SetExpr, SqlOption,
+    TableConstraint, TimezoneInfo, Value,
 };
 
+use crate::migrate;
 use crate::{Dialect, Options};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Dbinfo {
     pub dialect: Dialect,
     pub default_catalog: String,
@@ -16,15 +22,7 @@ pub struct Dbinfo {
 
 impl Dbinfo {
     pub fn with_options(options: Options) -> Self {
-        let schema = Schema {
-            name: options.default_schema.clone(),
-            tables: HashMap::new(),
-        };
-        let catalog = Catalog {
-            name: options.database.clone(),
-            default_schema: options.default_schema.clone(),
-            schemas: HashMap::from([(options.default_schema.clone(), schema)]),
-        };
+        let catalog = Catalog::new(&options.database, &options.default_schema);
         Self {
             dialect: options.dialect,
             default_catalog: options.database.clone(),
@@ -45,21 +43,171 @@ impl Dbinfo {
     }
 
     pub fn add_table(&mut self, name: &TableName, table: Table) -> Result<()> {
-        let catalog = if let Some(ref catalog_name) = name.catalog {
-            self.get_catalog_mut(catalog_name.value.as_str())?
-        } else {
-            self.default_catalog_mut()
+        let schema = self.get_schema_of_mut(name)?;
+        schema.add_table(&name.table.value, table);
+        Ok(())
+    }
+
+    pub fn add_view(&mut self, name: &TableName, view: View) -> Result<()> {
+        let schema = self.get_schema_of_mut(name)?;
+        schema.add_view(&name.table.value, view);
+        Ok(())
+    }
+
+    pub fn add_domain(&mut self, name: &TableName, domain: Domain) -> Result<()> {
+        let schema = self.get_schema_of_mut(name)?;
+        schema.domains.insert(name.table.value.clone(), domain);
+        Ok(())
+    }
+
+    // Adds the catalogs, schemas and objects of another Dbinfo, e.g. one
+    // inspected from the files of another service. An object both define is
+    // kept when the definitions are the same, and is an error otherwise.
+    pub fn merge(&mut self, other: Dbinfo) -> Result<()> {
+        if self.dialect != other.dialect {
+            anyhow::bail!(
+                "cannot merge a {} schema into a {} one",
+                other.dialect,
+                self.dialect
+            );
+        }
+        for (name, catalog) in other.catalogs {
+            match self.catalogs.get_mut(&name) {
+                Some(existing) => existing.merge(catalog)?,
+                None => self.add_catalog(&name, catalog),
+            }
+        }
+        Ok(())
+    }
+
+    // The domain a column type names, looked up in the schema of the table
+    // when unqualified as with Postgres' default search_path.
+    pub fn resolve_domain(&self, table_name: &[String], data_type: &DataType) -> Option<&Domain> {
+        let DataType::Custom(name, modifiers) = data_type else {
+            return None;
         };
+        if !modifiers.is_empty() {
+            return None;
+        }
+        let (catalog, schema, domain) = match name.0.as_slice() {
+            [domain] => (&table_name[0], &table_name[1], &domain.value),
+            [schema, domain] => (&table_name[0], &schema.value, &domain.value),
+            [catalog, schema, domain] => (&catalog.value, &schema.value, &domain.value),
+            _ => return None,
+        };
+        self.catalogs
+            .get(catalog)?
+            .schemas
+            .get(schema)?
+            .domains
+            .get(domain)
+    }
 
-        let schema = if let Some(ref schema_name) = name.schema {
-            catalog.get_schema_mut(schema_name.value.as_str())?
-        } else {
-            catalog.default_schema_mut()
+    // Every table, referenced tables coming before the tables whose foreign
+    // keys reference them. Tables are otherwise sorted by name, and a cycle of
+    // references is broken at its first table by name.
+    pub fn tables_in_dependency_order(&self) -> Vec<TableName> {
+        let mut names: Vec<migrate::ObjectName> = self
+            .catalogs
+            .values()
+            .flat_map(|c| c.schemas.values().map(move |s| (c, s)))
+            .flat_map(|(c, s)| {
+                s.tables
+                    .keys()
+                    .map(|t| migrate::ObjectName(vec![c.name.clone(), s.name.clone(), t.clone()]))
+            })
+            .collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // dependencies[i] lists the tables table i references
+        let dependencies: Vec<Vec<usize>> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let table = &self.catalogs[&name.0[0]].schemas[&name.0[1]].tables[&name.0[2]];
+                table
+                    .referenced_tables()
+                    .into_iter()
+                    .map(|foreign_table| migrate::resolve_table_name(self, name, foreign_table))
+                    .filter_map(|foreign_table| names.iter().position(|n| *n == foreign_table))
+                    .filter(|&j| j != i)
+                    .collect()
+            })
+            .collect();
+
+        migrate::dependency_order(&dependencies)
+            .into_iter()
+            .map(|i| TableName {
+                catalog: Some(Ident::new(&names[i].0[0])),
+                schema: Some(Ident::new(&names[i].0[1])),
+                table: Ident::new(&names[i].0[2]),
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> DbinfoStats {
+        let mut stats = DbinfoStats {
+            catalogs: self.catalogs.len(),
+            ..Default::default()
         };
+        for schema in self.catalogs.values().flat_map(|c| c.schemas.values()) {
+            stats.schemas += 1;
+            stats.tables += schema.tables.len();
+            stats.views += schema.views.len();
+            for table in schema.tables.values() {
+                stats.columns += table.columns.len();
+                stats.indexes += table.indexes.len();
+            }
+        }
+        stats
+    }
 
-        schema.add_table(&name.table.value, table);
+    // A hash of the schema which only changes when the schema does, e.g. to
+    // skip generating a migration when the fingerprint of the snapshot
+    // matches.
+    pub fn fingerprint(&self) -> String {
+        // 64-bit FNV-1a, which unlike std's hashers is stable across releases.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in self.normalized_json().to_string().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
 
-        Ok(())
+    // The json of the schema with its map keys sorted, as is everything whose
+    // order doesn't matter to the database, like the constraints of a table.
+    pub fn normalized_json(&self) -> serde_json::Value {
+        let mut dbinfo = self.clone();
+        for schema in dbinfo
+            .catalogs
+            .values_mut()
+            .flat_map(|c| c.schemas.values_mut())
+        {
+            for table in schema.tables.values_mut() {
+                table.constraints.sort();
+                table.indexes.sort();
+                table.triggers.sort();
+                table.policies.sort();
+            }
+            for view in schema.views.values_mut() {
+                view.indexes.sort();
+            }
+        }
+        sorted_keys(serde_json::to_value(&dbinfo).unwrap())
+    }
+
+    // The paths of the normalized json where `other` differs from this schema,
+    // e.g. `catalogs.test.schemas.public.tables.users.columns[1]`.
+    pub fn differing_paths(&self, other: &Dbinfo) -> Vec<String> {
+        let mut paths = vec![];
+        differing_paths(
+            "",
+            &self.normalized_json(),
+            &other.normalized_json(),
+            &mut paths,
+        );
+        paths
     }
 
     pub fn get_catalog(&self, name: &str) -> Result<&Catalog> {
@@ -75,23 +223,150 @@ impl Dbinfo {
     }
 
     pub fn get_table(&self, name: &TableName) -> Result<&Table> {
+        self.get_schema_of(name)?
+            .get_table(name.table.value.as_str())
+    }
+
+    pub fn get_table_mut(&mut self, name: &TableName) -> Result<&mut Table> {
+        self.get_schema_of_mut(name)?
+            .get_table_mut(name.table.value.as_str())
+    }
+
+    pub fn get_view(&self, name: &TableName) -> Result<&View> {
+        self.get_schema_of(name)?
+            .get_view(name.table.value.as_str())
+    }
+
+    pub fn get_view_mut(&mut self, name: &TableName) -> Result<&mut View> {
+        self.get_schema_of_mut(name)?
+            .get_view_mut(name.table.value.as_str())
+    }
+
+    pub fn get_schema_of(&self, name: &TableName) -> Result<&Schema> {
         let catalog = if let Some(ref catalog_name) = name.catalog {
             self.get_catalog(catalog_name.value.as_str())?
         } else {
             self.default_catalog()
         };
 
-        let schema = if let Some(ref schema_name) = name.schema {
-            catalog.get_schema(schema_name.value.as_str())?
+        if let Some(ref schema_name) = name.schema {
+            catalog.get_schema(schema_name.value.as_str())
+        } else {
+            Ok(catalog.default_schema())
+        }
+    }
+
+    pub fn get_schema_of_mut(&mut self, name: &TableName) -> Result<&mut Schema> {
+        let catalog = if let Some(ref catalog_name) = name.catalog {
+            self.get_catalog_mut(catalog_name.value.as_str())?
         } else {
-            catalog.default_schema()
+            self.default_catalog_mut()
         };
 
-        schema.get_table(name.table.value.as_str())
+        if let Some(ref schema_name) = name.schema {
+            catalog.get_schema_mut(schema_name.value.as_str())
+        } else {
+            Ok(catalog.default_schema_mut())
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// An indented tree of catalogs, schemas and their objects, sorted by name.
+impl fmt::Display for Dbinfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut catalogs: Vec<&Catalog> = self.catalogs.values().collect();
+        catalogs.sort_by(|a, b| a.name.cmp(&b.name));
+        for catalog in catalogs {
+            writeln!(f, "catalog {}", catalog.name)?;
+
+            let mut schemas: Vec<&Schema> = catalog.schemas.values().collect();
+            schemas.sort_by(|a, b| a.name.cmp(&b.name));
+            for schema in schemas {
+                writeln!(f, "  schema {}", schema.name)?;
+
+                let mut tables: Vec<&Table> = schema.tables.values().collect();
+                tables.sort_by(|a, b| a.name.cmp(&b.name));
+                for table in tables {
+                    writeln!(f, "    table {}", table.name)?;
+                    for column in &table.columns {
+                        write!(f, "      ")?;
+                        column.fmt_nullable(f, column.is_nullable(table))?;
+                        writeln!(f)?;
+                    }
+                    for constraint in &table.constraints {
+                        writeln!(f, "      {}", constraint)?;
+                    }
+                    for constraint in &table.exclusion_constraints {
+                        writeln!(f, "      {}", constraint)?;
+                    }
+                    for index in &table.indexes {
+                        let columns: Vec<String> =
+                            index.columns.iter().map(|c| c.to_string()).collect();
+                        let unique = if index.unique { "UNIQUE " } else { "" };
+                        writeln!(
+                            f,
+                            "      {}INDEX {} ({})",
+                            unique,
+                            index.name,
+                            columns.join(", ")
+                        )?;
+                    }
+                    for trigger in &table.triggers {
+                        writeln!(
+                            f,
+                            "      TRIGGER {} {}",
+                            trigger.name,
+                            trigger.definition(&table.name)
+                        )?;
+                    }
+                    for policy in &table.policies {
+                        writeln!(f, "      POLICY {} {}", policy.name, policy)?;
+                    }
+                }
+
+                let mut domains: Vec<&Domain> = schema.domains.values().collect();
+                domains.sort_by(|a, b| a.name.cmp(&b.name));
+                for domain in domains {
+                    writeln!(f, "    domain {} {}", domain.name, domain)?;
+                }
+
+                let mut routines: Vec<&Routine> = schema.routines.values().collect();
+                routines.sort_by_key(|routine| routine.signature());
+                for routine in routines {
+                    write!(f, "    function {}", routine.signature())?;
+                    if let Some(return_type) = &routine.return_type {
+                        write!(f, " RETURNS {}", return_type)?;
+                    }
+                    writeln!(f)?;
+                }
+
+                let mut views: Vec<&View> = schema.views.values().collect();
+                views.sort_by(|a, b| a.name.cmp(&b.name));
+                for view in views {
+                    let kind = if view.materialized {
+                        "materialized view"
+                    } else {
+                        "view"
+                    };
+                    writeln!(f, "    {} {}", kind, view.name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DbinfoStats {
+    pub catalogs: usize,
+    pub schemas: usize,
+    pub tables: usize,
+    pub columns: usize,
+    pub indexes: usize,
+    pub views: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Catalog {
     pub name: String,
     pub default_schema: String,
@@ -99,6 +374,14 @@ pub struct Catalog {
 }
 
 impl Catalog {
+    pub fn new(name: &str, default_schema: &str) -> Self {
+        Self {
+            name: name.into(),
+            default_schema: default_schema.into(),
+            schemas: HashMap::from([(default_schema.into(), Schema::new(default_schema))]),
+        }
+    }
+
     fn default_schema(&self) -> &Schema {
         self.schemas.get(&self.default_schema).unwrap()
     }
@@ -107,10 +390,28 @@ impl Catalog {
         self.schemas.get_mut(&self.default_schema).unwrap()
     }
 
-    fn add_schema(&mut self, name: &str, schema: Schema) {
+    pub fn add_schema(&mut self, name: &str, schema: Schema) {
         self.schemas.insert(name.into(), schema);
     }
 
+    fn merge(&mut self, other: Catalog) -> Result<()> {
+        if self.default_schema != other.default_schema {
+            anyhow::bail!(
+                "catalog {} has the default schema {} and {}",
+                self.name,
+                self.default_schema,
+                other.default_schema
+            );
+        }
+        for (name, schema) in other.schemas {
+            match self.schemas.get_mut(&name) {
+                Some(existing) => existing.merge(&self.name, schema)?,
+                None => self.add_schema(&name, schema),
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_schema(&self, name: &str) -> Result<&Schema> {
         self.schemas
             .get(name)
@@ -124,17 +425,62 @@ impl Catalog {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Schema {
     pub name: String,
     pub tables: HashMap<String, Table>,
+    pub views: HashMap<String, View>,
+    #[serde(default)]
+    pub domains: HashMap<String, Domain>, // Postgres only
+    #[serde(default)]
+    pub routines: HashMap<String, Routine>, // by signature, e.g. `add(INTEGER, INTEGER)`
 }
 
 impl Schema {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            tables: HashMap::new(),
+            views: HashMap::new(),
+            domains: HashMap::new(),
+            routines: HashMap::new(),
+        }
+    }
+
     fn add_table(&mut self, name: &str, table: Table) {
         self.tables.insert(name.into(), table);
     }
 
+    fn add_view(&mut self, name: &str, view: View) {
+        self.views.insert(name.into(), view);
+    }
+
+    fn merge(&mut self, catalog_name: &str, other: Schema) -> Result<()> {
+        let prefix = format!("{}.{}", catalog_name, self.name);
+        merge_objects(
+            "table",
+            &prefix,
+            &mut self.tables,
+            other.tables,
+            Table::semantically_eq,
+        )?;
+        merge_objects("view", &prefix, &mut self.views, other.views, View::eq)?;
+        merge_objects(
+            "domain",
+            &prefix,
+            &mut self.domains,
+            other.domains,
+            Domain::eq,
+        )?;
+        merge_objects(
+            "routine",
+            &prefix,
+            &mut self.routines,
+            other.routines,
+            Routine::eq,
+        )
+    }
+
     pub fn get_table(&self, name: &str) -> Result<&Table> {
         self.tables
             .get(name)
@@ -146,13 +492,26 @@ impl Schema {
             .get_mut(name)
             .ok_or(anyhow::anyhow!("table does not found"))
     }
+
+    pub fn get_view(&self, name: &str) -> Result<&View> {
+        self.views
+            .get(name)
+            .ok_or(anyhow::anyhow!("view does not found"))
+    }
+
+    pub fn get_view_mut(&mut self, name: &str) -> Result<&mut View> {
+        self.views
+            .get_mut(name)
+            .ok_or(anyhow::anyhow!("view does not found"))
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<Column>,
     pub constraints: Vec<TableConstraint>,
+    pub indexes: Vec<Index>,
     pub with_options: Vec<SqlOption>, // postgresql with options
     pub without_rowid: bool,          // sqlite without rowid
     pub engine: Option<String>,       // mysql storage engine
@@ -165,34 +524,1157 @@ pub struct Table {
     pub partition_by: Option<Box<Expr>>,
     pub options: Option<Vec<SqlOption>>,
     pub strict: bool, // sqlite strict tables: https://www.sqlite.org/stricttables.html
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    #[serde(default)]
+    pub exclusion_constraints: Vec<ExclusionConstraint>, // postgresql EXCLUDE constraints
+    #[serde(default)]
+    pub policies: Vec<Policy>, // postgresql row-level security policies
+}
+
+impl Table {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            columns: vec![],
+            constraints: vec![],
+            indexes: vec![],
+            with_options: vec![],
+            without_rowid: false,
+            engine: None,
+            comment: None,
+            auto_increment_offset: None,
+            default_charset: None,
+            collation: None,
+            on_commit: None,
+            order_by: None,
+            partition_by: None,
+            options: None,
+            strict: false,
+            triggers: vec![],
+            exclusion_constraints: vec![],
+            policies: vec![],
+        }
+    }
+
+    // Tables referenced by the foreign keys of this table, as written.
+    pub fn referenced_tables(&self) -> Vec<&ObjectName> {
+        let constraints = self.constraints.iter().filter_map(|c| match c {
+            TableConstraint::ForeignKey { foreign_table, .. } => Some(foreign_table),
+            _ => None,
+        });
+        let columns = self
+            .columns
+            .iter()
+            .flat_map(|c| c.options.iter())
+            .filter_map(|o| match &o.option {
+                ColumnOption::ForeignKey { foreign_table, .. } => Some(foreign_table),
+                _ => None,
+            });
+        constraints.chain(columns).collect()
+    }
+
+    pub fn get_column(&self, name: &str) -> Result<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or(anyhow::anyhow!("column does not found"))
+    }
+
+    pub fn get_column_mut(&mut self, name: &str) -> Result<&mut Column> {
+        self.columns
+            .iter_mut()
+            .find(|c| c.name == name)
+            .ok_or(anyhow::anyhow!("column does not found"))
+    }
+
+    // Compares tables regardless of the order of their constraints, indexes,
+    // policies and storage parameters, which the database doesn't keep. The order of
+    // columns and triggers matters.
+    pub fn semantically_eq(&self, other: &Table) -> bool {
+        let Table {
+            name,
+            columns,
+            constraints,
+            indexes,
+            with_options,
+            without_rowid,
+            engine,
+            comment,
+            auto_increment_offset,
+            default_charset,
+            collation,
+            on_commit,
+            order_by,
+            partition_by,
+            options,
+            strict,
+            triggers,
+            exclusion_constraints,
+            policies,
+        } = self;
+        *name == other.name
+            && *columns == other.columns
+            && same_elements(constraints, &other.constraints)
+            && same_elements(indexes, &other.indexes)
+            && same_elements(with_options, &other.with_options)
+            && *without_rowid == other.without_rowid
+            && *engine == other.engine
+            && *comment == other.comment
+            && *auto_increment_offset == other.auto_increment_offset
+            && *default_charset == other.default_charset
+            && *collation == other.collation
+            && *on_commit == other.on_commit
+            && *order_by == other.order_by
+            && *partition_by == other.partition_by
+            && *options == other.options
+            && *strict == other.strict
+            && *triggers == other.triggers
+            && same_elements(exclusion_constraints, &other.exclusion_constraints)
+            && same_elements(policies, &other.policies)
+    }
+}
+
+fn merge_objects<T>(
+    kind: &str,
+    prefix: &str,
+    objects: &mut HashMap<String, T>,
+    others: HashMap<String, T>,
+    eq: impl Fn(&T, &T) -> bool,
+) -> Result<()> {
+    // sorted, so that the first conflict reported doesn't vary
+    let mut others: Vec<(String, T)> = others.into_iter().collect();
+    others.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, other) in others {
+        match objects.get(&name) {
+            Some(existing) if !eq(existing, &other) => {
+                anyhow::bail!(
+                    "{} {}.{} is defined differently by both",
+                    kind,
+                    prefix,
+                    name
+                )
+            }
+            Some(_) => {}
+            None => {
+                objects.insert(name, other);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Whether two lists hold the same elements, in any order.
+pub fn same_elements<T: Ord>(a: &[T], b: &[T]) -> bool {
+    let (mut a, mut b): (Vec<&T>, Vec<&T>) = (a.iter().collect(), b.iter().collect());
+    a.sort();
+    b.sort();
+    a == b
+}
+
+pub fn constraint_name(constraint: &TableConstraint) -> Option<&Ident> {
+    match constraint {
+        TableConstraint::Unique { name, .. }
+        | TableConstraint::PrimaryKey { name, .. }
+        | TableConstraint::ForeignKey { name, .. }
+        | TableConstraint::Check { name, .. }
+        | TableConstraint::Index { name, .. } => name.as_ref(),
+        TableConstraint::FulltextOrSpatial { opt_index_name, .. } => opt_index_name.as_ref(),
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
     pub data_type: DataType,
     pub collation: Option<ObjectName>,
     pub options: Vec<ColumnOptionDef>,
+    pub identity: Option<Identity>, // normalized SERIAL, AUTO_INCREMENT and GENERATED ... AS IDENTITY
+}
+
+impl Column {
+    pub fn is_identity(&self) -> bool {
+        self.identity.is_some()
+    }
+
+    pub fn identity_kind(&self) -> Option<IdentityKind> {
+        self.identity.as_ref().map(|identity| identity.kind)
+    }
+
+    // Whether the column accepts NULL in its table: it doesn't when declared
+    // NOT NULL, when it is an identity column or when it is part of the
+    // primary key, which implies NOT NULL. SQLite letting NULL into the
+    // primary key of rowid tables is not taken into account.
+    pub fn is_nullable(&self, table: &Table) -> bool {
+        let not_null = self.options.iter().any(|o| {
+            matches!(
+                o.option,
+                ColumnOption::NotNull
+                    | ColumnOption::Unique {
+                        is_primary: true,
+                        ..
+                    }
+            )
+        });
+        let primary_key = table.constraints.iter().any(|c| {
+            matches!(c, TableConstraint::PrimaryKey { columns, .. } if columns.iter().any(|c| c.value == self.name))
+        });
+        !(not_null || primary_key || self.is_identity())
+    }
+
+    // Whether the rows of a table get a value when the column is added to it:
+    // NULL, its default, the next values of its identity or its generation
+    // expression. A NOT NULL column with none of these can't be added to a
+    // table with rows.
+    pub fn fills_existing_rows(&self) -> bool {
+        self.is_identity()
+            || self
+                .options
+                .iter()
+                .all(|o| !matches!(o.option, ColumnOption::NotNull))
+            || self.options.iter().any(|o| {
+                matches!(
+                    o.option,
+                    ColumnOption::Default(_) | ColumnOption::Generated { .. }
+                )
+            })
+    }
+
+    // Puts the options in a canonical order and drops the ones that restate
+    // the defaults (`NULL`, `DEFAULT NULL`) or are repeated, so that equivalent
+    // declarations compare equal.
+    pub fn normalize_options(&mut self) {
+        let not_null = self
+            .options
+            .iter()
+            .any(|o| matches!(o.option, ColumnOption::NotNull));
+
+        let mut options: Vec<ColumnOptionDef> = Vec::with_capacity(self.options.len());
+        for mut option in self.options.drain(..) {
+            if let ColumnOption::Check(expr) = &option.option {
+                option.option = ColumnOption::Check(normalize_check_expr(expr));
+            }
+            let redundant = match &option.option {
+                ColumnOption::Null => true,
+                ColumnOption::Default(Expr::Value(Value::Null)) => !not_null,
+                _ => false,
+            };
+            if !redundant && !options.contains(&option) {
+                options.push(option);
+            }
+        }
+        options.sort_by_key(|o| column_option_rank(&o.option));
+
+        self.options = options;
+    }
+}
+
+// An index with the implicit ASC and NULLS ordering of its columns spelled out,
+// Postgres sorting NULLs last in ascending order and the other dialects first.
+// How it was built (CONCURRENTLY) isn't part of its definition.
+pub fn normalize_index(index: &Index, dialect: Dialect) -> Index {
+    let mut index = index.clone();
+    for column in &mut index.columns {
+        let asc = column.asc.unwrap_or(true);
+        let nulls_first = match dialect {
+            Dialect::PostgreSql => !asc,
+            Dialect::MySql | Dialect::MariaDb | Dialect::SQLite => asc,
+        };
+        column.asc = Some(asc);
+        column.nulls_first = Some(column.nulls_first.unwrap_or(nulls_first));
+    }
+    index.concurrently = false;
+    index
+}
+
+// Identity sequence options in a fixed order, with the optional BY of
+// INCREMENT and WITH of START spelled out.
+pub fn normalize_sequence_options(options: Vec<SequenceOptions>) -> Vec<SequenceOptions> {
+    let mut options: Vec<SequenceOptions> = options
+        .into_iter()
+        .map(|option| match option {
+            SequenceOptions::IncrementBy(increment, _) => {
+                SequenceOptions::IncrementBy(increment, true)
+            }
+            SequenceOptions::StartWith(start, _) => SequenceOptions::StartWith(start, true),
+            option => option,
+        })
+        .collect();
+    options.sort();
+    options
+}
+
+// Table CHECK constraints with their expression normalized.
+pub fn normalize_constraint(constraint: &TableConstraint) -> TableConstraint {
+    match constraint {
+        TableConstraint::Check { name, expr } => TableConstraint::Check {
+            name: name.clone(),
+            expr: Box::new(normalize_check_expr(expr)),
+        },
+        constraint => constraint.clone(),
+    }
+}
+
+// Rewrites a CHECK expression so that cosmetic differences compare equal:
+// redundant parentheses are removed and literals are moved to the right of
+// comparisons, so `(0 < (x))` becomes `x > 0`.
+pub fn normalize_check_expr(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Nested(inner) => normalize_check_expr(inner),
+        expr => normalize_operand(expr),
+    }
+}
+
+fn normalize_operand(expr: &Expr) -> Expr {
+    match expr {
+        // Parentheses can only be dropped around operands that don't depend on
+        // the precedence of the surrounding operator.
+        Expr::Nested(inner) => match normalize_operand(inner) {
+            inner @ (Expr::Identifier(_)
+            | Expr::CompoundIdentifier(_)
+            | Expr::Value(_)
+            | Expr::Function(_)
+            | Expr::Nested(_)) => inner,
+            inner => Expr::Nested(Box::new(inner)),
+        },
+        Expr::BinaryOp { left, op, right } => {
+            let mut left = normalize_operand(left);
+            let mut right = normalize_operand(right);
+            // Comparisons bind tighter than AND and OR.
+            if matches!(op, BinaryOperator::And | BinaryOperator::Or) {
+                for operand in [&mut left, &mut right] {
+                    if let Expr::Nested(inner) = operand {
+                        if is_comparison(inner) {
+                            *operand = *inner.clone();
+                        }
+                    }
+                }
+            }
+            let flipped = match op {
+                BinaryOperator::Lt => Some(BinaryOperator::Gt),
+                BinaryOperator::LtEq => Some(BinaryOperator::GtEq),
+                BinaryOperator::Gt => Some(BinaryOperator::Lt),
+                BinaryOperator::GtEq => Some(BinaryOperator::LtEq),
+                BinaryOperator::Eq => Some(BinaryOperator::Eq),
+                BinaryOperator::NotEq => Some(BinaryOperator::NotEq),
+                _ => None,
+            };
+            match flipped {
+                Some(flipped)
+                    if matches!(left, Expr::Value(_)) && !matches!(right, Expr::Value(_)) =>
+                {
+                    Expr::BinaryOp {
+                        left: Box::new(right),
+                        op: flipped,
+                        right: Box::new(left),
+                    }
+                }
+                _ => Expr::BinaryOp {
+                    left: Box::new(left),
+                    op: op.clone(),
+                    right: Box::new(right),
+                },
+            }
+        }
+        Expr::UnaryOp { op, expr } => Expr::UnaryOp {
+            op: *op,
+            expr: Box::new(normalize_operand(expr)),
+        },
+        expr => expr.clone(),
+    }
+}
+
+// Whether two data types are spellings of the same type in `dialect`.
+pub fn data_type_equivalent(a: &DataType, b: &DataType, dialect: Dialect) -> bool {
+    canonical_data_type(a, dialect) == canonical_data_type(b, dialect)
+}
+
+// Rewrites the aliases of a data type to a single spelling.
+pub fn canonical_data_type(data_type: &DataType, dialect: Dialect) -> DataType {
+    use DataType::*;
+
+    match (dialect, data_type.clone()) {
+        (Dialect::MySql | Dialect::MariaDb, Bool | Boolean) => TinyInt(Some(1)),
+        (_, Character(length)) => Char(length),
+        (_, CharacterVarying(length) | CharVarying(length)) => Varchar(length),
+        (_, Bool) => Boolean,
+        (_, Double) => DoublePrecision,
+        // INTEGER PRIMARY KEY makes a rowid alias in SQLite, INT PRIMARY KEY doesn't.
+        (Dialect::SQLite, data_type) => data_type,
+        (_, Integer(width) | Int4(width)) => Int(width),
+        (_, Numeric(info) | Dec(info)) => Decimal(info),
+        (Dialect::PostgreSql, Int2(width)) => SmallInt(width),
+        (Dialect::PostgreSql, Int8(width)) => BigInt(width),
+        (Dialect::PostgreSql, Float4) => Real,
+        (Dialect::PostgreSql, Float(Some(precision))) if precision <= 24 => Real,
+        (Dialect::PostgreSql, Float8 | Float(_)) => DoublePrecision,
+        (Dialect::PostgreSql, Timestamp(precision, TimezoneInfo::WithoutTimeZone)) => {
+            Timestamp(precision, TimezoneInfo::None)
+        }
+        (Dialect::PostgreSql, Timestamp(precision, TimezoneInfo::Tz)) => {
+            Timestamp(precision, TimezoneInfo::WithTimeZone)
+        }
+        (Dialect::PostgreSql, Time(precision, TimezoneInfo::WithoutTimeZone)) => {
+            Time(precision, TimezoneInfo::None)
+        }
+        (Dialect::PostgreSql, Time(precision, TimezoneInfo::Tz)) => {
+            Time(precision, TimezoneInfo::WithTimeZone)
+        }
+        (Dialect::MySql | Dialect::MariaDb, UnsignedInteger(width) | UnsignedInt4(width)) => {
+            UnsignedInt(width)
+        }
+        (Dialect::MySql | Dialect::MariaDb, Real) => DoublePrecision,
+        (_, data_type) => data_type,
+    }
+}
+
+// A user-defined type as Postgres resolves it: the qualifiers naming the
+// default schema, which heads the search_path, are dropped, so `status` and
+// `public.status` are the same type.
+pub fn resolve_data_type(data_type: &DataType, catalog: &str, default_schema: &str) -> DataType {
+    match data_type {
+        DataType::Custom(name, modifiers) => {
+            let name = match name.0.as_slice() {
+                [c, s, n] if c.value == catalog && s.value == default_schema => vec![n.clone()],
+                [s, n] if s.value == default_schema => vec![n.clone()],
+                _ => name.0.clone(),
+            };
+            DataType::Custom(ObjectName(name), modifiers.clone())
+        }
+        DataType::Array(ArrayElemTypeDef::SquareBracket(element, size)) => {
+            DataType::Array(ArrayElemTypeDef::SquareBracket(
+                Box::new(resolve_data_type(element, catalog, default_schema)),
+                *size,
+            ))
+        }
+        DataType::Array(ArrayElemTypeDef::AngleBracket(element)) => {
+            DataType::Array(ArrayElemTypeDef::AngleBracket(Box::new(resolve_data_type(
+                element,
+                catalog,
+                default_schema,
+            ))))
+        }
+        data_type => data_type.clone(),
+    }
+}
+
+fn is_comparison(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::BinaryOp {
+            op: BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq
+                | BinaryOperator::Eq
+                | BinaryOperator::NotEq,
+            ..
+        } | Expr::IsNull(_)
+            | Expr::IsNotNull(_)
+            | Expr::Like { .. }
+            | Expr::ILike { .. }
+    )
+}
+
+fn column_option_rank(option: &ColumnOption) -> u8 {
+    match option {
+        ColumnOption::Null | ColumnOption::NotNull => 0,
+        ColumnOption::Default(_) => 1,
+        ColumnOption::Generated { .. } => 2,
+        ColumnOption::Unique {
+            is_primary: true, ..
+        } => 3,
+        ColumnOption::Unique { .. } => 4,
+        ColumnOption::ForeignKey { .. } => 5,
+        ColumnOption::Check(_) => 6,
+        ColumnOption::OnUpdate(_) => 7,
+        ColumnOption::CharacterSet(_) => 8,
+        ColumnOption::DialectSpecific(_) | ColumnOption::Options(_) => 9,
+        ColumnOption::Comment(_) => 10,
+    }
+}
+
+// The column name and type followed by its nullability, default, identity
+// and other options.
+impl fmt::Display for Column {
+    // Without its table, a column in a primary key constraint reads NULL.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_nullable(f, self.is_nullable(&Table::new("")))
+    }
+}
+
+impl Column {
+    fn fmt_nullable(&self, f: &mut fmt::Formatter, nullable: bool) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.data_type)?;
+        if let Some(collation) = &self.collation {
+            write!(f, " COLLATE {}", collation)?;
+        }
+
+        write!(f, "{}", if nullable { " NULL" } else { " NOT NULL" })?;
+        for option in &self.options {
+            if let ColumnOption::Default(expr) = &option.option {
+                write!(f, " DEFAULT {}", expr)?;
+            }
+        }
+        match self.identity_kind() {
+            Some(IdentityKind::Always) => write!(f, " GENERATED ALWAYS AS IDENTITY")?,
+            Some(IdentityKind::ByDefault) => write!(f, " GENERATED BY DEFAULT AS IDENTITY")?,
+            None => {}
+        }
+        for option in &self.options {
+            if !matches!(
+                option.option,
+                ColumnOption::Null | ColumnOption::NotNull | ColumnOption::Default(_)
+            ) {
+                write!(f, " {}", option)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct Identity {
+    pub kind: IdentityKind,
+    pub sequence_options: Vec<SequenceOptions>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum IdentityKind {
+    Always,    // GENERATED ALWAYS AS IDENTITY
+    ByDefault, // GENERATED BY DEFAULT AS IDENTITY, SERIAL, AUTO_INCREMENT, AUTOINCREMENT
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct TableName {
     pub catalog: Option<Ident>,
     pub schema: Option<Ident>,
     pub table: Ident,
 }
 
+impl fmt::Display for TableName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(catalog) = &self.catalog {
+            write!(f, "{}.", catalog.value)?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "{}.", schema.value)?;
+        }
+        write!(f, "{}", self.table.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Domain {
+    pub name: String,
+    pub data_type: DataType,
+    pub collation: Option<ObjectName>,
+    pub default: Option<Expr>,
+    pub not_null: bool,
+    pub constraints: Vec<DomainConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainConstraint {
+    pub name: Option<Ident>,
+    pub check: Expr,
+}
+
+impl fmt::Display for DomainConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "CONSTRAINT {} ", name)?;
+        }
+        write!(f, "CHECK ({})", self.check)
+    }
+}
+
+// `EXCLUDE [USING method] (element WITH operator, ...) [WHERE (predicate)]`,
+// which sqlparser can't parse.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct ExclusionConstraint {
+    pub name: Option<Ident>,
+    pub index_method: Option<Ident>, // btree when not given
+    pub elements: Vec<ExclusionElement>,
+    pub predicate: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct ExclusionElement {
+    pub expr: Expr,
+    pub operator: String, // e.g. `=` or `&&`
+}
+
+impl ExclusionConstraint {
+    // The name Postgres gives an unnamed constraint, after its first column.
+    pub fn default_name(&self, table_name: &str) -> String {
+        let column = match self.elements.first().map(|e| &e.expr) {
+            Some(Expr::Identifier(ident)) => ident.value.as_str(),
+            _ => "expr",
+        };
+        format!("{}_{}_excl", table_name, column)
+    }
+}
+
+impl fmt::Display for ExclusionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "CONSTRAINT {} ", name)?;
+        }
+        write!(f, "EXCLUDE ")?;
+        if let Some(index_method) = &self.index_method {
+            write!(f, "USING {} ", index_method)?;
+        }
+        let elements: Vec<String> = self
+            .elements
+            .iter()
+            .map(|e| format!("{} WITH {}", e.expr, e.operator))
+            .collect();
+        write!(f, "({})", elements.join(", "))?;
+        if let Some(predicate) = &self.predicate {
+            write!(f, " WHERE ({})", predicate)?;
+        }
+        Ok(())
+    }
+}
+
+// What follows the name in CREATE DOMAIN, e.g. `AS TEXT NOT NULL CHECK (...)`.
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AS {}", self.data_type)?;
+        if let Some(collation) = &self.collation {
+            write!(f, " COLLATE {}", collation)?;
+        }
+        if let Some(default) = &self.default {
+            write!(f, " DEFAULT {}", default)?;
+        }
+        if self.not_null {
+            write!(f, " NOT NULL")?;
+        }
+        for constraint in &self.constraints {
+            write!(f, " {}", constraint)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    InsteadOf,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum TriggerEvent {
+    Insert,
+    Update(Vec<Ident>), // the columns of UPDATE OF, empty for any column
+    Delete,
+    Truncate,
+}
+
+impl fmt::Display for TriggerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TriggerEvent::Insert => write!(f, "INSERT"),
+            TriggerEvent::Update(columns) if columns.is_empty() => write!(f, "UPDATE"),
+            TriggerEvent::Update(columns) => {
+                let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                write!(f, "UPDATE OF {}", columns.join(", "))
+            }
+            TriggerEvent::Delete => write!(f, "DELETE"),
+            TriggerEvent::Truncate => write!(f, "TRUNCATE"),
+        }
+    }
+}
+
+// A trigger of the table it is stored in. The action is everything after the
+// condition as written, e.g. `EXECUTE FUNCTION audit()` in Postgres or the
+// `BEGIN ... END` body in MySQL and SQLite.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct Trigger {
+    pub name: String,
+    pub timing: TriggerTiming,
+    pub events: Vec<TriggerEvent>,
+    pub for_each_row: bool,
+    pub condition: Option<Expr>,
+    pub action: String,
+}
+
+impl Trigger {
+    // What follows `CREATE TRIGGER name`, e.g.
+    // `BEFORE INSERT OR UPDATE ON users FOR EACH ROW EXECUTE FUNCTION touch()`.
+    pub fn definition(&self, table: &impl fmt::Display) -> String {
+        let timing = match self.timing {
+            TriggerTiming::Before => "BEFORE",
+            TriggerTiming::After => "AFTER",
+            TriggerTiming::InsteadOf => "INSTEAD OF",
+        };
+        let events: Vec<String> = self.events.iter().map(|e| e.to_string()).collect();
+        let each = if self.for_each_row {
+            "ROW"
+        } else {
+            "STATEMENT"
+        };
+        let mut definition = format!(
+            "{} {} ON {} FOR EACH {}",
+            timing,
+            events.join(" OR "),
+            table,
+            each
+        );
+        if let Some(condition) = &self.condition {
+            definition.push_str(&format!(" WHEN ({})", condition));
+        }
+        definition.push(' ');
+        definition.push_str(&self.action);
+        definition
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum PolicyCommand {
+    All,
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+// A Postgres row-level security policy of the table it is stored in.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct Policy {
+    pub name: String,
+    pub permissive: bool, // false for AS RESTRICTIVE
+    pub command: PolicyCommand,
+    pub roles: Vec<String>, // PUBLIC when empty
+    pub using: Option<Expr>,
+    pub with_check: Option<Expr>,
+}
+
+impl Policy {
+    pub fn roles(&self) -> String {
+        if self.roles.is_empty() {
+            "PUBLIC".to_string()
+        } else {
+            self.roles.join(", ")
+        }
+    }
+}
+
+// What follows `CREATE POLICY name ON table`, without the clauses left to
+// their default, e.g. `AS RESTRICTIVE FOR SELECT TO app USING (owner = current_user)`.
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut clauses = vec![];
+        if !self.permissive {
+            clauses.push("AS RESTRICTIVE".to_string());
+        }
+        let command = match self.command {
+            PolicyCommand::All => None,
+            PolicyCommand::Select => Some("SELECT"),
+            PolicyCommand::Insert => Some("INSERT"),
+            PolicyCommand::Update => Some("UPDATE"),
+            PolicyCommand::Delete => Some("DELETE"),
+        };
+        if let Some(command) = command {
+            clauses.push(format!("FOR {}", command));
+        }
+        if !self.roles.is_empty() {
+            clauses.push(format!("TO {}", self.roles()));
+        }
+        if let Some(using) = &self.using {
+            clauses.push(format!("USING ({})", using));
+        }
+        if let Some(with_check) = &self.with_check {
+            clauses.push(format!("WITH CHECK ({})", with_check));
+        }
+        write!(f, "{}", clauses.join(" "))
+    }
+}
+
+// A function, with its body as written between its quotes so that the SQL in
+// it is never parsed as statements of the schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Routine {
+    pub name: String,
+    pub args: Vec<OperateFunctionArg>,
+    pub return_type: Option<DataType>,
+    pub language: Option<String>,
+    pub behavior: Option<FunctionBehavior>,
+    pub called_on_null: Option<FunctionCalledOnNull>,
+    pub parallel: Option<FunctionParallel>,
+    pub body: Option<String>,
+}
+
+impl Routine {
+    // Postgres identifies a function by its name and the types of its input
+    // arguments, so that overloads can coexist.
+    pub fn signature(&self) -> String {
+        let types: Vec<String> = self
+            .args
+            .iter()
+            .filter(|arg| arg.mode != Some(ArgMode::Out))
+            .map(|arg| arg.data_type.to_string())
+            .collect();
+        format!("{}({})", self.name, types.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct Index {
+    pub name: String,
+    pub unique: bool,
+    pub using: Option<Ident>,
+    pub columns: Vec<OrderByExpr>,
+    pub include: Vec<Ident>,
+    pub nulls_distinct: Option<bool>,
+    pub predicate: Option<Expr>,
+    #[serde(default)]
+    pub concurrently: bool, // Postgres CREATE INDEX CONCURRENTLY, which can't run in a transaction
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct View {
     pub name: String,
     pub materialized: bool,
     pub columns: Vec<ViewColumn>,
     pub query: Box<Query>,
     pub comment: Option<String>,
+    #[serde(default)]
+    pub indexes: Vec<Index>, // Postgres materialized views only
+}
+
+impl View {
+    // `VIEW` or `MATERIALIZED VIEW`, as CREATE and DROP name it.
+    pub fn kind(&self) -> &'static str {
+        if self.materialized {
+            "MATERIALIZED VIEW"
+        } else {
+            "VIEW"
+        }
+    }
+
+    // The names of the columns the view returns, None when some can't be
+    // told without the underlying tables (wildcards, unaliased expressions).
+    pub fn column_names(&self) -> Option<Vec<String>> {
+        if !self.columns.is_empty() {
+            return Some(self.columns.iter().map(|c| c.name.clone()).collect());
+        }
+        let mut body = self.query.body.as_ref();
+        let select = loop {
+            match body {
+                SetExpr::Select(select) => break select,
+                SetExpr::Query(query) => body = query.body.as_ref(),
+                SetExpr::SetOperation { left, .. } => body = left.as_ref(),
+                _ => return None,
+            }
+        };
+        select
+            .projection
+            .iter()
+            .map(|item| match item {
+                SelectItem::ExprWithAlias { alias, .. } => Some(alias.value.clone()),
+                SelectItem::UnnamedExpr(Expr::Identifier(ident)) => Some(ident.value.clone()),
+                SelectItem::UnnamedExpr(Expr::CompoundIdentifier(idents)) => {
+                    idents.last().map(|ident| ident.value.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
 pub struct ViewColumn {
     pub name: String,
     pub data_type: Option<DataType>,
     pub options: Vec<SqlOption>,
 }
+
+fn differing_paths(
+    path: &str,
+    left: &serde_json::Value,
+    right: &serde_json::Value,
+    paths: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            let keys: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match (l.get(key), r.get(key)) {
+                    (Some(l), Some(r)) => differing_paths(&path, l, r, paths),
+                    _ => paths.push(path),
+                }
+            }
+        }
+        (Value::Array(l), Value::Array(r)) if l.len() == r.len() => {
+            for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                differing_paths(&format!("{}[{}]", path, i), l, r, paths);
+            }
+        }
+        (l, r) if l != r => paths.push(path.to_string()),
+        _ => {}
+    }
+}
+
+fn sorted_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<(String, serde_json::Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sorted_keys(v)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(sorted_keys).collect())
+        }
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspector::Inspector;
+    use crate::test_support::test_options;
+    use crate::test_support::DbinfoBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE TABLE app.users (
+                id SERIAL PRIMARY KEY,
+                email VARCHAR(255) NOT NULL UNIQUE,
+                active BOOLEAN DEFAULT true
+            );
+            CREATE UNIQUE INDEX users_email_idx ON app.users (email);
+            CREATE TABLE posts (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES app.users (id));
+            CREATE VIEW active_users AS SELECT id FROM app.users WHERE active;
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        Inspector::new(&mut dbinfo)
+            .inspect(sql, "test.sql")
+            .unwrap();
+
+        let expected = r#"catalog test
+  schema app
+    table users
+      id INT NOT NULL GENERATED BY DEFAULT AS IDENTITY
+      email VARCHAR(255) NOT NULL
+      active BOOLEAN NULL DEFAULT true
+      PRIMARY KEY ("id")
+      UNIQUE ("email")
+      UNIQUE INDEX users_email_idx ("email")
+  schema public
+    table posts
+      id INT NULL
+      user_id INT NULL
+      FOREIGN KEY ("user_id") REFERENCES "app"."users"("id")
+    view active_users
+"#;
+        assert_eq!(dbinfo.to_string(), expected);
+    }
+
+    #[test]
+    fn test_differing_paths() {
+        let previous = DbinfoBuilder::new(Dialect::PostgreSql, "test").build();
+        let current = DbinfoBuilder::new(Dialect::PostgreSql, "test")
+            .table("users")
+            .build();
+        assert!(previous.differing_paths(&previous).is_empty());
+        assert_eq!(
+            previous.differing_paths(&current),
+            vec!["catalogs.test.schemas.public.tables.users"]
+        );
+
+        let mut renamed = current.clone();
+        renamed.get_catalog_mut("test").unwrap().default_schema = "app".to_string();
+        assert_eq!(
+            current.differing_paths(&renamed),
+            vec!["catalogs.test.default_schema"]
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_statement_order() {
+        let fingerprint = |statements: &[&str]| {
+            let options = test_options(Dialect::PostgreSql);
+            let mut dbinfo = Dbinfo::with_options(options);
+            let sources: Vec<(String, String)> = statements
+                .iter()
+                .enumerate()
+                .map(|(i, sql)| (format!("{}.sql", i), sql.to_string()))
+                .collect();
+            Inspector::new(&mut dbinfo).inspect_all(&sources).unwrap();
+            dbinfo.fingerprint()
+        };
+
+        let statements = [
+            "CREATE SCHEMA app;",
+            "CREATE TABLE users (id INT PRIMARY KEY, email TEXT);",
+            "CREATE TABLE app.posts (id INT, user_id INT);",
+            "CREATE TABLE tags (name TEXT);",
+            "ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);",
+            "ALTER TABLE app.posts ADD CONSTRAINT posts_user_fk FOREIGN KEY (user_id) REFERENCES users (id);",
+            "ALTER TABLE app.posts ADD CONSTRAINT posts_id_check CHECK (id > 0);",
+        ];
+        let reordered = [6, 3, 2, 5, 0, 4, 1].map(|i| statements[i]);
+
+        let expected = fingerprint(&statements);
+        assert_eq!(expected.len(), 16);
+        assert_eq!(fingerprint(&reordered), expected);
+        assert_ne!(
+            fingerprint(&statements[..6]),
+            expected,
+            "a dropped constraint changes the fingerprint"
+        );
+    }
+
+    #[test]
+    fn test_tables_in_dependency_order() {
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        Inspector::new(&mut dbinfo)
+            .inspect(
+                "CREATE SCHEMA app;
+                 CREATE TABLE app.order_items (id INT, order_id INT REFERENCES orders (id));
+                 CREATE TABLE orders (id INT PRIMARY KEY, user_id INT REFERENCES users (id));
+                 CREATE TABLE users (id INT PRIMARY KEY);
+                 CREATE TABLE categories (id INT PRIMARY KEY, parent_id INT REFERENCES categories (id));",
+                "test.sql",
+            )
+            .unwrap();
+
+        let order: Vec<String> = dbinfo
+            .tables_in_dependency_order()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "test.public.categories",
+                "test.public.users",
+                "test.public.orders",
+                "test.app.order_items",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+
+        let mut merged = inspect(
+            "CREATE SCHEMA billing;
+             CREATE TABLE billing.invoices (id INT PRIMARY KEY);
+             CREATE TABLE users (id INT PRIMARY KEY);",
+        );
+        merged
+            .merge(inspect(
+                "CREATE SCHEMA shipping;
+                 CREATE TABLE shipping.parcels (id INT PRIMARY KEY);
+                 CREATE TABLE users (id INT PRIMARY KEY);",
+            ))
+            .unwrap();
+        assert_eq!(
+            merged,
+            inspect(
+                "CREATE SCHEMA billing;
+                 CREATE SCHEMA shipping;
+                 CREATE TABLE billing.invoices (id INT PRIMARY KEY);
+                 CREATE TABLE shipping.parcels (id INT PRIMARY KEY);
+                 CREATE TABLE users (id INT PRIMARY KEY);",
+            )
+        );
+
+        let err = merged
+            .merge(inspect("CREATE TABLE users (id BIGINT PRIMARY KEY);"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "table test.public.users is defined differently by both"
+        );
+    }
+
+    #[test]
+    fn test_data_type_aliases() {
+        let equivalent = |a: &str, b: &str, dialect: Dialect| {
+            let inspect = |data_type: &str| {
+                let options = test_options(dialect);
+                let mut dbinfo = Dbinfo::with_options(options);
+                let sql = format!("CREATE TABLE t (c {});", data_type);
+                Inspector::new(&mut dbinfo)
+                    .inspect(&sql, "test.sql")
+                    .unwrap();
+                let table = dbinfo.default_catalog().default_schema().get_table("t");
+                table.unwrap().columns[0].data_type.clone()
+            };
+            data_type_equivalent(&inspect(a), &inspect(b), dialect)
+        };
+
+        assert!(equivalent("INT", "INTEGER", Dialect::PostgreSql));
+        assert!(equivalent("INT4", "INTEGER", Dialect::PostgreSql));
+        assert!(equivalent("INT8", "BIGINT", Dialect::PostgreSql));
+        assert!(equivalent(
+            "VARCHAR(255)",
+            "CHARACTER VARYING(255)",
+            Dialect::PostgreSql
+        ));
+        assert!(equivalent("BOOL", "BOOLEAN", Dialect::PostgreSql));
+        assert!(equivalent(
+            "NUMERIC(10,2)",
+            "DECIMAL(10,2)",
+            Dialect::PostgreSql
+        ));
+        assert!(equivalent(
+            "FLOAT8",
+            "DOUBLE PRECISION",
+            Dialect::PostgreSql
+        ));
+        assert!(equivalent(
+            "TIMESTAMPTZ",
+            "TIMESTAMP WITH TIME ZONE",
+            Dialect::PostgreSql
+        ));
+        assert!(equivalent(
+            "TIMESTAMP",
+            "TIMESTAMP WITHOUT TIME ZONE",
+            Dialect::PostgreSql
+        ));
+        assert!(equivalent("BOOLEAN", "TINYINT(1)", Dialect::MySql));
+        assert!(equivalent("BOOL", "BOOLEAN", Dialect::SQLite));
+
+        assert!(!equivalent(
+            "VARCHAR(255)",
+            "VARCHAR(100)",
+            Dialect::PostgreSql
+        ));
+        assert!(!equivalent("INT", "BIGINT", Dialect::PostgreSql));
+        assert!(!equivalent("TIMESTAMP", "TIMESTAMPTZ", Dialect::PostgreSql));
+        assert!(!equivalent("INT", "INTEGER", Dialect::SQLite));
+    }
+}
@@ -0,0 +1,353 @@
+use serde_derive::Serialize;
+use sqlparser::ast::{ColumnOption, ReferentialAction, TableConstraint};
+
+use crate::dbinfo::{canonical_data_type, Column, Dbinfo, IdentityKind, Table};
+use crate::migrate::{resolve_table_name, ObjectName};
+use crate::Dialect;
+
+// A description of the tables of a Dbinfo for tools outside of migi, e.g. to
+// generate types from. Unlike the snapshot, it doesn't expose sqlparser types
+// and its format only changes along with `version`.
+#[derive(Debug, Serialize)]
+struct JsonSchema {
+    version: u32,
+    dialect: Dialect,
+    catalogs: Vec<JsonCatalog>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonCatalog {
+    name: String,
+    schemas: Vec<JsonSchemaObject>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSchemaObject {
+    name: String,
+    tables: Vec<JsonTable>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonTable {
+    name: String,
+    columns: Vec<JsonColumn>,
+    primary_key: Vec<String>,
+    unique: Vec<Vec<String>>,
+    foreign_keys: Vec<JsonForeignKey>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonColumn {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    nullable: bool,
+    default: Option<String>,
+    identity: Option<&'static str>,
+    domain: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonForeignKey {
+    columns: Vec<String>,
+    references: JsonReference,
+    on_delete: Option<String>,
+    on_update: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonReference {
+    catalog: String,
+    schema: String,
+    table: String,
+    columns: Vec<String>,
+}
+
+impl Dbinfo {
+    // Catalogs, schemas and tables are sorted by name, columns are in table
+    // order and types are the canonical spelling of their aliases.
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        let mut catalogs: Vec<JsonCatalog> = self
+            .catalogs
+            .values()
+            .map(|catalog| {
+                let mut schemas: Vec<JsonSchemaObject> = catalog
+                    .schemas
+                    .values()
+                    .map(|schema| {
+                        let mut tables: Vec<JsonTable> = schema
+                            .tables
+                            .values()
+                            .map(|table| {
+                                let name = ObjectName(vec![
+                                    catalog.name.clone(),
+                                    schema.name.clone(),
+                                    table.name.clone(),
+                                ]);
+                                self.json_table(&name, table)
+                            })
+                            .collect();
+                        tables.sort_by(|a, b| a.name.cmp(&b.name));
+                        JsonSchemaObject {
+                            name: schema.name.clone(),
+                            tables,
+                        }
+                    })
+                    .collect();
+                schemas.sort_by(|a, b| a.name.cmp(&b.name));
+                JsonCatalog {
+                    name: catalog.name.clone(),
+                    schemas,
+                }
+            })
+            .collect();
+        catalogs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        serde_json::to_value(JsonSchema {
+            version: 1,
+            dialect: self.dialect,
+            catalogs,
+        })
+        .unwrap()
+    }
+
+    fn json_table(&self, name: &ObjectName, table: &Table) -> JsonTable {
+        let names = |idents: &[sqlparser::ast::Ident]| -> Vec<String> {
+            idents.iter().map(|i| i.value.clone()).collect()
+        };
+        let reference = |foreign_table: &sqlparser::ast::ObjectName, columns: Vec<String>| {
+            let resolved = resolve_table_name(self, name, foreign_table);
+            JsonReference {
+                catalog: resolved.0[0].clone(),
+                schema: resolved.0[1].clone(),
+                table: resolved.0[2].clone(),
+                columns,
+            }
+        };
+        let action = |action: &Option<ReferentialAction>| action.map(|a| a.to_string());
+
+        let mut primary_key = vec![];
+        let mut unique = vec![];
+        let mut foreign_keys = vec![];
+        for constraint in &table.constraints {
+            match constraint {
+                TableConstraint::PrimaryKey { columns, .. } => primary_key = names(columns),
+                TableConstraint::Unique { columns, .. } => unique.push(names(columns)),
+                TableConstraint::ForeignKey {
+                    columns,
+                    foreign_table,
+                    referred_columns,
+                    on_delete,
+                    on_update,
+                    ..
+                } => foreign_keys.push(JsonForeignKey {
+                    columns: names(columns),
+                    references: reference(foreign_table, names(referred_columns)),
+                    on_delete: action(on_delete),
+                    on_update: action(on_update),
+                }),
+                _ => {}
+            }
+        }
+        for column in &table.columns {
+            for option in &column.options {
+                if let ColumnOption::ForeignKey {
+                    foreign_table,
+                    referred_columns,
+                    on_delete,
+                    on_update,
+                    ..
+                } = &option.option
+                {
+                    foreign_keys.push(JsonForeignKey {
+                        columns: vec![column.name.clone()],
+                        references: reference(foreign_table, names(referred_columns)),
+                        on_delete: action(on_delete),
+                        on_update: action(on_update),
+                    });
+                }
+            }
+        }
+
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| self.json_column(name, table, column))
+            .collect();
+        JsonTable {
+            name: table.name.clone(),
+            columns,
+            primary_key,
+            unique,
+            foreign_keys,
+        }
+    }
+
+    // Columns typed with a domain are described by the domain's base type,
+    // NOT NULL and default.
+    fn json_column(&self, table_name: &ObjectName, table: &Table, column: &Column) -> JsonColumn {
+        let domain = self.resolve_domain(&table_name.0, &column.data_type);
+        let nullable = column.is_nullable(table) && !domain.is_some_and(|d| d.not_null);
+        let mut default = domain.and_then(|d| d.default.as_ref().map(|e| e.to_string()));
+        for option in &column.options {
+            if let ColumnOption::Default(expr) = &option.option {
+                default = Some(expr.to_string());
+            }
+        }
+        JsonColumn {
+            name: column.name.clone(),
+            data_type: canonical_data_type(
+                domain.map_or(&column.data_type, |d| &d.data_type),
+                self.dialect,
+            )
+            .to_string(),
+            nullable,
+            default,
+            identity: column.identity_kind().map(|kind| match kind {
+                IdentityKind::Always => "always",
+                IdentityKind::ByDefault => "by default",
+            }),
+            domain: domain.map(|d| d.name.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspector::Inspector;
+    use crate::test_support::test_options;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json_schema() {
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        Inspector::new(&mut dbinfo)
+            .inspect(
+                r#"
+                CREATE TABLE users (
+                    id SERIAL PRIMARY KEY,
+                    email CHARACTER VARYING(255) NOT NULL UNIQUE,
+                    active BOOL DEFAULT true
+                );
+                CREATE TABLE posts (
+                    id INTEGER GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+                    user_id INT REFERENCES users (id) ON DELETE CASCADE,
+                    title TEXT
+                );
+                "#,
+                "test.sql",
+            )
+            .unwrap();
+
+        let expected = r#"{
+  "catalogs": [
+    {
+      "name": "test",
+      "schemas": [
+        {
+          "name": "public",
+          "tables": [
+            {
+              "columns": [
+                {
+                  "default": null,
+                  "domain": null,
+                  "identity": "always",
+                  "name": "id",
+                  "nullable": false,
+                  "type": "INT"
+                },
+                {
+                  "default": null,
+                  "domain": null,
+                  "identity": null,
+                  "name": "user_id",
+                  "nullable": true,
+                  "type": "INT"
+                },
+                {
+                  "default": null,
+                  "domain": null,
+                  "identity": null,
+                  "name": "title",
+                  "nullable": true,
+                  "type": "TEXT"
+                }
+              ],
+              "foreign_keys": [
+                {
+                  "columns": [
+                    "user_id"
+                  ],
+                  "on_delete": "CASCADE",
+                  "on_update": null,
+                  "references": {
+                    "catalog": "test",
+                    "columns": [
+                      "id"
+                    ],
+                    "schema": "public",
+                    "table": "users"
+                  }
+                }
+              ],
+              "name": "posts",
+              "primary_key": [
+                "id"
+              ],
+              "unique": []
+            },
+            {
+              "columns": [
+                {
+                  "default": null,
+                  "domain": null,
+                  "identity": "by default",
+                  "name": "id",
+                  "nullable": false,
+                  "type": "INT"
+                },
+                {
+                  "default": null,
+                  "domain": null,
+                  "identity": null,
+                  "name": "email",
+                  "nullable": false,
+                  "type": "VARCHAR(255)"
+                },
+                {
+                  "default": "true",
+                  "domain": null,
+                  "identity": null,
+                  "name": "active",
+                  "nullable": true,
+                  "type": "BOOLEAN"
+                }
+              ],
+              "foreign_keys": [],
+              "name": "users",
+              "primary_key": [
+                "id"
+              ],
+              "unique": [
+                [
+                  "email"
+                ]
+              ]
+            }
+          ]
+        }
+      ]
+    }
+  ],
+  "dialect": "postgresql",
+  "version": 1
+}"#;
+        assert_eq!(
+            serde_json::to_string_pretty(&dbinfo.to_json_schema()).unwrap(),
+            expected
+        );
+    }
+}
@@ -1,63 +1,681 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 use anyhow::Result;
-use sqlparser::ast::{ColumnDef, ObjectName, Statement};
-use sqlparser::dialect::{self, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use serde_derive::{Deserialize, Serialize};
+use sqlparser::ast::{
+    AlterColumnOperation, AlterTableOperation, ColumnDef, ColumnOption, ColumnOptionDef,
+    CommentObject, CreateFunctionBody, CreateTableOptions, DataType, Expr, GeneratedAs, Ident,
+    KeyOrIndexDisplay, MySQLColumnPosition, ObjectName, OneOrManyWithParens, OrderByExpr,
+    SchemaName, Statement, TableConstraint, Value,
+};
 use sqlparser::keywords::Keyword;
 use sqlparser::parser::Parser;
 use sqlparser::tokenizer::{Location, Token, TokenWithLocation, Tokenizer};
 
-use crate::dbinfo::{Column, Dbinfo, Table, TableName};
-use crate::Dialect;
+use crate::cache::ParseCache;
+use crate::dbinfo::{
+    constraint_name, normalize_check_expr, normalize_sequence_options, Catalog, Column, Dbinfo,
+    Domain, DomainConstraint, ExclusionConstraint, ExclusionElement, Identity, IdentityKind, Index,
+    Policy, PolicyCommand, Routine, Schema, Table, TableName, Trigger, TriggerEvent, TriggerTiming,
+    View, ViewColumn,
+};
+use crate::{CaseFolding, Dialect, Options};
+
+// Below this number of files, parsing them on several threads costs more than
+// it saves.
+pub const PARALLEL_MIN_FILES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedStatement {
+    pub kind: String,     // leading keywords of the statement, e.g. `INSERT INTO`
+    pub location: String, // file:line:column
+}
+
+impl fmt::Display for UnsupportedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} is not supported", self.location, self.kind)
+    }
+}
+
+// A statement that couldn't be parsed or inspected, collected instead of
+// failing with `with_keep_going`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectionError {
+    pub location: String, // file:line:column of the statement
+    pub message: String,
+}
+
+impl fmt::Display for InspectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.location, self.message)
+    }
+}
+
+// An error or unsupported statement as reported to tools such as CI
+// annotations, with its location split. Messages without a location, e.g. a
+// missing file, have none.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    pub message: String,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Diagnostic {
+    // Splits the `file:line:column` a message starts with, as the errors
+    // returned by the inspector do.
+    pub fn from_message(message: &str, severity: Severity) -> Self {
+        let located = message.split_once(' ').and_then(|(location, rest)| {
+            let (file, line, column) = split_location(location)?;
+            Some(Self {
+                file,
+                line: Some(line),
+                column: Some(column),
+                message: rest.to_string(),
+                severity,
+            })
+        });
+        located.unwrap_or_else(|| Self {
+            file: None,
+            line: None,
+            column: None,
+            message: message.to_string(),
+            severity,
+        })
+    }
+}
+
+impl From<&InspectionError> for Diagnostic {
+    fn from(error: &InspectionError) -> Self {
+        Self::from_message(&error.to_string(), Severity::Error)
+    }
+}
+
+impl From<&UnsupportedStatement> for Diagnostic {
+    fn from(unsupported: &UnsupportedStatement) -> Self {
+        Self::from_message(&unsupported.to_string(), Severity::Warning)
+    }
+}
+
+// `file:line:column` or `line:column`, as format_location writes them.
+fn split_location(location: &str) -> Option<(Option<String>, u64, u64)> {
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next().map(|file| file.to_string());
+    Some((file, line, column))
+}
+
+// An object defined by an inspected statement, reported by
+// `inspect_with_events` so that tools can map the objects of a Dbinfo back to
+// their sources. Names are qualified with the catalog and schema the object
+// was created in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaEvent {
+    TableDefined {
+        name: TableName,
+        location: String, // file:line:column of the statement
+    },
+    ColumnDefined {
+        table: TableName,
+        name: String,
+        location: String,
+    },
+    IndexDefined {
+        table: TableName,
+        name: String,
+        location: String,
+    },
+    ViewDefined {
+        name: TableName,
+        location: String,
+    },
+    FunctionDefined {
+        name: TableName,
+        signature: String,
+        location: String,
+    },
+    DomainDefined {
+        name: TableName,
+        location: String,
+    },
+    TriggerDefined {
+        table: TableName,
+        name: String,
+        location: String,
+    },
+}
+
+// The default catalog and the default schema of each catalog, which `USE` and
+// `SET search_path` only change until the end of a file.
+struct Defaults {
+    catalog: String,
+    schemas: Vec<(String, String)>,
+}
+
+// A statement as parsed by the inspector, which also recognizes a few
+// statements sqlparser can't parse.
+#[derive(Serialize, Deserialize)]
+enum ParsedStatement {
+    Statement(Box<Statement>),
+    ValidateConstraint {
+        table_name: ObjectName,
+        name: Ident,
+    },
+    CreateDomain {
+        name: ObjectName,
+        domain: Box<Domain>,
+    },
+    CreateTrigger {
+        table_name: ObjectName,
+        trigger: Box<Trigger>,
+        or_replace: bool,
+        if_not_exists: bool,
+    },
+    AddExclusionConstraint {
+        table_name: ObjectName,
+        constraint: Box<ExclusionConstraint>,
+    },
+    CreatePolicy {
+        table_name: ObjectName,
+        policy: Box<Policy>,
+    },
+}
+
+// A statement of `inspect_all`, with the file it comes from and the defaults
+// it is inspected with.
+struct PendingStatement<'s> {
+    filename: &'s String,
+    dialect: Dialect,
+    catalog: Option<String>,
+    search_path: Option<Vec<String>>,
+    stmt: ParsedStatement,
+    loc: Location,
+}
+
+// The statements of a file and the kinds of the unsupported ones skipped while
+// parsing it.
+struct ParsedFile {
+    statements: Vec<(ParsedStatement, Location)>,
+    unsupported: Vec<(&'static str, Location)>,
+}
+
+// A ParsedFile as stored in the parse cache, with locations as (line, column).
+#[derive(Serialize, Deserialize)]
+struct CachedFile<S> {
+    statements: Vec<(S, u64, u64)>,
+    unsupported: Vec<(String, u64, u64)>,
+}
 
 pub struct Inspector<'a> {
     dbinfo: &'a mut Dbinfo,
     filename: Option<String>,
+    file_dialect: Dialect, // the Dbinfo's unless the file has a migi:dialect directive
+    strict: bool,
+    ignore_dml: bool,
+    case_folding: Option<CaseFolding>, // the dialect's of each file when not given
+    include_schemas: Vec<String>,
+    unsupported_statements: Vec<UnsupportedStatement>,
+    keep_going: bool,
+    errors: Vec<InspectionError>,
+    parallel: bool,
+    cache: Option<ParseCache>,
+    events: Option<Vec<SchemaEvent>>, // only collected by inspect_with_events
 }
 
 impl<'a> Inspector<'a> {
     pub fn new(dbinfo: &'a mut Dbinfo) -> Self {
         Self {
+            file_dialect: dbinfo.dialect,
             dbinfo,
             filename: None,
+            strict: false,
+            ignore_dml: false,
+            case_folding: None,
+            include_schemas: Vec::new(),
+            unsupported_statements: Vec::new(),
+            keep_going: false,
+            errors: Vec::new(),
+            parallel: false,
+            cache: None,
+            events: None,
         }
     }
 
+    // An inspector with the strictness, DML handling, case folding and
+    // included schemas of the options.
+    pub fn from_options(dbinfo: &'a mut Dbinfo, options: &Options) -> Self {
+        Self::new(dbinfo)
+            .with_strict(options.strict)
+            .with_ignore_dml(options.ignore_dml)
+            .with_case_folding(options.case_folding)
+            .with_include_schemas(options.include_schemas.clone())
+    }
+
+    // In strict mode, statements that can't be applied to the Dbinfo are errors
+    // instead of being recorded as unsupported.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    // Skips the statements changing rows rather than the schema, such as seed
+    // data, which are otherwise recorded as unsupported.
+    pub fn with_ignore_dml(mut self, ignore_dml: bool) -> Self {
+        self.ignore_dml = ignore_dml;
+        self
+    }
+
+    // Folds unquoted identifiers before they are stored in the Dbinfo, the
+    // way the dialect of each file does by default.
+    pub fn with_case_folding(mut self, case_folding: CaseFolding) -> Self {
+        self.case_folding = Some(case_folding);
+        self
+    }
+
+    // Restricts the inspection to statements affecting these schemas, others
+    // are skipped. An empty list includes every schema.
+    pub fn with_include_schemas(mut self, schemas: Vec<String>) -> Self {
+        self.include_schemas = schemas;
+        self
+    }
+
+    // Records the statements that can't be parsed or inspected and goes on
+    // with the next ones, instead of failing on the first. Strict mode still
+    // fails on the first error.
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    // Parses the files given to `inspect_all` on several threads when there
+    // are at least PARALLEL_MIN_FILES of them. When the CREATE statements of
+    // the files don't depend on each other, each file's are also inspected on
+    // its own thread and the objects they create merged, the other statements
+    // being still applied one at a time, in the same order.
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    // Reuses the statements parsed from files whose contents were already
+    // parsed with the same dialect.
+    pub fn with_cache(mut self, cache: ParseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn unsupported_statements(&self) -> &[UnsupportedStatement] {
+        &self.unsupported_statements
+    }
+
+    pub fn errors(&self) -> &[InspectionError] {
+        &self.errors
+    }
+
+    // The recorded errors followed by the unsupported statements.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let errors = self.errors.iter().map(Diagnostic::from);
+        let unsupported = self.unsupported_statements.iter().map(Diagnostic::from);
+        errors.chain(unsupported).collect()
+    }
+
     pub fn inspect(&mut self, sql: &str, filename: &str) -> Result<()> {
         self.filename = Some(filename.to_string());
+        self.file_dialect = self.dialect_directive(sql)?.unwrap_or(self.dbinfo.dialect);
+
+        let defaults = self.defaults();
+        let result = self.parse(sql).and_then(|stmts| {
+            stmts
+                .into_iter()
+                .try_for_each(|(stmt, loc)| self.inspect_statement(stmt, loc))
+        });
+        self.restore_defaults(&defaults);
+        result
+    }
 
-        let dialect: Box<dyn dialect::Dialect> = match self.dbinfo.dialect {
-            Dialect::PostgreSql => Box::new(PostgreSqlDialect {}),
-            Dialect::MySql => Box::new(MySqlDialect {}),
-            Dialect::SQLite => Box::new(SQLiteDialect {}),
+    // Inspects like `inspect`, also returning the objects defined by the
+    // statements in the order they were applied.
+    pub fn inspect_with_events(&mut self, sql: &str, filename: &str) -> Result<Vec<SchemaEvent>> {
+        self.events = Some(Vec::new());
+        let result = self.inspect(sql, filename);
+        let events = self.events.take().unwrap_or_default();
+        result.map(|_| events)
+    }
+
+    // Inspects several `(filename, sql)` sources as a whole, in two passes:
+    // every CREATE first, databases and schemas before the rest, then the
+    // statements changing existing objects, such as ALTER TABLE, CREATE INDEX
+    // and COMMENT ON, each in file order. A statement is applied with the USE
+    // and SET search_path before it in its file.
+    pub fn inspect_all(&mut self, sources: &[(String, String)]) -> Result<()> {
+        let mut parsed = Vec::new();
+        if self.parallel && sources.len() >= PARALLEL_MIN_FILES {
+            for ((filename, _), stmts) in sources.iter().zip(self.parse_parallel(sources)) {
+                parsed.push((filename, stmts?));
+            }
+        } else {
+            for (filename, sql) in sources {
+                self.filename = Some(filename.clone());
+                parsed.push((filename, self.parse(sql)?));
+            }
+        }
+
+        let mut stmts = Vec::new();
+        for ((filename, parsed), (_, sql)) in parsed.into_iter().zip(sources) {
+            let dialect = self.dialect_directive(sql)?.unwrap_or(self.dbinfo.dialect);
+            let mut catalog = None;
+            let mut search_path = None;
+            for (stmt, loc) in parsed {
+                if let ParsedStatement::Statement(stmt) = &stmt {
+                    if let Statement::Use { db_name } = &**stmt {
+                        catalog = Some(db_name.value.clone());
+                    }
+                    if let Some(schemas) = search_path_schemas(stmt) {
+                        search_path = Some(schemas);
+                    }
+                }
+                stmts.push(PendingStatement {
+                    filename,
+                    dialect,
+                    catalog: catalog.clone(),
+                    search_path: search_path.clone(),
+                    stmt,
+                    loc,
+                });
+            }
+        }
+
+        stmts.sort_by_key(|pending| inspection_pass(&pending.stmt));
+
+        let defaults = self.defaults();
+        let independent = stmts
+            .iter()
+            .all(|pending| inspection_pass(&pending.stmt) != 1 || is_independent(&pending.stmt));
+        let result = if self.parallel && sources.len() >= PARALLEL_MIN_FILES && independent {
+            self.inspect_creates_parallel(stmts, &defaults)
+        } else {
+            stmts
+                .into_iter()
+                .try_for_each(|pending| self.inspect_pending(pending, &defaults))
         };
-        let quote_style = match self.dbinfo.dialect {
-            Dialect::PostgreSql => Some('"'),
-            Dialect::MySql => Some('`'),
-            Dialect::SQLite => Some('`'),
+        self.restore_defaults(&defaults);
+        result
+    }
+
+    fn inspect_pending(&mut self, pending: PendingStatement, defaults: &Defaults) -> Result<()> {
+        self.filename = Some(pending.filename.clone());
+        self.file_dialect = pending.dialect;
+        self.restore_defaults(defaults);
+        if let Some(catalog) = pending.catalog {
+            if self.dbinfo.get_catalog(&catalog).is_ok() {
+                self.dbinfo.default_catalog = catalog;
+            }
+        }
+        // a search_path without any existing schema is reported by the SET
+        // statement itself
+        if let Some(schemas) = pending.search_path {
+            let _ = self.set_search_path(&schemas);
+        }
+        self.inspect_statement(pending.stmt, pending.loc)
+    }
+
+    // Applies the statements sorted by pass, those of the second pass on as
+    // many threads as there are CPUs: the statements of each file create
+    // their objects in a copy of the Dbinfo, which are merged back in file
+    // order. An object created by two files is an error, as it is when they
+    // are inspected one after the other.
+    fn inspect_creates_parallel(
+        &mut self,
+        mut stmts: Vec<PendingStatement>,
+        defaults: &Defaults,
+    ) -> Result<()> {
+        let creates = stmts.partition_point(|pending| inspection_pass(&pending.stmt) == 0);
+        let later = stmts.partition_point(|pending| inspection_pass(&pending.stmt) <= 1);
+        let later = stmts.split_off(later);
+        let creates = stmts.split_off(creates);
+        for pending in stmts {
+            self.inspect_pending(pending, defaults)?;
+        }
+
+        let mut files: Vec<Vec<PendingStatement>> = vec![];
+        for pending in creates {
+            match files.last_mut() {
+                Some(file) if file[0].filename == pending.filename => file.push(pending),
+                _ => files.push(vec![pending]),
+            }
+        }
+        let filenames: Vec<&String> = files.iter().map(|file| file[0].filename).collect();
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = files.len().div_ceil(threads).max(1);
+        let mut chunks: Vec<Vec<Vec<PendingStatement>>> = vec![];
+        for file in files {
+            match chunks.last_mut() {
+                Some(chunk) if chunk.len() < chunk_size => chunk.push(file),
+                _ => chunks.push(vec![file]),
+            }
+        }
+
+        let this = &*self;
+        let inspected: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = chunks
+                .into_iter()
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .into_iter()
+                            .map(|file| {
+                                let mut dbinfo = this.dbinfo.clone();
+                                let mut inspector = this.fork(&mut dbinfo);
+                                let result = file.into_iter().try_for_each(|pending| {
+                                    inspector.inspect_pending(pending, defaults)
+                                });
+                                let unsupported =
+                                    std::mem::take(&mut inspector.unsupported_statements);
+                                let errors = std::mem::take(&mut inspector.errors);
+                                (result.map(|_| dbinfo), unsupported, errors)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let before = self.dbinfo.clone();
+        let mut created: HashMap<String, &String> = HashMap::new();
+        for ((dbinfo, unsupported, errors), filename) in
+            inspected.into_iter().flatten().zip(filenames)
+        {
+            self.unsupported_statements.extend(unsupported);
+            self.errors.extend(errors);
+            let dbinfo = dbinfo?;
+            for object in created_objects(&before, &dbinfo) {
+                if let Some(other) = created.get(&object) {
+                    anyhow::bail!("{} is created in both {} and {}", object, other, filename);
+                }
+                created.insert(object, filename);
+            }
+            self.dbinfo.merge(dbinfo)?;
+        }
+
+        for pending in later {
+            self.inspect_pending(pending, defaults)?;
+        }
+        Ok(())
+    }
+
+    // An inspector with the same settings as this one, for another Dbinfo.
+    fn fork<'b>(&self, dbinfo: &'b mut Dbinfo) -> Inspector<'b> {
+        let mut inspector = Inspector::new(dbinfo)
+            .with_strict(self.strict)
+            .with_ignore_dml(self.ignore_dml)
+            .with_include_schemas(self.include_schemas.clone())
+            .with_keep_going(self.keep_going);
+        inspector.case_folding = self.case_folding;
+        inspector
+    }
+
+    // Parses the sources on as many threads as there are CPUs, each with an
+    // inspector of its own whose unsupported statements and errors are then
+    // added to this one's in file order, up to the first file failing.
+    fn parse_parallel(
+        &mut self,
+        sources: &[(String, String)],
+    ) -> Vec<Result<Vec<(ParsedStatement, Location)>>> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = sources.len().div_ceil(threads);
+        let (dialect, strict, keep_going, case_folding) = (
+            self.dbinfo.dialect,
+            self.strict,
+            self.keep_going,
+            self.case_folding,
+        );
+        let cache = &self.cache;
+
+        let chunks: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = sources
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        // parsing only reads the dialect of the Dbinfo
+                        let mut dbinfo = Dbinfo {
+                            dialect,
+                            default_catalog: String::new(),
+                            catalogs: Default::default(),
+                        };
+                        let mut inspector = Inspector::new(&mut dbinfo)
+                            .with_strict(strict)
+                            .with_keep_going(keep_going);
+                        inspector.case_folding = case_folding;
+                        inspector.cache = cache.clone();
+                        chunk
+                            .iter()
+                            .map(|(filename, sql)| {
+                                inspector.filename = Some(filename.clone());
+                                let parsed = inspector.parse(sql);
+                                let unsupported =
+                                    std::mem::take(&mut inspector.unsupported_statements);
+                                let errors = std::mem::take(&mut inspector.errors);
+                                (parsed, unsupported, errors)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut results = Vec::with_capacity(sources.len());
+        for (parsed, unsupported, errors) in chunks.into_iter().flatten() {
+            let failed = parsed.is_err();
+            self.unsupported_statements.extend(unsupported);
+            self.errors.extend(errors);
+            results.push(parsed);
+            if failed {
+                break;
+            }
+        }
+        results
+    }
+
+    fn parse(&mut self, sql: &str) -> Result<Vec<(ParsedStatement, Location)>> {
+        let Some(cache) = self.cache.clone() else {
+            return Ok(self.parse_file(sql)?.statements);
         };
+        let dialect = self.dbinfo.dialect;
+        let case_folding = self
+            .case_folding
+            .unwrap_or_else(|| CaseFolding::default_for(dialect));
 
-        let tokens = Tokenizer::new(&*dialect, sql).tokenize_with_location()?;
+        if let Some(cached) = cache.get::<CachedFile<ParsedStatement>>(dialect, case_folding, sql) {
+            for (kind, line, column) in cached.unsupported {
+                self.unsupported(&kind, Location { line, column })?;
+            }
+            let statements = cached
+                .statements
+                .into_iter()
+                .map(|(stmt, line, column)| (stmt, Location { line, column }))
+                .collect();
+            return Ok(statements);
+        }
 
-        // FIXME: This is a dirty hack to quote all words that are not keywords.
-        let tokens = tokens
-            .into_iter()
-            .map(|tok| match tok.token {
-                Token::Word(ref w) => {
-                    if w.keyword == Keyword::NoKeyword {
-                        let mut w = w.clone();
-                        w.quote_style = quote_style;
-                        TokenWithLocation {
-                            token: Token::Word(w),
-                            location: tok.location,
-                        }
-                    } else {
-                        tok
+        // files with errors are parsed again, to report them again
+        let errors = self.errors.len();
+        let parsed = self.parse_file(sql)?;
+        if self.errors.len() == errors {
+            let cached = CachedFile {
+                statements: parsed
+                    .statements
+                    .iter()
+                    .map(|(stmt, loc)| (stmt, loc.line, loc.column))
+                    .collect(),
+                unsupported: parsed
+                    .unsupported
+                    .iter()
+                    .map(|(kind, loc)| (kind.to_string(), loc.line, loc.column))
+                    .collect(),
+            };
+            cache.put(dialect, case_folding, sql, &cached);
+        }
+        Ok(parsed.statements)
+    }
+
+    fn parse_file(&mut self, sql: &str) -> Result<ParsedFile> {
+        let file_dialect = self.dialect_directive(sql)?.unwrap_or(self.dbinfo.dialect);
+        let dialect = file_dialect.sqlparser_dialect();
+        let quote_style = Some(file_dialect.quote_char());
+        let case_folding = self
+            .case_folding
+            .unwrap_or_else(|| CaseFolding::default_for(file_dialect));
+
+        let mut tokens = match Tokenizer::new(&*dialect, sql).tokenize_with_location() {
+            Ok(tokens) => tokens,
+            // nothing of the file can be parsed
+            Err(e) if self.keeps_going() => {
+                self.record_error(e.location, anyhow::anyhow!("{}", e.message));
+                return Ok(ParsedFile {
+                    statements: vec![],
+                    unsupported: vec![],
+                });
+            }
+            Err(e) => anyhow::bail!("{} {}", self.location(e.location), e.message),
+        };
+
+        // FIXME: This is a dirty hack to quote all words that are not keywords,
+        // folded first when they weren't quoted in the file.
+        // The tokens are changed in place, as large files have millions.
+        for tok in &mut tokens {
+            if let Token::Word(w) = &mut tok.token {
+                if w.keyword == Keyword::NoKeyword {
+                    if w.quote_style.is_none() && case_folding != CaseFolding::Preserve {
+                        w.value = case_folding.fold(&w.value);
                     }
+                    w.quote_style = quote_style;
                 }
-                _ => tok,
-            })
-            .collect();
+            }
+        }
+
+        let mut exclusions = match file_dialect {
+            Dialect::PostgreSql => take_exclusion_constraints(&mut tokens),
+            _ => HashMap::new(),
+        };
 
         let mut parser = Parser::new(&*dialect).with_tokens_with_locations(tokens);
+        let mut stmts = Vec::new();
+        let mut unsupported = Vec::new();
 
         loop {
             // ignore empty statements
@@ -68,19 +686,487 @@ impl<'a> Inspector<'a> {
                 break;
             }
 
-            let stmt = parser.parse_statement()?;
-            self.inspect_stmt(stmt, tok.location)?;
+            if let Some(kind) = unparsable_statement_kind(&parser) {
+                while !matches!(parser.next_token().token, Token::SemiColon | Token::EOF) {}
+                self.unsupported(kind, tok.location)?;
+                unsupported.push((kind, tok.location));
+                continue;
+            }
+
+            let start = parser.index();
+            let parsed = self
+                .parse_statement(&mut parser, sql, file_dialect, tok.location)
+                .and_then(|stmt| {
+                    let key = (tok.location.line, tok.location.column);
+                    let elements = exclusions.remove(&key).unwrap_or_default();
+                    let mut parsed = vec![stmt];
+                    for tokens in elements {
+                        parsed.push(self.parse_inline_exclusion_constraint(
+                            &parsed[0],
+                            tokens,
+                            &*dialect,
+                            tok.location,
+                        )?);
+                    }
+                    Ok(parsed)
+                });
+            match parsed {
+                Ok(parsed) => stmts.extend(parsed.into_iter().map(|stmt| (stmt, tok.location))),
+                Err(e) if self.keeps_going() => {
+                    self.record_error(tok.location, e);
+                    // resume at the next statement, unless the error was
+                    // raised on the semicolon ending this one
+                    let at_end = parser.index() > start && {
+                        parser.prev_token();
+                        parser.next_token().token == Token::SemiColon
+                    };
+                    if !at_end {
+                        while !matches!(parser.next_token().token, Token::SemiColon | Token::EOF) {}
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(ParsedFile {
+            statements: stmts,
+            unsupported,
+        })
+    }
+
+    // Parses the statement starting at the current token.
+    fn parse_statement(
+        &mut self,
+        parser: &mut Parser,
+        sql: &str,
+        file_dialect: Dialect,
+        loc: Location,
+    ) -> Result<ParsedStatement> {
+        if is_create_domain(parser) {
+            if file_dialect != Dialect::PostgreSql {
+                anyhow::bail!(
+                    "{} CREATE DOMAIN is only supported by PostgreSQL",
+                    self.location(loc)
+                );
+            }
+            let (name, domain) =
+                parse_create_domain(parser).map_err(|e| self.parse_error(loc, e))?;
+            return Ok(ParsedStatement::CreateDomain {
+                name,
+                domain: Box::new(domain),
+            });
+        }
+
+        if is_create_trigger(parser) {
+            let (table_name, trigger, or_replace, if_not_exists) =
+                parse_create_trigger(parser, sql, file_dialect)
+                    .map_err(|e| self.parse_error(loc, e))?;
+            return Ok(ParsedStatement::CreateTrigger {
+                table_name,
+                trigger: Box::new(trigger),
+                or_replace,
+                if_not_exists,
+            });
+        }
+
+        if is_create_policy(parser) {
+            if file_dialect != Dialect::PostgreSql {
+                anyhow::bail!(
+                    "{} CREATE POLICY is only supported by PostgreSQL",
+                    self.location(loc)
+                );
+            }
+            let (table_name, policy) =
+                parse_create_policy(parser).map_err(|e| self.parse_error(loc, e))?;
+            return Ok(ParsedStatement::CreatePolicy {
+                table_name,
+                policy: Box::new(policy),
+            });
+        }
+
+        if let Some((table_name, name)) = parse_validate_constraint(parser) {
+            return Ok(ParsedStatement::ValidateConstraint { table_name, name });
+        }
+
+        if let Some((table_name, n)) = peek_add_exclusion_constraint(parser) {
+            if file_dialect != Dialect::PostgreSql {
+                anyhow::bail!(
+                    "{} EXCLUDE constraints are only supported by PostgreSQL",
+                    self.location(loc)
+                );
+            }
+            for _ in 0..n {
+                parser.next_token();
+            }
+            let constraint =
+                parse_exclusion_constraint(parser).map_err(|e| self.parse_error(loc, e))?;
+            return Ok(ParsedStatement::AddExclusionConstraint {
+                table_name,
+                constraint: Box::new(constraint),
+            });
+        }
+
+        let mut stmt = parser
+            .parse_statement()
+            .map_err(|e| self.parse_error(loc, e))?;
+
+        // sqlparser only parses BigQuery's PARTITION BY, PostgreSQL's
+        // declarative partitioning clause is left after the column list.
+        // `PARTITION OF` tables can't be parsed at all.
+        if let Statement::CreateTable { partition_by, .. } = &mut stmt {
+            if file_dialect == Dialect::PostgreSql
+                && partition_by.is_none()
+                && parser.parse_keywords(&[Keyword::PARTITION, Keyword::BY])
+            {
+                let expr = parser.parse_expr().map_err(|e| self.parse_error(loc, e))?;
+                *partition_by = Some(Box::new(expr));
+            }
+        }
+
+        // Constraints are stored whether they are validated or not.
+        if let Statement::AlterTable { operations, .. } = &stmt {
+            if file_dialect == Dialect::PostgreSql
+                && matches!(
+                    operations.last(),
+                    Some(AlterTableOperation::AddConstraint(_))
+                )
+            {
+                let _ = parser.parse_keywords(&[Keyword::NOT, Keyword::VALID]);
+            }
+        }
+
+        Ok(ParsedStatement::Statement(Box::new(stmt)))
+    }
+
+    // Parses an EXCLUDE constraint taken out of the CREATE TABLE statement it
+    // was declared in.
+    fn parse_inline_exclusion_constraint(
+        &self,
+        create_table: &ParsedStatement,
+        tokens: Vec<TokenWithLocation>,
+        dialect: &dyn sqlparser::dialect::Dialect,
+        loc: Location,
+    ) -> Result<ParsedStatement> {
+        let ParsedStatement::Statement(stmt) = create_table else {
+            anyhow::bail!("{} unexpected EXCLUDE constraint", self.location(loc));
+        };
+        let Statement::CreateTable { name, .. } = &**stmt else {
+            anyhow::bail!("{} unexpected EXCLUDE constraint", self.location(loc));
+        };
+        let mut parser = Parser::new(dialect).with_tokens_with_locations(tokens);
+        let constraint = parse_exclusion_constraint(&mut parser)
+            .and_then(|constraint| {
+                parser.expect_token(&Token::EOF)?;
+                Ok(constraint)
+            })
+            .map_err(|e| self.parse_error(loc, e))?;
+        Ok(ParsedStatement::AddExclusionConstraint {
+            table_name: name.clone(),
+            constraint: Box::new(constraint),
+        })
+    }
+
+    // Looks for a `-- migi:dialect=<name>` comment among the leading comments
+    // of a file, which overrides the configured dialect for that file.
+    fn dialect_directive(&self, sql: &str) -> Result<Option<Dialect>> {
+        for (i, line) in sql.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some(comment) = line.strip_prefix("--") else {
+                break;
+            };
+            if let Some(name) = comment.trim().strip_prefix("migi:dialect=") {
+                let name = name.trim();
+                let dialect = name.parse::<Dialect>().map_err(|_| {
+                    let loc = Location {
+                        line: i as u64 + 1,
+                        column: 1,
+                    };
+                    anyhow::anyhow!("{} unknown dialect: {}", self.location(loc), name)
+                })?;
+                return Ok(Some(dialect));
+            }
         }
+        Ok(None)
+    }
 
+    // Records a statement or clause that can't be applied to the Dbinfo, which
+    // is an error in strict mode.
+    fn unsupported(&mut self, kind: &str, loc: Location) -> Result<()> {
+        let unsupported = UnsupportedStatement {
+            kind: kind.to_string(),
+            location: self.location(loc),
+        };
+        if self.strict {
+            anyhow::bail!("{}", unsupported);
+        }
+        self.unsupported_statements.push(unsupported);
         Ok(())
     }
 
+    fn keeps_going(&self) -> bool {
+        self.keep_going && !self.strict
+    }
+
+    fn record_error(&mut self, loc: Location, e: anyhow::Error) {
+        let location = self.location(loc);
+        let message = e.to_string();
+        // most errors already start with the location of the statement
+        let message = match message.strip_prefix(&format!("{} ", location)) {
+            Some(message) => message.to_string(),
+            None => message,
+        };
+        self.errors.push(InspectionError { location, message });
+    }
+
+    fn inspect_statement(&mut self, stmt: ParsedStatement, loc: Location) -> Result<()> {
+        match self.inspect_parsed(stmt, loc) {
+            Err(e) if self.keeps_going() => {
+                self.record_error(loc, e);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
     fn location(&self, loc: Location) -> String {
-        if let Some(filename) = &self.filename {
-            format!("{}:{}:{}", filename, loc.line, loc.column)
-        } else {
-            format!("{}:{}", loc.line, loc.column)
+        format_location(&self.filename, loc)
+    }
+
+    fn emit(&mut self, loc: Location, event: impl FnOnce(String) -> SchemaEvent) {
+        let location = self.location(loc);
+        if let Some(events) = &mut self.events {
+            events.push(event(location));
+        }
+    }
+
+    // Names are unquoted, as Dbinfo stores them.
+    fn qualified_name(&self, name: &TableName) -> TableName {
+        let catalog = match &name.catalog {
+            Some(catalog) => catalog.value.clone(),
+            None => self.dbinfo.default_catalog.clone(),
+        };
+        let schema = match &name.schema {
+            Some(schema) => Some(schema.value.clone()),
+            None => self
+                .dbinfo
+                .get_catalog(&catalog)
+                .ok()
+                .map(|c| c.default_schema.clone()),
+        };
+        TableName {
+            catalog: Some(Ident::new(catalog)),
+            schema: schema.map(Ident::new),
+            table: Ident::new(&name.table.value),
+        }
+    }
+
+    // Points a parser error at the start of the statement it was raised in.
+    fn parse_error(&self, loc: Location, e: impl fmt::Display) -> anyhow::Error {
+        anyhow::anyhow!("{} {}", self.location(loc), e)
+    }
+
+    fn inspect_parsed(&mut self, stmt: ParsedStatement, loc: Location) -> Result<()> {
+        if !self.is_included(&stmt, loc)? {
+            log::debug!(
+                "{} skipping statement outside the included schemas",
+                self.location(loc)
+            );
+            return Ok(());
+        }
+
+        match stmt {
+            ParsedStatement::Statement(stmt) => {
+                log::debug!("{} {}", self.location(loc), stmt);
+                self.inspect_stmt(*stmt, loc)
+            }
+            ParsedStatement::ValidateConstraint { table_name, name } => {
+                log::debug!(
+                    "{} ALTER TABLE {} VALIDATE CONSTRAINT {}",
+                    self.location(loc),
+                    table_name,
+                    name
+                );
+                let table_name = self.inspect_table_name(table_name, loc)?;
+                let table = self.get_table(&table_name, loc)?;
+                if !table
+                    .constraints
+                    .iter()
+                    .any(|c| constraint_name(c).map(|n| &n.value) == Some(&name.value))
+                {
+                    anyhow::bail!(
+                        "{} constraint does not found: {}",
+                        self.location(loc),
+                        name.value
+                    );
+                }
+                Ok(())
+            }
+            ParsedStatement::CreateDomain { name, domain } => {
+                log::debug!("{} CREATE DOMAIN {}", self.location(loc), name);
+                let name = self.inspect_table_name(name, loc)?;
+                let schema = self
+                    .dbinfo
+                    .get_schema_of(&name)
+                    .map_err(|e| anyhow::anyhow!("{} {}: {}", self.location(loc), e, name))?;
+                if schema.domains.contains_key(&name.table.value) {
+                    anyhow::bail!("{} domain {} already exists", self.location(loc), name);
+                }
+                self.dbinfo.add_domain(&name, *domain)?;
+                let name = self.qualified_name(&name);
+                self.emit(loc, |location| SchemaEvent::DomainDefined {
+                    name,
+                    location,
+                });
+                Ok(())
+            }
+            ParsedStatement::CreateTrigger {
+                table_name,
+                trigger,
+                or_replace,
+                if_not_exists,
+            } => {
+                log::debug!(
+                    "{} CREATE TRIGGER {} ON {}",
+                    self.location(loc),
+                    trigger.name,
+                    table_name
+                );
+                let table_name = self.inspect_table_name(table_name, loc)?;
+                let location = self.location(loc);
+                let table = self.get_table_mut(&table_name, loc)?;
+                let trigger_name = trigger.name.clone();
+                match table.triggers.iter().position(|t| t.name == trigger.name) {
+                    Some(_) if if_not_exists => return Ok(()),
+                    Some(i) if or_replace => table.triggers[i] = *trigger,
+                    Some(_) => {
+                        anyhow::bail!("{} trigger {} already exists", location, trigger.name)
+                    }
+                    None => table.triggers.push(*trigger),
+                }
+                let table = self.qualified_name(&table_name);
+                self.emit(loc, |location| SchemaEvent::TriggerDefined {
+                    table,
+                    name: trigger_name,
+                    location,
+                });
+                Ok(())
+            }
+            ParsedStatement::AddExclusionConstraint {
+                table_name,
+                constraint,
+            } => {
+                log::debug!(
+                    "{} ALTER TABLE {} ADD {}",
+                    self.location(loc),
+                    table_name,
+                    constraint
+                );
+                let table_name = self.inspect_table_name(table_name, loc)?;
+                let location = self.location(loc);
+                let table = self.get_table_mut(&table_name, loc)?;
+                let name = constraint
+                    .name
+                    .as_ref()
+                    .map_or_else(|| constraint.default_name(&table.name), |n| n.value.clone());
+                if table
+                    .constraints
+                    .iter()
+                    .any(|c| constraint_name(c).map(|n| &n.value) == Some(&name))
+                    || table
+                        .exclusion_constraints
+                        .iter()
+                        .any(|c| c.name.as_ref().map(|n| &n.value) == Some(&name))
+                {
+                    anyhow::bail!("{} constraint {} already exists", location, name);
+                }
+                table.exclusion_constraints.push(*constraint);
+                Ok(())
+            }
+            ParsedStatement::CreatePolicy { table_name, policy } => {
+                log::debug!(
+                    "{} CREATE POLICY {} ON {}",
+                    self.location(loc),
+                    policy.name,
+                    table_name
+                );
+                let table_name = self.inspect_table_name(table_name, loc)?;
+                let location = self.location(loc);
+                let table = self.get_table_mut(&table_name, loc)?;
+                if table.policies.iter().any(|p| p.name == policy.name) {
+                    anyhow::bail!("{} policy {} already exists", location, policy.name);
+                }
+                table.policies.push(*policy);
+                Ok(())
+            }
+        }
+    }
+
+    // Statements which are not bound to a schema are always included.
+    fn is_included(&self, stmt: &ParsedStatement, loc: Location) -> Result<bool> {
+        if self.include_schemas.is_empty() {
+            return Ok(true);
+        }
+
+        let name = match stmt {
+            ParsedStatement::ValidateConstraint { table_name, .. } => table_name.clone(),
+            ParsedStatement::CreateDomain { name, .. } => name.clone(),
+            ParsedStatement::CreateTrigger { table_name, .. } => table_name.clone(),
+            ParsedStatement::AddExclusionConstraint { table_name, .. } => table_name.clone(),
+            ParsedStatement::CreatePolicy { table_name, .. } => table_name.clone(),
+            ParsedStatement::Statement(stmt) => match &**stmt {
+                Statement::CreateTable { name, .. }
+                | Statement::CreateView { name, .. }
+                | Statement::CreateFunction { name, .. }
+                | Statement::AlterTable { name, .. }
+                | Statement::CreateIndex {
+                    table_name: name, ..
+                } => name.clone(),
+                Statement::Comment {
+                    object_type: CommentObject::Table,
+                    object_name,
+                    ..
+                } => object_name.clone(),
+                Statement::Comment {
+                    object_type: CommentObject::Column,
+                    object_name,
+                    ..
+                } => {
+                    let parts = &object_name.0[..object_name.0.len().saturating_sub(1)];
+                    ObjectName(parts.to_vec())
+                }
+                Statement::CreateSchema {
+                    schema_name: SchemaName::Simple(name) | SchemaName::NamedAuthorization(name, _),
+                    ..
+                } if self.file_dialect == Dialect::PostgreSql => {
+                    return Ok(name
+                        .0
+                        .last()
+                        .is_none_or(|n| self.include_schemas.contains(&n.value)));
+                }
+                _ => return Ok(true),
+            },
+        };
+        // invalid names are reported when the statement is inspected
+        if name.0.is_empty() {
+            return Ok(true);
         }
+
+        let (catalog, schema, table) = self.inspect_object_name(name, loc)?;
+        let schema = match schema {
+            Some(schema) => schema.value,
+            None => match self.dbinfo.get_schema_of(&TableName {
+                catalog,
+                schema: None,
+                table,
+            }) {
+                Ok(schema) => schema.name.clone(),
+                Err(_) => return Ok(true),
+            },
+        };
+        Ok(self.include_schemas.contains(&schema))
     }
 
     fn inspect_stmt(&mut self, stmt: Statement, loc: Location) -> Result<()> {
@@ -98,25 +1184,143 @@ impl<'a> Inspector<'a> {
                 if_not_exists,
                 temporary,
             } => {
-                todo!()
-            }
-            Statement::CreateTable {
-                // or_replace,
-                temporary,
-                external,
-                // global,
-                // if_not_exists,
-                transient,
-                name,
-                columns,
-                constraints,
-                table_properties,
-                with_options,
-                file_format,
-                location,
-                query,
-                without_rowid,
-                like,
+                if temporary {
+                    return self.unsupported("CREATE TEMPORARY VIEW", loc);
+                }
+                if options != CreateTableOptions::None {
+                    return self.unsupported("CREATE VIEW ... WITH/OPTIONS", loc);
+                }
+                if !cluster_by.is_empty() {
+                    return self.unsupported("CREATE VIEW ... CLUSTER BY", loc);
+                }
+                if with_no_schema_binding {
+                    return self.unsupported("CREATE VIEW ... WITH NO SCHEMA BINDING", loc);
+                }
+
+                let view_name = self.inspect_table_name(name, loc)?;
+
+                if self.dbinfo.get_view(&view_name).is_ok() && !or_replace {
+                    if if_not_exists {
+                        return Ok(());
+                    }
+                    anyhow::bail!(
+                        "{} view {} already exists",
+                        self.location(loc),
+                        view_name.table
+                    );
+                }
+
+                let columns = columns
+                    .into_iter()
+                    .map(|col| ViewColumn {
+                        name: col.name.value,
+                        data_type: col.data_type,
+                        options: col.options.unwrap_or_default(),
+                    })
+                    .collect();
+
+                let view = View {
+                    name: view_name.table.value.clone(),
+                    materialized,
+                    columns,
+                    query,
+                    comment,
+                    indexes: vec![],
+                };
+
+                self.dbinfo
+                    .add_view(&view_name, view)
+                    .map_err(|e| anyhow::anyhow!("{} {}", self.location(loc), e))?;
+                let name = self.qualified_name(&view_name);
+                self.emit(loc, |location| SchemaEvent::ViewDefined { name, location });
+            }
+            Statement::CreateFunction {
+                or_replace,
+                temporary,
+                if_not_exists,
+                name,
+                args,
+                return_type,
+                function_body,
+                behavior,
+                called_on_null,
+                parallel,
+                using,
+                language,
+                determinism_specifier,
+                options,
+                remote_connection,
+            } => {
+                if temporary {
+                    return self.unsupported("CREATE TEMPORARY FUNCTION", loc);
+                }
+                if using.is_some()
+                    || determinism_specifier.is_some()
+                    || options.is_some()
+                    || remote_connection.is_some()
+                {
+                    return self.unsupported("CREATE FUNCTION", loc);
+                }
+
+                let routine_name = self.inspect_table_name(name, loc)?;
+                // The body is kept as the string literal it was written as.
+                let body = function_body.map(|body| match body {
+                    CreateFunctionBody::AsBeforeOptions(expr)
+                    | CreateFunctionBody::AsAfterOptions(expr)
+                    | CreateFunctionBody::Return(expr) => match expr {
+                        Expr::Value(Value::DollarQuotedString(s)) => s.value,
+                        Expr::Value(Value::SingleQuotedString(s)) => s,
+                        expr => expr.to_string(),
+                    },
+                });
+                let routine = Routine {
+                    name: routine_name.table.value.clone(),
+                    args: args.unwrap_or_default(),
+                    return_type,
+                    language: language.map(|language| language.value),
+                    behavior,
+                    called_on_null,
+                    parallel,
+                    body,
+                };
+
+                let signature = routine.signature();
+                let location = self.location(loc);
+                let schema = self
+                    .dbinfo
+                    .get_schema_of_mut(&routine_name)
+                    .map_err(|e| anyhow::anyhow!("{} {}", location, e))?;
+                if schema.routines.contains_key(&signature) && !or_replace {
+                    if if_not_exists {
+                        return Ok(());
+                    }
+                    anyhow::bail!("{} function {} already exists", location, signature);
+                }
+                schema.routines.insert(signature.clone(), routine);
+                let name = self.qualified_name(&routine_name);
+                self.emit(loc, |location| SchemaEvent::FunctionDefined {
+                    name,
+                    signature,
+                    location,
+                });
+            }
+            Statement::CreateTable {
+                // or_replace,
+                temporary,
+                external,
+                // global,
+                // if_not_exists,
+                transient,
+                name,
+                columns,
+                constraints,
+                table_properties,
+                with_options,
+                file_format,
+                location,
+                query,
+                without_rowid,
+                like,
                 clone,
                 engine,
                 comment,
@@ -133,81 +1337,60 @@ impl<'a> Inspector<'a> {
                 ..
             } => {
                 if temporary {
-                    anyhow::bail!(
-                        "{} CREATE TEMPORARY TABLE is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TEMPORARY TABLE", loc);
                 }
                 if external {
-                    anyhow::bail!(
-                        "{} CREATE EXTERNAL TABLE is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE EXTERNAL TABLE", loc);
                 }
                 if transient {
-                    anyhow::bail!(
-                        "{} CREATE TRANSIENT TABLE is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TRANSIENT TABLE", loc);
                 }
                 if !table_properties.is_empty() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... TBLPROPERTIES is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... TBLPROPERTIES", loc);
                 }
                 if file_format.is_some() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... STORED AS is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... STORED AS", loc);
                 }
                 if location.is_some() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... LOCATION is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... LOCATION", loc);
                 }
                 if query.is_some() {
-                    anyhow::bail!("{} CREATE TABLE AS is not supported", self.location(loc));
+                    return self.unsupported("CREATE TABLE AS", loc);
                 }
                 if clone.is_some() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... CLONE is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... CLONE", loc);
                 }
                 if on_cluster.is_some() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... ON CLUSTER is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... ON CLUSTER", loc);
                 }
                 if cluster_by.is_some() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... CLUSTER BY is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... CLUSTER BY", loc);
                 }
 
                 let table_name = self.inspect_table_name(name, loc)?;
 
+                let mut columns = columns;
+                let mut column_constraints: Vec<TableConstraint> = columns
+                    .iter_mut()
+                    .flat_map(take_column_constraints)
+                    .collect();
+                column_constraints.extend(constraints);
+                let constraints = column_constraints;
+
                 let columns = columns
                     .into_iter()
                     .map(|col| self.inspect_column(col, loc))
                     .collect::<Result<Vec<Column>>>()?;
 
                 if like.is_some() {
-                    anyhow::bail!(
-                        "{} CREATE TABLE ... LIKE is not supported",
-                        self.location(loc)
-                    );
+                    return self.unsupported("CREATE TABLE ... LIKE", loc);
                 }
 
                 let table = Table {
                     name: table_name.table.value.clone(),
                     columns,
                     constraints,
+                    indexes: vec![],
                     with_options,
                     without_rowid,
                     engine,
@@ -220,9 +1403,28 @@ impl<'a> Inspector<'a> {
                     partition_by,
                     options,
                     strict,
+                    triggers: vec![],
+                    exclusion_constraints: vec![],
+                    policies: vec![],
                 };
 
-                self.dbinfo.add_table(&table_name, table);
+                let column_names: Vec<String> =
+                    table.columns.iter().map(|c| c.name.clone()).collect();
+                self.dbinfo
+                    .add_table(&table_name, table)
+                    .map_err(|e| anyhow::anyhow!("{} {}", self.location(loc), e))?;
+                let name = self.qualified_name(&table_name);
+                self.emit(loc, |location| SchemaEvent::TableDefined {
+                    name: name.clone(),
+                    location,
+                });
+                for column in column_names {
+                    self.emit(loc, |location| SchemaEvent::ColumnDefined {
+                        table: name.clone(),
+                        name: column,
+                        location,
+                    });
+                }
             }
             Statement::CreateIndex {
                 name,
@@ -231,20 +1433,128 @@ impl<'a> Inspector<'a> {
                 columns,
                 unique,
                 concurrently,
-                // if_not_exists,
+                if_not_exists,
                 include,
                 nulls_distinct,
                 predicate,
                 ..
             } => {
-                todo!()
+                let table_name = self.inspect_table_name(table_name, loc)?;
+                // Postgres materialized views take indexes as tables do.
+                let view = self
+                    .dbinfo
+                    .get_view(&table_name)
+                    .ok()
+                    .filter(|view| view.materialized);
+                let (relation, indexes) = match view {
+                    Some(view) => (&view.name, &view.indexes),
+                    None => {
+                        let table = self.get_table(&table_name, loc)?;
+                        (&table.name, &table.indexes)
+                    }
+                };
+
+                let name = match name {
+                    Some(name) => {
+                        let (catalog, schema, index) = self.inspect_object_name(name, loc)?;
+                        if catalog.is_some() || schema.is_some() {
+                            let index_name = TableName {
+                                catalog,
+                                schema,
+                                table: index.clone(),
+                            };
+                            let same_schema =
+                                self.dbinfo.get_schema_of(&index_name).is_ok_and(|schema| {
+                                    std::ptr::eq(
+                                        schema,
+                                        self.dbinfo.get_schema_of(&table_name).unwrap(),
+                                    )
+                                });
+                            if !same_schema {
+                                anyhow::bail!(
+                                    "{} index {} must be in the same schema as table {}",
+                                    self.location(loc),
+                                    index_name,
+                                    table_name
+                                );
+                            }
+                        }
+                        index.value
+                    }
+                    None => default_index_name(relation, &columns),
+                };
+
+                if indexes.iter().any(|index| index.name == name) {
+                    if if_not_exists {
+                        return Ok(());
+                    }
+                    anyhow::bail!("{} index {} already exists", self.location(loc), name);
+                }
+
+                let index = Index {
+                    name,
+                    unique,
+                    using,
+                    columns,
+                    include,
+                    nulls_distinct,
+                    predicate,
+                    concurrently,
+                };
+
+                let index_name = index.name.clone();
+                if view.is_some() {
+                    self.dbinfo.get_view_mut(&table_name)?.indexes.push(index);
+                } else {
+                    self.get_table_mut(&table_name, loc)?.indexes.push(index);
+                }
+                let table = self.qualified_name(&table_name);
+                self.emit(loc, |location| SchemaEvent::IndexDefined {
+                    table,
+                    name: index_name,
+                    location,
+                });
             }
             Statement::CreateSchema {
                 schema_name,
                 // if_not_exists,
                 ..
             } => {
-                todo!()
+                let name = match schema_name {
+                    SchemaName::Simple(name) => name,
+                    SchemaName::NamedAuthorization(name, _) => name,
+                    SchemaName::UnnamedAuthorization(_) => {
+                        return self
+                            .unsupported("CREATE SCHEMA AUTHORIZATION without a schema name", loc);
+                    }
+                };
+
+                match self.file_dialect {
+                    // MySQL treats CREATE SCHEMA as a synonym for CREATE DATABASE.
+                    Dialect::MySql | Dialect::MariaDb => self.inspect_create_database(name, loc)?,
+                    Dialect::PostgreSql => {
+                        let (catalog_name, schema_name) = match name.0.len() {
+                            1 => (self.dbinfo.default_catalog.clone(), name.0[0].value.clone()),
+                            2 => (name.0[0].value.clone(), name.0[1].value.clone()),
+                            _ => anyhow::bail!(
+                                "{} invalid schema name: {:?}",
+                                self.location(loc),
+                                name
+                            ),
+                        };
+
+                        let filename = &self.filename;
+                        let catalog = self.dbinfo.get_catalog_mut(&catalog_name).map_err(|e| {
+                            anyhow::anyhow!("{} {}", format_location(filename, loc), e)
+                        })?;
+                        if !catalog.schemas.contains_key(&schema_name) {
+                            catalog.add_schema(&schema_name, Schema::new(&schema_name));
+                        }
+                    }
+                    Dialect::SQLite => {
+                        return self.unsupported("CREATE SCHEMA", loc);
+                    }
+                }
             }
             Statement::CreateDatabase {
                 db_name,
@@ -253,121 +1563,2727 @@ impl<'a> Inspector<'a> {
                 // if_not_exists,
                 ..
             } => {
-                todo!()
+                if location.is_some() || managed_location.is_some() {
+                    return self.unsupported("CREATE DATABASE ... LOCATION", loc);
+                }
+
+                self.inspect_create_database(db_name, loc)?;
             }
             Statement::AlterTable {
                 name,
                 // if_exists,
-                only,
+                // only,
                 operations,
                 location,
                 ..
             } => {
-                todo!()
+                if location.is_some() {
+                    return self.unsupported("ALTER TABLE ... SET LOCATION", loc);
+                }
+
+                let table_name = self.inspect_table_name(name, loc)?;
+
+                for operation in operations {
+                    self.inspect_alter_table_operation(&table_name, operation, loc)?;
+                }
             }
-            Statement::CreateExtension {
-                name,
-                // if_not_exists,
-                cascade,
-                schema,
-                version,
-                ..
-            } => {
-                todo!()
+            Statement::Comment {
+                object_type,
+                object_name,
+                comment,
+                if_exists,
+            } => match object_type {
+                CommentObject::Table => {
+                    let table_name = self.inspect_table_name(object_name, loc)?;
+                    if if_exists && self.dbinfo.get_table(&table_name).is_err() {
+                        return Ok(());
+                    }
+
+                    self.get_table_mut(&table_name, loc)?.comment = comment;
+                }
+                CommentObject::Column => {
+                    let mut parts = object_name.0;
+                    let column_name = match parts.pop() {
+                        Some(column_name) if !parts.is_empty() => column_name,
+                        _ => {
+                            anyhow::bail!("{} invalid column name: {:?}", self.location(loc), parts)
+                        }
+                    };
+                    let table_name = self.inspect_table_name(ObjectName(parts), loc)?;
+                    if if_exists && self.dbinfo.get_table(&table_name).is_err() {
+                        return Ok(());
+                    }
+
+                    let column = self.get_column_mut(&table_name, &column_name.value, loc)?;
+                    column
+                        .options
+                        .retain(|o| !matches!(o.option, ColumnOption::Comment(_)));
+                    if let Some(comment) = comment {
+                        column.options.push(ColumnOptionDef {
+                            name: None,
+                            option: ColumnOption::Comment(comment),
+                        });
+                    }
+                    column.normalize_options();
+                }
+            },
+            Statement::Use { db_name } => {
+                if !matches!(self.file_dialect, Dialect::MySql | Dialect::MariaDb) {
+                    return self.unsupported("USE", loc);
+                }
+                self.dbinfo.get_catalog(&db_name.value).map_err(|e| {
+                    anyhow::anyhow!("{} {}: {}", self.location(loc), e, db_name.value)
+                })?;
+                self.dbinfo.default_catalog = db_name.value;
+            }
+            Statement::SetVariable { .. } if self.file_dialect == Dialect::PostgreSql => {
+                let Some(schemas) = search_path_schemas(&stmt) else {
+                    return self.unsupported(&statement_kind(&stmt), loc);
+                };
+                self.set_search_path(&schemas)
+                    .map_err(|e| anyhow::anyhow!("{} {}", self.location(loc), e))?;
+            }
+            Statement::Insert(_)
+            | Statement::Update { .. }
+            | Statement::Delete(_)
+            | Statement::Truncate { .. }
+                if self.ignore_dml =>
+            {
+                log::debug!("{} ignoring {}", self.location(loc), statement_kind(&stmt));
             }
             _ => {
-                println!("Other statement: {:?}", stmt);
+                return self.unsupported(&statement_kind(&stmt), loc);
             }
         }
         Ok(())
     }
 
-    fn inspect_table_name(&self, name: ObjectName, loc: Location) -> Result<TableName> {
-        match name.0.len() {
-            1 => Ok(TableName {
-                catalog: None,
-                schema: None,
-                table: name.0[0].clone(),
-            }),
-            2 => match self.dbinfo.dialect {
-                Dialect::PostgreSql => Ok(TableName {
-                    catalog: None,
-                    schema: Some(name.0[0].clone()),
-                    table: name.0[1].clone(),
-                }),
-                Dialect::MySql => Ok(TableName {
-                    catalog: Some(name.0[0].clone()),
-                    schema: None,
-                    table: name.0[1].clone(),
-                }),
-                Dialect::SQLite => {
-                    anyhow::bail!("{} invalid table name: {:?}", self.location(loc), name)
+    // Unqualified objects are created in the first schema of the search_path
+    // that exists.
+    fn set_search_path(&mut self, schemas: &[String]) -> Result<()> {
+        let catalog_name = self.dbinfo.default_catalog.clone();
+        let catalog = self.dbinfo.get_catalog_mut(&catalog_name)?;
+        let Some(schema) = schemas.iter().find(|s| catalog.schemas.contains_key(*s)) else {
+            anyhow::bail!(
+                "no schema of the search_path exists: {}",
+                schemas.join(", ")
+            );
+        };
+        catalog.default_schema = schema.clone();
+        Ok(())
+    }
+
+    fn defaults(&self) -> Defaults {
+        Defaults {
+            catalog: self.dbinfo.default_catalog.clone(),
+            schemas: self
+                .dbinfo
+                .catalogs
+                .values()
+                .map(|c| (c.name.clone(), c.default_schema.clone()))
+                .collect(),
+        }
+    }
+
+    // Catalogs created since the defaults were taken keep their default schema.
+    fn restore_defaults(&mut self, defaults: &Defaults) {
+        self.dbinfo.default_catalog = defaults.catalog.clone();
+        for (name, schema) in &defaults.schemas {
+            if let Ok(catalog) = self.dbinfo.get_catalog_mut(name) {
+                catalog.default_schema = schema.clone();
+            }
+        }
+    }
+
+    fn inspect_create_database(&mut self, name: ObjectName, loc: Location) -> Result<()> {
+        if name.0.len() != 1 {
+            anyhow::bail!("{} invalid database name: {:?}", self.location(loc), name);
+        }
+
+        let name = &name.0[0].value;
+        if self.dbinfo.get_catalog(name).is_err() {
+            // In MySQL the database is itself the schema its tables belong to.
+            let default_schema = self.dbinfo.dialect.default_schema(name);
+            self.dbinfo
+                .add_catalog(name, Catalog::new(name, &default_schema));
+        }
+
+        Ok(())
+    }
+
+    fn inspect_alter_table_operation(
+        &mut self,
+        table_name: &TableName,
+        operation: AlterTableOperation,
+        loc: Location,
+    ) -> Result<()> {
+        match operation {
+            AlterTableOperation::AddColumn {
+                mut column_def,
+                column_position,
+                if_not_exists,
+                ..
+            } => {
+                let table = self.get_table(table_name, loc)?;
+                if table.get_column(&column_def.name.value).is_ok() {
+                    if if_not_exists {
+                        return Ok(());
+                    }
+                    anyhow::bail!(
+                        "{} column {} already exists",
+                        self.location(loc),
+                        column_def.name.value
+                    );
                 }
-            },
-            3 => match self.dbinfo.dialect {
-                Dialect::PostgreSql => Ok(TableName {
-                    catalog: Some(name.0[0].clone()),
-                    schema: Some(name.0[1].clone()),
-                    table: name.0[2].clone(),
-                }),
-                Dialect::MySql => {
-                    anyhow::bail!("{} invalid table name: {:?}", self.location(loc), name)
-                }
-                Dialect::SQLite => {
-                    anyhow::bail!("{} invalid table name: {:?}", self.location(loc), name)
+                let constraints = take_column_constraints(&mut column_def);
+                let column = self.inspect_column(column_def, loc)?;
+                let column_name = column.name.clone();
+                let table = self.get_table_mut(table_name, loc)?;
+                add_constraints(table, constraints);
+                match column_insert_position(table, column_position.as_ref()) {
+                    Ok(position) => table.columns.insert(position, column),
+                    Err(after) => {
+                        anyhow::bail!("{} column does not found: {}", self.location(loc), after)
+                    }
                 }
-            },
-            _ => anyhow::bail!("{} invalid table name: {:?}", self.location(loc), name),
+                let table = self.qualified_name(table_name);
+                self.emit(loc, |location| SchemaEvent::ColumnDefined {
+                    table,
+                    name: column_name,
+                    location,
+                });
+            }
+            AlterTableOperation::DropColumn {
+                column_name,
+                if_exists,
+                ..
+            } => {
+                let table = self.get_table_mut(table_name, loc)?;
+                let Some(i) = table
+                    .columns
+                    .iter()
+                    .position(|c| c.name == column_name.value)
+                else {
+                    if if_exists {
+                        return Ok(());
+                    }
+                    anyhow::bail!(
+                        "{} column does not found: {}",
+                        self.location(loc),
+                        column_name.value
+                    );
+                };
+                table.columns.remove(i);
+            }
+            AlterTableOperation::RenameColumn {
+                old_column_name,
+                new_column_name,
+            } => {
+                self.get_column_mut(table_name, &old_column_name.value, loc)?
+                    .name = new_column_name.value;
+            }
+            AlterTableOperation::RenameTable {
+                table_name: new_name,
+            } => {
+                let new_name = self.inspect_table_name(new_name, loc)?;
+                let mut table = self.get_table(table_name, loc)?.clone();
+                table.name = new_name.table.value.clone();
+                self.dbinfo
+                    .add_table(&new_name, table)
+                    .map_err(|e| anyhow::anyhow!("{} {}", self.location(loc), e))?;
+                self.dbinfo
+                    .get_schema_of_mut(table_name)?
+                    .tables
+                    .remove(&table_name.table.value);
+            }
+            AlterTableOperation::AlterColumn { column_name, op } => {
+                let column = self.get_column_mut(table_name, &column_name.value, loc)?;
+                match op {
+                    AlterColumnOperation::SetNotNull => {
+                        column.options.retain(|o| {
+                            !matches!(o.option, ColumnOption::Null | ColumnOption::NotNull)
+                        });
+                        column.options.push(ColumnOptionDef {
+                            name: None,
+                            option: ColumnOption::NotNull,
+                        });
+                        column.normalize_options();
+                    }
+                    AlterColumnOperation::DropNotNull => {
+                        column
+                            .options
+                            .retain(|o| !matches!(o.option, ColumnOption::NotNull));
+                    }
+                    AlterColumnOperation::SetDefault { value } => {
+                        column
+                            .options
+                            .retain(|o| !matches!(o.option, ColumnOption::Default(_)));
+                        column.options.push(ColumnOptionDef {
+                            name: None,
+                            option: ColumnOption::Default(value),
+                        });
+                        column.normalize_options();
+                    }
+                    AlterColumnOperation::DropDefault => {
+                        column
+                            .options
+                            .retain(|o| !matches!(o.option, ColumnOption::Default(_)));
+                    }
+                    AlterColumnOperation::SetDataType { data_type, .. } => {
+                        column.data_type = data_type;
+                    }
+                    op => {
+                        let kind = format!("ALTER TABLE ... ALTER COLUMN {} {}", column_name, op);
+                        return self.unsupported(&kind, loc);
+                    }
+                }
+            }
+            AlterTableOperation::ModifyColumn {
+                col_name,
+                data_type,
+                options,
+                column_position,
+            } => {
+                self.inspect_redefine_column(
+                    table_name,
+                    &col_name.value,
+                    ColumnDef {
+                        name: col_name.clone(),
+                        data_type,
+                        collation: None,
+                        options: into_column_option_defs(options),
+                    },
+                    column_position.as_ref(),
+                    loc,
+                )?;
+            }
+            AlterTableOperation::ChangeColumn {
+                old_name,
+                new_name,
+                data_type,
+                options,
+                column_position,
+            } => {
+                self.inspect_redefine_column(
+                    table_name,
+                    &old_name.value,
+                    ColumnDef {
+                        name: new_name,
+                        data_type,
+                        collation: None,
+                        options: into_column_option_defs(options),
+                    },
+                    column_position.as_ref(),
+                    loc,
+                )?;
+            }
+            AlterTableOperation::AddConstraint(constraint) => {
+                let table = self.get_table(table_name, loc)?;
+                if let Some(name) = constraint_name(&constraint) {
+                    if table
+                        .constraints
+                        .iter()
+                        .any(|c| constraint_name(c).map(|n| &n.value) == Some(&name.value))
+                    {
+                        anyhow::bail!(
+                            "{} constraint already exists: {}",
+                            self.location(loc),
+                            name.value
+                        );
+                    }
+                }
+                self.get_table_mut(table_name, loc)?
+                    .constraints
+                    .push(constraint);
+            }
+            AlterTableOperation::DropConstraint {
+                if_exists, name, ..
+            } => {
+                let table = self.get_table_mut(table_name, loc)?;
+                let exclusion = table.exclusion_constraints.iter().position(|c| {
+                    c.name.as_ref().map_or_else(
+                        || c.default_name(&table.name) == name.value,
+                        |n| n.value == name.value,
+                    )
+                });
+                match table
+                    .constraints
+                    .iter()
+                    .position(|c| constraint_name(c).map(|n| &n.value) == Some(&name.value))
+                {
+                    Some(i) => {
+                        table.constraints.remove(i);
+                    }
+                    None if exclusion.is_some() => {
+                        table.exclusion_constraints.remove(exclusion.unwrap());
+                    }
+                    None if if_exists => {}
+                    None => {
+                        anyhow::bail!(
+                            "{} constraint does not found: {}",
+                            self.location(loc),
+                            name.value
+                        );
+                    }
+                }
+            }
+            operation => {
+                return self.unsupported(&format!("ALTER TABLE ... {}", operation), loc);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn inspect_redefine_column(
+        &mut self,
+        table_name: &TableName,
+        old_name: &str,
+        column_def: ColumnDef,
+        column_position: Option<&MySQLColumnPosition>,
+        loc: Location,
+    ) -> Result<()> {
+        let mut column_def = column_def;
+        let constraints = take_column_constraints(&mut column_def);
+        let column = self.inspect_column(column_def, loc)?;
+        let table = self.get_table_mut(table_name, loc)?;
+        let Some(i) = table.columns.iter().position(|c| c.name == old_name) else {
+            anyhow::bail!("{} column does not found: {}", self.location(loc), old_name);
+        };
+
+        if column_position.is_some() {
+            let previous = table.columns.remove(i);
+            match column_insert_position(table, column_position) {
+                Ok(position) => table.columns.insert(position, column),
+                Err(after) => {
+                    table.columns.insert(i, previous);
+                    anyhow::bail!("{} column does not found: {}", self.location(loc), after);
+                }
+            }
+        } else {
+            table.columns[i] = column;
+        }
+        add_constraints(table, constraints);
+
+        Ok(())
+    }
+
+    fn get_table(&self, name: &TableName, loc: Location) -> Result<&Table> {
+        self.dbinfo
+            .get_table(name)
+            .map_err(|e| anyhow::anyhow!("{} {}: {}", self.location(loc), e, name))
+    }
+
+    fn get_table_mut(&mut self, name: &TableName, loc: Location) -> Result<&mut Table> {
+        let filename = &self.filename;
+        self.dbinfo
+            .get_table_mut(name)
+            .map_err(|e| anyhow::anyhow!("{} {}: {}", format_location(filename, loc), e, name))
+    }
+
+    fn get_column_mut(
+        &mut self,
+        table_name: &TableName,
+        column_name: &str,
+        loc: Location,
+    ) -> Result<&mut Column> {
+        let filename = &self.filename;
+        self.dbinfo
+            .get_table_mut(table_name)
+            .map_err(|e| {
+                anyhow::anyhow!("{} {}: {}", format_location(filename, loc), e, table_name)
+            })?
+            .get_column_mut(column_name)
+            .map_err(|e| {
+                anyhow::anyhow!("{} {}: {}", format_location(filename, loc), e, column_name)
+            })
+    }
+
+    fn inspect_table_name(&self, name: ObjectName, loc: Location) -> Result<TableName> {
+        let (catalog, schema, table) = self.inspect_object_name(name, loc)?;
+        Ok(TableName {
+            catalog,
+            schema,
+            table,
+        })
+    }
+
+    // Splits a possibly qualified object name into its catalog, schema and
+    // object parts according to the dialect's naming rules.
+    fn inspect_object_name(
+        &self,
+        name: ObjectName,
+        loc: Location,
+    ) -> Result<(Option<Ident>, Option<Ident>, Ident)> {
+        let mut parts = name.0.clone().into_iter();
+        match (parts.len(), self.file_dialect) {
+            (1, _) => Ok((None, None, parts.next().unwrap())),
+            (2, Dialect::PostgreSql) => Ok((None, parts.next(), parts.next().unwrap())),
+            (2, Dialect::MySql | Dialect::MariaDb) => {
+                Ok((parts.next(), None, parts.next().unwrap()))
+            }
+            (3, Dialect::PostgreSql) => Ok((parts.next(), parts.next(), parts.next().unwrap())),
+            _ => anyhow::bail!("{} invalid object name: {}", self.location(loc), name),
         }
     }
 
     fn inspect_column(&self, column: ColumnDef, _loc: Location) -> Result<Column> {
-        let column = Column {
+        let mut data_type = column.data_type;
+        let mut identity = None;
+
+        // SERIAL types are shorthands for an integer column backed by a sequence,
+        // which is the same thing as a BY DEFAULT identity column.
+        if self.file_dialect == Dialect::PostgreSql {
+            if let Some(integer_type) = serial_integer_type(&data_type) {
+                data_type = integer_type;
+                identity = Some(Identity {
+                    kind: IdentityKind::ByDefault,
+                    sequence_options: vec![],
+                });
+            }
+        }
+
+        let mut options = Vec::with_capacity(column.options.len());
+        for option in column.options {
+            match option.option {
+                ColumnOption::Generated {
+                    generated_as: generated_as @ (GeneratedAs::Always | GeneratedAs::ByDefault),
+                    sequence_options,
+                    generation_expr: None,
+                    ..
+                } => {
+                    identity = Some(Identity {
+                        kind: if generated_as == GeneratedAs::Always {
+                            IdentityKind::Always
+                        } else {
+                            IdentityKind::ByDefault
+                        },
+                        sequence_options: normalize_sequence_options(
+                            sequence_options.unwrap_or_default(),
+                        ),
+                    });
+                }
+                // MySQL AUTO_INCREMENT and SQLite AUTOINCREMENT
+                ColumnOption::DialectSpecific(ref tokens) if is_auto_increment(tokens) => {
+                    identity = Some(Identity {
+                        kind: IdentityKind::ByDefault,
+                        sequence_options: vec![],
+                    });
+                }
+                _ => options.push(option),
+            }
+        }
+
+        let mut column = Column {
             name: column.name.value,
-            data_type: column.data_type,
+            data_type,
             collation: column.collation,
-            options: column.options,
+            options,
+            identity,
         };
+        column.normalize_options();
         Ok(column)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use sqlparser::ast::Ident;
+// Detects statements sqlparser can't parse. They are skipped as a whole so the
+// rest of the file can still be inspected.
+fn unparsable_statement_kind(parser: &Parser) -> Option<&'static str> {
+    let words: Vec<String> = (0..5)
+        .map(|n| match parser.peek_nth_token(n).token {
+            Token::Word(w) => w.value.to_uppercase(),
+            _ => String::new(),
+        })
+        .collect();
+    let mut words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
 
-    use crate::Options;
+    if words.first() != Some(&"CREATE") {
+        return None;
+    }
+    words.remove(0);
+    if words.starts_with(&["OR", "REPLACE"]) {
+        words.drain(..2);
+    }
 
-    use super::*;
+    match words.first() {
+        Some(&"CONSTRAINT") if words.get(1) == Some(&"TRIGGER") => {
+            Some("CREATE CONSTRAINT TRIGGER")
+        }
+        _ => None,
+    }
+}
 
-    #[test]
-    fn test_create_table() {
-        let sql = r#"
-            CREATE TABLE t (
-                id INT PRIMARY KEY,
-                name TEXT NOT NULL
-            );
-        "#;
+// sqlparser can't parse PostgreSQL's `ALTER TABLE ... VALIDATE CONSTRAINT`,
+// so it is recognized by peeking at the tokens and consumed only on a match.
+fn parse_validate_constraint(parser: &mut Parser) -> Option<(ObjectName, Ident)> {
+    let (table_name, n) = peek_alter_table(parser)?;
+    if !is_word(&parser.peek_nth_token(n).token, "VALIDATE")
+        || !is_word(&parser.peek_nth_token(n + 1).token, "CONSTRAINT")
+    {
+        return None;
+    }
+    let name = word_ident(parser.peek_nth_token(n + 2).token)?;
 
-        let options = Options {
-            dialect: Dialect::PostgreSql,
-            database: "test".to_string(),
-            default_schema: "public".to_string(),
-            paths: vec![],
-        };
-        let mut dbinfo = Dbinfo::with_options(options);
-        let mut inspector = Inspector::new(&mut dbinfo);
-        inspector.inspect(sql, "test.sql").unwrap();
+    for _ in 0..n + 3 {
+        parser.next_token();
+    }
+    Some((table_name, name))
+}
 
-        let table = dbinfo
-            .get_table(&TableName {
-                catalog: None,
-                schema: None,
-                table: Ident::new("t".to_string()),
-            })
+// `ALTER TABLE name ADD [CONSTRAINT name] EXCLUDE ...`, which sqlparser can't
+// parse. Returns the table name and the number of tokens before the constraint.
+fn peek_add_exclusion_constraint(parser: &Parser) -> Option<(ObjectName, usize)> {
+    let (table_name, n) = peek_alter_table(parser)?;
+    if !is_word(&parser.peek_nth_token(n).token, "ADD") {
+        return None;
+    }
+    let start = n + 1;
+    let mut n = start;
+    if is_word(&parser.peek_nth_token(n).token, "CONSTRAINT") {
+        n += 2;
+    }
+    if !is_word(&parser.peek_nth_token(n).token, "EXCLUDE") {
+        return None;
+    }
+    Some((table_name, start))
+}
+
+// Looks ahead for `ALTER TABLE [IF EXISTS] [ONLY] name`, returning the table
+// name and the number of tokens it spans.
+fn peek_alter_table(parser: &Parser) -> Option<(ObjectName, usize)> {
+    let mut n = 0;
+    for value in ["ALTER", "TABLE"] {
+        if !is_word(&parser.peek_nth_token(n).token, value) {
+            return None;
+        }
+        n += 1;
+    }
+    for words in [&["IF", "EXISTS"][..], &["ONLY"][..]] {
+        if words
+            .iter()
+            .enumerate()
+            .all(|(i, w)| is_word(&parser.peek_nth_token(n + i).token, w))
+        {
+            n += words.len();
+        }
+    }
+
+    let mut table_name = vec![word_ident(parser.peek_nth_token(n).token)?];
+    n += 1;
+    while parser.peek_nth_token(n).token == Token::Period {
+        table_name.push(word_ident(parser.peek_nth_token(n + 1).token)?);
+        n += 2;
+    }
+    Some((ObjectName(table_name), n))
+}
+
+fn is_word(token: &Token, value: &str) -> bool {
+    matches!(token, Token::Word(w) if w.value.eq_ignore_ascii_case(value))
+}
+
+fn word_ident(token: Token) -> Option<Ident> {
+    match token {
+        Token::Word(w) => Some(Ident {
+            value: w.value,
+            quote_style: w.quote_style,
+        }),
+        _ => None,
+    }
+}
+
+// `[CONSTRAINT name] EXCLUDE [USING method] (expr WITH operator, ...) [WHERE (predicate)]`
+fn parse_exclusion_constraint(
+    parser: &mut Parser,
+) -> Result<ExclusionConstraint, sqlparser::parser::ParserError> {
+    let name = if parser.parse_keyword(Keyword::CONSTRAINT) {
+        Some(parser.parse_identifier(false)?)
+    } else {
+        None
+    };
+    parser.expect_keyword(Keyword::EXCLUDE)?;
+    let index_method = if parser.parse_keyword(Keyword::USING) {
+        Some(parser.parse_identifier(false)?)
+    } else {
+        None
+    };
+
+    parser.expect_token(&Token::LParen)?;
+    let mut elements = vec![];
+    loop {
+        let expr = parser.parse_expr()?;
+        parser.expect_keyword(Keyword::WITH)?;
+        // operators are kept as written, e.g. `&&` or `OPERATOR(pg_catalog.=)`
+        let mut operator = String::new();
+        let mut depth = 0;
+        loop {
+            let token = parser.peek_token().token;
+            match token {
+                Token::Comma | Token::RParen if depth == 0 => break,
+                Token::EOF => break,
+                Token::LParen => depth += 1,
+                Token::RParen => depth -= 1,
+                _ => {}
+            }
+            operator.push_str(&parser.next_token().to_string());
+        }
+        if operator.is_empty() {
+            return parser.expected("an operator", parser.peek_token());
+        }
+        elements.push(ExclusionElement { expr, operator });
+        if !parser.consume_token(&Token::Comma) {
+            break;
+        }
+    }
+    parser.expect_token(&Token::RParen)?;
+
+    let predicate = if parser.parse_keyword(Keyword::WHERE) {
+        parser.expect_token(&Token::LParen)?;
+        let predicate = parser.parse_expr()?;
+        parser.expect_token(&Token::RParen)?;
+        Some(predicate)
+    } else {
+        None
+    };
+
+    Ok(ExclusionConstraint {
+        name,
+        index_method,
+        elements,
+        predicate,
+    })
+}
+
+// sqlparser can't parse EXCLUDE constraints, so they are taken out of the
+// column lists of CREATE TABLE statements before parsing. Their tokens are
+// returned by the location of the statement they were declared in.
+fn take_exclusion_constraints(
+    tokens: &mut Vec<TokenWithLocation>,
+) -> HashMap<(u64, u64), Vec<Vec<TokenWithLocation>>> {
+    let significant = |t: &TokenWithLocation| !matches!(t.token, Token::Whitespace(_));
+    let mut taken = HashMap::new();
+    let mut removed = vec![false; tokens.len()];
+
+    let mut i = 0;
+    while i < tokens.len() {
+        if !significant(&tokens[i]) || tokens[i].token == Token::SemiColon {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut depth = 0usize;
+        let mut end = i;
+        while end < tokens.len() && !(depth == 0 && tokens[end].token == Token::SemiColon) {
+            match tokens[end].token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            end += 1;
+        }
+        i = end;
+
+        // CREATE [...] TABLE name (
+        if !is_word(&tokens[start].token, "CREATE") {
+            continue;
+        }
+        let Some(open) = (start..end).find(|&j| tokens[j].token == Token::LParen) else {
+            continue;
+        };
+        if !(start..open).any(|j| is_word(&tokens[j].token, "TABLE")) {
+            continue;
+        }
+
+        // the elements of the column list, as token ranges between commas
+        let mut elements = vec![];
+        let mut depth = 0;
+        let mut element_start = open + 1;
+        for (j, token) in tokens.iter().enumerate().take(end).skip(open + 1) {
+            match token.token {
+                Token::LParen => depth += 1,
+                Token::RParen if depth == 0 => {
+                    elements.push((element_start, j));
+                    break;
+                }
+                Token::RParen => depth -= 1,
+                Token::Comma if depth == 0 => {
+                    elements.push((element_start, j));
+                    element_start = j + 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut constraints = vec![];
+        let mut kept_before = false;
+        for (k, &(from, to)) in elements.iter().enumerate() {
+            let words: Vec<_> = (from..to)
+                .filter(|&j| significant(&tokens[j]))
+                .take(3)
+                .collect();
+            let is_exclusion = match words[..] {
+                [first, ..] if is_word(&tokens[first].token, "EXCLUDE") => true,
+                [first, _, third] => {
+                    is_word(&tokens[first].token, "CONSTRAINT")
+                        && is_word(&tokens[third].token, "EXCLUDE")
+                }
+                _ => false,
+            };
+            if !is_exclusion {
+                kept_before = true;
+                continue;
+            }
+            constraints.push(tokens[from..to].to_vec());
+            // along with the comma separating it from the kept elements
+            let (from, to) = if kept_before {
+                (from - 1, to)
+            } else if k + 1 < elements.len() {
+                (from, to + 1)
+            } else {
+                (from, to)
+            };
+            removed[from..to].iter_mut().for_each(|r| *r = true);
+        }
+        if !constraints.is_empty() {
+            let loc = tokens[start].location;
+            taken.insert((loc.line, loc.column), constraints);
+        }
+    }
+
+    if !taken.is_empty() {
+        let mut removed = removed.into_iter();
+        tokens.retain(|_| !removed.next().unwrap());
+    }
+    taken
+}
+
+fn is_create_domain(parser: &Parser) -> bool {
+    let is_word = |n: usize, value: &str| matches!(parser.peek_nth_token(n).token, Token::Word(w) if w.value.eq_ignore_ascii_case(value));
+    is_word(0, "CREATE") && is_word(1, "DOMAIN")
+}
+
+// sqlparser doesn't know PostgreSQL's `CREATE DOMAIN name [AS] type [COLLATE c]
+// [DEFAULT e] [[CONSTRAINT n] {NOT NULL | NULL | CHECK (e)}]...`.
+fn parse_create_domain(parser: &mut Parser) -> Result<(ObjectName, Domain)> {
+    parser.next_token(); // CREATE
+    parser.next_token(); // DOMAIN
+    let name = parser.parse_object_name(false)?;
+    let _ = parser.parse_keyword(Keyword::AS);
+    let mut domain = Domain {
+        name: name.0.last().unwrap().value.clone(),
+        data_type: parser.parse_data_type()?,
+        collation: None,
+        default: None,
+        not_null: false,
+        constraints: vec![],
+    };
+
+    loop {
+        if matches!(parser.peek_token().token, Token::SemiColon | Token::EOF) {
+            break;
+        }
+        if parser.parse_keyword(Keyword::COLLATE) {
+            domain.collation = Some(parser.parse_object_name(false)?);
+            continue;
+        }
+        if parser.parse_keyword(Keyword::DEFAULT) {
+            domain.default = Some(parser.parse_expr()?);
+            continue;
+        }
+        let constraint_name = if parser.parse_keyword(Keyword::CONSTRAINT) {
+            Some(parser.parse_identifier(false)?)
+        } else {
+            None
+        };
+        if parser.parse_keywords(&[Keyword::NOT, Keyword::NULL]) {
+            domain.not_null = true;
+        } else if parser.parse_keyword(Keyword::NULL) {
+            domain.not_null = false;
+        } else if parser.parse_keyword(Keyword::CHECK) {
+            parser.expect_token(&Token::LParen)?;
+            let check = normalize_check_expr(&parser.parse_expr()?);
+            parser.expect_token(&Token::RParen)?;
+            domain.constraints.push(DomainConstraint {
+                name: constraint_name,
+                check,
+            });
+        } else {
+            return Ok(parser.expected("a domain constraint", parser.peek_token())?);
+        }
+    }
+    Ok((name, domain))
+}
+
+fn is_create_policy(parser: &Parser) -> bool {
+    let is_word = |n: usize, value: &str| matches!(parser.peek_nth_token(n).token, Token::Word(w) if w.value.eq_ignore_ascii_case(value));
+    is_word(0, "CREATE") && is_word(1, "POLICY")
+}
+
+// sqlparser doesn't know PostgreSQL's `CREATE POLICY name ON table
+// [AS {PERMISSIVE | RESTRICTIVE}] [FOR {ALL | SELECT | INSERT | UPDATE | DELETE}]
+// [TO role [, ...]] [USING (e)] [WITH CHECK (e)]`.
+fn parse_create_policy(parser: &mut Parser) -> Result<(ObjectName, Policy)> {
+    parser.next_token(); // CREATE
+    parser.next_token(); // POLICY
+    let name = parser.parse_identifier(false)?.value;
+    parser.expect_keyword(Keyword::ON)?;
+    let table_name = parser.parse_object_name(false)?;
+
+    let mut permissive = true;
+    if parser.parse_keyword(Keyword::AS) {
+        if parse_word(parser, "RESTRICTIVE") {
+            permissive = false;
+        } else if !parse_word(parser, "PERMISSIVE") {
+            return Ok(parser.expected("PERMISSIVE or RESTRICTIVE", parser.peek_token())?);
+        }
+    }
+
+    let mut command = PolicyCommand::All;
+    if parser.parse_keyword(Keyword::FOR) {
+        command = if parse_word(parser, "ALL") {
+            PolicyCommand::All
+        } else if parse_word(parser, "SELECT") {
+            PolicyCommand::Select
+        } else if parse_word(parser, "INSERT") {
+            PolicyCommand::Insert
+        } else if parse_word(parser, "UPDATE") {
+            PolicyCommand::Update
+        } else if parse_word(parser, "DELETE") {
+            PolicyCommand::Delete
+        } else {
+            return Ok(parser.expected("a policy command", parser.peek_token())?);
+        };
+    }
+
+    // Granting to PUBLIC is the same as granting to no role in particular.
+    let mut roles = vec![];
+    if parser.parse_keyword(Keyword::TO) {
+        roles = parser
+            .parse_comma_separated(|p| p.parse_identifier(false))?
+            .into_iter()
+            .map(|role| role.value)
+            .filter(|role| !role.eq_ignore_ascii_case("PUBLIC"))
+            .collect();
+    }
+
+    let parenthesized_expr = |parser: &mut Parser| -> Result<Expr> {
+        parser.expect_token(&Token::LParen)?;
+        let expr = parser.parse_expr()?;
+        parser.expect_token(&Token::RParen)?;
+        Ok(expr)
+    };
+    let using = if parser.parse_keyword(Keyword::USING) {
+        Some(parenthesized_expr(parser)?)
+    } else {
+        None
+    };
+    let with_check = if parser.parse_keywords(&[Keyword::WITH, Keyword::CHECK]) {
+        Some(parenthesized_expr(parser)?)
+    } else {
+        None
+    };
+
+    Ok((
+        table_name,
+        Policy {
+            name,
+            permissive,
+            command,
+            roles,
+            using,
+            with_check,
+        },
+    ))
+}
+
+fn is_create_trigger(parser: &Parser) -> bool {
+    let is_word = |n: usize, value: &str| matches!(parser.peek_nth_token(n).token, Token::Word(w) if w.value.eq_ignore_ascii_case(value));
+    is_word(0, "CREATE")
+        && (is_word(1, "TRIGGER")
+            || is_word(1, "OR") && is_word(2, "REPLACE") && is_word(3, "TRIGGER"))
+}
+
+// sqlparser doesn't know `CREATE [OR REPLACE] TRIGGER [IF NOT EXISTS] name
+// {BEFORE | AFTER | INSTEAD OF} event [OR event]... ON table
+// [FOR [EACH] {ROW | STATEMENT}] [WHEN condition] action`. The action is kept
+// as written, up to the semicolon ending the statement, which may be nested in
+// a `BEGIN ... END` block.
+fn parse_create_trigger(
+    parser: &mut Parser,
+    sql: &str,
+    dialect: Dialect,
+) -> Result<(ObjectName, Trigger, bool, bool)> {
+    parser.next_token(); // CREATE
+    let or_replace = parser.parse_keywords(&[Keyword::OR, Keyword::REPLACE]);
+    parser.next_token(); // TRIGGER
+    let if_not_exists = parser.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+    let name = parser.parse_object_name(false)?.0.pop().unwrap().value;
+
+    let timing = if parse_word(parser, "BEFORE") {
+        TriggerTiming::Before
+    } else if parse_word(parser, "AFTER") {
+        TriggerTiming::After
+    } else if parse_word(parser, "INSTEAD") && parse_word(parser, "OF") {
+        TriggerTiming::InsteadOf
+    } else {
+        return Ok(parser.expected("BEFORE, AFTER or INSTEAD OF", parser.peek_token())?);
+    };
+
+    let mut events = vec![];
+    loop {
+        let event = if parse_word(parser, "INSERT") {
+            TriggerEvent::Insert
+        } else if parse_word(parser, "UPDATE") {
+            let mut columns = vec![];
+            if parse_word(parser, "OF") {
+                columns = parser.parse_comma_separated(|p| p.parse_identifier(false))?;
+            }
+            TriggerEvent::Update(columns)
+        } else if parse_word(parser, "DELETE") {
+            TriggerEvent::Delete
+        } else if parse_word(parser, "TRUNCATE") {
+            TriggerEvent::Truncate
+        } else {
+            return Ok(parser.expected("a trigger event", parser.peek_token())?);
+        };
+        events.push(event);
+        if !parser.parse_keyword(Keyword::OR) {
+            break;
+        }
+    }
+
+    parser.expect_keyword(Keyword::ON)?;
+    let table_name = parser.parse_object_name(false)?;
+
+    // PostgreSQL triggers fire once per statement unless told otherwise, the
+    // others only have row triggers.
+    let mut for_each_row = dialect != Dialect::PostgreSql;
+    if parser.parse_keyword(Keyword::FOR) {
+        let _ = parser.parse_keyword(Keyword::EACH);
+        if parse_word(parser, "ROW") {
+            for_each_row = true;
+        } else if parse_word(parser, "STATEMENT") {
+            for_each_row = false;
+        } else {
+            return Ok(parser.expected("ROW or STATEMENT", parser.peek_token())?);
+        }
+    }
+    let condition = if parser.parse_keyword(Keyword::WHEN) {
+        Some(normalize_check_expr(&parser.parse_expr()?))
+    } else {
+        None
+    };
+
+    let start = parser.peek_token();
+    if dialect == Dialect::PostgreSql && !parse_word(parser, "EXECUTE") {
+        return Ok(parser.expected("EXECUTE FUNCTION", start)?);
+    }
+    let mut depth = 0;
+    let end = loop {
+        let tok = parser.peek_token();
+        match &tok.token {
+            Token::EOF => break None,
+            Token::SemiColon if depth == 0 => break Some(tok.location),
+            Token::Word(w) if w.keyword == Keyword::BEGIN || w.keyword == Keyword::CASE => {
+                depth += 1
+            }
+            // `END IF`, `END LOOP` and the like close blocks which weren't
+            // counted, `END CASE` closes a CASE statement.
+            Token::Word(w) if w.keyword == Keyword::END => {
+                parser.next_token();
+                if !["IF", "LOOP", "WHILE", "REPEAT"]
+                    .iter()
+                    .any(|block| parse_word(parser, block))
+                {
+                    let _ = parse_word(parser, "CASE");
+                    depth -= 1;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        parser.next_token();
+    };
+    let start = location_offset(sql, start.location);
+    let end = end.map_or(sql.len(), |loc| location_offset(sql, loc));
+
+    let trigger = Trigger {
+        name,
+        timing,
+        events,
+        for_each_row,
+        condition,
+        action: sql[start..end].trim_end().to_string(),
+    };
+    Ok((table_name, trigger, or_replace, if_not_exists))
+}
+
+// Consumes the next token if it is the given word, whether it is a keyword or not.
+fn parse_word(parser: &mut Parser, value: &str) -> bool {
+    match parser.peek_token().token {
+        Token::Word(w) if w.value.eq_ignore_ascii_case(value) => {
+            parser.next_token();
+            true
+        }
+        _ => false,
+    }
+}
+
+// The byte offset of a token location, whose line and column count characters
+// from 1.
+pub(crate) fn location_offset(sql: &str, loc: Location) -> usize {
+    let line_start: usize = sql
+        .split_inclusive('\n')
+        .take(loc.line as usize - 1)
+        .map(|line| line.len())
+        .sum();
+    line_start
+        + sql[line_start..]
+            .chars()
+            .take(loc.column as usize - 1)
+            .map(|c| c.len_utf8())
+            .sum::<usize>()
+}
+
+// Describes a statement by its leading keywords, which sqlparser always
+// renders in upper case.
+fn statement_kind(stmt: &Statement) -> String {
+    stmt.to_string()
+        .split_whitespace()
+        .take_while(|word| word.chars().all(|c| c.is_ascii_uppercase() || c == '_'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The schemas of `SET search_path TO a, b`, `"$user"` left out as migi
+// doesn't know the user the migrations run as.
+fn search_path_schemas(stmt: &Statement) -> Option<Vec<String>> {
+    let Statement::SetVariable {
+        variables: OneOrManyWithParens::One(variable),
+        value,
+        ..
+    } = stmt
+    else {
+        return None;
+    };
+    if !matches!(&variable.0[..], [name] if name.value.eq_ignore_ascii_case("search_path")) {
+        return None;
+    }
+    value
+        .iter()
+        .filter_map(|value| match value {
+            Expr::Identifier(ident) if ident.value == "$user" => None,
+            Expr::Value(Value::SingleQuotedString(s)) if s == "$user" => None,
+            // `SET search_path TO DEFAULT` isn't supported
+            Expr::Identifier(ident)
+                if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("default") =>
+            {
+                Some(None)
+            }
+            Expr::Identifier(ident) => Some(Some(ident.value.clone())),
+            Expr::Value(Value::SingleQuotedString(s)) => Some(Some(s.clone())),
+            _ => Some(None),
+        })
+        .collect()
+}
+
+// Statements creating namespaces are applied first, then the objects living
+// in them, then the statements modifying existing objects.
+fn inspection_pass(stmt: &ParsedStatement) -> u8 {
+    let stmt = match stmt {
+        ParsedStatement::Statement(stmt) => stmt,
+        ParsedStatement::ValidateConstraint { .. } => return 2,
+        ParsedStatement::CreateDomain { .. } => return 1,
+        ParsedStatement::CreateTrigger { .. } => return 2,
+        ParsedStatement::AddExclusionConstraint { .. } => return 2,
+        ParsedStatement::CreatePolicy { .. } => return 2,
+    };
+    match &**stmt {
+        Statement::CreateDatabase { .. } | Statement::CreateSchema { .. } => 0,
+        Statement::AlterTable { .. }
+        | Statement::CreateIndex { .. }
+        | Statement::Comment { .. } => 2,
+        _ => 1,
+    }
+}
+
+// Whether a statement of the second pass only creates an object, without
+// depending on or replacing the objects other files create.
+fn is_independent(stmt: &ParsedStatement) -> bool {
+    let stmt = match stmt {
+        ParsedStatement::Statement(stmt) => stmt,
+        ParsedStatement::CreateDomain { .. } => return true,
+        _ => return false,
+    };
+    matches!(
+        &**stmt,
+        Statement::CreateTable { .. }
+            | Statement::CreateView {
+                or_replace: false,
+                if_not_exists: false,
+                ..
+            }
+            | Statement::CreateFunction {
+                or_replace: false,
+                if_not_exists: false,
+                ..
+            }
+            | Statement::Use { .. }
+    ) || search_path_schemas(stmt).is_some()
+}
+
+// The objects of a Dbinfo missing from an earlier state of it, e.g.
+// `table test.public.users`.
+fn created_objects(before: &Dbinfo, after: &Dbinfo) -> Vec<String> {
+    let objects = |schema: &Schema| -> HashSet<(&'static str, String)> {
+        let names = |kind: &'static str| move |name: &String| (kind, name.clone());
+        schema
+            .tables
+            .keys()
+            .map(names("table"))
+            .chain(schema.views.keys().map(names("view")))
+            .chain(schema.domains.keys().map(names("domain")))
+            .chain(schema.routines.keys().map(names("function")))
+            .collect()
+    };
+
+    let mut created = vec![];
+    for catalog in after.catalogs.values() {
+        for schema in catalog.schemas.values() {
+            let existing = before
+                .get_catalog(&catalog.name)
+                .ok()
+                .and_then(|c| c.schemas.get(&schema.name))
+                .map(objects)
+                .unwrap_or_default();
+            for (kind, name) in objects(schema).difference(&existing) {
+                created.push(format!(
+                    "{} {}.{}.{}",
+                    kind, catalog.name, schema.name, name
+                ));
+            }
+        }
+    }
+    created.sort();
+    created
+}
+
+fn serial_integer_type(data_type: &DataType) -> Option<DataType> {
+    let DataType::Custom(name, modifiers) = data_type else {
+        return None;
+    };
+    if name.0.len() != 1 || !modifiers.is_empty() {
+        return None;
+    }
+
+    match name.0[0].value.to_lowercase().as_str() {
+        "smallserial" | "serial2" => Some(DataType::SmallInt(None)),
+        "serial" | "serial4" => Some(DataType::Int(None)),
+        "bigserial" | "serial8" => Some(DataType::BigInt(None)),
+        _ => None,
+    }
+}
+
+fn is_auto_increment(tokens: &[Token]) -> bool {
+    matches!(
+        tokens,
+        [Token::Word(w)] if w.keyword == Keyword::AUTO_INCREMENT || w.keyword == Keyword::AUTOINCREMENT
+    )
+}
+
+// Mimics PostgreSQL's naming of indexes created without an explicit name.
+fn default_index_name(table: &str, columns: &[OrderByExpr]) -> String {
+    let mut name = table.to_string();
+    for column in columns {
+        match &column.expr {
+            sqlparser::ast::Expr::Identifier(ident) => {
+                name.push('_');
+                name.push_str(&ident.value);
+            }
+            _ => name.push_str("_expr"),
+        }
+    }
+    name.push_str("_idx");
+    name
+}
+
+// Reads the `(filename, sql)` sources of the given files and directories,
+// whose *.sql files are read recursively, sorted by their relative path: the
+// order their statements are inspected in. Directories reached again through
+// a symbolic link are skipped, and every unreadable path is in the error.
+pub fn read_sources(paths: &[String]) -> Result<Vec<(String, String)>> {
+    let mut sources = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if !path.exists() {
+            errors.push(format!("{}: no such file or directory", path.display()));
+            continue;
+        }
+        let files = if path.is_dir() {
+            let mut files = Vec::new();
+            if let Err(e) = collect_sql_files(path, Path::new(""), &mut HashSet::new(), &mut files)
+            {
+                errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+            files.sort();
+            files.into_iter().map(|file| path.join(file)).collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+        for file in files {
+            match std::fs::read_to_string(&file) {
+                Ok(sql) => sources.push((file.display().to_string(), sql)),
+                Err(e) => errors.push(format!("{}: {}", file.display(), e)),
+            }
+        }
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("cannot read the schema files:\n  {}", errors.join("\n  "));
+    }
+    Ok(sources)
+}
+
+fn collect_sql_files(
+    root: &Path,
+    dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !visited.insert(root.join(dir).canonicalize()?) {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(root.join(dir))? {
+        let relative = dir.join(entry?.file_name());
+        let path = root.join(&relative);
+        if path.is_dir() {
+            collect_sql_files(root, &relative, visited, files)?;
+        } else if path.is_file() && path.extension().is_some_and(|ext| ext == "sql") {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+fn format_location(filename: &Option<String>, loc: Location) -> String {
+    if let Some(filename) = filename {
+        format!("{}:{}:{}", filename, loc.line, loc.column)
+    } else {
+        format!("{}:{}", loc.line, loc.column)
+    }
+}
+
+// Takes the PRIMARY KEY and UNIQUE options out of a column definition as table
+// constraints, so that `id INT PRIMARY KEY` and `id INT, PRIMARY KEY (id)` make
+// the same table.
+fn take_column_constraints(column_def: &mut ColumnDef) -> Vec<TableConstraint> {
+    let mut constraints = Vec::new();
+    column_def.options.retain(|option| match &option.option {
+        ColumnOption::Unique {
+            is_primary,
+            characteristics,
+        } => {
+            constraints.push(if *is_primary {
+                TableConstraint::PrimaryKey {
+                    name: option.name.clone(),
+                    index_name: None,
+                    index_type: None,
+                    columns: vec![column_def.name.clone()],
+                    index_options: vec![],
+                    characteristics: *characteristics,
+                }
+            } else {
+                TableConstraint::Unique {
+                    name: option.name.clone(),
+                    index_name: None,
+                    index_type_display: KeyOrIndexDisplay::None,
+                    index_type: None,
+                    columns: vec![column_def.name.clone()],
+                    index_options: vec![],
+                    characteristics: *characteristics,
+                }
+            });
+            false
+        }
+        // `REFERENCES` is the same foreign key as a table-level one on the
+        // column.
+        ColumnOption::ForeignKey {
+            foreign_table,
+            referred_columns,
+            on_delete,
+            on_update,
+            characteristics,
+        } => {
+            constraints.push(TableConstraint::ForeignKey {
+                name: option.name.clone(),
+                columns: vec![column_def.name.clone()],
+                foreign_table: foreign_table.clone(),
+                referred_columns: referred_columns.clone(),
+                on_delete: *on_delete,
+                on_update: *on_update,
+                characteristics: *characteristics,
+            });
+            false
+        }
+        _ => true,
+    });
+    constraints
+}
+
+fn add_constraints(table: &mut Table, constraints: Vec<TableConstraint>) {
+    for constraint in constraints {
+        if !table.constraints.contains(&constraint) {
+            table.constraints.push(constraint);
+        }
+    }
+}
+
+// Returns the index a column should be inserted at, or the name of the missing
+// column referenced by `AFTER`.
+fn column_insert_position<'p>(
+    table: &Table,
+    position: Option<&'p MySQLColumnPosition>,
+) -> Result<usize, &'p str> {
+    match position {
+        None => Ok(table.columns.len()),
+        Some(MySQLColumnPosition::First) => Ok(0),
+        Some(MySQLColumnPosition::After(ident)) => table
+            .columns
+            .iter()
+            .position(|c| c.name == ident.value)
+            .map(|i| i + 1)
+            .ok_or(ident.value.as_str()),
+    }
+}
+
+fn into_column_option_defs(options: Vec<ColumnOption>) -> Vec<ColumnOptionDef> {
+    options
+        .into_iter()
+        .map(|option| ColumnOptionDef { name: None, option })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlparser::ast::{Ident, TableConstraint};
+
+    use crate::test_support::test_options;
+    use crate::{CaseFolding, Options};
+
+    use super::*;
+
+    #[test]
+    fn test_create_table() {
+        let sql = r#"
+            CREATE TABLE t (
+                id INT PRIMARY KEY,
+                name TEXT NOT NULL
+            );
+        "#;
+
+        let options = Options::builder()
+            .dialect(Dialect::PostgreSql)
+            .database("test")
+            .build()
+            .unwrap();
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+
+        let table = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("t".to_string()),
+            })
             .unwrap();
         assert_eq!(table.columns.len(), 2);
         assert_eq!(table.columns[0].name, "id");
         assert_eq!(table.columns[1].name, "name");
     }
+
+    #[test]
+    fn test_inspect_all_applies_alter_after_create() {
+        let sources = vec![
+            (
+                "alter.sql".to_string(),
+                r#"
+                    ALTER TABLE t ADD COLUMN email TEXT;
+                    CREATE INDEX t_email_idx ON t (email);
+                    COMMENT ON TABLE t IS 'users';
+                "#
+                .to_string(),
+            ),
+            (
+                "create.sql".to_string(),
+                r#"
+                    CREATE TABLE t (
+                        id INT PRIMARY KEY
+                    );
+                "#
+                .to_string(),
+            ),
+        ];
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect_all(&sources).unwrap();
+
+        let table = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("t".to_string()),
+            })
+            .unwrap();
+        assert_eq!(table.columns.len(), 2);
+        assert_eq!(table.columns[1].name, "email");
+        assert_eq!(table.indexes.len(), 1);
+        assert_eq!(table.indexes[0].name, "t_email_idx");
+        assert_eq!(table.comment, Some("users".to_string()));
+    }
+
+    #[test]
+    fn test_parallel_inspection_matches_serial() {
+        let mut sources: Vec<(String, String)> = (0..2 * PARALLEL_MIN_FILES)
+            .map(|i| {
+                (
+                    format!("{:02}.sql", i),
+                    format!(
+                        "CREATE TABLE t{i} (id INT PRIMARY KEY);
+                         ALTER TABLE t{} ADD COLUMN next_id INT;
+                         GRANT SELECT ON t{i} TO reader;",
+                        (i + 1) % (2 * PARALLEL_MIN_FILES)
+                    ),
+                )
+            })
+            .collect();
+        sources[3].1.push_str("CREATE TABLE broken id INT;");
+
+        let inspect = |parallel: bool, sources: &[(String, String)]| {
+            let mut dbinfo = Dbinfo::with_options(
+                Options::builder()
+                    .dialect(Dialect::PostgreSql)
+                    .database("test")
+                    .build()
+                    .unwrap(),
+            );
+            let mut inspector = Inspector::new(&mut dbinfo)
+                .with_keep_going(true)
+                .with_parallel(parallel);
+            let result = inspector.inspect_all(sources).map_err(|e| e.to_string());
+            let unsupported = inspector.unsupported_statements().to_vec();
+            let errors = inspector.errors().to_vec();
+            (result, unsupported, errors, dbinfo)
+        };
+
+        let serial = inspect(false, &sources);
+        assert_eq!(serial.0, Ok(()));
+        assert_eq!(serial.1.len(), 2 * PARALLEL_MIN_FILES);
+        assert_eq!(serial.2.len(), 1);
+        assert_eq!(inspect(true, &sources), serial);
+
+        // files are all parsed before any statement is applied
+        let inspect_strict = |parallel: bool| {
+            let mut dbinfo = Dbinfo::with_options(
+                Options::builder()
+                    .dialect(Dialect::PostgreSql)
+                    .database("test")
+                    .build()
+                    .unwrap(),
+            );
+            Inspector::new(&mut dbinfo)
+                .with_strict(true)
+                .with_parallel(parallel)
+                .inspect_all(&sources)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(inspect_strict(true), inspect_strict(false));
+        assert!(inspect_strict(true).starts_with("03.sql:3:75 sql parser error"));
+    }
+
+    #[test]
+    fn test_parallel_creates_match_serial() {
+        let files = 2 * PARALLEL_MIN_FILES;
+        let mut sources: Vec<(String, String)> = (0..files)
+            .map(|i| {
+                let next = (i + 1) % files;
+                (
+                    format!("{:02}.sql", i),
+                    format!(
+                        "CREATE TABLE app.t{i} (id INT PRIMARY KEY, next_id INT REFERENCES app.t{next} (id));
+                         CREATE VIEW app.v{i} AS SELECT id FROM app.t{next};
+                         CREATE INDEX t{next}_next_id ON app.t{next} (next_id);"
+                    ),
+                )
+            })
+            .collect();
+        sources[files - 1].1.push_str("CREATE SCHEMA app;");
+
+        let inspect = |parallel: bool, sources: &[(String, String)]| {
+            let mut dbinfo = Dbinfo::with_options(test_options(Dialect::PostgreSql));
+            Inspector::new(&mut dbinfo)
+                .with_parallel(parallel)
+                .inspect_all(sources)
+                .map(|_| dbinfo)
+                .map_err(|e| e.to_string())
+        };
+        let serial = inspect(false, &sources).unwrap();
+        assert_eq!(
+            serial.get_catalog("test").unwrap().schemas["app"]
+                .tables
+                .len(),
+            files
+        );
+        assert_eq!(inspect(true, &sources), Ok(serial));
+
+        sources[7].1.push_str("CREATE TABLE app.t3 (id BIGINT);");
+        assert_eq!(
+            inspect(true, &sources),
+            Err("table test.app.t3 is created in both 03.sql and 07.sql".to_string())
+        );
+    }
+
+    #[test]
+    fn test_changed_file_misses_parse_cache() {
+        let dir = std::env::temp_dir().join(format!("migi-cache-{}", std::process::id()));
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(
+                Options::builder()
+                    .dialect(Dialect::PostgreSql)
+                    .database("test")
+                    .build()
+                    .unwrap(),
+            );
+            let mut inspector = Inspector::new(&mut dbinfo).with_cache(ParseCache::new(&dir));
+            inspector.inspect(sql, "schema.sql").unwrap();
+            let unsupported = inspector.unsupported_statements().len();
+            (dbinfo, unsupported)
+        };
+        let entries = || {
+            let mut entries: Vec<_> = std::fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            entries.sort();
+            entries
+        };
+
+        let users = "CREATE TABLE users (id INT); \
+            CREATE CONSTRAINT TRIGGER own AFTER INSERT ON users FOR EACH ROW EXECUTE FUNCTION f();";
+        let (inspected, unsupported) = inspect(users);
+        assert_eq!(unsupported, 1);
+        assert_eq!(entries().len(), 1);
+        assert_eq!(inspect(users), (inspected.clone(), 1));
+        assert_eq!(entries().len(), 1);
+
+        let accounts = "CREATE TABLE accounts (id INT);";
+        let (changed, _) = inspect(accounts);
+        assert_ne!(changed, inspected);
+        assert!(changed
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("accounts"),
+            })
+            .is_ok());
+        assert_eq!(entries().len(), 2);
+
+        // unchanged files are not parsed again: serving the users file from
+        // the entry of the accounts one shows in the result
+        let accounts_entry = entries()
+            .into_iter()
+            .find(|path| std::fs::read_to_string(path).unwrap().contains("accounts"))
+            .unwrap();
+        for path in entries() {
+            if path != accounts_entry {
+                std::fs::copy(&accounts_entry, path).unwrap();
+            }
+        }
+        let result = inspect(users);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result, (changed, 0));
+    }
+
+    #[test]
+    fn test_errors_are_located() {
+        let cases = [
+            (
+                "ALTER TABLE missing ADD COLUMN x INT;",
+                "test.sql:2:1 table does not found: missing",
+            ),
+            (
+                "ALTER TABLE t DROP COLUMN missing;",
+                "test.sql:2:1 column does not found: missing",
+            ),
+            (
+                "CREATE INDEX idx ON missing (id);",
+                "test.sql:2:1 table does not found: missing",
+            ),
+            (
+                "COMMENT ON COLUMN t.missing IS 'x';",
+                "test.sql:2:1 column does not found: missing",
+            ),
+        ];
+
+        for (stmt, expected) in cases {
+            let sql = format!("CREATE TABLE t (id INT);\n{}", stmt);
+
+            let options = test_options(Dialect::PostgreSql);
+            let mut dbinfo = Dbinfo::with_options(options);
+            let mut inspector = Inspector::new(&mut dbinfo);
+            let err = inspector.inspect(&sql, "test.sql").unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_qualified_object_names() {
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE SCHEMA reporting;
+            CREATE TABLE app.t (id INT);
+            CREATE INDEX app.t_id_idx ON app.t (id);
+            CREATE VIEW reporting.v AS SELECT id FROM app.t;
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+
+        let table = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: Some(Ident::new("app")),
+                table: Ident::new("t"),
+            })
+            .unwrap();
+        assert_eq!(table.indexes[0].name, "t_id_idx");
+        assert!(dbinfo
+            .get_view(&TableName {
+                catalog: None,
+                schema: Some(Ident::new("reporting")),
+                table: Ident::new("v"),
+            })
+            .is_ok());
+
+        let sql = "CREATE INDEX reporting.t_id_idx ON app.t (id);";
+        let mut inspector = Inspector::new(&mut dbinfo);
+        let err = inspector.inspect(sql, "test.sql").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:1:1 index reporting.t_id_idx must be in the same schema as table app.t"
+        );
+    }
+
+    #[test]
+    fn test_invalid_object_name() {
+        let options = Options::builder()
+            .dialect(Dialect::SQLite)
+            .database("test")
+            .build()
+            .unwrap();
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        let err = inspector
+            .inspect("CREATE TABLE a.b.t (id INT);", "test.sql")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("test.sql:1:1 invalid object name"));
+    }
+
+    #[test]
+    fn test_mariadb_sequence() {
+        let options = Options::builder()
+            .dialect(Dialect::MariaDb)
+            .database("test")
+            .build()
+            .unwrap();
+        let sql = r#"
+            CREATE SEQUENCE s INCREMENT BY 10 MINVALUE 1 START WITH 100 CACHE 1000;
+            CREATE TABLE test.t (id INT AUTO_INCREMENT PRIMARY KEY, n INT DEFAULT 0);
+        "#;
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert_eq!(inspector.unsupported_statements().len(), 1);
+
+        let table = dbinfo
+            .get_catalog("test")
+            .unwrap()
+            .get_schema("test")
+            .unwrap()
+            .get_table("t")
+            .unwrap();
+        assert!(table.get_column("id").unwrap().is_identity());
+    }
+
+    #[test]
+    fn test_use_selects_the_default_catalog() {
+        let options = Options::builder()
+            .dialect(Dialect::MySql)
+            .database("test")
+            .build()
+            .unwrap();
+        let sql = r#"
+            CREATE DATABASE a;
+            USE a;
+            CREATE TABLE users (id INT);
+            CREATE DATABASE b;
+            USE b;
+            CREATE TABLE posts (id INT);
+            ALTER TABLE posts ADD COLUMN title TEXT;
+        "#;
+        // each database defaults to the schema named after it
+        let tables = |dbinfo: &Dbinfo, catalog: &str| {
+            let schema = dbinfo.get_catalog(catalog).unwrap().get_schema(catalog);
+            let mut tables: Vec<String> = schema.unwrap().tables.keys().cloned().collect();
+            tables.sort();
+            tables
+        };
+
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut dbinfo)
+            .with_strict(true)
+            .inspect(sql, "test.sql")
+            .unwrap();
+        assert_eq!(tables(&dbinfo, "a"), ["users"]);
+        assert_eq!(tables(&dbinfo, "b"), ["posts"]);
+        assert!(tables(&dbinfo, "test").is_empty());
+        // the database is only selected until the end of the file
+        assert_eq!(dbinfo.default_catalog, "test");
+
+        // so does a migration creating the databases
+        let empty = Dbinfo::with_options(options.clone());
+        let migration = crate::migrate::MigrationGenerator::new(&empty, &dbinfo)
+            .generate()
+            .unwrap();
+        let mut applied = empty.clone();
+        migration.apply(&mut applied).unwrap();
+        assert_eq!(applied, dbinfo);
+
+        // ALTER TABLE is applied after every CREATE, still in the database
+        // selected before it
+        let sources = [
+            ("1.sql".to_string(), sql.to_string()),
+            ("2.sql".to_string(), "CREATE TABLE t (id INT);".to_string()),
+        ];
+        let mut all = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut all)
+            .with_strict(true)
+            .inspect_all(&sources)
+            .unwrap();
+        assert_eq!(
+            all.get_catalog("b").unwrap(),
+            dbinfo.get_catalog("b").unwrap()
+        );
+        assert_eq!(tables(&all, "test"), ["t"]);
+
+        let mut dbinfo = Dbinfo::with_options(options);
+        let err = Inspector::new(&mut dbinfo)
+            .inspect("USE missing;", "test.sql")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:1:1 catalog does not found: missing"
+        );
+    }
+
+    #[test]
+    fn test_search_path_selects_the_default_schema() {
+        let options = test_options(Dialect::PostgreSql);
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE TABLE settings (id INT);
+            SET search_path TO "$user", missing, app, public;
+            CREATE TABLE users (id INT);
+            CREATE INDEX ON users (id);
+            SET search_path = public;
+            CREATE TABLE logs (id INT);
+            SET statement_timeout = 0;
+        "#;
+        let tables = |dbinfo: &Dbinfo, schema: &str| {
+            let schema = dbinfo.get_catalog("test").unwrap().get_schema(schema);
+            let mut tables: Vec<String> = schema.unwrap().tables.keys().cloned().collect();
+            tables.sort();
+            tables
+        };
+
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        // other SET statements are still skipped
+        assert_eq!(inspector.unsupported_statements().len(), 1);
+        assert_eq!(tables(&dbinfo, "app"), ["users"]);
+        assert_eq!(tables(&dbinfo, "public"), ["logs", "settings"]);
+        // the schema is only selected until the end of the file
+        assert_eq!(dbinfo.get_catalog("test").unwrap().default_schema, "public");
+
+        let sources = [
+            ("1.sql".to_string(), sql.to_string()),
+            ("2.sql".to_string(), "CREATE TABLE t (id INT);".to_string()),
+        ];
+        let mut all = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut all).inspect_all(&sources).unwrap();
+        assert_eq!(tables(&all, "app"), ["users"]);
+        assert_eq!(tables(&all, "public"), ["logs", "settings", "t"]);
+
+        let mut dbinfo = Dbinfo::with_options(options);
+        let err = Inspector::new(&mut dbinfo)
+            .inspect("SET search_path TO missing;", "test.sql")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:1:1 no schema of the search_path exists: missing"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let sql = r#"
+            CREATE TABLE a (id INT, name TEXT);
+            CREATE TABLE b (id INT);
+            CREATE INDEX a_name_idx ON a (name);
+            CREATE VIEW v AS SELECT id FROM a;
+            INSERT INTO b VALUES (1);
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert_eq!(inspector.unsupported_statements().len(), 1);
+
+        let stats = dbinfo.stats();
+        assert_eq!(stats.catalogs, 1);
+        assert_eq!(stats.schemas, 1);
+        assert_eq!(stats.tables, 2);
+        assert_eq!(stats.columns, 3);
+        assert_eq!(stats.indexes, 1);
+        assert_eq!(stats.views, 1);
+    }
+
+    #[test]
+    fn test_unsupported_statements() {
+        let sql = r#"
+            CREATE TABLE t (id INT);
+            INSERT INTO t VALUES (1);
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert_eq!(
+            inspector.unsupported_statements(),
+            &[UnsupportedStatement {
+                kind: "INSERT INTO".to_string(),
+                location: "test.sql:3:13".to_string(),
+            }]
+        );
+
+        let mut inspector = Inspector::new(&mut dbinfo).with_strict(true);
+        let err = inspector.inspect(sql, "test.sql").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:3:13 INSERT INTO is not supported"
+        );
+    }
+
+    #[test]
+    fn test_ignore_dml() {
+        let sql = r#"
+            CREATE TABLE t (id INT);
+            INSERT INTO t VALUES (1);
+            UPDATE t SET id = 2;
+            DELETE FROM t WHERE id = 2;
+            TRUNCATE t;
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert_eq!(inspector.unsupported_statements().len(), 4);
+
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo)
+            .with_ignore_dml(true)
+            .with_strict(true);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert!(inspector.unsupported_statements().is_empty());
+        assert_eq!(dbinfo.stats().tables, 1);
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let sql = r#"
+            CREATE TABLE t (id INT);
+            CREATE CONSTRAINT TRIGGER t_check AFTER INSERT ON t
+                FOR EACH ROW EXECUTE FUNCTION check_t();
+            CREATE TEMPORARY TABLE tmp (id INT);
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert_eq!(
+            inspector.unsupported_statements(),
+            &[
+                UnsupportedStatement {
+                    kind: "CREATE CONSTRAINT TRIGGER".to_string(),
+                    location: "test.sql:3:13".to_string(),
+                },
+                UnsupportedStatement {
+                    kind: "CREATE TEMPORARY TABLE".to_string(),
+                    location: "test.sql:5:13".to_string(),
+                },
+            ]
+        );
+        assert_eq!(dbinfo.stats().tables, 1);
+
+        let mut inspector = Inspector::new(&mut dbinfo).with_strict(true);
+        let err = inspector.inspect(sql, "test.sql").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:3:13 CREATE CONSTRAINT TRIGGER is not supported"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_are_located() {
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let sources = vec![
+            (
+                "users.sql".to_string(),
+                "CREATE TABLE users (id INT);".to_string(),
+            ),
+            (
+                "posts.sql".to_string(),
+                "CREATE TABLE posts (id INT);\n\nCREATE TABLE comments (id INT,, body TEXT);"
+                    .to_string(),
+            ),
+        ];
+        let err = Inspector::new(&mut dbinfo)
+            .inspect_all(&sources)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "posts.sql:3:1 sql parser error: Expected column name or constraint definition, found: , at Line: 3, Column 31"
+        );
+
+        let err = Inspector::new(&mut dbinfo)
+            .inspect("CREATE TABLE t (name TEXT DEFAULT 'unterminated);", "t.sql")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("t.sql:1:35 Unterminated string literal"));
+    }
+
+    #[test]
+    fn test_keep_going_reports_every_error() {
+        let options = test_options(Dialect::PostgreSql);
+        let sql = "CREATE TABLE users (id INT,, name TEXT);
+CREATE TABLE posts (id INT);
+CREATE TABLE comments (id INT REFERENCES);
+CREATE INDEX posts_id ON posts (id);
+CREATE INDEX posts_id ON posts (id);
+CREATE TABLE tags (id INT)";
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo).with_keep_going(true);
+        inspector.inspect(sql, "test.sql").unwrap();
+
+        let errors: Vec<String> = inspector.errors().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            [
+                "test.sql:1:1 sql parser error: Expected column name or constraint definition, found: , at Line: 1, Column 28",
+                "test.sql:3:1 sql parser error: Expected identifier, found: ) at Line: 3, Column 41",
+                "test.sql:5:1 index posts_id already exists",
+            ]
+        );
+        assert_eq!(dbinfo.stats().tables, 2);
+
+        let mut inspector = Inspector::new(&mut dbinfo)
+            .with_keep_going(true)
+            .with_strict(true);
+        let err = inspector.inspect(sql, "test.sql").unwrap_err();
+        assert!(err.to_string().starts_with("test.sql:1:1 sql parser error"));
+    }
+
+    #[test]
+    fn test_diagnostics_json() {
+        let options = test_options(Dialect::PostgreSql);
+        let sql = "CREATE TABLE users (id INT);\nCREATE TABLE posts (id INT,, title TEXT);\nINSERT INTO users VALUES (1);";
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo).with_keep_going(true);
+        inspector.inspect(sql, "schema/app.sql").unwrap();
+
+        assert_eq!(
+            serde_json::to_value(inspector.diagnostics()).unwrap(),
+            serde_json::json!([
+                {
+                    "file": "schema/app.sql",
+                    "line": 2,
+                    "column": 1,
+                    "message": "sql parser error: Expected column name or constraint definition, found: , at Line: 2, Column 28",
+                    "severity": "error",
+                },
+                {
+                    "file": "schema/app.sql",
+                    "line": 3,
+                    "column": 1,
+                    "message": "INSERT INTO is not supported",
+                    "severity": "warning",
+                },
+            ])
+        );
+
+        // errors returned instead of recorded, and those of no file
+        let diagnostic = |message: &str| Diagnostic::from_message(message, Severity::Error);
+        assert_eq!(
+            diagnostic("a:b.sql:4:2 table does not found: t"),
+            Diagnostic {
+                file: Some("a:b.sql".to_string()),
+                line: Some(4),
+                column: Some(2),
+                message: "table does not found: t".to_string(),
+                severity: Severity::Error,
+            }
+        );
+        assert_eq!(diagnostic("4:2 x").file, None);
+        assert_eq!(diagnostic("4:2 x").line, Some(4));
+        assert_eq!(diagnostic("no such file: a.sql").line, None);
+    }
+
+    #[test]
+    fn test_case_folding() {
+        let inspect = |dialect: Dialect, case_folding: Option<CaseFolding>| {
+            let quote = dialect.quote_char();
+            let sql = format!("CREATE TABLE App_Users (Id INT, {quote}Display_Name{quote} TEXT);");
+            let mut dbinfo = Dbinfo {
+                dialect,
+                default_catalog: "test".into(),
+                catalogs: HashMap::from([("test".into(), Catalog::new("test", "public"))]),
+            };
+            let mut inspector = Inspector::new(&mut dbinfo);
+            if let Some(case_folding) = case_folding {
+                inspector = inspector.with_case_folding(case_folding);
+            }
+            inspector.inspect(&sql, "test.sql").unwrap();
+            let schema = dbinfo
+                .get_catalog("test")
+                .unwrap()
+                .get_schema("public")
+                .unwrap();
+            let (name, table) = schema.tables.iter().next().unwrap();
+            let columns: Vec<_> = table.columns.iter().map(|c| c.name.clone()).collect();
+            (name.clone(), columns)
+        };
+
+        let folded = |name: &str, columns: &[&str]| {
+            (
+                name.to_string(),
+                columns.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            inspect(Dialect::PostgreSql, None),
+            folded("app_users", &["id", "Display_Name"])
+        );
+        assert_eq!(
+            inspect(Dialect::MySql, None),
+            folded("App_Users", &["Id", "Display_Name"])
+        );
+        assert_eq!(
+            inspect(Dialect::PostgreSql, Some(CaseFolding::Lower)),
+            folded("app_users", &["id", "Display_Name"])
+        );
+        assert_eq!(
+            inspect(Dialect::PostgreSql, Some(CaseFolding::Preserve)),
+            folded("App_Users", &["Id", "Display_Name"])
+        );
+        assert_eq!(
+            inspect(Dialect::PostgreSql, Some(CaseFolding::Upper)),
+            folded("APP_USERS", &["ID", "Display_Name"])
+        );
+    }
+
+    #[test]
+    fn test_dialect_directive() {
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector
+            .inspect(
+                "-- schema for the mysql replica\n-- migi:dialect=mysql\nCREATE TABLE `t` (id INT) ENGINE=InnoDB;",
+                "mysql.sql",
+            )
+            .unwrap();
+        let err = inspector
+            .inspect(
+                "\n-- migi:dialect=oracle\nCREATE TABLE u (id INT);",
+                "oracle.sql",
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "oracle.sql:2:1 unknown dialect: oracle");
+
+        let table = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("t"),
+            })
+            .unwrap();
+        assert_eq!(table.engine.as_deref(), Some("InnoDB"));
+    }
+
+    #[test]
+    fn test_dialect_directive_changes_inspection() {
+        // CREATE SCHEMA creates a database in MySQL, named before the table
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        Inspector::new(&mut dbinfo)
+            .inspect(
+                "-- migi:dialect=mysql\nCREATE SCHEMA shop;\nCREATE TABLE shop.orders (id INT);",
+                "mysql.sql",
+            )
+            .unwrap();
+        let catalog = dbinfo.get_catalog("shop").unwrap();
+        assert!(catalog
+            .get_schema("public")
+            .unwrap()
+            .tables
+            .contains_key("orders"));
+
+        // SERIAL is an identity column in Postgres only
+        let options = test_options(Dialect::MySql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        Inspector::new(&mut dbinfo)
+            .inspect(
+                "-- migi:dialect=postgresql\nCREATE TABLE t (id SERIAL);",
+                "postgres.sql",
+            )
+            .unwrap();
+        let table = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("t"),
+            })
+            .unwrap();
+        assert_eq!(table.columns[0].data_type, DataType::Int(None));
+        assert!(table.columns[0].is_identity());
+    }
+
+    #[test]
+    fn test_alter_table_constraints() {
+        let sql = r#"
+            CREATE TABLE users (id INT PRIMARY KEY, email TEXT);
+            CREATE TABLE posts (id INT, user_id INT);
+            ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);
+            ALTER TABLE posts ADD CONSTRAINT posts_user_fk
+                FOREIGN KEY (user_id) REFERENCES users (id) NOT VALID;
+            ALTER TABLE posts VALIDATE CONSTRAINT posts_user_fk;
+            ALTER TABLE posts DROP CONSTRAINT posts_user_fk;
+            ALTER TABLE posts DROP CONSTRAINT IF EXISTS posts_user_fk;
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        let err = inspector
+            .inspect(
+                "ALTER TABLE posts DROP CONSTRAINT posts_user_fk;",
+                "test.sql",
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:1:1 constraint does not found: posts_user_fk"
+        );
+
+        let table_name = |table: &str| TableName {
+            catalog: None,
+            schema: None,
+            table: Ident::new(table),
+        };
+        let users = dbinfo.get_table(&table_name("users")).unwrap();
+        assert!(matches!(
+            &users.constraints[..],
+            [
+                TableConstraint::PrimaryKey { .. },
+                TableConstraint::Unique { name: Some(name), .. },
+            ] if name.value == "users_email_key"
+        ));
+        let posts = dbinfo.get_table(&table_name("posts")).unwrap();
+        assert!(posts.constraints.is_empty());
+    }
+
+    #[test]
+    fn test_idempotent_add_and_drop_column() {
+        let sql = r#"
+            CREATE TABLE users (id INT, email TEXT);
+            ALTER TABLE users ADD COLUMN IF NOT EXISTS email VARCHAR(100);
+            ALTER TABLE users ADD COLUMN IF NOT EXISTS name TEXT;
+            ALTER TABLE users DROP COLUMN IF EXISTS age;
+            ALTER TABLE users DROP COLUMN IF EXISTS id;
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        // applying the alterations twice changes nothing more
+        inspector.inspect(sql, "test.sql").unwrap();
+        inspector
+            .inspect(
+                "ALTER TABLE users ADD COLUMN IF NOT EXISTS name TEXT;
+                 ALTER TABLE users DROP COLUMN IF EXISTS id;",
+                "test.sql",
+            )
+            .unwrap();
+        for (sql, message) in [
+            (
+                "ALTER TABLE users ADD COLUMN name TEXT;",
+                "test.sql:1:1 column name already exists",
+            ),
+            (
+                "ALTER TABLE users DROP COLUMN id;",
+                "test.sql:1:1 column does not found: id",
+            ),
+        ] {
+            let err = inspector.inspect(sql, "test.sql").unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+
+        let users = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("users"),
+            })
+            .unwrap();
+        let columns: Vec<String> = users
+            .columns
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.data_type))
+            .collect();
+        assert_eq!(columns, ["email TEXT", "name TEXT"]);
+    }
+
+    #[test]
+    fn test_inline_and_table_level_foreign_keys_are_equal() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+
+        let users = "CREATE TABLE users (id INT PRIMARY KEY);";
+        let table_level = inspect(&format!(
+            "{} CREATE TABLE posts (id INT, user_id INT, author_id INT,
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+                CONSTRAINT posts_author_fk FOREIGN KEY (author_id) REFERENCES users (id));",
+            users
+        ));
+        let inline = inspect(&format!(
+            "{} CREATE TABLE posts (id INT,
+                user_id INT REFERENCES users (id) ON DELETE CASCADE,
+                author_id INT CONSTRAINT posts_author_fk REFERENCES users (id));",
+            users
+        ));
+        assert_eq!(inline, table_level);
+
+        let added = inspect(&format!(
+            "{} CREATE TABLE posts (id INT, user_id INT REFERENCES users (id) ON DELETE CASCADE);
+                ALTER TABLE posts ADD COLUMN author_id INT CONSTRAINT posts_author_fk REFERENCES users (id);",
+            users
+        ));
+        assert_eq!(added, table_level);
+    }
+
+    #[test]
+    fn test_include_schemas() {
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE SCHEMA tenant_1;
+            CREATE TABLE app.users (id INT);
+            CREATE TABLE tenant_1.users (id INT);
+            CREATE TABLE tenant_1.orders (id INT);
+            ALTER TABLE tenant_1.missing ADD COLUMN name TEXT;
+            COMMENT ON COLUMN tenant_1.users.id IS 'id';
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector =
+            Inspector::new(&mut dbinfo).with_include_schemas(vec!["app".to_string()]);
+        inspector.inspect(sql, "test.sql").unwrap();
+
+        let catalog = dbinfo.get_catalog("test").unwrap();
+        let mut schemas: Vec<&str> = catalog.schemas.keys().map(|k| k.as_str()).collect();
+        schemas.sort();
+        assert_eq!(schemas, vec!["app", "public"]);
+        assert_eq!(dbinfo.stats().tables, 1);
+    }
+
+    #[test]
+    fn test_locations_after_comments() {
+        let sql = "/*\n * schema\n /* nested */\n */\n-- users\r\n\n\
+                   CREATE TABLE t (id INT, name TEXT DEFAULT $$a\nb$$);\n\
+                   /* é */ ALTER TABLE missing ADD COLUMN x INT;";
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        let err = inspector.inspect(sql, "test.sql").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:9:9 table does not found: missing"
+        );
+    }
+
+    #[test]
+    fn test_inspect_with_events() {
+        let sql = "CREATE SCHEMA app;\n\
+                   CREATE TABLE app.users (id INT, email TEXT);\n\
+                   CREATE INDEX ON app.users (email);\n\
+                   ALTER TABLE app.users ADD COLUMN name TEXT;\n\
+                   CREATE VIEW names AS SELECT name FROM app.users;";
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        let events = Inspector::new(&mut dbinfo)
+            .inspect_with_events(sql, "test.sql")
+            .unwrap();
+
+        let name = |schema: &str, table: &str| TableName {
+            catalog: Some(Ident::new("test")),
+            schema: Some(Ident::new(schema)),
+            table: Ident::new(table),
+        };
+        let users = name("app", "users");
+        assert_eq!(
+            events,
+            [
+                SchemaEvent::TableDefined {
+                    name: users.clone(),
+                    location: "test.sql:2:1".to_string(),
+                },
+                SchemaEvent::ColumnDefined {
+                    table: users.clone(),
+                    name: "id".to_string(),
+                    location: "test.sql:2:1".to_string(),
+                },
+                SchemaEvent::ColumnDefined {
+                    table: users.clone(),
+                    name: "email".to_string(),
+                    location: "test.sql:2:1".to_string(),
+                },
+                SchemaEvent::IndexDefined {
+                    table: users.clone(),
+                    name: "users_email_idx".to_string(),
+                    location: "test.sql:3:1".to_string(),
+                },
+                SchemaEvent::ColumnDefined {
+                    table: users.clone(),
+                    name: "name".to_string(),
+                    location: "test.sql:4:1".to_string(),
+                },
+                SchemaEvent::ViewDefined {
+                    name: name("public", "names"),
+                    location: "test.sql:5:1".to_string(),
+                },
+            ]
+        );
+
+        // events are only collected when asked for
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert!(inspector.events.is_none());
+    }
+
+    #[test]
+    fn test_create_domain() {
+        let sql = r#"
+            CREATE DOMAIN email AS TEXT NOT NULL CHECK ((VALUE LIKE '%@%'));
+            CREATE DOMAIN positive INT DEFAULT 1 CONSTRAINT positive_check CHECK (VALUE > 0);
+            CREATE TABLE users (id INT, email email, age positive);
+        "#;
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        let mut inspector = Inspector::new(&mut dbinfo).with_strict(true);
+        inspector.inspect(sql, "test.sql").unwrap();
+
+        let schema = dbinfo.get_catalog("test").unwrap().schemas.get("public");
+        let email = schema.unwrap().domains.get("email").unwrap();
+        assert_eq!(email.data_type, DataType::Text);
+        assert!(email.not_null);
+        assert_eq!(email.constraints.len(), 1);
+        assert_eq!(email.constraints[0].name, None);
+        assert_eq!(email.constraints[0].check.to_string(), "VALUE LIKE '%@%'");
+        assert_eq!(
+            dbinfo.to_string(),
+            [
+                "catalog test",
+                "  schema public",
+                "    table users",
+                "      id INT NULL",
+                "      email \"email\" NULL",
+                "      age \"positive\" NULL",
+                "    domain email AS TEXT NOT NULL CHECK (VALUE LIKE '%@%')",
+                "    domain positive AS INT DEFAULT 1 CONSTRAINT \"positive_check\" CHECK (VALUE > 0)",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let table = [
+            "test".to_string(),
+            "public".to_string(),
+            "users".to_string(),
+        ];
+        let users = dbinfo
+            .get_table(&TableName {
+                catalog: None,
+                schema: None,
+                table: Ident::new("users"),
+            })
+            .unwrap();
+        let domain = |i: usize| {
+            dbinfo
+                .resolve_domain(&table, &users.columns[i].data_type)
+                .map(|d| d.name.as_str())
+        };
+        assert_eq!(domain(0), None);
+        assert_eq!(domain(1), Some("email"));
+        assert_eq!(domain(2), Some("positive"));
+
+        let mut dbinfo = Dbinfo::with_options(Options {
+            dialect: Dialect::MySql,
+            ..options
+        });
+        let err = Inspector::new(&mut dbinfo)
+            .inspect(
+                "CREATE TABLE t (id INT);\nCREATE DOMAIN d AS INT;",
+                "test.sql",
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:2:1 CREATE DOMAIN is only supported by PostgreSQL"
+        );
+    }
+
+    #[test]
+    fn test_exclusion_constraints() {
+        let sql = "
+            CREATE TABLE bookings (
+                id INT,
+                EXCLUDE USING gist (room WITH =, during WITH &&),
+                room INT,
+                CONSTRAINT one_owner EXCLUDE (owner WITH =) WHERE (owner > 0),
+                CONSTRAINT positive CHECK (id > 0),
+                owner INT
+            );
+            ALTER TABLE bookings ADD CONSTRAINT one_id EXCLUDE (id WITH =);
+            ALTER TABLE bookings DROP CONSTRAINT bookings_room_excl;
+        ";
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        let mut inspector = Inspector::new(&mut dbinfo).with_strict(true);
+        inspector.inspect(sql, "test.sql").unwrap();
+        assert_eq!(
+            dbinfo.to_string(),
+            [
+                "catalog test",
+                "  schema public",
+                "    table bookings",
+                "      id INT NULL",
+                "      room INT NULL",
+                "      owner INT NULL",
+                "      CONSTRAINT \"positive\" CHECK (\"id\" > 0)",
+                "      CONSTRAINT \"one_owner\" EXCLUDE (\"owner\" WITH =) WHERE (\"owner\" > 0)",
+                "      CONSTRAINT \"one_id\" EXCLUDE (\"id\" WITH =)",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let mut dbinfo = Dbinfo::with_options(Options {
+            dialect: Dialect::MySql,
+            ..options
+        });
+        let err = Inspector::new(&mut dbinfo)
+            .inspect(
+                "CREATE TABLE t (id INT);\nALTER TABLE t ADD EXCLUDE (id WITH =);",
+                "test.sql",
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "test.sql:2:1 EXCLUDE constraints are only supported by PostgreSQL"
+        );
+    }
+
+    #[test]
+    fn test_create_function_with_dollar_quoted_body() {
+        let body = "
+            BEGIN
+                NEW.updated_at := now();
+                INSERT INTO audit VALUES ('users; updated');
+                RETURN NEW;
+            END;
+        ";
+        let sql = format!(
+            r#"
+            CREATE TABLE users (id INT, updated_at TIMESTAMP);
+            CREATE FUNCTION touch() RETURNS trigger LANGUAGE plpgsql AS $fn${}$fn$;
+            CREATE FUNCTION add(a INTEGER, b INTEGER) RETURNS INTEGER AS 'SELECT a + b;' LANGUAGE sql IMMUTABLE;
+            CREATE FUNCTION add(a BIGINT, b BIGINT) RETURNS BIGINT AS $$ SELECT a + b; $$ LANGUAGE sql;
+            CREATE TABLE posts (id INT);
+            "#,
+            body
+        );
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo).with_strict(true);
+        inspector.inspect(&sql, "test.sql").unwrap();
+
+        let schema = dbinfo.get_catalog("test").unwrap().schemas.get("public");
+        let schema = schema.unwrap();
+        assert_eq!(schema.tables.len(), 2);
+        let touch = schema.routines.get("touch()").unwrap();
+        assert_eq!(touch.body.as_deref(), Some(body));
+        assert_eq!(touch.language.as_deref(), Some("plpgsql"));
+        let mut signatures: Vec<&String> = schema.routines.keys().collect();
+        signatures.sort();
+        assert_eq!(
+            signatures,
+            ["add(BIGINT, BIGINT)", "add(INTEGER, INTEGER)", "touch()"]
+        );
+        assert_eq!(
+            schema.routines["add(INTEGER, INTEGER)"].body.as_deref(),
+            Some("SELECT a + b;")
+        );
+    }
+
+    #[test]
+    fn test_create_trigger() {
+        let body = "BEGIN
+                IF NEW.total < 0 THEN
+                    SET NEW.total = 0;
+                END IF;
+                SET NEW.label = CASE WHEN NEW.total > 100 THEN 'big' ELSE 'small' END;
+            END";
+        let sql = format!(
+            r#"
+            CREATE TABLE orders (id INT, total INT, label TEXT);
+            CREATE TRIGGER orders_clamp BEFORE UPDATE OF total, label ON orders FOR EACH ROW {};
+            CREATE TABLE items (id INT);
+            "#,
+            body
+        );
+
+        let options = test_options(Dialect::MySql);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo).with_strict(true);
+        inspector.inspect(&sql, "test.sql").unwrap();
+
+        let schema = dbinfo.get_catalog("test").unwrap().schemas.get("public");
+        let schema = schema.unwrap();
+        assert_eq!(schema.tables.len(), 2);
+        let triggers = &schema.tables["orders"].triggers;
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].name, "orders_clamp");
+        assert_eq!(triggers[0].timing, TriggerTiming::Before);
+        assert_eq!(
+            triggers[0].events,
+            [TriggerEvent::Update(vec![
+                Ident::with_quote('`', "total"),
+                Ident::with_quote('`', "label")
+            ])]
+        );
+        assert!(triggers[0].for_each_row);
+        assert_eq!(triggers[0].action, body);
+
+        let err = Inspector::new(&mut dbinfo)
+            .inspect(
+                "CREATE TRIGGER orders_clamp AFTER INSERT ON orders FOR EACH ROW SET @n = 1;",
+                "other.sql",
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "other.sql:1:1 trigger orders_clamp already exists"
+        );
+    }
+
+    #[test]
+    fn test_read_sources_from_directory() {
+        let dir = std::env::temp_dir().join(format!("migi-sources-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("b.sql"), "-- b.sql").unwrap();
+        std::fs::write(dir.join("b/2.sql"), "-- b/2.sql").unwrap();
+        std::fs::write(dir.join("a.sql"), "-- a.sql").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("b/loop")).unwrap();
+
+        let sources = read_sources(&[dir.display().to_string()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let filenames: Vec<String> = sources
+            .unwrap()
+            .into_iter()
+            .map(|(filename, sql)| {
+                assert_eq!(
+                    sql,
+                    format!("-- {}", &filename[dir.display().to_string().len() + 1..])
+                );
+                filename
+            })
+            .collect();
+        assert_eq!(
+            filenames,
+            ["a.sql", "b/2.sql", "b.sql"]
+                .iter()
+                .map(|file| dir.join(file).display().to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_read_sources_reports_every_missing_path() {
+        let dir = std::env::temp_dir().join(format!("migi-missing-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.sql"), "").unwrap();
+        let path = |file: &str| dir.join(file).display().to_string();
+
+        let result = read_sources(&[path("a.sql"), path("b.sql"), path("c")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "cannot read the schema files:\n  {}: no such file or directory\n  {}: no such file or directory",
+                path("b.sql"),
+                path("c")
+            )
+        );
+    }
 }
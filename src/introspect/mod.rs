@@ -0,0 +1,966 @@
+use anyhow::Result;
+use sqlparser::ast::{
+    ColumnOption, ColumnOptionDef, DataType, Expr, Ident, ObjectName, ReferentialAction,
+    TableConstraint,
+};
+use sqlparser::keywords::{Keyword, ALL_KEYWORDS};
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+use crate::dbinfo::{Column, Dbinfo, Identity, IdentityKind, Schema, Table, TableName};
+use crate::inspector::{location_offset, Inspector};
+use crate::{Dialect, Options};
+
+// A connection to a live database, implemented over whichever driver the
+// caller uses. Rows are returned as text, NULL being `None`.
+pub trait Connection {
+    fn query(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>>;
+}
+
+impl Connection for postgres::Client {
+    fn query(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>> {
+        // The simple query protocol returns every value as text.
+        Ok(self
+            .simple_query(sql)?
+            .into_iter()
+            .filter_map(|message| match message {
+                postgres::SimpleQueryMessage::Row(row) => Some(
+                    (0..row.len())
+                        .map(|i| row.get(i).map(str::to_string))
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+const SYSTEM_SCHEMAS: &str = "('pg_catalog', 'information_schema', 'pg_toast')";
+
+// Builds a Dbinfo from the information_schema of a live Postgres database, the
+// same way the inspector would from the SQL files creating it, so that the two
+// can be diffed. Domains, indexes and views are read as the SQL Postgres
+// reconstructs for them and handed to the inspector. View queries come back
+// reformatted and with qualified columns, so a view diffed against its file is
+// replaced even when unchanged.
+pub struct Introspector<'a, C: Connection> {
+    connection: &'a mut C,
+    options: Options,
+}
+
+impl<'a, C: Connection> Introspector<'a, C> {
+    pub fn new(connection: &'a mut C, options: Options) -> Self {
+        Self {
+            connection,
+            options,
+        }
+    }
+
+    pub fn introspect(&mut self) -> Result<Dbinfo> {
+        if self.options.dialect != Dialect::PostgreSql {
+            anyhow::bail!(
+                "introspecting {} databases is not supported yet",
+                self.options.dialect
+            );
+        }
+
+        let mut dbinfo = Dbinfo::with_options(self.options.clone());
+        let schemas = self.query(&format!(
+            "SELECT schema_name FROM information_schema.schemata \
+             WHERE schema_name NOT IN {} AND schema_name NOT LIKE 'pg_temp%' \
+             AND schema_name NOT LIKE 'pg_toast_temp%' ORDER BY schema_name",
+            SYSTEM_SCHEMAS
+        ))?;
+        let catalog = dbinfo.get_catalog_mut(&self.options.database)?;
+        for row in schemas {
+            let name = text(&row, 0)?;
+            if !catalog.schemas.contains_key(&name) {
+                catalog.add_schema(&name, Schema::new(&name));
+            }
+        }
+
+        let tables = self.query(&format!(
+            "SELECT table_schema, table_name FROM information_schema.tables \
+             WHERE table_type = 'BASE TABLE' AND table_schema NOT IN {} \
+             ORDER BY table_schema, table_name",
+            SYSTEM_SCHEMAS
+        ))?;
+        for row in tables {
+            let name = table_name(&text(&row, 0)?, &text(&row, 1)?);
+            dbinfo.add_table(&name, Table::new(&name.table.value))?;
+        }
+
+        self.introspect_constraints(&mut dbinfo)?;
+        self.introspect_foreign_keys(&mut dbinfo)?;
+        self.introspect_columns(&mut dbinfo)?;
+        self.introspect_checks(&mut dbinfo)?;
+
+        let mut sql = self.domains_sql()?;
+        sql.extend(self.views_sql()?);
+        sql.extend(self.indexes_sql()?);
+        Inspector::new(&mut dbinfo).inspect(&sql.join(";\n"), &self.options.database)?;
+
+        Ok(dbinfo)
+    }
+
+    fn introspect_columns(&mut self, dbinfo: &mut Dbinfo) -> Result<()> {
+        // is_nullable also says NO for the columns of a NOT NULL domain, which
+        // the column itself doesn't declare.
+        let columns = self.query(&format!(
+            "SELECT table_schema, table_name, column_name, data_type, udt_name, \
+             character_maximum_length, numeric_precision, numeric_scale, \
+             (SELECT CASE WHEN a.attnotnull THEN 'NO' ELSE 'YES' END FROM pg_attribute a \
+             WHERE a.attrelid = format('%I.%I', table_schema, table_name)::regclass \
+             AND a.attname = column_name), \
+             column_default, is_identity, identity_generation, domain_schema, domain_name \
+             FROM information_schema.columns WHERE table_schema NOT IN {} \
+             ORDER BY table_schema, table_name, ordinal_position",
+            SYSTEM_SCHEMAS
+        ))?;
+        for row in columns {
+            let name = table_name(&text(&row, 0)?, &text(&row, 1)?);
+            let Ok(table) = dbinfo.get_table_mut(&name) else {
+                // a view
+                continue;
+            };
+
+            let column_name = text(&row, 2)?;
+            // Columns of a domain report the type of the domain.
+            let data_type = match (row[12].as_deref(), row[13].as_deref()) {
+                (Some(schema), Some(domain)) if schema == self.options.default_schema => {
+                    DataType::Custom(ObjectName(vec![ident(domain)]), vec![])
+                }
+                (Some(schema), Some(domain)) => {
+                    DataType::Custom(ObjectName(vec![ident(schema), ident(domain)]), vec![])
+                }
+                _ => parse_data_type(
+                    &text(&row, 3)?,
+                    &text(&row, 4)?,
+                    row[5].as_deref(),
+                    row[6].as_deref(),
+                    row[7].as_deref(),
+                )?,
+            };
+            let mut default = row[9].as_deref().map(parse_expr).transpose()?;
+
+            let mut identity = match (row[10].as_deref(), row[11].as_deref()) {
+                (Some("YES"), Some("ALWAYS")) => Some(Identity {
+                    kind: IdentityKind::Always,
+                    sequence_options: vec![],
+                }),
+                (Some("YES"), _) => Some(Identity {
+                    kind: IdentityKind::ByDefault,
+                    sequence_options: vec![],
+                }),
+                _ => None,
+            };
+            // SERIAL columns are inspected as BY DEFAULT identity columns.
+            if matches!(&default, Some(Expr::Function(f)) if f.name.0[0].value == "nextval") {
+                default = None;
+                identity = Some(Identity {
+                    kind: IdentityKind::ByDefault,
+                    sequence_options: vec![],
+                });
+            }
+
+            // Primary keys and identity columns are NOT NULL whether or not
+            // the column says so.
+            let primary_key = table.constraints.iter().any(|c| {
+                matches!(c, TableConstraint::PrimaryKey { columns, .. }
+                    if columns.iter().any(|c| c.value == column_name))
+            });
+            let mut options = vec![];
+            if row[8].as_deref() == Some("NO") && !primary_key && identity.is_none() {
+                options.push(ColumnOptionDef {
+                    name: None,
+                    option: ColumnOption::NotNull,
+                });
+            }
+            if let Some(default) = default {
+                options.push(ColumnOptionDef {
+                    name: None,
+                    option: ColumnOption::Default(default),
+                });
+            }
+
+            let mut column = Column {
+                name: column_name,
+                data_type,
+                collation: None,
+                options,
+                identity,
+            };
+            column.normalize_options();
+            table.columns.push(column);
+        }
+        Ok(())
+    }
+
+    fn introspect_constraints(&mut self, dbinfo: &mut Dbinfo) -> Result<()> {
+        let rows = self.query(&format!(
+            "SELECT tc.table_schema, tc.table_name, tc.constraint_name, tc.constraint_type, \
+             kcu.column_name \
+             FROM information_schema.table_constraints tc \
+             JOIN information_schema.key_column_usage kcu \
+             ON kcu.constraint_schema = tc.constraint_schema \
+             AND kcu.constraint_name = tc.constraint_name AND kcu.table_name = tc.table_name \
+             WHERE tc.constraint_type IN ('PRIMARY KEY', 'UNIQUE') AND tc.table_schema NOT IN {} \
+             ORDER BY tc.table_schema, tc.table_name, tc.constraint_name, kcu.ordinal_position",
+            SYSTEM_SCHEMAS
+        ))?;
+
+        for group in group_rows(&rows, 3) {
+            let name = table_name(&text(group[0], 0)?, &text(group[0], 1)?);
+            let columns = group
+                .iter()
+                .map(|row| Ok(ident(&text(row, 4)?)))
+                .collect::<Result<Vec<Ident>>>()?;
+            let column_names: Vec<&str> = columns.iter().map(|c| c.value.as_str()).collect();
+            let table = &name.table.value;
+
+            let constraint_name = text(group[0], 2)?;
+            let constraint = if text(group[0], 3)? == "PRIMARY KEY" {
+                TableConstraint::PrimaryKey {
+                    name: explicit_name(constraint_name, format!("{}_pkey", table)),
+                    index_name: None,
+                    index_type: None,
+                    columns,
+                    index_options: vec![],
+                    characteristics: None,
+                }
+            } else {
+                let default_name = format!("{}_{}_key", table, column_names.join("_"));
+                TableConstraint::Unique {
+                    name: explicit_name(constraint_name, default_name),
+                    index_name: None,
+                    index_type_display: sqlparser::ast::KeyOrIndexDisplay::None,
+                    index_type: None,
+                    columns,
+                    index_options: vec![],
+                    characteristics: None,
+                }
+            };
+            dbinfo.get_table_mut(&name)?.constraints.push(constraint);
+        }
+        Ok(())
+    }
+
+    fn introspect_foreign_keys(&mut self, dbinfo: &mut Dbinfo) -> Result<()> {
+        let rows = self.query(&format!(
+            "SELECT kcu.table_schema, kcu.table_name, kcu.constraint_name, kcu.column_name, \
+             rkcu.table_schema, rkcu.table_name, rkcu.column_name, rc.delete_rule, rc.update_rule \
+             FROM information_schema.referential_constraints rc \
+             JOIN information_schema.key_column_usage kcu \
+             ON kcu.constraint_schema = rc.constraint_schema \
+             AND kcu.constraint_name = rc.constraint_name \
+             JOIN information_schema.key_column_usage rkcu \
+             ON rkcu.constraint_schema = rc.unique_constraint_schema \
+             AND rkcu.constraint_name = rc.unique_constraint_name \
+             AND rkcu.ordinal_position = kcu.position_in_unique_constraint \
+             WHERE kcu.table_schema NOT IN {} \
+             ORDER BY kcu.table_schema, kcu.table_name, kcu.constraint_name, kcu.ordinal_position",
+            SYSTEM_SCHEMAS
+        ))?;
+
+        for group in group_rows(&rows, 2) {
+            let schema = text(group[0], 0)?;
+            let name = table_name(&schema, &text(group[0], 1)?);
+            let columns = group
+                .iter()
+                .map(|row| Ok(ident(&text(row, 3)?)))
+                .collect::<Result<Vec<Ident>>>()?;
+            let referred_columns = group
+                .iter()
+                .map(|row| Ok(ident(&text(row, 6)?)))
+                .collect::<Result<Vec<Ident>>>()?;
+
+            // Tables of the default schema are referenced as they usually are
+            // written, without their schema.
+            let foreign_schema = text(group[0], 4)?;
+            let foreign_table = ident(&text(group[0], 5)?);
+            let foreign_table = if foreign_schema == self.options.default_schema {
+                ObjectName(vec![foreign_table])
+            } else {
+                ObjectName(vec![ident(&foreign_schema), foreign_table])
+            };
+
+            let column_names: Vec<&str> = columns.iter().map(|c| c.value.as_str()).collect();
+            let default_name = format!("{}_{}_fkey", name.table.value, column_names.join("_"));
+            let constraint = TableConstraint::ForeignKey {
+                name: explicit_name(text(group[0], 2)?, default_name),
+                columns,
+                foreign_table,
+                referred_columns,
+                on_delete: referential_action(group[0][7].as_deref()),
+                on_update: referential_action(group[0][8].as_deref()),
+                characteristics: None,
+            };
+            dbinfo.get_table_mut(&name)?.constraints.push(constraint);
+        }
+        Ok(())
+    }
+
+    // CHECK constraints on a single column are read back as column options
+    // when Postgres named them the way it names unnamed column checks.
+    fn introspect_checks(&mut self, dbinfo: &mut Dbinfo) -> Result<()> {
+        let rows = self.query(&format!(
+            "SELECT n.nspname, c.relname, con.conname, pg_get_constraintdef(con.oid), \
+             CASE WHEN array_length(con.conkey, 1) = 1 THEN (SELECT a.attname FROM pg_attribute a \
+             WHERE a.attrelid = con.conrelid AND a.attnum = con.conkey[1]) END \
+             FROM pg_constraint con JOIN pg_class c ON c.oid = con.conrelid \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE con.contype = 'c' AND n.nspname NOT IN {} \
+             ORDER BY n.nspname, c.relname, con.conname",
+            SYSTEM_SCHEMAS
+        ))?;
+
+        for row in rows {
+            let name = table_name(&text(&row, 0)?, &text(&row, 1)?);
+            let constraint_name = text(&row, 2)?;
+            let expr = parse_check(&text(&row, 3)?)?;
+            let table = dbinfo.get_table_mut(&name)?;
+
+            let column = row[4]
+                .as_deref()
+                .filter(|column| constraint_name == format!("{}_{}_check", table.name, column));
+            match column.and_then(|column| table.columns.iter_mut().find(|c| c.name == column)) {
+                Some(column) => {
+                    column.options.push(ColumnOptionDef {
+                        name: None,
+                        option: ColumnOption::Check(expr),
+                    });
+                    column.normalize_options();
+                }
+                None => {
+                    let default_name = format!("{}_check", table.name);
+                    table.constraints.push(TableConstraint::Check {
+                        name: explicit_name(constraint_name, default_name),
+                        expr: Box::new(expr),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn domains_sql(&mut self) -> Result<Vec<String>> {
+        let rows = self.query(&format!(
+            "SELECT n.nspname, t.typname, format_type(t.typbasetype, t.typtypmod), \
+             t.typnotnull, t.typdefault, con.conname, pg_get_constraintdef(con.oid) \
+             FROM pg_type t JOIN pg_namespace n ON n.oid = t.typnamespace \
+             LEFT JOIN pg_constraint con ON con.contypid = t.oid AND con.contype = 'c' \
+             WHERE t.typtype = 'd' AND n.nspname NOT IN {} \
+             ORDER BY n.nspname, t.typname, con.conname",
+            SYSTEM_SCHEMAS
+        ))?;
+
+        let mut statements = vec![];
+        for group in group_rows(&rows, 2) {
+            let domain = text(group[0], 1)?;
+            let mut sql = format!(
+                "CREATE DOMAIN {}.{} AS {}",
+                ident(&text(group[0], 0)?),
+                ident(&domain),
+                text(group[0], 2)?
+            );
+            if let Some(default) = &group[0][4] {
+                sql += &format!(" DEFAULT {}", default);
+            }
+            if group[0][3].as_deref() == Some("t") {
+                sql += " NOT NULL";
+            }
+            for row in group.iter().filter(|row| row[5].is_some()) {
+                if let Some(name) = explicit_name(text(row, 5)?, format!("{}_check", domain)) {
+                    sql += &format!(" CONSTRAINT {}", name);
+                }
+                sql += &format!(" {}", text(row, 6)?);
+            }
+            statements.push(undo_deparse(&sql)?);
+        }
+        Ok(statements)
+    }
+
+    fn views_sql(&mut self) -> Result<Vec<String>> {
+        let rows = self.query(&format!(
+            "SELECT schemaname, viewname, definition, 'VIEW' FROM pg_views \
+             WHERE schemaname NOT IN {0} \
+             UNION ALL SELECT schemaname, matviewname, definition, 'MATERIALIZED VIEW' \
+             FROM pg_matviews WHERE schemaname NOT IN {0} \
+             ORDER BY 1, 2",
+            SYSTEM_SCHEMAS
+        ))?;
+
+        rows.iter()
+            .map(|row| {
+                let definition = text(row, 2)?;
+                undo_deparse(&format!(
+                    "CREATE {} {}.{} AS {}",
+                    text(row, 3)?,
+                    ident(&text(row, 0)?),
+                    ident(&text(row, 1)?),
+                    definition.trim().trim_end_matches(';')
+                ))
+            })
+            .collect()
+    }
+
+    // Indexes backing a constraint are part of the constraint.
+    fn indexes_sql(&mut self) -> Result<Vec<String>> {
+        let rows = self.query(&format!(
+            "SELECT pg_get_indexdef(i.indexrelid) FROM pg_index i \
+             JOIN pg_class c ON c.oid = i.indexrelid \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE n.nspname NOT IN {} AND NOT EXISTS (SELECT 1 FROM pg_constraint con \
+             WHERE con.conindid = i.indexrelid AND con.conrelid = i.indrelid) \
+             ORDER BY n.nspname, c.relname",
+            SYSTEM_SCHEMAS
+        ))?;
+
+        rows.iter()
+            .map(|row| {
+                let mut sql = text(row, 0)?;
+                // The predicate comes parenthesized.
+                if let Some((index, predicate)) = sql.split_once(" WHERE (") {
+                    let predicate = predicate.strip_suffix(')').unwrap_or(predicate);
+                    sql = format!("{} WHERE {}", index, predicate);
+                }
+                undo_deparse(&sql)
+            })
+            .collect()
+    }
+
+    fn query(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>> {
+        self.connection.query(sql)
+    }
+}
+
+fn text(row: &[Option<String>], i: usize) -> Result<String> {
+    row.get(i)
+        .cloned()
+        .flatten()
+        .ok_or_else(|| anyhow::anyhow!("unexpected NULL in column {} of {:?}", i, row))
+}
+
+// Rows sorted by table and constraint, grouped by the first `columns` values.
+fn group_rows(rows: &[Vec<Option<String>>], columns: usize) -> Vec<Vec<&Vec<Option<String>>>> {
+    let mut groups: Vec<Vec<&Vec<Option<String>>>> = vec![];
+    for row in rows {
+        match groups.last_mut() {
+            Some(group) if group[0][..columns] == row[..columns] => group.push(row),
+            _ => groups.push(vec![row]),
+        }
+    }
+    groups
+}
+
+fn table_name(schema: &str, table: &str) -> TableName {
+    TableName {
+        catalog: None,
+        schema: Some(Ident::new(schema)),
+        table: Ident::new(table),
+    }
+}
+
+// Identifiers are quoted the way the inspector quotes them: every word but
+// keywords.
+fn ident(value: &str) -> Ident {
+    if ALL_KEYWORDS
+        .binary_search(&value.to_uppercase().as_str())
+        .is_ok()
+    {
+        Ident::new(value)
+    } else {
+        Ident::with_quote('"', value)
+    }
+}
+
+// Constraints left unnamed get a name from Postgres, which is dropped again so
+// that they compare equal to the unnamed constraints of the SQL files.
+fn explicit_name(name: String, default_name: String) -> Option<Ident> {
+    if name == default_name {
+        None
+    } else {
+        Some(ident(&name))
+    }
+}
+
+fn referential_action(rule: Option<&str>) -> Option<ReferentialAction> {
+    match rule {
+        Some("CASCADE") => Some(ReferentialAction::Cascade),
+        Some("SET NULL") => Some(ReferentialAction::SetNull),
+        Some("SET DEFAULT") => Some(ReferentialAction::SetDefault),
+        Some("RESTRICT") => Some(ReferentialAction::Restrict),
+        // NO ACTION is the default
+        _ => None,
+    }
+}
+
+fn parse_data_type(
+    data_type: &str,
+    udt_name: &str,
+    length: Option<&str>,
+    precision: Option<&str>,
+    scale: Option<&str>,
+) -> Result<DataType> {
+    let sql = match data_type {
+        "USER-DEFINED" => format!("\"{}\"", udt_name),
+        // udt_name is the element type prefixed with an underscore, e.g. _int4
+        "ARRAY" => format!("{}[]", udt_name.trim_start_matches('_')),
+        "character varying" | "character" | "bit" | "bit varying" => match length {
+            Some(length) => format!("{}({})", data_type, length),
+            None => data_type.to_string(),
+        },
+        // precision and scale are always reported for the other numeric types
+        "numeric" => match (precision, scale) {
+            (Some(precision), Some(scale)) => format!("numeric({},{})", precision, scale),
+            _ => data_type.to_string(),
+        },
+        _ => data_type.to_string(),
+    };
+    Parser::new(&sqlparser::dialect::PostgreSqlDialect {})
+        .try_with_sql(&sql)?
+        .parse_data_type()
+        .map_err(|e| anyhow::anyhow!("invalid data type {}: {}", sql, e))
+}
+
+// Takes back what Postgres adds when it prints an expression: the casts to text
+// of string columns and literals, together with the parentheses around the
+// column, the `~~` operators it turns LIKE into, and the default btree method
+// of indexes. A `(title)::text` becomes `title` and `x ~~ 'a%'::text` becomes
+// `x LIKE 'a%'`.
+fn undo_deparse(sql: &str) -> Result<String> {
+    let dialect = sqlparser::dialect::PostgreSqlDialect {};
+    let tokens = Tokenizer::new(&dialect, sql)
+        .tokenize_with_location()
+        .map_err(|e| anyhow::anyhow!("invalid SQL {}: {}", sql, e))?;
+    let mut spans = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let start = location_offset(sql, token.location);
+        let end = match tokens.get(i + 1) {
+            Some(next) => location_offset(sql, next.location),
+            None => sql.len(),
+        };
+        spans.push((&token.token, start..end));
+    }
+
+    let is_word = |token: &Token, value: &str| matches!(token, Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(value));
+    let mut out: Vec<(&Token, String)> = vec![];
+    let mut i = 0;
+    while i < spans.len() {
+        let (token, span) = &spans[i];
+        let next = |n: usize| spans.get(i + n).map(|(token, _)| *token);
+        match token {
+            Token::DoubleColon if next(1).is_some_and(|t| is_word(t, "text")) => {
+                unwrap_column(&mut out);
+                i += 2;
+            }
+            Token::DoubleColon
+                if next(1).is_some_and(|t| is_word(t, "character"))
+                    && next(2) == Some(&Token::Whitespace(Whitespace::Space))
+                    && next(3).is_some_and(|t| is_word(t, "varying")) =>
+            {
+                unwrap_column(&mut out);
+                i += 4;
+            }
+            Token::DoubleTilde => {
+                out.push((token, "LIKE".to_string()));
+                i += 1;
+            }
+            Token::DoubleTildeAsterisk => {
+                out.push((token, "ILIKE".to_string()));
+                i += 1;
+            }
+            Token::ExclamationMarkDoubleTilde => {
+                out.push((token, "NOT LIKE".to_string()));
+                i += 1;
+            }
+            Token::ExclamationMarkDoubleTildeAsterisk => {
+                out.push((token, "NOT ILIKE".to_string()));
+                i += 1;
+            }
+            Token::Word(w)
+                if w.keyword == Keyword::USING
+                    && next(1) == Some(&Token::Whitespace(Whitespace::Space))
+                    && next(2).is_some_and(|t| is_word(t, "btree")) =>
+            {
+                // the space before USING
+                out.pop();
+                i += 3;
+            }
+            _ => {
+                out.push((token, sql[span.clone()].to_string()));
+                i += 1;
+            }
+        }
+    }
+    Ok(out.into_iter().map(|(_, text)| text).collect())
+}
+
+// Drops the parentheses Postgres puts around a column it casts.
+fn unwrap_column(out: &mut Vec<(&Token, String)>) {
+    if let [.., (Token::LParen, _), (Token::Word(_), _), (Token::RParen, _)] = out.as_slice() {
+        out.pop();
+        let column = out.pop().unwrap();
+        out.pop();
+        out.push(column);
+    }
+}
+
+// Parses SQL the way the inspector does, with every word but keywords quoted.
+fn parser(sql: &str) -> Result<Parser<'static>> {
+    let dialect = &sqlparser::dialect::PostgreSqlDialect {};
+    let mut tokens = Tokenizer::new(dialect, sql)
+        .tokenize_with_location()
+        .map_err(|e| anyhow::anyhow!("invalid SQL {}: {}", sql, e))?;
+    for token in &mut tokens {
+        if let Token::Word(w) = &mut token.token {
+            if w.keyword == Keyword::NoKeyword {
+                w.quote_style = Some('"');
+            }
+        }
+    }
+    Ok(Parser::new(dialect).with_tokens_with_locations(tokens))
+}
+
+// A CHECK constraint as pg_get_constraintdef prints it.
+fn parse_check(sql: &str) -> Result<Expr> {
+    let sql = undo_deparse(sql)?;
+    let mut parser = parser(&sql)?;
+    parser
+        .expect_keyword(Keyword::CHECK)
+        .and_then(|_| parser.parse_expr())
+        .map_err(|e| anyhow::anyhow!("invalid check {}: {}", sql, e))
+}
+
+fn parse_expr(sql: &str) -> Result<Expr> {
+    let sql = undo_deparse(sql)?;
+    let expr = parser(&sql)?
+        .parse_expr()
+        .map_err(|e| anyhow::anyhow!("invalid default {}: {}", sql, e))?;
+    // Postgres reports literal defaults with their type, e.g. 'a'::text.
+    Ok(match expr {
+        Expr::Cast { expr, .. } if matches!(*expr, Expr::Value(_)) => *expr,
+        expr => expr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspector::Inspector;
+    use crate::migrate::MigrationGenerator;
+    use crate::test_support::test_options;
+
+    use super::*;
+
+    // Answers the queries of the introspector with the rows of the first
+    // information_schema table its SQL mentions, and the queries of the
+    // Postgres catalogs with no rows.
+    struct FakeConnection(Vec<(&'static str, Vec<Vec<Option<&'static str>>>)>);
+
+    impl Connection for FakeConnection {
+        fn query(&mut self, sql: &str) -> Result<Vec<Vec<Option<String>>>> {
+            let rows = self
+                .0
+                .iter()
+                .find(|(table, _)| sql.contains(&format!("FROM information_schema.{} ", table)))
+                .map(|(_, rows)| rows.as_slice())
+                .unwrap_or_default();
+            Ok(rows
+                .iter()
+                .map(|row| row.iter().map(|v| v.map(|v| v.to_string())).collect())
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_introspect_postgres() {
+        let options = test_options(Dialect::PostgreSql);
+        let mut inspected = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut inspected)
+            .inspect(
+                r#"
+                CREATE SCHEMA app;
+                CREATE TABLE app.users (
+                    id SERIAL PRIMARY KEY,
+                    email VARCHAR(255) NOT NULL UNIQUE,
+                    name TEXT DEFAULT 'anonymous',
+                    balance NUMERIC(10,2)
+                );
+                CREATE TABLE posts (
+                    id INT GENERATED ALWAYS AS IDENTITY,
+                    user_id INT NOT NULL,
+                    tags TEXT[],
+                    CONSTRAINT posts_author FOREIGN KEY (user_id) REFERENCES app.users (id) ON DELETE CASCADE
+                );
+                "#,
+                "test.sql",
+            )
+            .unwrap();
+
+        let no = Some("NO");
+        let yes = Some("YES");
+        let mut connection = FakeConnection(vec![
+            ("schemata", vec![vec![Some("app")], vec![Some("public")]]),
+            (
+                "tables",
+                vec![
+                    vec![Some("app"), Some("users")],
+                    vec![Some("public"), Some("posts")],
+                ],
+            ),
+            (
+                "columns",
+                vec![
+                    vec![
+                        Some("app"),
+                        Some("users"),
+                        Some("id"),
+                        Some("integer"),
+                        Some("int4"),
+                        None,
+                        Some("32"),
+                        Some("0"),
+                        no,
+                        Some("nextval('app.users_id_seq'::regclass)"),
+                        no,
+                        None,
+                        None,
+                        None,
+                    ],
+                    vec![
+                        Some("app"),
+                        Some("users"),
+                        Some("email"),
+                        Some("character varying"),
+                        Some("varchar"),
+                        Some("255"),
+                        None,
+                        None,
+                        no,
+                        None,
+                        no,
+                        None,
+                        None,
+                        None,
+                    ],
+                    vec![
+                        Some("app"),
+                        Some("users"),
+                        Some("name"),
+                        Some("text"),
+                        Some("text"),
+                        None,
+                        None,
+                        None,
+                        yes,
+                        Some("'anonymous'::text"),
+                        no,
+                        None,
+                        None,
+                        None,
+                    ],
+                    vec![
+                        Some("app"),
+                        Some("users"),
+                        Some("balance"),
+                        Some("numeric"),
+                        Some("numeric"),
+                        None,
+                        Some("10"),
+                        Some("2"),
+                        yes,
+                        None,
+                        no,
+                        None,
+                        None,
+                        None,
+                    ],
+                    vec![
+                        Some("public"),
+                        Some("posts"),
+                        Some("id"),
+                        Some("integer"),
+                        Some("int4"),
+                        None,
+                        Some("32"),
+                        Some("0"),
+                        no,
+                        None,
+                        yes,
+                        Some("ALWAYS"),
+                        None,
+                        None,
+                    ],
+                    vec![
+                        Some("public"),
+                        Some("posts"),
+                        Some("user_id"),
+                        Some("integer"),
+                        Some("int4"),
+                        None,
+                        Some("32"),
+                        Some("0"),
+                        no,
+                        None,
+                        no,
+                        None,
+                        None,
+                        None,
+                    ],
+                    vec![
+                        Some("public"),
+                        Some("posts"),
+                        Some("tags"),
+                        Some("ARRAY"),
+                        Some("_text"),
+                        None,
+                        None,
+                        None,
+                        yes,
+                        None,
+                        no,
+                        None,
+                        None,
+                        None,
+                    ],
+                ],
+            ),
+            (
+                "table_constraints",
+                vec![
+                    vec![
+                        Some("app"),
+                        Some("users"),
+                        Some("users_email_key"),
+                        Some("UNIQUE"),
+                        Some("email"),
+                    ],
+                    vec![
+                        Some("app"),
+                        Some("users"),
+                        Some("users_pkey"),
+                        Some("PRIMARY KEY"),
+                        Some("id"),
+                    ],
+                ],
+            ),
+            (
+                "referential_constraints",
+                vec![vec![
+                    Some("public"),
+                    Some("posts"),
+                    Some("posts_author"),
+                    Some("user_id"),
+                    Some("app"),
+                    Some("users"),
+                    Some("id"),
+                    Some("CASCADE"),
+                    Some("NO ACTION"),
+                ]],
+            ),
+        ]);
+        let introspected = Introspector::new(&mut connection, options)
+            .introspect()
+            .unwrap();
+
+        let migration = MigrationGenerator::new(&inspected, &introspected)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(summary, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_undo_deparse() {
+        assert_eq!(
+            undo_deparse("CHECK ((((title)::text <> ''::text) OR (body IS NOT NULL)))").unwrap(),
+            "CHECK (((title <> '') OR (body IS NOT NULL)))"
+        );
+        assert_eq!(
+            undo_deparse("CHECK ((VALUE ~~ '%@%'::text))").unwrap(),
+            "CHECK ((VALUE LIKE '%@%'))"
+        );
+        assert_eq!(
+            undo_deparse(
+                "CREATE INDEX t_name ON app.t USING btree (lower((name)::character varying))"
+            )
+            .unwrap(),
+            "CREATE INDEX t_name ON app.t (lower(name))"
+        );
+        assert_eq!(
+            undo_deparse("CREATE INDEX t_doc ON app.t USING gin (doc)").unwrap(),
+            "CREATE INDEX t_doc ON app.t USING gin (doc)"
+        );
+    }
+
+    // Runs against the database MIGI_TEST_DATABASE_URL points to, which it
+    // fills with its own schema, and is skipped when it isn't set.
+    #[test]
+    fn test_introspect_database() {
+        let Ok(url) = std::env::var("MIGI_TEST_DATABASE_URL") else {
+            return;
+        };
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE DOMAIN app.email AS TEXT CHECK (VALUE LIKE '%@%');
+            CREATE DOMAIN positive AS INT NOT NULL DEFAULT 1 CONSTRAINT positive_value CHECK (VALUE > 0);
+            CREATE TABLE app.users (
+                id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+                email app.email NOT NULL UNIQUE,
+                age INT CHECK (age >= 18),
+                created_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            CREATE TABLE app.posts (
+                id SERIAL PRIMARY KEY,
+                author_id BIGINT NOT NULL REFERENCES app.users (id) ON DELETE CASCADE,
+                title VARCHAR(200) NOT NULL,
+                score positive,
+                body TEXT,
+                CONSTRAINT title_or_body CHECK (title <> '' OR body IS NOT NULL)
+            );
+            CREATE INDEX posts_author ON app.posts (author_id, id DESC);
+            CREATE UNIQUE INDEX posts_title ON app.posts (lower(title)) WHERE body IS NOT NULL;
+            CREATE VIEW app.titles AS SELECT id, title FROM app.posts;
+            CREATE MATERIALIZED VIEW app.counts AS SELECT author_id, count(*) AS n FROM app.posts GROUP BY author_id;
+            CREATE INDEX counts_author ON app.counts (author_id);
+        "#;
+        let mut client = postgres::Client::connect(&url, postgres::NoTls).unwrap();
+        client
+            .batch_execute(&format!(
+                "DROP SCHEMA IF EXISTS app CASCADE; DROP DOMAIN IF EXISTS positive CASCADE; {}",
+                sql
+            ))
+            .unwrap();
+
+        let options = test_options(Dialect::PostgreSql);
+        let mut inspected = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut inspected)
+            .inspect(sql, "test.sql")
+            .unwrap();
+        let introspected = Introspector::new(&mut client, options)
+            .introspect()
+            .unwrap();
+
+        // Only the views, which Postgres reformats, are told apart.
+        let migration = MigrationGenerator::new(&introspected, &inspected)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "- DROP MATERIALIZED VIEW test.app.counts",
+                "+ CREATE MATERIALIZED VIEW test.app.counts",
+                "~ CREATE OR REPLACE VIEW test.app.titles",
+            ]
+        );
+    }
+}
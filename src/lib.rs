@@ -1,31 +1,251 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
 use anyhow::{self, Result};
 use serde_derive::{Deserialize, Serialize};
+use sqlparser::dialect::{MySqlDialect, PostgreSqlDialect, SQLiteDialect};
 
+pub mod cache;
 pub mod dbinfo;
+pub mod export;
 pub mod inspector;
+#[cfg(feature = "database")]
+pub mod introspect;
 pub mod migrate;
+pub mod render;
+pub mod selftest;
+pub mod snapshot;
+#[cfg(any(test, feature = "testing"))]
+pub mod test_support;
+pub mod translate;
+pub mod writer;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Dialect {
     PostgreSql,
     MySql,
+    MariaDb,
     SQLite,
 }
 
+impl Dialect {
+    pub fn sqlparser_dialect(&self) -> Box<dyn sqlparser::dialect::Dialect> {
+        match self {
+            Dialect::PostgreSql => Box::new(PostgreSqlDialect {}),
+            // sqlparser has no MariaDB dialect, MySQL is the closest.
+            Dialect::MySql | Dialect::MariaDb => Box::new(MySqlDialect {}),
+            Dialect::SQLite => Box::new(SQLiteDialect {}),
+        }
+    }
+
+    // SQLite also accepts MySQL backticks, but double quotes are its standard.
+    pub fn quote_char(&self) -> char {
+        match self {
+            Dialect::PostgreSql => '"',
+            Dialect::MySql | Dialect::MariaDb => '`',
+            Dialect::SQLite => '"',
+        }
+    }
+
+    // The schema tables belong to when none is given: MySQL has no schemas
+    // apart from its databases.
+    pub fn default_schema(&self, database: &str) -> String {
+        match self {
+            Dialect::PostgreSql => "public".into(),
+            Dialect::MySql | Dialect::MariaDb => database.into(),
+            Dialect::SQLite => "main".into(),
+        }
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "postgresql" | "postgres" | "pg" => Ok(Dialect::PostgreSql),
+            "mysql" => Ok(Dialect::MySql),
+            "mariadb" => Ok(Dialect::MariaDb),
+            "sqlite" | "sqlite3" => Ok(Dialect::SQLite),
+            _ => anyhow::bail!("unknown dialect: {}", s),
+        }
+    }
+}
+
+// Same spellings as the config file.
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dialect::PostgreSql => write!(f, "postgresql"),
+            Dialect::MySql => write!(f, "mysql"),
+            Dialect::MariaDb => write!(f, "mariadb"),
+            Dialect::SQLite => write!(f, "sqlite"),
+        }
+    }
+}
+
+// How the inspector stores unquoted identifiers, quoted ones are kept as
+// written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseFolding {
+    Lower,    // users, as Postgres does
+    Preserve, // Users, as MySQL, MariaDB and SQLite compare names
+    Upper,    // USERS, as the SQL standard does
+}
+
+impl CaseFolding {
+    pub fn default_for(dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::PostgreSql => CaseFolding::Lower,
+            Dialect::MySql | Dialect::MariaDb | Dialect::SQLite => CaseFolding::Preserve,
+        }
+    }
+
+    pub fn fold(&self, ident: &str) -> String {
+        match self {
+            CaseFolding::Lower => ident.to_lowercase(),
+            CaseFolding::Preserve => ident.to_string(),
+            CaseFolding::Upper => ident.to_uppercase(),
+        }
+    }
+}
+
+// How generated migration files are versioned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Naming {
+    #[default]
+    Sequential, // 0001_create_users.sql
+    Timestamp, // 20240115123000_create_users.sql, in UTC
+}
+
+// How generated migration files are laid out for the tool applying them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Sql, // 0001_create_users.sql
+    Flyway,    // V0001__create_users.sql
+    Liquibase, // 0001_create_users.sql as a formatted SQL changelog
+}
+
+// How Postgres migrations name the objects of the default schema, which
+// otherwise land in the first schema of the search_path of the connection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Qualify {
+    #[default]
+    Auto, // only names outside the default schema are qualified
+    Always,     // every name is qualified with its schema
+    SearchPath, // SET search_path TO the default schema first
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Options {
     pub dialect: Dialect,
     pub database: String,
     pub default_schema: String,
-    pub paths: Vec<String>,
+    pub paths: Vec<String>, // files, or directories of *.sql files
+    pub strict: bool,
+    pub include_schemas: Vec<String>, // empty to include every schema
+    pub ignore: Vec<String>, // glob patterns of tables, views and domains never migrated, e.g. `jobs_*`
+    pub migrations_dir: String,
+    pub snapshot_path: String, // the schema the last migration was generated from
+    pub naming: Naming,
+    pub format: Format,
+    pub transactional: bool, // wrap migrations in BEGIN; ... COMMIT; where the dialect allows
+    pub qualify: Qualify,
+    pub group_alters: bool, // one ALTER TABLE per table with several clauses, where the dialect allows
+    pub idempotent: bool, // CREATE ... IF NOT EXISTS and DROP ... IF EXISTS, where the dialect allows
+    pub ignore_dml: bool, // skip INSERT, UPDATE, DELETE and TRUNCATE, e.g. seed data, instead of warning about them
+    pub case_folding: CaseFolding,
+}
+
+impl Options {
+    // Options for library users, defaulted as a config file would be, e.g.
+    // Options::builder().dialect(Dialect::PostgreSql).database("shop").path("schema.sql").build()
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OptionsBuilder {
+    config: Config,
+}
+
+impl OptionsBuilder {
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.config.dialect = Some(dialect);
+        self
+    }
+
+    pub fn database(mut self, database: &str) -> Self {
+        self.config.database = database.into();
+        self
+    }
+
+    pub fn default_schema(mut self, default_schema: &str) -> Self {
+        self.config.default_schema = Some(DefaultSchema::Name(default_schema.into()));
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.config.paths.push(path.into());
+        self
+    }
+
+    pub fn case_folding(mut self, case_folding: CaseFolding) -> Self {
+        self.config.case_folding = Some(case_folding);
+        self
+    }
+
+    pub fn build(self) -> Result<Options> {
+        self.config.to_options()
+    }
+}
+
+// Either `default_schema = "app"` or a table of names by dialect such as
+// `default_schema = { postgresql = "app", mysql = "shop" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DefaultSchema {
+    Name(String),
+    PerDialect(HashMap<Dialect, String>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Config {
     pub dialect: Option<Dialect>,
     pub database: String,
-    pub default_schema: Option<String>,
+    pub default_schema: Option<DefaultSchema>,
     pub paths: Vec<String>,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub include_schemas: Vec<String>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    pub migrations_dir: Option<String>,
+    pub snapshot_path: Option<String>,
+    #[serde(default)]
+    pub naming: Naming,
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub transactional: bool,
+    #[serde(default)]
+    pub qualify: Qualify,
+    #[serde(default)]
+    pub group_alters: bool,
+    #[serde(default)]
+    pub idempotent: bool,
+    #[serde(default)]
+    pub ignore_dml: bool,
+    pub case_folding: Option<CaseFolding>, // defaults to the dialect's
 }
 
 impl Config {
@@ -38,16 +258,134 @@ impl Config {
             return Err(anyhow::anyhow!("database is required"));
         }
 
-        let default_schema = self
-            .default_schema
-            .clone()
-            .unwrap_or_else(|| "public".into());
+        let default_schema = match &self.default_schema {
+            Some(DefaultSchema::Name(name)) => Some(name.clone()),
+            Some(DefaultSchema::PerDialect(names)) => names.get(&dialect).cloned(),
+            None => None,
+        };
+        let default_schema =
+            default_schema.unwrap_or_else(|| dialect.default_schema(&self.database));
 
         Ok(Options {
             dialect,
             database: self.database.clone(),
             default_schema,
             paths: self.paths.clone(),
+            strict: self.strict,
+            include_schemas: self.include_schemas.clone(),
+            ignore: self.ignore.clone(),
+            migrations_dir: self
+                .migrations_dir
+                .clone()
+                .unwrap_or_else(|| "migrations".into()),
+            snapshot_path: self
+                .snapshot_path
+                .clone()
+                .unwrap_or_else(|| snapshot::DEFAULT_PATH.into()),
+            naming: self.naming,
+            format: self.format,
+            transactional: self.transactional,
+            qualify: self.qualify,
+            group_alters: self.group_alters,
+            idempotent: self.idempotent,
+            ignore_dml: self.ignore_dml,
+            case_folding: self
+                .case_folding
+                .unwrap_or_else(|| CaseFolding::default_for(dialect)),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_schema() {
+        let default_schema = |dialect: Dialect, default_schema: Option<DefaultSchema>| {
+            let config = Config {
+                dialect: Some(dialect),
+                database: "shop".into(),
+                default_schema,
+                ..Default::default()
+            };
+            config.to_options().unwrap().default_schema
+        };
+
+        assert_eq!(default_schema(Dialect::PostgreSql, None), "public");
+        assert_eq!(default_schema(Dialect::MySql, None), "shop");
+        assert_eq!(default_schema(Dialect::SQLite, None), "main");
+
+        let name = Some(DefaultSchema::Name("app".into()));
+        assert_eq!(default_schema(Dialect::PostgreSql, name.clone()), "app");
+        assert_eq!(default_schema(Dialect::MySql, name), "app");
+
+        let per_dialect = Some(DefaultSchema::PerDialect(HashMap::from([(
+            Dialect::PostgreSql,
+            "app".into(),
+        )])));
+        assert_eq!(
+            default_schema(Dialect::PostgreSql, per_dialect.clone()),
+            "app"
+        );
+        assert_eq!(default_schema(Dialect::MySql, per_dialect), "shop");
+    }
+
+    #[test]
+    fn test_options_builder() {
+        let options = Options::builder()
+            .dialect(Dialect::MySql)
+            .database("shop")
+            .path("schema")
+            .path("views.sql")
+            .build()
+            .unwrap();
+        let config = Config {
+            dialect: Some(Dialect::MySql),
+            database: "shop".into(),
+            paths: vec!["schema".into(), "views.sql".into()],
+            ..Default::default()
+        };
+        assert_eq!(options, config.to_options().unwrap());
+        assert_eq!(options.default_schema, "shop");
+        assert_eq!(options.case_folding, CaseFolding::Preserve);
+
+        let options = Options::builder()
+            .dialect(Dialect::PostgreSql)
+            .database("shop")
+            .default_schema("app")
+            .build()
+            .unwrap();
+        assert_eq!(options.default_schema, "app");
+        assert_eq!(options.case_folding, CaseFolding::Lower);
+
+        let options = Options::builder()
+            .dialect(Dialect::PostgreSql)
+            .database("shop")
+            .case_folding(CaseFolding::Upper)
+            .build()
+            .unwrap();
+        assert_eq!(options.case_folding, CaseFolding::Upper);
+
+        let err = Options::builder().database("shop").build().unwrap_err();
+        assert_eq!(err.to_string(), "dialect is required");
+    }
+
+    #[test]
+    fn test_dialect_from_str() {
+        for (s, dialect) in [
+            ("postgresql", Dialect::PostgreSql),
+            ("Postgres", Dialect::PostgreSql),
+            ("PG", Dialect::PostgreSql),
+            ("mysql", Dialect::MySql),
+            ("MariaDB", Dialect::MariaDb),
+            ("sqlite", Dialect::SQLite),
+            ("SQLite3", Dialect::SQLite),
+        ] {
+            assert_eq!(s.parse::<Dialect>().unwrap(), dialect);
+            assert_eq!(dialect.to_string().parse::<Dialect>().unwrap(), dialect);
+        }
+        let err = "oracle".parse::<Dialect>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown dialect: oracle");
+    }
+}
@@ -1,8 +1,21 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use config::Config;
+use migi::cache::{self, ParseCache};
+use migi::dbinfo::Dbinfo;
+use migi::inspector::{read_sources, Diagnostic, Inspector, Severity};
+#[cfg(feature = "database")]
+use migi::introspect::Introspector;
+use migi::migrate::{MigrationGenerator, RawPlacement};
+use migi::render::Renderer;
+use migi::selftest;
+use migi::snapshot;
+use migi::translate;
+use migi::writer::{migration_name, MigrationWriter};
+use serde::de::DeserializeOwned;
+use serde::de::IntoDeserializer;
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -10,33 +23,620 @@ struct Cli {
     #[arg(short, long, value_name = "FILE")]
     config: Option<PathBuf>,
 
+    /// Overrides the dialect set in the config file (postgresql, mysql, mariadb or sqlite)
+    #[arg(long, global = true)]
+    dialect: Option<migi::Dialect>,
+
+    /// Fail on statements that can't be inspected instead of warning about them
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Skip INSERT, UPDATE, DELETE and TRUNCATE statements, e.g. seed data, instead of warning about them
+    #[arg(long, global = true)]
+    ignore_dml: bool,
+
+    /// Only inspect and migrate this schema, can be given several times
+    #[arg(long = "schema", global = true, value_name = "SCHEMA")]
+    schemas: Vec<String>,
+
+    /// Never migrate tables or domains matching this glob, e.g. `jobs_*` or `app.*`, can be given several times
+    #[arg(long, global = true, value_name = "PATTERN")]
+    ignore: Vec<String>,
+
+    /// Overrides the snapshot file the next migration is diffed against
+    #[arg(long, global = true, value_name = "FILE")]
+    snapshot: Option<String>,
+
+    /// Parse every file again instead of reusing the statements parsed from unchanged files
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Print each inspected statement
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Only print errors and the output of the command
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Inspect {},
-    Generate {},
+    Inspect {
+        /// Print the number of inspected objects instead of the schema
+        #[arg(long)]
+        stats: bool,
+        /// How errors are printed (human or json), json prints them as an array on stdout
+        #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+        error_format: ErrorFormat,
+    },
+    Generate {
+        /// Print the planned operations without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the SQL creating the whole current schema instead of a diff
+        #[arg(long)]
+        baseline: bool,
+        /// Name of the migration, derived from its first operation by default
+        #[arg(long)]
+        name: Option<String>,
+        /// Overrides how migration files are versioned (sequential or timestamp)
+        #[arg(long, value_parser = parse_config_value::<migi::Naming>)]
+        naming: Option<migi::Naming>,
+        /// Overrides the layout of migration files (sql, flyway or liquibase)
+        #[arg(long, value_parser = parse_config_value::<migi::Format>)]
+        format: Option<migi::Format>,
+        /// Overrides how Postgres names are qualified (auto, always or search_path)
+        #[arg(long, value_parser = parse_config_value::<migi::Qualify>)]
+        qualify: Option<migi::Qualify>,
+        #[command(flatten)]
+        diff: DiffArgs,
+        /// Exit with an error when the schema has no changes
+        #[arg(long)]
+        fail_on_empty: bool,
+        /// Wrap the migration in a transaction where the dialect allows it
+        #[arg(long)]
+        transactional: bool,
+        /// Merge the changes to a table into a single ALTER TABLE where the dialect allows
+        #[arg(long)]
+        group_alters: bool,
+        /// Create and drop with IF NOT EXISTS and IF EXISTS so a failed migration can be run again
+        #[arg(long)]
+        idempotent: bool,
+        /// SQL file run verbatim before the generated operations, can be given several times
+        #[arg(long, value_name = "FILE")]
+        before: Vec<PathBuf>,
+        /// SQL file run verbatim after the generated operations, can be given several times
+        #[arg(long, value_name = "FILE")]
+        after: Vec<PathBuf>,
+        /// Comment each statement with why it was generated
+        #[arg(long)]
+        explain: bool,
+        /// Write one migration per changed object, in the order to apply them, along with a manifest listing them
+        #[arg(long, conflicts_with = "name")]
+        split: bool,
+        /// Render the migration for another dialect than the schema files are written in, translating types where possible
+        #[arg(long, value_name = "DIALECT")]
+        target: Option<migi::Dialect>,
+        /// Diff against the live Postgres database at this URL instead of the snapshot, which is still written
+        #[cfg(feature = "database")]
+        #[arg(long, value_name = "URL", conflicts_with = "baseline")]
+        database_url: Option<String>,
+    },
+    /// Inspect the schema files without generating anything
+    Check {
+        /// Report every statement that can't be parsed or inspected instead of stopping at the first
+        #[arg(long)]
+        keep_going: bool,
+    },
+    /// Check that the next migration turns the snapshot into the current schema
+    Verify {
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+    /// Check that a schema file renders back to SQL inspecting to the same schema
+    Selftest {
+        /// Schema file to round-trip
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ErrorFormat {
+    Human,
+    Json, // [{"file", "line", "column", "message", "severity"}]
+}
+
+// How generate and verify diff the snapshot against the current schema.
+#[derive(Args)]
+struct DiffArgs {
+    /// Move tables dropped from a schema and created unchanged in another
+    #[arg(long)]
+    detect_moves: bool,
+    /// Rename tables dropped and created unchanged under another name in the same schema
+    #[arg(long)]
+    detect_renames: bool,
+    /// Prefer operations that avoid long table locks, e.g. validating NOT NULL first
+    #[arg(long)]
+    safe_migrations: bool,
+}
+
+impl DiffArgs {
+    fn generator<'a>(
+        &self,
+        options: &migi::Options,
+        previous: &'a Dbinfo,
+        current: &'a Dbinfo,
+    ) -> MigrationGenerator<'a> {
+        MigrationGenerator::new(previous, current)
+            .with_include_schemas(options.include_schemas.clone())
+            .with_ignore(options.ignore.clone())
+            .with_detect_table_moves(self.detect_moves)
+            .with_detect_table_renames(self.detect_renames)
+            .with_safe_migrations(self.safe_migrations)
+    }
+}
+
+// Writes log records to stderr, warnings prefixed the way they always were.
+struct Logger;
+
+impl log::Log for Logger {
+    // sqlparser logs its parsing steps at the debug level too.
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && (metadata.level() <= log::Level::Info || metadata.target().starts_with("migi"))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            log::Level::Error => eprintln!("error: {}", record.args()),
+            log::Level::Warn => eprintln!("warning: {}", record.args()),
+            log::Level::Info => eprintln!("{}", record.args()),
+            log::Level::Debug | log::Level::Trace => {
+                eprintln!(
+                    "{}: {}",
+                    record.level().as_str().to_lowercase(),
+                    record.args()
+                )
+            }
+        }
+    }
+
+    fn flush(&self) {}
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    log::set_logger(&Logger)?;
+    log::set_max_level(if cli.verbose {
+        log::LevelFilter::Debug
+    } else if cli.quiet {
+        log::LevelFilter::Error
+    } else {
+        log::LevelFilter::Info
+    });
+
     let config_path = cli.config.unwrap_or(PathBuf::from("migi.toml"));
     let settings = Config::builder()
         .add_source(config::File::from(config_path))
         .build()?;
-    let options = settings.try_deserialize::<migi::Config>()?.to_options()?;
+    let mut config = settings.try_deserialize::<migi::Config>()?;
+    if let Some(dialect) = cli.dialect {
+        config.dialect = Some(dialect);
+    }
+    if cli.strict {
+        config.strict = true;
+    }
+    if cli.ignore_dml {
+        config.ignore_dml = true;
+    }
+    if !cli.schemas.is_empty() {
+        config.include_schemas = cli.schemas.clone();
+    }
+    config.ignore.extend(cli.ignore.iter().cloned());
+    if let Some(snapshot) = &cli.snapshot {
+        config.snapshot_path = Some(snapshot.clone());
+    }
+    let options = config.to_options()?;
+    let cache = (!cli.no_cache).then(|| ParseCache::new(cache::DEFAULT_DIR));
+    let cache = cache.as_ref();
 
     match &cli.command {
-        Commands::Inspect {} => inspect(options)?,
-        Commands::Generate {} => {}
+        Commands::Inspect {
+            stats,
+            error_format,
+        } => inspect(options, cache, *stats, *error_format)?,
+        Commands::Generate {
+            dry_run,
+            baseline,
+            name,
+            naming,
+            format,
+            qualify,
+            diff,
+            fail_on_empty,
+            transactional,
+            group_alters,
+            idempotent,
+            before,
+            after,
+            explain,
+            split,
+            target,
+            #[cfg(feature = "database")]
+            database_url,
+        } => {
+            let mut options = options;
+            if let Some(naming) = naming {
+                options.naming = *naming;
+            }
+            if let Some(format) = format {
+                options.format = *format;
+            }
+            if let Some(qualify) = qualify {
+                options.qualify = *qualify;
+            }
+            if *transactional {
+                options.transactional = true;
+            }
+            if *group_alters {
+                options.group_alters = true;
+            }
+            if *idempotent {
+                options.idempotent = true;
+            }
+            // The target dialect has its own default schema and case folding.
+            let target = match target {
+                Some(target) => Some(
+                    migi::Config {
+                        dialect: Some(*target),
+                        case_folding: None,
+                        ..config.clone()
+                    }
+                    .to_options()?,
+                ),
+                None => None,
+            };
+            #[cfg(feature = "database")]
+            let database_url = database_url.as_deref();
+            #[cfg(not(feature = "database"))]
+            let database_url = None;
+            generate(
+                options,
+                cache,
+                *dry_run,
+                *baseline,
+                name.as_deref(),
+                diff,
+                *fail_on_empty,
+                before,
+                after,
+                *explain,
+                *split,
+                target,
+                database_url,
+            )?
+        }
+        Commands::Check { keep_going } => check(options, cache, *keep_going)?,
+        Commands::Verify { diff } => verify(options, cache, diff)?,
+        Commands::Selftest { file } => selftest(options, file)?,
     }
 
     Ok(())
 }
 
-fn inspect(options: migi::Options) -> Result<()> {
-    todo!()
+// Accepts exactly the same spellings as the config file.
+fn parse_config_value<T: DeserializeOwned>(s: &str) -> Result<T, String> {
+    T::deserialize(s.into_deserializer()).map_err(|e: serde::de::value::Error| e.to_string())
+}
+
+// Inspects the configured files, returning the resulting Dbinfo along with the
+// number of statements that were not applied to it. With `keep_going`, every
+// error is printed before failing.
+fn load_dbinfo(
+    options: &migi::Options,
+    cache: Option<&ParseCache>,
+    keep_going: bool,
+) -> Result<(Dbinfo, usize)> {
+    let sources = read_sources(&options.paths)?;
+
+    let mut dbinfo = Dbinfo::with_options(options.clone());
+    let mut inspector = Inspector::from_options(&mut dbinfo, options)
+        .with_keep_going(keep_going)
+        .with_parallel(true);
+    if let Some(cache) = cache {
+        inspector = inspector.with_cache(cache.clone());
+    }
+    inspector.inspect_all(&sources)?;
+
+    let errors = inspector.errors();
+    if !errors.is_empty() {
+        for error in errors {
+            log::error!("{}", error);
+        }
+        anyhow::bail!("{} statements could not be inspected", errors.len());
+    }
+
+    let unsupported_statements = inspector.unsupported_statements();
+    for unsupported in unsupported_statements {
+        log::warn!("{}", unsupported);
+    }
+    let other_statements = unsupported_statements.len();
+
+    Ok((dbinfo, other_statements))
+}
+
+// Like load_dbinfo with `keep_going`, but when inspection fails every error
+// and unsupported statement is printed on stdout as a json array instead, for
+// CI to annotate the schema files with.
+fn load_dbinfo_json(
+    options: &migi::Options,
+    cache: Option<&ParseCache>,
+) -> Result<(Dbinfo, usize)> {
+    let mut dbinfo = Dbinfo::with_options(options.clone());
+    let mut inspector = Inspector::from_options(&mut dbinfo, options)
+        .with_keep_going(true)
+        .with_parallel(true);
+    if let Some(cache) = cache {
+        inspector = inspector.with_cache(cache.clone());
+    }
+    let result = read_sources(&options.paths).and_then(|sources| inspector.inspect_all(&sources));
+
+    let mut diagnostics = inspector.diagnostics();
+    if let Err(e) = &result {
+        diagnostics.push(Diagnostic::from_message(&e.to_string(), Severity::Error));
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+        anyhow::bail!("{} statements could not be inspected", errors);
+    }
+
+    let unsupported_statements = inspector.unsupported_statements();
+    for unsupported in unsupported_statements {
+        log::warn!("{}", unsupported);
+    }
+    let other_statements = unsupported_statements.len();
+
+    Ok((dbinfo, other_statements))
+}
+
+fn inspect(
+    options: migi::Options,
+    cache: Option<&ParseCache>,
+    stats: bool,
+    error_format: ErrorFormat,
+) -> Result<()> {
+    let (dbinfo, other_statements) = match error_format {
+        ErrorFormat::Human => load_dbinfo(&options, cache, false)?,
+        ErrorFormat::Json => load_dbinfo_json(&options, cache)?,
+    };
+
+    if stats {
+        let stats = dbinfo.stats();
+        println!("catalogs: {}", stats.catalogs);
+        println!("schemas: {}", stats.schemas);
+        println!("tables: {}", stats.tables);
+        println!("columns: {}", stats.columns);
+        println!("indexes: {}", stats.indexes);
+        println!("views: {}", stats.views);
+        println!("other statements: {}", other_statements);
+    } else {
+        print!("{}", dbinfo);
+    }
+
+    Ok(())
+}
+
+fn check(options: migi::Options, cache: Option<&ParseCache>, keep_going: bool) -> Result<()> {
+    let (dbinfo, _) = load_dbinfo(&options, cache, keep_going)?;
+
+    let stats = dbinfo.stats();
+    log::info!(
+        "OK: {} tables across {} schemas",
+        stats.tables,
+        stats.schemas
+    );
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate(
+    options: migi::Options,
+    cache: Option<&ParseCache>,
+    dry_run: bool,
+    baseline: bool,
+    name: Option<&str>,
+    diff: &DiffArgs,
+    fail_on_empty: bool,
+    before: &[PathBuf],
+    after: &[PathBuf],
+    explain: bool,
+    split: bool,
+    target: Option<migi::Options>,
+    database_url: Option<&str>,
+) -> Result<()> {
+    let (current, _) = load_dbinfo(&options, cache, false)?;
+
+    // The schema files are inspected in their own dialect, everything else,
+    // the snapshot included, is in the target one.
+    let (current, options) = match target {
+        Some(target) => {
+            let (current, warnings) = translate::translate(&current, &target);
+            for warning in warnings {
+                log::warn!("{}", warning);
+            }
+            (
+                current,
+                migi::Options {
+                    dialect: target.dialect,
+                    default_schema: target.default_schema,
+                    case_folding: target.case_folding,
+                    ..options
+                },
+            )
+        }
+        None => (current, options),
+    };
+
+    // The baseline is diffed against an empty schema, migrations against the
+    // schema of the last generated migration or of the live database.
+    let snapshot_path = Path::new(&options.snapshot_path);
+    let previous = if baseline {
+        Dbinfo::with_options(options.clone())
+    } else if let Some(url) = database_url {
+        introspect_database(url, &options)?
+    } else {
+        snapshot::load(snapshot_path, &options)?
+    };
+
+    let mut migration = diff
+        .generator(&options, &previous, &current)
+        .with_baseline(baseline || (database_url.is_none() && !snapshot_path.exists()))
+        .generate()?;
+    let raw = [(RawPlacement::Before, before), (RawPlacement::After, after)];
+    for (placement, paths) in raw {
+        for path in paths {
+            let sql = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+            migration.add_raw(placement, &sql);
+        }
+    }
+
+    if fail_on_empty && migration.is_empty() {
+        anyhow::bail!("no changes");
+    }
+
+    // split migrations are consumed by split_by_object
+    let warnings = std::mem::take(&mut migration.warnings);
+    let renderer = Renderer::new(&options).with_explain(explain);
+    if dry_run {
+        for (i, operation) in migration.operations.iter().enumerate() {
+            match migration.explanations.get(i).filter(|_| explain) {
+                Some(explanation) => println!("{} -- {}", operation, explanation),
+                None => println!("{}", operation),
+            }
+        }
+    } else if baseline {
+        print!("{}", renderer.render(&migration)?);
+    } else if migration.is_empty() {
+        log::info!("no changes");
+    } else if split {
+        let mut parts = vec![];
+        for part in migration.split_by_object() {
+            parts.push((migration_name(&part), renderer.render(&part)?));
+        }
+        let (paths, manifest) = MigrationWriter::new(&options.migrations_dir)
+            .with_naming(options.naming)
+            .with_format(options.format)
+            .write_split(&parts)?;
+        snapshot::save(snapshot_path, &current)?;
+        for path in paths {
+            println!("{}", path.display());
+        }
+        println!("{}", manifest.display());
+    } else {
+        let sql = renderer.render(&migration)?;
+        let name = name
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| migration_name(&migration));
+        let path = MigrationWriter::new(&options.migrations_dir)
+            .with_naming(options.naming)
+            .with_format(options.format)
+            .write(&name, &sql)?;
+        snapshot::save(snapshot_path, &current)?;
+        println!("{}", path.display());
+    }
+
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "database")]
+fn introspect_database(url: &str, options: &migi::Options) -> Result<Dbinfo> {
+    let mut client = postgres::Client::connect(url, postgres::NoTls)?;
+    Introspector::new(&mut client, options.clone()).introspect()
+}
+
+#[cfg(not(feature = "database"))]
+fn introspect_database(_url: &str, _options: &migi::Options) -> Result<Dbinfo> {
+    anyhow::bail!("migi was built without the database feature")
+}
+
+// Applies the migration `generate` would write to the snapshot, and diffs the
+// result against the current schema: anything left is an operation the
+// generator missed.
+fn verify(options: migi::Options, cache: Option<&ParseCache>, diff: &DiffArgs) -> Result<()> {
+    let previous = snapshot::load(Path::new(&options.snapshot_path), &options)?;
+    let (current, _) = load_dbinfo(&options, cache, false)?;
+
+    let generator = diff.generator(&options, &previous, &current);
+    let migration = generator.generate()?;
+    let mut applied = previous.clone();
+    migration.apply(&mut applied)?;
+
+    // The schemas are compared as json rather than diffed again, so that what
+    // the generator misses doesn't go unnoticed.
+    let generator = diff.generator(&options, &applied, &current);
+    let paths = generator
+        .managed(&applied)
+        .differing_paths(&generator.managed(&current));
+    if !paths.is_empty() {
+        for path in &paths {
+            println!("{}", path);
+        }
+        let remaining = generator.generate()?;
+        for operation in &remaining.operations {
+            println!("{}", operation);
+        }
+        anyhow::bail!(
+            "the migration does not reproduce the schema, {} objects differ",
+            paths.len()
+        );
+    }
+
+    log::info!(
+        "OK: {} operations reproduce the schema",
+        migration.operations.len()
+    );
+    Ok(())
+}
+
+// Inspects the file, renders the schema back to SQL and inspects that SQL
+// again: any difference is a fidelity bug of the inspector or the renderer.
+fn selftest(options: migi::Options, file: &Path) -> Result<()> {
+    let sql = std::fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("cannot read {}: {}", file.display(), e))?;
+    let round_trip = selftest::round_trip(&options, &sql, &file.display().to_string())?;
+    log::debug!("rendered schema:\n{}", round_trip.sql);
+
+    if !round_trip.is_faithful() {
+        println!("{}", round_trip.diff());
+        anyhow::bail!(
+            "{} does not round-trip, the rendered schema differs",
+            file.display()
+        );
+    }
+
+    let stats = round_trip.inspected.stats();
+    log::info!(
+        "OK: {} tables across {} schemas round-trip",
+        stats.tables,
+        stats.schemas
+    );
+    Ok(())
 }
@@ -0,0 +1,455 @@
+use sqlparser::ast::{ColumnOption, Expr, OnCommit};
+
+use super::{
+    constraint_key, default_value, normalized_default, resolved, AlterDomainOperation,
+    AlterTableOperation, MigrationGenerator, MigrationOperation, ObjectName,
+};
+use crate::dbinfo::{Column, Dbinfo, Domain, Schema, Table, View};
+
+// Why each operation was generated, from the objects the generator compared,
+// e.g. `column email changed: type from VARCHAR(100) to VARCHAR(255)`.
+impl<'a> MigrationGenerator<'a> {
+    pub(super) fn explain(&self, operation: &MigrationOperation) -> String {
+        let operations = &self.migrations.operations;
+        match operation {
+            MigrationOperation::CreateDatabase { name } => format!("database {} is new", name),
+            MigrationOperation::DropDatabase { name } => format!("database {} was removed", name),
+            MigrationOperation::CreateSchema { name } => {
+                if self.baseline && find_schema(self.previous, name).is_some() {
+                    format!(
+                        "the database comes without its default schema {}",
+                        name.0[1]
+                    )
+                } else {
+                    format!("schema {} is new", name)
+                }
+            }
+            MigrationOperation::DropSchema { name } => format!("schema {} was removed", name),
+            MigrationOperation::CreateTable { name, .. } => {
+                let rebuilt = operations.iter().find_map(|op| match op {
+                    MigrationOperation::CopyTableData { from, to, .. } if to == name => Some(from),
+                    _ => None,
+                });
+                if let Some(from) = rebuilt {
+                    format!(
+                        "table {} can't be altered in place, its new version is created",
+                        from
+                    )
+                } else if find_table(self.previous, name).is_some() {
+                    self.explain_recreated_table(name)
+                } else {
+                    format!("table {} is new", name)
+                }
+            }
+            MigrationOperation::DropTable { name, .. } => {
+                let rebuilt = operations.iter().any(|op| {
+                    matches!(op, MigrationOperation::CopyTableData { from, .. } if from == name)
+                });
+                if rebuilt {
+                    format!("table {} was copied to its new version", name)
+                } else if find_table(self.current, name).is_some() {
+                    self.explain_recreated_table(name)
+                } else {
+                    format!("table {} was removed", name)
+                }
+            }
+            MigrationOperation::AlterTable { name, operation } => {
+                self.explain_alter_table(name, operation)
+            }
+            MigrationOperation::CopyTableData { from, columns, .. } => format!(
+                "the data of the columns {} of table {} is kept",
+                columns.join(", "),
+                from
+            ),
+            MigrationOperation::RenameTable { from, to } => {
+                let rebuilt = operations.iter().any(
+                    |op| matches!(op, MigrationOperation::CopyTableData { to, .. } if to == from),
+                );
+                if rebuilt {
+                    format!("the new version of table {} takes its name", to)
+                } else {
+                    format!("table {} was renamed to {} without other changes", from, to)
+                }
+            }
+            MigrationOperation::MoveTable { from, to } => {
+                format!("table {} was moved to {} without other changes", from, to)
+            }
+            MigrationOperation::CreateDomain { name, .. } => {
+                if find_domain(self.previous, name).is_some() {
+                    format!("domain {} changed in a way ALTER DOMAIN can't make", name)
+                } else {
+                    format!("domain {} is new", name)
+                }
+            }
+            MigrationOperation::DropDomain { name, .. } => {
+                if find_domain(self.current, name).is_some() {
+                    format!("domain {} changed in a way ALTER DOMAIN can't make", name)
+                } else {
+                    format!("domain {} was removed", name)
+                }
+            }
+            MigrationOperation::AlterDomain { name, operation } => {
+                self.explain_alter_domain(name, operation)
+            }
+            MigrationOperation::CreateTrigger { table, trigger } => {
+                let previous = find_table(self.previous, table)
+                    .and_then(|t| t.triggers.iter().find(|t| t.name == trigger.name));
+                match previous {
+                    Some(_) => format!("trigger {} on {} changed", trigger.name, table),
+                    None => format!("trigger {} on {} is new", trigger.name, table),
+                }
+            }
+            MigrationOperation::DropTrigger { table, trigger } => {
+                let current = find_table(self.current, table)
+                    .and_then(|t| t.triggers.iter().find(|t| t.name == trigger.name));
+                match current {
+                    Some(_) => format!("trigger {} on {} changed", trigger.name, table),
+                    None => format!("trigger {} on {} was removed", trigger.name, table),
+                }
+            }
+            MigrationOperation::CreatePolicy { table, policy } => {
+                let previous = find_table(self.previous, table)
+                    .and_then(|t| t.policies.iter().find(|p| p.name == policy.name));
+                match previous {
+                    Some(_) => format!("policy {} on {} changed", policy.name, table),
+                    None => format!("policy {} on {} is new", policy.name, table),
+                }
+            }
+            MigrationOperation::DropPolicy { table, policy } => {
+                let current = find_table(self.current, table)
+                    .and_then(|t| t.policies.iter().find(|p| p.name == policy.name));
+                match current {
+                    Some(_) => format!("policy {} on {} changed", policy.name, table),
+                    None => format!("policy {} on {} was removed", policy.name, table),
+                }
+            }
+            MigrationOperation::AlterPolicy { table, current, .. } => format!(
+                "roles or expressions of policy {} on {} changed",
+                current.name, table
+            ),
+            MigrationOperation::CreateView { name, view } => {
+                if find_view(self.previous, name).is_some() {
+                    format!(
+                        "{} {} changed, it is created again",
+                        view.kind().to_lowercase(),
+                        name
+                    )
+                } else {
+                    format!("{} {} is new", view.kind().to_lowercase(), name)
+                }
+            }
+            MigrationOperation::DropView { name, view } => {
+                if find_view(self.current, name).is_some() {
+                    format!(
+                        "{} {} changed in a way that can't be altered",
+                        view.kind().to_lowercase(),
+                        name
+                    )
+                } else {
+                    format!("{} {} was removed", view.kind().to_lowercase(), name)
+                }
+            }
+            MigrationOperation::ReplaceView { name, view } => format!(
+                "{} {} changed, its columns are kept",
+                view.kind().to_lowercase(),
+                name
+            ),
+            MigrationOperation::CreateViewIndex { view, index } => {
+                let previous = find_view(self.previous, view)
+                    .is_some_and(|v| v.indexes.iter().any(|i| i.name == index.name));
+                if previous {
+                    format!("index {} changed", index.name)
+                } else {
+                    format!("index {} is new", index.name)
+                }
+            }
+            MigrationOperation::DropViewIndex { view, index } => {
+                let current = find_view(self.current, view)
+                    .is_some_and(|v| v.indexes.iter().any(|i| i.name == index.name));
+                if current {
+                    format!(
+                        "index {} changed, it is dropped to be created again",
+                        index.name
+                    )
+                } else {
+                    format!("index {} was removed", index.name)
+                }
+            }
+            MigrationOperation::Raw { .. } => "raw SQL added to the migration".to_string(),
+        }
+    }
+
+    // A table whose partitioning or ON COMMIT action changed is dropped and
+    // created again.
+    fn explain_recreated_table(&self, name: &ObjectName) -> String {
+        let (previous_table, current_table) = (
+            find_table(self.previous, name),
+            find_table(self.current, name),
+        );
+        let previous = previous_table.and_then(|t| t.partition_by.as_deref());
+        let current = current_table.and_then(|t| t.partition_by.as_deref());
+        if previous == current {
+            let previous = previous_table.and_then(|t| t.on_commit);
+            let current = current_table.and_then(|t| t.on_commit);
+            if previous != current {
+                return format!(
+                    "ON COMMIT action of table {} changed from {} to {}",
+                    name,
+                    on_commit(previous),
+                    on_commit(current)
+                );
+            }
+            return format!("table {} changed in a way ALTER TABLE can't make", name);
+        }
+        format!(
+            "partitioning of table {} changed from {} to {}",
+            name,
+            or_none(previous),
+            or_none(current)
+        )
+    }
+
+    fn explain_alter_table(&self, name: &ObjectName, operation: &AlterTableOperation) -> String {
+        let previous = find_table(self.previous, name);
+        let current = find_table(self.current, name);
+        match operation {
+            AlterTableOperation::AddColumn { column } => format!("column {} is new", column.name),
+            AlterTableOperation::DropColumn { column } => {
+                format!("column {} was removed", column.name)
+            }
+            AlterTableOperation::AlterColumn {
+                previous,
+                current,
+                previous_nullable,
+                current_nullable,
+            } => format!(
+                "column {} changed: {}",
+                current.name,
+                self.column_changes(
+                    name,
+                    (previous, *previous_nullable),
+                    (current, *current_nullable)
+                )
+                .join(", ")
+            ),
+            AlterTableOperation::MoveColumn { column, after } => match after {
+                Some(after) => format!("column {} moved after {}", column.name, after),
+                None => format!("column {} moved first", column.name),
+            },
+            AlterTableOperation::SetColumnDefault { column, value } => {
+                match previous.and_then(|t| t.get_column(column).ok()) {
+                    Some(previous) => format!(
+                        "column {} changed default from {} to {}",
+                        column,
+                        or_none(default_value(previous)),
+                        value
+                    ),
+                    None => format!("column {} has default {}", column, value),
+                }
+            }
+            AlterTableOperation::DropColumnDefault { column } => {
+                match previous
+                    .and_then(|t| t.get_column(column).ok())
+                    .and_then(default_value)
+                {
+                    Some(value) => format!("column {} no longer has default {}", column, value),
+                    None => format!("column {} no longer has a default", column),
+                }
+            }
+            AlterTableOperation::AddConstraint { constraint } => {
+                let key = constraint_key(constraint);
+                let changed = previous.and_then(|t| {
+                    t.constraints
+                        .iter()
+                        .find(|c| constraint_key(c) == key && c != constraint)
+                });
+                match changed {
+                    Some(previous) => {
+                        format!("constraint changed from {} to {}", previous, constraint)
+                    }
+                    None => format!("constraint {} is new", constraint),
+                }
+            }
+            AlterTableOperation::DropConstraint { constraint } => {
+                let key = constraint_key(constraint);
+                let changed = current.is_some_and(|t| {
+                    t.constraints
+                        .iter()
+                        .any(|c| constraint_key(c) == key && c != *constraint)
+                });
+                if changed {
+                    format!(
+                        "constraint {} changed, it is dropped to be added again",
+                        constraint
+                    )
+                } else {
+                    format!("constraint {} was removed", constraint)
+                }
+            }
+            AlterTableOperation::AddExclusionConstraint { constraint } => {
+                format!("constraint {} is new", constraint)
+            }
+            AlterTableOperation::DropExclusionConstraint { constraint } => {
+                format!("constraint {} was removed", constraint)
+            }
+            AlterTableOperation::AddIndex { index } => {
+                if previous.is_some_and(|t| t.indexes.iter().any(|i| i.name == index.name)) {
+                    format!("index {} changed", index.name)
+                } else {
+                    format!("index {} is new", index.name)
+                }
+            }
+            AlterTableOperation::DropIndex { index } => {
+                if current.is_some_and(|t| t.indexes.iter().any(|i| i.name == index.name)) {
+                    format!(
+                        "index {} changed, it is dropped to be created again",
+                        index.name
+                    )
+                } else {
+                    format!("index {} was removed", index.name)
+                }
+            }
+            AlterTableOperation::SetEngine { engine } => format!(
+                "engine changed from {} to {}",
+                or_default(previous.and_then(|t| t.engine.as_deref())),
+                engine
+            ),
+            AlterTableOperation::SetDefaultCharset { charset } => format!(
+                "default charset changed from {} to {}",
+                or_default(previous.and_then(|t| t.default_charset.as_deref())),
+                charset
+            ),
+            AlterTableOperation::SetCollation { collation } => format!(
+                "collation changed from {} to {}",
+                or_default(previous.and_then(|t| t.collation.as_deref())),
+                collation
+            ),
+            AlterTableOperation::SetOptions { options } => format!(
+                "storage parameters {} were set",
+                options
+                    .iter()
+                    .map(|o| o.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            AlterTableOperation::ResetOptions { names } => {
+                format!("storage parameters {} were removed", names.join(", "))
+            }
+            AlterTableOperation::AddNotNullCheck { column }
+            | AlterTableOperation::ValidateNotNullCheck { column }
+            | AlterTableOperation::DropNotNullCheck { column } => format!(
+                "column {} becomes NOT NULL, checked without locking the table first",
+                column
+            ),
+        }
+    }
+
+    // What differs between two versions of a column, e.g. `NOT NULL added`.
+    fn column_changes(
+        &self,
+        table_name: &ObjectName,
+        (previous, previous_nullable): (&Column, bool),
+        (current, current_nullable): (&Column, bool),
+    ) -> Vec<String> {
+        let dialect = self.current.dialect;
+        let mut changes = vec![];
+        if resolved(self.previous, table_name, previous).data_type
+            != resolved(self.current, table_name, current).data_type
+        {
+            changes.push(format!(
+                "type from {} to {}",
+                previous.data_type, current.data_type
+            ));
+        }
+        if previous.collation != current.collation {
+            changes.push(format!(
+                "collation from {} to {}",
+                or_default(previous.collation.as_ref()),
+                or_default(current.collation.as_ref())
+            ));
+        }
+        match (previous_nullable, current_nullable) {
+            (true, false) => changes.push("NOT NULL added".to_string()),
+            (false, true) => changes.push("NOT NULL removed".to_string()),
+            _ => {}
+        }
+        if normalized_default(previous, dialect) != normalized_default(current, dialect) {
+            changes.push(format!(
+                "default from {} to {}",
+                or_none(default_value(previous)),
+                or_none(default_value(current))
+            ));
+        }
+        if previous.identity != current.identity {
+            changes.push("identity changed".to_string());
+        }
+        if comment(previous) != comment(current) {
+            changes.push("comment changed".to_string());
+        }
+        if changes.is_empty() {
+            changes.push("options changed".to_string());
+        }
+        changes
+    }
+
+    fn explain_alter_domain(&self, name: &ObjectName, operation: &AlterDomainOperation) -> String {
+        let previous = find_domain(self.previous, name);
+        match operation {
+            AlterDomainOperation::SetDefault { value } => format!(
+                "domain {} changed default from {} to {}",
+                name,
+                or_none(previous.and_then(|d| d.default.as_ref())),
+                value
+            ),
+            AlterDomainOperation::DropDefault => format!("domain {} no longer has a default", name),
+            AlterDomainOperation::SetNotNull => format!("domain {} became NOT NULL", name),
+            AlterDomainOperation::DropNotNull => format!("domain {} is no longer NOT NULL", name),
+            AlterDomainOperation::AddConstraint { constraint } => {
+                format!("constraint {} of domain {} is new", constraint, name)
+            }
+            AlterDomainOperation::DropConstraint { constraint } => {
+                format!("constraint {} of domain {} was removed", constraint, name)
+            }
+        }
+    }
+}
+
+fn comment(column: &Column) -> Option<&String> {
+    column.options.iter().find_map(|o| match &o.option {
+        ColumnOption::Comment(comment) => Some(comment),
+        _ => None,
+    })
+}
+
+fn or_none(value: Option<&Expr>) -> String {
+    value.map_or("none".to_string(), |v| v.to_string())
+}
+
+fn on_commit(value: Option<OnCommit>) -> &'static str {
+    match value {
+        Some(OnCommit::DeleteRows) => "DELETE ROWS",
+        Some(OnCommit::PreserveRows) => "PRESERVE ROWS",
+        Some(OnCommit::Drop) => "DROP",
+        None => "none",
+    }
+}
+
+fn or_default(value: Option<impl ToString>) -> String {
+    value.map_or("the default".to_string(), |v| v.to_string())
+}
+
+fn find_schema<'d>(dbinfo: &'d Dbinfo, name: &ObjectName) -> Option<&'d Schema> {
+    dbinfo.catalogs.get(&name.0[0])?.schemas.get(&name.0[1])
+}
+
+fn find_table<'d>(dbinfo: &'d Dbinfo, name: &ObjectName) -> Option<&'d Table> {
+    find_schema(dbinfo, name)?.tables.get(&name.0[2])
+}
+
+fn find_view<'d>(dbinfo: &'d Dbinfo, name: &ObjectName) -> Option<&'d View> {
+    find_schema(dbinfo, name)?.views.get(&name.0[2])
+}
+
+fn find_domain<'d>(dbinfo: &'d Dbinfo, name: &ObjectName) -> Option<&'d Domain> {
+    find_schema(dbinfo, name)?.domains.get(&name.0[2])
+}
@@ -1,63 +1,1142 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 
 use anyhow::Result;
+use sqlparser::ast::{
+    CharacterLength, ColumnOption, ColumnOptionDef, DataType, ExactNumberInfo, Expr, SqlOption,
+    TableConstraint,
+};
 
-use crate::dbinfo::{Catalog, Column, Dbinfo, Schema, Table};
+use crate::dbinfo::{
+    canonical_data_type, constraint_name, data_type_equivalent, normalize_check_expr,
+    normalize_constraint, normalize_index, resolve_data_type, same_elements, Catalog, Column,
+    Dbinfo, Domain, DomainConstraint, ExclusionConstraint, Index, Policy, Schema, Table, Trigger,
+    View,
+};
+use crate::Dialect;
+
+mod explain;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ObjectName(pub Vec<String>);
 
+impl fmt::Display for ObjectName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
 pub enum MigrationOperation<'a> {
-    CreateDatabase { name: String },
-    DropDatabase { name: String },
-    CreateSchema { name: ObjectName },
-    DropSchema { name: ObjectName },
-    CreateTable { name: ObjectName, table: &'a Table },
-    DropTable { name: ObjectName, table: &'a Table },
-    AlterTable(AlterTableOperation),
+    CreateDatabase {
+        name: String,
+    },
+    DropDatabase {
+        name: String,
+    },
+    CreateSchema {
+        name: ObjectName,
+    },
+    DropSchema {
+        name: ObjectName,
+    },
+    // A rebuilt table is created without its indexes, which are named after
+    // those of the table it replaces, and they are added once it is renamed.
+    CreateTable {
+        name: ObjectName,
+        table: &'a Table,
+        with_indexes: bool,
+    },
+    DropTable {
+        name: ObjectName,
+        table: &'a Table,
+    },
+    AlterTable {
+        name: ObjectName,
+        operation: AlterTableOperation<'a>,
+    },
+    CopyTableData {
+        from: ObjectName,
+        to: ObjectName,
+        columns: Vec<&'a str>,
+    },
+    RenameTable {
+        from: ObjectName,
+        to: ObjectName,
+    },
+    CreateDomain {
+        name: ObjectName,
+        domain: &'a Domain,
+    },
+    DropDomain {
+        name: ObjectName,
+        domain: &'a Domain,
+    },
+    AlterDomain {
+        name: ObjectName,
+        operation: AlterDomainOperation<'a>,
+    },
+    CreateTrigger {
+        table: ObjectName,
+        trigger: &'a Trigger,
+    },
+    DropTrigger {
+        table: ObjectName,
+        trigger: &'a Trigger,
+    },
+    CreatePolicy {
+        table: ObjectName,
+        policy: &'a Policy,
+    },
+    DropPolicy {
+        table: ObjectName,
+        policy: &'a Policy,
+    },
+    // ALTER POLICY, for policies whose roles or expressions changed.
+    AlterPolicy {
+        table: ObjectName,
+        previous: &'a Policy,
+        current: &'a Policy,
+    },
+    // Materialized views can't be altered, a changed one is dropped and
+    // created again with its indexes.
+    CreateView {
+        name: ObjectName,
+        view: &'a View,
+    },
+    DropView {
+        name: ObjectName,
+        view: &'a View,
+    },
+    // CREATE OR REPLACE VIEW, for views whose columns are kept.
+    ReplaceView {
+        name: ObjectName,
+        view: &'a View,
+    },
+    CreateViewIndex {
+        view: ObjectName,
+        index: &'a Index,
+    },
+    DropViewIndex {
+        view: ObjectName,
+        index: &'a Index,
+    },
+    // Moves a table to another schema, or another database in MySQL.
+    MoveTable {
+        from: ObjectName,
+        to: ObjectName,
+    },
+    // SQL migi can't infer, e.g. a backfill, rendered verbatim.
+    Raw {
+        sql: String,
+    },
+}
+
+// Where raw SQL goes relative to the generated operations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RawPlacement {
+    Before,
+    After,
+}
+
+// A one-line summary of the operation, e.g. `+ CREATE TABLE app.public.users`.
+impl<'a> fmt::Display for MigrationOperation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationOperation::CreateDatabase { name } => write!(f, "+ CREATE DATABASE {}", name),
+            MigrationOperation::DropDatabase { name } => write!(f, "- DROP DATABASE {}", name),
+            MigrationOperation::CreateSchema { name } => write!(f, "+ CREATE SCHEMA {}", name),
+            MigrationOperation::DropSchema { name } => write!(f, "- DROP SCHEMA {}", name),
+            MigrationOperation::CreateTable { name, .. } => write!(f, "+ CREATE TABLE {}", name),
+            MigrationOperation::DropTable { name, .. } => write!(f, "- DROP TABLE {}", name),
+            MigrationOperation::AlterTable { name, operation } => {
+                write!(f, "~ ALTER TABLE {} {}", name, operation)
+            }
+            MigrationOperation::CopyTableData { from, to, .. } => {
+                write!(f, "~ COPY DATA {} TO {}", from, to)
+            }
+            MigrationOperation::RenameTable { from, to } => {
+                write!(f, "~ RENAME TABLE {} TO {}", from, to)
+            }
+            MigrationOperation::MoveTable { from, to } => {
+                write!(f, "~ MOVE TABLE {} TO {}", from, to)
+            }
+            MigrationOperation::CreateDomain { name, .. } => write!(f, "+ CREATE DOMAIN {}", name),
+            MigrationOperation::DropDomain { name, .. } => write!(f, "- DROP DOMAIN {}", name),
+            MigrationOperation::AlterDomain { name, operation } => {
+                write!(f, "~ ALTER DOMAIN {} {}", name, operation)
+            }
+            MigrationOperation::CreateTrigger { table, trigger } => {
+                write!(f, "+ CREATE TRIGGER {} ON {}", trigger.name, table)
+            }
+            MigrationOperation::DropTrigger { table, trigger } => {
+                write!(f, "- DROP TRIGGER {} ON {}", trigger.name, table)
+            }
+            MigrationOperation::CreatePolicy { table, policy } => {
+                write!(f, "+ CREATE POLICY {} ON {}", policy.name, table)
+            }
+            MigrationOperation::DropPolicy { table, policy } => {
+                write!(f, "- DROP POLICY {} ON {}", policy.name, table)
+            }
+            MigrationOperation::AlterPolicy { table, current, .. } => {
+                write!(f, "~ ALTER POLICY {} ON {}", current.name, table)
+            }
+            MigrationOperation::CreateView { name, view } => {
+                write!(f, "+ CREATE {} {}", view.kind(), name)
+            }
+            MigrationOperation::DropView { name, view } => {
+                write!(f, "- DROP {} {}", view.kind(), name)
+            }
+            MigrationOperation::ReplaceView { name, view } => {
+                write!(f, "~ CREATE OR REPLACE {} {}", view.kind(), name)
+            }
+            MigrationOperation::CreateViewIndex { view, index } => {
+                write!(f, "+ CREATE INDEX {} ON {}", index.name, view)
+            }
+            MigrationOperation::DropViewIndex { view, index } => {
+                write!(f, "- DROP INDEX {} ON {}", index.name, view)
+            }
+            MigrationOperation::Raw { sql } => {
+                write!(f, "~ RAW {}", sql.lines().next().unwrap_or_default())
+            }
+        }
+    }
+}
+
+pub enum AlterDomainOperation<'a> {
+    SetDefault { value: &'a Expr },
+    DropDefault,
+    SetNotNull,
+    DropNotNull,
+    AddConstraint { constraint: &'a DomainConstraint },
+    DropConstraint { constraint: &'a DomainConstraint },
+}
+
+impl<'a> fmt::Display for AlterDomainOperation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlterDomainOperation::SetDefault { value } => write!(f, "SET DEFAULT {}", value),
+            AlterDomainOperation::DropDefault => write!(f, "DROP DEFAULT"),
+            AlterDomainOperation::SetNotNull => write!(f, "SET NOT NULL"),
+            AlterDomainOperation::DropNotNull => write!(f, "DROP NOT NULL"),
+            AlterDomainOperation::AddConstraint { constraint } => match &constraint.name {
+                Some(name) => write!(f, "ADD CONSTRAINT {}", name.value),
+                None => write!(f, "ADD {}", constraint),
+            },
+            AlterDomainOperation::DropConstraint { constraint } => match &constraint.name {
+                Some(name) => write!(f, "DROP CONSTRAINT {}", name.value),
+                None => write!(f, "DROP {}", constraint),
+            },
+        }
+    }
 }
 
 pub enum AlterDatabaseOperation {}
 
-pub enum AlterTableOperation {
-    AddColumn,
-    DropColumn,
-    AlterColumn,
-    AddIndex,
-    DropIndex,
+pub enum AlterTableOperation<'a> {
+    AddColumn {
+        column: &'a Column,
+    },
+    DropColumn {
+        column: &'a Column,
+    },
+    // The nullability of either column within its table, where a primary key
+    // makes a column NOT NULL without saying so.
+    AlterColumn {
+        previous: &'a Column,
+        current: &'a Column,
+        previous_nullable: bool,
+        current_nullable: bool,
+    },
+    // Puts a column after another one, or first.
+    MoveColumn {
+        column: &'a Column,
+        after: Option<&'a str>,
+    },
+    SetColumnDefault {
+        column: &'a str,
+        value: &'a Expr,
+    },
+    DropColumnDefault {
+        column: &'a str,
+    },
+    AddConstraint {
+        constraint: &'a TableConstraint,
+    },
+    DropConstraint {
+        constraint: &'a TableConstraint,
+    },
+    AddExclusionConstraint {
+        constraint: &'a ExclusionConstraint,
+    },
+    DropExclusionConstraint {
+        constraint: &'a ExclusionConstraint,
+    },
+    AddIndex {
+        index: &'a Index,
+    },
+    DropIndex {
+        index: &'a Index,
+    },
+    SetEngine {
+        engine: &'a str,
+    },
+    SetDefaultCharset {
+        charset: &'a str,
+    },
+    SetCollation {
+        collation: &'a str,
+    },
+    SetOptions {
+        options: Vec<&'a SqlOption>,
+    },
+    ResetOptions {
+        names: Vec<&'a str>,
+    },
+    // The steps setting NOT NULL on a populated Postgres table without holding
+    // a lock while every row is checked: a NOT VALID check constraint that is
+    // validated separately, and dropped once SET NOT NULL has used it.
+    AddNotNullCheck {
+        column: &'a str,
+    },
+    ValidateNotNullCheck {
+        column: &'a str,
+    },
+    DropNotNullCheck {
+        column: &'a str,
+    },
+}
+
+impl<'a> fmt::Display for AlterTableOperation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AlterTableOperation::AddColumn { column } => write!(f, "ADD COLUMN {}", column.name),
+            AlterTableOperation::DropColumn { column } => {
+                write!(f, "DROP COLUMN {}", column.name)
+            }
+            AlterTableOperation::AlterColumn { current, .. } => {
+                write!(f, "ALTER COLUMN {}", current.name)
+            }
+            AlterTableOperation::MoveColumn { column, after } => match after {
+                Some(after) => write!(f, "MOVE COLUMN {} AFTER {}", column.name, after),
+                None => write!(f, "MOVE COLUMN {} FIRST", column.name),
+            },
+            AlterTableOperation::SetColumnDefault { column, value } => {
+                write!(f, "ALTER COLUMN {} SET DEFAULT {}", column, value)
+            }
+            AlterTableOperation::DropColumnDefault { column } => {
+                write!(f, "ALTER COLUMN {} DROP DEFAULT", column)
+            }
+            AlterTableOperation::AddConstraint { constraint } => {
+                match constraint_name(constraint) {
+                    Some(name) => write!(f, "ADD CONSTRAINT {}", name.value),
+                    None => write!(f, "ADD {}", constraint),
+                }
+            }
+            AlterTableOperation::DropConstraint { constraint } => {
+                match constraint_name(constraint) {
+                    Some(name) => write!(f, "DROP CONSTRAINT {}", name.value),
+                    None => write!(f, "DROP {}", constraint),
+                }
+            }
+            AlterTableOperation::AddExclusionConstraint { constraint } => {
+                write!(f, "ADD {}", constraint)
+            }
+            AlterTableOperation::DropExclusionConstraint { constraint } => match &constraint.name {
+                Some(name) => write!(f, "DROP CONSTRAINT {}", name.value),
+                None => write!(f, "DROP {}", constraint),
+            },
+            AlterTableOperation::AddIndex { index } => write!(f, "ADD INDEX {}", index.name),
+            AlterTableOperation::DropIndex { index } => write!(f, "DROP INDEX {}", index.name),
+            AlterTableOperation::SetEngine { engine } => write!(f, "ENGINE={}", engine),
+            AlterTableOperation::SetDefaultCharset { charset } => {
+                write!(f, "DEFAULT CHARSET={}", charset)
+            }
+            AlterTableOperation::SetCollation { collation } => write!(f, "COLLATE={}", collation),
+            AlterTableOperation::SetOptions { options } => {
+                let options: Vec<String> = options
+                    .iter()
+                    .map(|o| format!("{}={}", o.name.value, o.value))
+                    .collect();
+                write!(f, "SET ({})", options.join(", "))
+            }
+            AlterTableOperation::ResetOptions { names } => {
+                write!(f, "RESET ({})", names.join(", "))
+            }
+            AlterTableOperation::AddNotNullCheck { column } => {
+                write!(f, "ADD CHECK ({} IS NOT NULL) NOT VALID", column)
+            }
+            AlterTableOperation::ValidateNotNullCheck { column } => {
+                write!(f, "VALIDATE CHECK ({} IS NOT NULL)", column)
+            }
+            AlterTableOperation::DropNotNullCheck { column } => {
+                write!(f, "DROP CHECK ({} IS NOT NULL)", column)
+            }
+        }
+    }
 }
 
 pub struct Migration<'a> {
     pub operations: Vec<MigrationOperation<'a>>,
+    pub warnings: Vec<MigrationWarning>,
+    // Why each operation was generated, in the order of the operations.
+    pub explanations: Vec<String>,
+}
+
+impl<'a> Migration<'a> {
+    // Whether the schemas are the same, warnings alone don't make a migration.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    // Raw SQL placed before goes after the raw SQL already there, so several
+    // pieces keep the order they are added in.
+    pub fn add_raw(&mut self, placement: RawPlacement, sql: &str) {
+        let operation = MigrationOperation::Raw {
+            sql: sql.to_string(),
+        };
+        let position = match placement {
+            RawPlacement::Before => self
+                .operations
+                .iter()
+                .take_while(|op| matches!(op, MigrationOperation::Raw { .. }))
+                .count(),
+            RawPlacement::After => self.operations.len(),
+        };
+        self.operations.insert(position, operation);
+        if position <= self.explanations.len() {
+            self.explanations
+                .insert(position, "raw SQL added to the migration".to_string());
+        }
+    }
+
+    // One migration per object for large migrations to be reviewed piece by
+    // piece, applied in the order given. Consecutive operations on the same
+    // table, view, domain or schema make a part, so the parts keep the order
+    // of this migration, raw SQL making parts of its own. The warnings go
+    // with the first part.
+    pub fn split_by_object(self) -> Vec<Migration<'a>> {
+        let objects: Vec<Option<ObjectName>> = (0..self.operations.len())
+            .map(|i| self.object_of(i))
+            .collect();
+        let mut explanations = self.explanations.into_iter();
+        let mut parts: Vec<Migration<'a>> = vec![];
+        let mut last = None;
+        for (operation, object) in self.operations.into_iter().zip(objects) {
+            if parts.is_empty() || object.is_none() || object != last {
+                parts.push(Migration {
+                    operations: vec![],
+                    warnings: vec![],
+                    explanations: vec![],
+                });
+            }
+            let part = parts.last_mut().unwrap();
+            part.operations.push(operation);
+            part.explanations.extend(explanations.next());
+            last = object;
+        }
+        if let Some(first) = parts.first_mut() {
+            first.warnings = self.warnings;
+        }
+        parts
+    }
+
+    // The object the i-th operation changes, None for raw SQL.
+    fn object_of(&self, i: usize) -> Option<ObjectName> {
+        let name = match &self.operations[i] {
+            MigrationOperation::CreateDatabase { name }
+            | MigrationOperation::DropDatabase { name } => ObjectName(vec![name.clone()]),
+            // A rebuilt table is created under a temporary name and filled next.
+            MigrationOperation::CreateTable { name, .. } => match self.operations.get(i + 1) {
+                Some(MigrationOperation::CopyTableData { from, to, .. }) if to == name => {
+                    from.clone()
+                }
+                _ => name.clone(),
+            },
+            MigrationOperation::CreateSchema { name }
+            | MigrationOperation::DropSchema { name }
+            | MigrationOperation::DropTable { name, .. }
+            | MigrationOperation::AlterTable { name, .. }
+            | MigrationOperation::CreateDomain { name, .. }
+            | MigrationOperation::DropDomain { name, .. }
+            | MigrationOperation::AlterDomain { name, .. }
+            | MigrationOperation::CreateView { name, .. }
+            | MigrationOperation::DropView { name, .. }
+            | MigrationOperation::ReplaceView { name, .. } => name.clone(),
+            MigrationOperation::CopyTableData { from, .. } => from.clone(),
+            MigrationOperation::RenameTable { to, .. }
+            | MigrationOperation::MoveTable { to, .. } => to.clone(),
+            MigrationOperation::CreateTrigger { table, .. }
+            | MigrationOperation::DropTrigger { table, .. }
+            | MigrationOperation::CreatePolicy { table, .. }
+            | MigrationOperation::DropPolicy { table, .. }
+            | MigrationOperation::AlterPolicy { table, .. } => table.clone(),
+            MigrationOperation::CreateViewIndex { view, .. }
+            | MigrationOperation::DropViewIndex { view, .. } => view.clone(),
+            MigrationOperation::Raw { .. } => return None,
+        };
+        Some(name)
+    }
+
+    // Applies the operations to an in-memory schema, as running the rendered
+    // migration would to the database.
+    pub fn apply(&self, dbinfo: &mut Dbinfo) -> Result<()> {
+        for operation in &self.operations {
+            operation
+                .apply(dbinfo)
+                .map_err(|e| anyhow::anyhow!("cannot apply {}: {}", operation, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> MigrationOperation<'a> {
+    pub fn apply(&self, dbinfo: &mut Dbinfo) -> Result<()> {
+        match self {
+            MigrationOperation::CreateDatabase { name } => {
+                if dbinfo.catalogs.contains_key(name) {
+                    anyhow::bail!("database already exists");
+                }
+                let default_schema = dbinfo.dialect.default_schema(name);
+                dbinfo.add_catalog(name, Catalog::new(name, &default_schema));
+            }
+            MigrationOperation::DropDatabase { name } => {
+                dbinfo
+                    .catalogs
+                    .remove(name)
+                    .ok_or(anyhow::anyhow!("database does not found"))?;
+            }
+            MigrationOperation::CreateSchema { name } => {
+                let catalog = dbinfo.get_catalog_mut(&name.0[0])?;
+                if catalog.schemas.contains_key(&name.0[1]) {
+                    anyhow::bail!("schema already exists");
+                }
+                catalog.add_schema(&name.0[1], Schema::new(&name.0[1]));
+            }
+            MigrationOperation::DropSchema { name } => {
+                dbinfo
+                    .get_catalog_mut(&name.0[0])?
+                    .schemas
+                    .remove(&name.0[1])
+                    .ok_or(anyhow::anyhow!("schema does not found"))?;
+            }
+            MigrationOperation::CreateTable {
+                name,
+                table,
+                with_indexes,
+            } => {
+                // A rebuilt table is created under a temporary name.
+                let mut table = (*table).clone();
+                table.name = name.0[2].clone();
+                if !with_indexes {
+                    table.indexes.clear();
+                }
+                insert_table(dbinfo, name, table)?;
+            }
+            MigrationOperation::DropTable { name, .. } => {
+                remove_table(dbinfo, name)?;
+            }
+            MigrationOperation::AlterTable { name, operation } => {
+                operation.apply(get_schema_mut(dbinfo, name)?.get_table_mut(&name.0[2])?)?;
+            }
+            MigrationOperation::CopyTableData { from, to, .. } => {
+                for name in [from, to] {
+                    get_schema_mut(dbinfo, name)?.get_table(&name.0[2])?;
+                }
+            }
+            MigrationOperation::RenameTable { from, to }
+            | MigrationOperation::MoveTable { from, to } => {
+                let mut table = remove_table(dbinfo, from)?;
+                table.name = to.0[2].clone();
+                insert_table(dbinfo, to, table)?;
+            }
+            MigrationOperation::CreateDomain { name, domain } => {
+                let schema = get_schema_mut(dbinfo, name)?;
+                if schema.domains.contains_key(&name.0[2]) {
+                    anyhow::bail!("domain already exists");
+                }
+                schema.domains.insert(name.0[2].clone(), (*domain).clone());
+            }
+            MigrationOperation::DropDomain { name, .. } => {
+                get_schema_mut(dbinfo, name)?
+                    .domains
+                    .remove(&name.0[2])
+                    .ok_or(anyhow::anyhow!("domain does not found"))?;
+            }
+            MigrationOperation::AlterDomain { name, operation } => {
+                let domain = get_schema_mut(dbinfo, name)?
+                    .domains
+                    .get_mut(&name.0[2])
+                    .ok_or(anyhow::anyhow!("domain does not found"))?;
+                operation.apply(domain)?;
+            }
+            MigrationOperation::CreateTrigger { table, trigger } => {
+                let table = get_schema_mut(dbinfo, table)?.get_table_mut(&table.0[2])?;
+                if table.triggers.iter().any(|t| t.name == trigger.name) {
+                    anyhow::bail!("trigger already exists");
+                }
+                table.triggers.push((*trigger).clone());
+            }
+            MigrationOperation::DropTrigger { table, trigger } => {
+                let table = get_schema_mut(dbinfo, table)?.get_table_mut(&table.0[2])?;
+                let position = table
+                    .triggers
+                    .iter()
+                    .position(|t| t.name == trigger.name)
+                    .ok_or(anyhow::anyhow!("trigger does not found"))?;
+                table.triggers.remove(position);
+            }
+            MigrationOperation::CreatePolicy { table, policy } => {
+                let table = get_schema_mut(dbinfo, table)?.get_table_mut(&table.0[2])?;
+                if table.policies.iter().any(|p| p.name == policy.name) {
+                    anyhow::bail!("policy already exists");
+                }
+                table.policies.push((*policy).clone());
+            }
+            MigrationOperation::DropPolicy { table, policy } => {
+                let table = get_schema_mut(dbinfo, table)?.get_table_mut(&table.0[2])?;
+                let position = table
+                    .policies
+                    .iter()
+                    .position(|p| p.name == policy.name)
+                    .ok_or(anyhow::anyhow!("policy does not found"))?;
+                table.policies.remove(position);
+            }
+            MigrationOperation::AlterPolicy { table, current, .. } => {
+                let table = get_schema_mut(dbinfo, table)?.get_table_mut(&table.0[2])?;
+                let policy = table
+                    .policies
+                    .iter_mut()
+                    .find(|p| p.name == current.name)
+                    .ok_or(anyhow::anyhow!("policy does not found"))?;
+                *policy = (*current).clone();
+            }
+            MigrationOperation::CreateView { name, view } => {
+                let schema = get_schema_mut(dbinfo, name)?;
+                if schema.views.contains_key(&name.0[2]) {
+                    anyhow::bail!("view already exists");
+                }
+                schema.views.insert(name.0[2].clone(), (*view).clone());
+            }
+            MigrationOperation::DropView { name, .. } => {
+                get_schema_mut(dbinfo, name)?
+                    .views
+                    .remove(&name.0[2])
+                    .ok_or(anyhow::anyhow!("view does not found"))?;
+            }
+            MigrationOperation::ReplaceView { name, view } => {
+                let schema = get_schema_mut(dbinfo, name)?;
+                schema.get_view_mut(&name.0[2])?;
+                schema.views.insert(name.0[2].clone(), (*view).clone());
+            }
+            MigrationOperation::CreateViewIndex { view, index } => {
+                let view = get_schema_mut(dbinfo, view)?.get_view_mut(&view.0[2])?;
+                if view.indexes.iter().any(|i| i.name == index.name) {
+                    anyhow::bail!("index already exists");
+                }
+                view.indexes.push((*index).clone());
+            }
+            MigrationOperation::DropViewIndex { view, index } => {
+                let view = get_schema_mut(dbinfo, view)?.get_view_mut(&view.0[2])?;
+                let position = view
+                    .indexes
+                    .iter()
+                    .position(|i| i.name == index.name)
+                    .ok_or(anyhow::anyhow!("index does not found"))?;
+                view.indexes.remove(position);
+            }
+            // What the SQL changes is unknown to migi.
+            MigrationOperation::Raw { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+impl<'a> AlterDomainOperation<'a> {
+    pub fn apply(&self, domain: &mut Domain) -> Result<()> {
+        match self {
+            AlterDomainOperation::SetDefault { value } => domain.default = Some((*value).clone()),
+            AlterDomainOperation::DropDefault => domain.default = None,
+            AlterDomainOperation::SetNotNull => domain.not_null = true,
+            AlterDomainOperation::DropNotNull => domain.not_null = false,
+            AlterDomainOperation::AddConstraint { constraint } => {
+                domain.constraints.push((*constraint).clone())
+            }
+            AlterDomainOperation::DropConstraint { constraint } => {
+                let position = domain
+                    .constraints
+                    .iter()
+                    .position(|c| c == *constraint)
+                    .ok_or(anyhow::anyhow!("constraint does not found"))?;
+                domain.constraints.remove(position);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> AlterTableOperation<'a> {
+    pub fn apply(&self, table: &mut Table) -> Result<()> {
+        match self {
+            AlterTableOperation::AddColumn { column } => {
+                if table.get_column(&column.name).is_ok() {
+                    anyhow::bail!("column already exists");
+                }
+                table.columns.push((*column).clone());
+            }
+            AlterTableOperation::DropColumn { column } => {
+                table.get_column(&column.name)?;
+                table.columns.retain(|c| c.name != column.name);
+            }
+            AlterTableOperation::AlterColumn {
+                previous, current, ..
+            } => {
+                *table.get_column_mut(&previous.name)? = (*current).clone();
+            }
+            AlterTableOperation::MoveColumn { column, after } => {
+                let column = table.get_column(&column.name)?.clone();
+                table.columns.retain(|c| c.name != column.name);
+                let position = match after {
+                    Some(after) => {
+                        table
+                            .columns
+                            .iter()
+                            .position(|c| c.name == *after)
+                            .ok_or(anyhow::anyhow!("column does not found"))?
+                            + 1
+                    }
+                    None => 0,
+                };
+                table.columns.insert(position, column);
+            }
+            AlterTableOperation::SetColumnDefault { column, value } => {
+                let column = table.get_column_mut(column)?;
+                column
+                    .options
+                    .retain(|o| !matches!(o.option, ColumnOption::Default(_)));
+                column.options.push(ColumnOptionDef {
+                    name: None,
+                    option: ColumnOption::Default((*value).clone()),
+                });
+                column.normalize_options();
+            }
+            AlterTableOperation::DropColumnDefault { column } => {
+                table
+                    .get_column_mut(column)?
+                    .options
+                    .retain(|o| !matches!(o.option, ColumnOption::Default(_)));
+            }
+            AlterTableOperation::AddConstraint { constraint } => {
+                table.constraints.push((*constraint).clone())
+            }
+            AlterTableOperation::DropConstraint { constraint } => {
+                let position = table
+                    .constraints
+                    .iter()
+                    .position(|c| c == *constraint)
+                    .ok_or(anyhow::anyhow!("constraint does not found"))?;
+                table.constraints.remove(position);
+            }
+            AlterTableOperation::AddExclusionConstraint { constraint } => {
+                table.exclusion_constraints.push((*constraint).clone())
+            }
+            AlterTableOperation::DropExclusionConstraint { constraint } => {
+                let position = table
+                    .exclusion_constraints
+                    .iter()
+                    .position(|c| c == *constraint)
+                    .ok_or(anyhow::anyhow!("constraint does not found"))?;
+                table.exclusion_constraints.remove(position);
+            }
+            AlterTableOperation::AddIndex { index } => table.indexes.push((*index).clone()),
+            AlterTableOperation::DropIndex { index } => {
+                let position = table
+                    .indexes
+                    .iter()
+                    .position(|i| i.name == index.name)
+                    .ok_or(anyhow::anyhow!("index does not found"))?;
+                table.indexes.remove(position);
+            }
+            AlterTableOperation::SetEngine { engine } => table.engine = Some(engine.to_string()),
+            AlterTableOperation::SetDefaultCharset { charset } => {
+                table.default_charset = Some(charset.to_string())
+            }
+            AlterTableOperation::SetCollation { collation } => {
+                table.collation = Some(collation.to_string())
+            }
+            AlterTableOperation::SetOptions { options } => {
+                for option in options {
+                    table.with_options.retain(|o| o.name != option.name);
+                    table.with_options.push((*option).clone());
+                }
+            }
+            AlterTableOperation::ResetOptions { names } => {
+                table
+                    .with_options
+                    .retain(|o| !names.contains(&o.name.value.as_str()));
+            }
+            // The check only lives while NOT NULL is being set.
+            AlterTableOperation::AddNotNullCheck { .. }
+            | AlterTableOperation::ValidateNotNullCheck { .. }
+            | AlterTableOperation::DropNotNullCheck { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationWarning {
+    pub message: String,
+    pub destructive: bool, // the migration may lose data
+}
+
+impl fmt::Display for MigrationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.destructive {
+            write!(f, "{} (destructive)", self.message)
+        } else {
+            write!(f, "{}", self.message)
+        }
+    }
 }
 
 pub struct MigrationGenerator<'a> {
     pub previous: &'a Dbinfo,
     pub current: &'a Dbinfo,
     pub migrations: Migration<'a>,
+    pub include_schemas: Vec<String>,
+    pub ignore: Vec<String>,
+    pub detect_table_moves: bool,
+    pub detect_table_renames: bool,
+    pub safe_migrations: bool,
+    pub baseline: bool,
 }
 
 impl<'a> MigrationGenerator<'a> {
     pub fn new(previous: &'a Dbinfo, current: &'a Dbinfo) -> Self {
         Self {
-            previous: previous,
-            current: current,
+            previous,
+            current,
             migrations: Migration {
                 operations: Vec::new(),
+                warnings: Vec::new(),
+                explanations: Vec::new(),
             },
+            include_schemas: Vec::new(),
+            ignore: Vec::new(),
+            detect_table_moves: false,
+            detect_table_renames: false,
+            safe_migrations: false,
+            baseline: false,
+        }
+    }
+
+    // Only schemas in the list are compared, an empty list compares every
+    // schema.
+    pub fn with_include_schemas(mut self, schemas: Vec<String>) -> Self {
+        self.include_schemas = schemas;
+        self
+    }
+
+    // Tables, views and domains matching one of these glob patterns, by name
+    // or by `schema.name`, are never created, altered or dropped. `*` matches
+    // any characters and `?` a single one.
+    pub fn with_ignore(mut self, patterns: Vec<String>) -> Self {
+        self.ignore = patterns;
+        self
+    }
+
+    // Dropped and created tables with the same name and structure in another
+    // schema are moved instead. Off by default, as the table may really have
+    // been dropped and another one created.
+    pub fn with_detect_table_moves(mut self, detect_table_moves: bool) -> Self {
+        self.detect_table_moves = detect_table_moves;
+        self
+    }
+
+    // Dropped and created tables with the same structure in the same schema
+    // are renamed instead. Off by default, as the table may really have been
+    // replaced by another one.
+    pub fn with_detect_table_renames(mut self, detect_table_renames: bool) -> Self {
+        self.detect_table_renames = detect_table_renames;
+        self
+    }
+
+    // Prefers sequences of operations that don't lock tables for long, such
+    // as validating NOT NULL before setting it in Postgres.
+    pub fn with_safe_migrations(mut self, safe_migrations: bool) -> Self {
+        self.safe_migrations = safe_migrations;
+        self
+    }
+
+    // The previous schema stands for an empty database, as for a baseline or
+    // the first migration. Every Postgres database comes with the `public`
+    // schema, another default schema is created.
+    pub fn with_baseline(mut self, baseline: bool) -> Self {
+        self.baseline = baseline;
+        self
+    }
+
+    fn is_included(&self, schema: &str) -> bool {
+        self.include_schemas.is_empty() || self.include_schemas.iter().any(|s| s == schema)
+    }
+
+    fn is_ignored(&self, schema: &str, name: &str) -> bool {
+        let qualified = format!("{}.{}", schema, name);
+        self.ignore
+            .iter()
+            .any(|pattern| glob_match(pattern, name) || glob_match(pattern, &qualified))
+    }
+
+    // A schema holding ignored objects is kept, as they aren't managed by the
+    // migrations.
+    fn holds_ignored(&self, schema: &Schema) -> bool {
+        schema
+            .tables
+            .keys()
+            .chain(schema.views.keys())
+            .chain(schema.domains.keys())
+            .any(|name| self.is_ignored(&schema.name, name))
+    }
+
+    // The part of `dbinfo` the migrations manage, without the schemas left
+    // out of `include_schemas` and the ignored tables, views and domains.
+    pub fn managed(&self, dbinfo: &Dbinfo) -> Dbinfo {
+        let mut dbinfo = dbinfo.clone();
+        for catalog in dbinfo.catalogs.values_mut() {
+            catalog.schemas.retain(|name, _| self.is_included(name));
+            for schema in catalog.schemas.values_mut() {
+                let name = schema.name.clone();
+                schema.tables.retain(|k, _| !self.is_ignored(&name, k));
+                schema.views.retain(|k, _| !self.is_ignored(&name, k));
+                schema.domains.retain(|k, _| !self.is_ignored(&name, k));
+            }
         }
+        dbinfo
     }
 
     pub fn generate(mut self) -> Result<Migration<'a>> {
         self.gen_catalogs()?;
+        if self.detect_table_renames {
+            self.detect_table_renames();
+        }
+        if self.detect_table_moves {
+            self.detect_table_moves();
+        }
+        self.sort_tables_by_dependencies();
+        self.sort_views();
+        self.sort_triggers();
+        self.migrations.explanations = self
+            .migrations
+            .operations
+            .iter()
+            .map(|op| self.explain(op))
+            .collect();
         Ok(self.migrations)
     }
 
+    // Views are dropped before and created after the tables, whose columns
+    // they may use.
+    fn sort_views(&mut self) {
+        let (mut dropped, others): (Vec<_>, Vec<_>) =
+            self.migrations.operations.drain(..).partition(|op| {
+                matches!(
+                    op,
+                    MigrationOperation::DropView { .. } | MigrationOperation::DropViewIndex { .. }
+                )
+            });
+        let (others, created): (Vec<_>, Vec<_>) = others.into_iter().partition(|op| {
+            !matches!(
+                op,
+                MigrationOperation::CreateView { .. }
+                    | MigrationOperation::ReplaceView { .. }
+                    | MigrationOperation::CreateViewIndex { .. }
+            )
+        });
+        dropped.extend(others);
+        dropped.extend(created);
+        self.migrations.operations = dropped;
+    }
+
+    // Triggers and policies are dropped before and created or altered after
+    // every other operation, so that the tables and columns they use exist
+    // when they are created and no longer need them when they are dropped.
+    fn sort_triggers(&mut self) {
+        let (mut dropped, others): (Vec<_>, Vec<_>) =
+            self.migrations.operations.drain(..).partition(|op| {
+                matches!(
+                    op,
+                    MigrationOperation::DropTrigger { .. } | MigrationOperation::DropPolicy { .. }
+                )
+            });
+        let (others, created): (Vec<_>, Vec<_>) = others.into_iter().partition(|op| {
+            !matches!(
+                op,
+                MigrationOperation::CreateTrigger { .. }
+                    | MigrationOperation::CreatePolicy { .. }
+                    | MigrationOperation::AlterPolicy { .. }
+            )
+        });
+        dropped.extend(others);
+        dropped.extend(created);
+        self.migrations.operations = dropped;
+    }
+
+    // Replaces a created table by the rename of the single dropped table of its
+    // schema it matches once renamed.
+    fn detect_table_renames(&mut self) {
+        self.replace_dropped_tables(
+            |dropped_name, dropped, name, table| {
+                let mut renamed = dropped.clone();
+                renamed.name = table.name.clone();
+                dropped_name.0[..2] == name.0[..2] && renamed.semantically_eq(table)
+            },
+            |from, to| MigrationOperation::RenameTable { from, to },
+        );
+    }
+
+    // Replaces a created table by the move of the single dropped table it
+    // matches, Postgres only moving tables within a database.
+    fn detect_table_moves(&mut self) {
+        let dialect = self.current.dialect;
+        if dialect == Dialect::SQLite {
+            return;
+        }
+        self.replace_dropped_tables(
+            |dropped_name, dropped, name, table| {
+                dropped_name.0[2] == name.0[2]
+                    && (dialect != Dialect::PostgreSql || dropped_name.0[0] == name.0[0])
+                    && dropped.semantically_eq(table)
+            },
+            |from, to| MigrationOperation::MoveTable { from, to },
+        );
+    }
+
+    // Replaces the creation of a table and the drop of the single table it
+    // matches by one operation keeping the data of the dropped table.
+    fn replace_dropped_tables(
+        &mut self,
+        matches: impl Fn(&ObjectName, &Table, &ObjectName, &Table) -> bool,
+        replacement: fn(ObjectName, ObjectName) -> MigrationOperation<'a>,
+    ) {
+        let dropped: Vec<(usize, &ObjectName, &Table)> = self
+            .migrations
+            .operations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, op)| match op {
+                MigrationOperation::DropTable { name, table } => Some((i, name, *table)),
+                _ => None,
+            })
+            .collect();
+        let candidates = |name: &ObjectName, table: &Table| -> Vec<(usize, ObjectName)> {
+            dropped
+                .iter()
+                .filter(|(_, dropped_name, dropped_table)| {
+                    matches(dropped_name, dropped_table, name, table)
+                })
+                .map(|(i, dropped_name, _)| (*i, (*dropped_name).clone()))
+                .collect()
+        };
+
+        let mut replaced: Vec<(usize, usize, ObjectName, ObjectName)> = Vec::new();
+        for (i, op) in self.migrations.operations.iter().enumerate() {
+            let MigrationOperation::CreateTable { name, table, .. } = op else {
+                continue;
+            };
+            if let [(dropped, from)] = candidates(name, table).as_slice() {
+                replaced.push((i, *dropped, from.clone(), name.clone()));
+            }
+        }
+        // A dropped table matching several created ones is ambiguous.
+        let matched: Vec<usize> = replaced.iter().map(|(_, dropped, _, _)| *dropped).collect();
+        replaced.retain(|(_, dropped, _, _)| matched.iter().filter(|d| *d == dropped).count() == 1);
+
+        // Triggers and policies go along with their table.
+        let mut removed = BTreeSet::new();
+        for (created, dropped, from, to) in replaced {
+            for (i, op) in self.migrations.operations.iter().enumerate() {
+                if matches!(op, MigrationOperation::CreateTrigger { table, .. }
+                    | MigrationOperation::CreatePolicy { table, .. } if *table == to)
+                {
+                    removed.insert(i);
+                }
+            }
+            self.migrations.operations[created] = replacement(from, to);
+            removed.insert(dropped);
+        }
+        let mut i = 0;
+        self.migrations.operations.retain(|_| {
+            i += 1;
+            !removed.contains(&(i - 1))
+        });
+    }
+
+    // Referenced tables are created before and dropped after the tables
+    // referencing them. Other operations keep their positions, and tables
+    // without dependencies between them keep their relative order.
+    fn sort_tables_by_dependencies(&mut self) {
+        let mut operations: Vec<Option<MigrationOperation<'a>>> =
+            self.migrations.operations.drain(..).map(Some).collect();
+
+        for create in [true, false] {
+            let positions: Vec<usize> = operations
+                .iter()
+                .enumerate()
+                .filter_map(|(i, op)| match op {
+                    Some(MigrationOperation::CreateTable { .. }) if create => Some(i),
+                    Some(MigrationOperation::DropTable { .. }) if !create => Some(i),
+                    _ => None,
+                })
+                .collect();
+            let tables: Vec<(&ObjectName, &Table)> = positions
+                .iter()
+                .map(|&i| match &operations[i] {
+                    Some(MigrationOperation::CreateTable { name, table, .. })
+                    | Some(MigrationOperation::DropTable { name, table }) => (name, *table),
+                    _ => unreachable!(),
+                })
+                .collect();
+
+            // dependencies[i] lists the tables table i references
+            let dbinfo = if create { self.current } else { self.previous };
+            let dependencies: Vec<Vec<usize>> = tables
+                .iter()
+                .enumerate()
+                .map(|(i, (name, table))| {
+                    table
+                        .referenced_tables()
+                        .into_iter()
+                        .map(|foreign_table| resolve_table_name(dbinfo, name, foreign_table))
+                        .filter_map(|foreign_table| {
+                            tables.iter().position(|(name, _)| **name == foreign_table)
+                        })
+                        .filter(|&j| j != i)
+                        .collect()
+                })
+                .collect();
+
+            let mut sorted = dependency_order(&dependencies);
+            if !create {
+                sorted.reverse();
+            }
+
+            let sorted_operations: Vec<MigrationOperation<'a>> = sorted
+                .iter()
+                .map(|&i| operations[positions[i]].take().unwrap())
+                .collect();
+            for (position, operation) in positions.iter().zip(sorted_operations) {
+                operations[*position] = Some(operation);
+            }
+        }
+
+        self.migrations.operations = operations.into_iter().map(Option::unwrap).collect();
+    }
+
     fn gen_catalogs(&mut self) -> Result<()> {
-        let previous_catalogs: HashSet<&str> =
+        let previous_catalogs: BTreeSet<&str> =
             self.previous.catalogs.keys().map(|k| k.as_str()).collect();
 
-        let current_catalogs: HashSet<&str> =
+        let current_catalogs: BTreeSet<&str> =
             self.current.catalogs.keys().map(|k| k.as_str()).collect();
 
         let dropped_catalogs = previous_catalogs.difference(&current_catalogs);
@@ -65,6 +1144,14 @@ impl<'a> MigrationGenerator<'a> {
         let common_catalogs = previous_catalogs.intersection(&current_catalogs);
 
         for catalog in dropped_catalogs {
+            let previous_catalog = self.previous.catalogs.get(*catalog).unwrap();
+            if previous_catalog
+                .schemas
+                .values()
+                .any(|s| self.holds_ignored(s))
+            {
+                continue;
+            }
             self.migrations
                 .operations
                 .push(MigrationOperation::DropDatabase {
@@ -78,13 +1165,34 @@ impl<'a> MigrationGenerator<'a> {
                 .push(MigrationOperation::CreateDatabase {
                     name: catalog.to_string(),
                 });
+
+            // The default schema comes with the database.
+            let current_catalog = self.current.catalogs.get(*catalog).unwrap();
+            let mut schemas: Vec<&Schema> = current_catalog
+                .schemas
+                .values()
+                .filter(|s| self.is_included(&s.name))
+                .collect();
+            schemas.sort_by(|a, b| a.name.cmp(&b.name));
+            for schema in schemas {
+                if schema.name != current_catalog.default_schema {
+                    self.migrations
+                        .operations
+                        .push(MigrationOperation::CreateSchema {
+                            name: ObjectName(vec![catalog.to_string(), schema.name.clone()]),
+                        });
+                }
+                self.gen_domains(catalog, None, schema)?;
+                self.gen_create_tables(catalog, schema)?;
+                self.gen_views(catalog, None, schema);
+            }
         }
 
         for catalog in common_catalogs {
             let previous_catalog = self.previous.catalogs.get(*catalog).unwrap();
             let current_catalog = self.current.catalogs.get(*catalog).unwrap();
 
-            if previous_catalog.default_schema != current_catalog.default_schema {
+            if previous_catalog != current_catalog {
                 self.gen_schemas(previous_catalog, current_catalog)?;
             }
         }
@@ -93,83 +1201,368 @@ impl<'a> MigrationGenerator<'a> {
     }
 
     fn gen_schemas(&mut self, previous: &'a Catalog, current: &'a Catalog) -> Result<()> {
-        let previous_schemas: HashSet<&str> = previous.schemas.keys().map(|k| k.as_str()).collect();
-        let current_schemas: HashSet<&str> = current.schemas.keys().map(|k| k.as_str()).collect();
+        let previous_schemas: BTreeSet<&str> = previous
+            .schemas
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| self.is_included(k))
+            .collect();
+        let current_schemas: BTreeSet<&str> = current
+            .schemas
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| self.is_included(k))
+            .collect();
 
         let dropped_schemas = previous_schemas.difference(&current_schemas);
         let created_schemas = current_schemas.difference(&previous_schemas);
         let common_schemas = previous_schemas.intersection(&current_schemas);
 
         for schema in dropped_schemas {
+            if self.holds_ignored(previous.schemas.get(*schema).unwrap()) {
+                continue;
+            }
             self.migrations
                 .operations
                 .push(MigrationOperation::DropSchema {
                     name: ObjectName(vec![current.name.clone(), schema.to_string()]),
                 });
         }
+        if self.baseline
+            && self.current.dialect == Dialect::PostgreSql
+            && current.default_schema != "public"
+            && self.is_included(&current.default_schema)
+        {
+            self.migrations
+                .operations
+                .push(MigrationOperation::CreateSchema {
+                    name: ObjectName(vec![current.name.clone(), current.default_schema.clone()]),
+                });
+        }
         for schema in created_schemas {
             self.migrations
                 .operations
                 .push(MigrationOperation::CreateSchema {
                     name: ObjectName(vec![current.name.clone(), schema.to_string()]),
                 });
+            let schema = current.schemas.get(*schema).unwrap();
+            self.gen_domains(&current.name, None, schema)?;
+            self.gen_create_tables(&current.name, schema)?;
+            self.gen_views(&current.name, None, schema);
         }
 
         for schema in common_schemas {
             let previous_schema = previous.schemas.get(*schema).unwrap();
             let current_schema = current.schemas.get(*schema).unwrap();
 
+            // Domains are created before the tables using them and dropped
+            // after.
+            self.gen_domains(&current.name, Some(previous_schema), current_schema)?;
             if previous_schema.tables != current_schema.tables {
                 self.gen_tables(&current.name, previous_schema, current_schema)?;
             }
+            self.gen_drop_domains(&current.name, previous_schema, current_schema);
+            self.gen_views(&current.name, Some(previous_schema), current_schema);
         }
 
         Ok(())
     }
 
-    fn gen_tables(
+    fn gen_domains(
         &mut self,
         catalog_name: &str,
-        previous: &'a Schema,
+        previous: Option<&'a Schema>,
         current: &'a Schema,
     ) -> Result<()> {
-        let previous_tables: HashSet<&str> = previous.tables.keys().map(|k| k.as_str()).collect();
-        let current_tables: HashSet<&str> = current.tables.keys().map(|k| k.as_str()).collect();
+        let domains: BTreeSet<&str> = current
+            .domains
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !self.is_ignored(&current.name, k))
+            .collect();
+        for domain in domains {
+            let name = ObjectName(vec![
+                catalog_name.to_string(),
+                current.name.clone(),
+                domain.to_string(),
+            ]);
+            let current_domain = current.domains.get(domain).unwrap();
+            match previous.and_then(|schema| schema.domains.get(domain)) {
+                None => self
+                    .migrations
+                    .operations
+                    .push(MigrationOperation::CreateDomain {
+                        name,
+                        domain: current_domain,
+                    }),
+                Some(previous_domain) if previous_domain != current_domain => {
+                    self.gen_alter_domain(name, previous_domain, current_domain)?
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn gen_alter_domain(
+        &mut self,
+        name: ObjectName,
+        previous: &'a Domain,
+        current: &'a Domain,
+    ) -> Result<()> {
+        let dialect = self.current.dialect;
+        if !data_type_equivalent(&previous.data_type, &current.data_type, dialect)
+            || previous.collation != current.collation
+        {
+            anyhow::bail!(
+                "domain {} changes its type from {} to {}, which can't be altered",
+                name,
+                previous.data_type,
+                current.data_type
+            );
+        }
 
-        let dropped_tables = previous_tables.difference(&current_tables);
-        let created_tables = current_tables.difference(&previous_tables);
-        let common_tables = previous_tables.intersection(&current_tables);
+        let mut operations = Vec::new();
+        match (&previous.default, &current.default) {
+            (previous, Some(value)) if previous.as_ref() != Some(value) => {
+                operations.push(AlterDomainOperation::SetDefault { value })
+            }
+            (Some(_), None) => operations.push(AlterDomainOperation::DropDefault),
+            _ => {}
+        }
+        match (previous.not_null, current.not_null) {
+            (false, true) => operations.push(AlterDomainOperation::SetNotNull),
+            (true, false) => operations.push(AlterDomainOperation::DropNotNull),
+            _ => {}
+        }
+        for constraint in &previous.constraints {
+            if !current.constraints.contains(constraint) {
+                operations.push(AlterDomainOperation::DropConstraint { constraint });
+            }
+        }
+        for constraint in &current.constraints {
+            if !previous.constraints.contains(constraint) {
+                operations.push(AlterDomainOperation::AddConstraint { constraint });
+            }
+        }
 
-        for table in dropped_tables {
+        for operation in operations {
             self.migrations
                 .operations
-                .push(MigrationOperation::DropTable {
-                    name: ObjectName(vec![
-                        catalog_name.to_string(),
-                        current.name.clone(),
-                        table.to_string(),
-                    ]),
-                    table: previous.tables.get(*table).unwrap(),
+                .push(MigrationOperation::AlterDomain {
+                    name: name.clone(),
+                    operation,
                 });
         }
-        for table in created_tables {
-            self.migrations
-                .operations
-                .push(MigrationOperation::CreateTable {
-                    name: ObjectName(vec![
-                        catalog_name.to_string(),
-                        current.name.clone(),
+        Ok(())
+    }
+
+    fn gen_drop_domains(&mut self, catalog_name: &str, previous: &'a Schema, current: &Schema) {
+        let domains: BTreeSet<&str> = previous
+            .domains
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !self.is_ignored(&previous.name, k))
+            .collect();
+        for domain in domains {
+            if !current.domains.contains_key(domain) {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::DropDomain {
+                        name: ObjectName(vec![
+                            catalog_name.to_string(),
+                            current.name.clone(),
+                            domain.to_string(),
+                        ]),
+                        domain: previous.domains.get(domain).unwrap(),
+                    });
+            }
+        }
+    }
+
+    // A changed view is replaced when its columns are kept, otherwise it is
+    // dropped and created again. Materialized views are always created again,
+    // which also refreshes them.
+    fn gen_views(&mut self, catalog_name: &str, previous: Option<&'a Schema>, current: &'a Schema) {
+        let views = |schema: &'a Schema| -> BTreeMap<&'a str, &'a View> {
+            schema
+                .views
+                .iter()
+                .filter(|(name, _)| !self.is_ignored(&schema.name, name))
+                .map(|(name, view)| (name.as_str(), view))
+                .collect()
+        };
+        let previous_views = previous.map(views).unwrap_or_default();
+        let current_views = views(current);
+        let view_name = |view: &str| {
+            ObjectName(vec![
+                catalog_name.to_string(),
+                current.name.clone(),
+                view.to_string(),
+            ])
+        };
+
+        let changed = |previous: &View, current: &View| {
+            previous.materialized != current.materialized
+                || previous.columns != current.columns
+                || previous.query != current.query
+        };
+        let dialect = self.current.dialect;
+        let replaceable =
+            |previous: &View, current: &View| view_replaceable(dialect, previous, current);
+        for (name, view) in &previous_views {
+            let recreated = current_views
+                .get(name)
+                .is_some_and(|current| changed(view, current) && !replaceable(view, current));
+            if recreated || !current_views.contains_key(name) {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::DropView {
+                        name: view_name(name),
+                        view,
+                    });
+            }
+        }
+        let equal =
+            |a: &Index, b: &Index| normalize_index(a, dialect) == normalize_index(b, dialect);
+        for (name, view) in &current_views {
+            let previous_view = match previous_views.get(name) {
+                Some(previous) if !changed(previous, view) => previous,
+                Some(previous) if replaceable(previous, view) => {
+                    self.migrations
+                        .operations
+                        .push(MigrationOperation::ReplaceView {
+                            name: view_name(name),
+                            view,
+                        });
+                    continue;
+                }
+                _ => {
+                    self.migrations
+                        .operations
+                        .push(MigrationOperation::CreateView {
+                            name: view_name(name),
+                            view,
+                        });
+                    continue;
+                }
+            };
+            for index in &previous_view.indexes {
+                if !view
+                    .indexes
+                    .iter()
+                    .any(|i| i.name == index.name && equal(i, index))
+                {
+                    self.migrations
+                        .operations
+                        .push(MigrationOperation::DropViewIndex {
+                            view: view_name(name),
+                            index,
+                        });
+                }
+            }
+            for index in &view.indexes {
+                if !previous_view
+                    .indexes
+                    .iter()
+                    .any(|i| i.name == index.name && equal(i, index))
+                {
+                    self.migrations
+                        .operations
+                        .push(MigrationOperation::CreateViewIndex {
+                            view: view_name(name),
+                            index,
+                        });
+                }
+            }
+        }
+    }
+
+    fn gen_create_tables(&mut self, catalog_name: &str, schema: &'a Schema) -> Result<()> {
+        let tables: BTreeSet<&str> = schema
+            .tables
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !self.is_ignored(&schema.name, k))
+            .collect();
+        for table in tables {
+            let name = ObjectName(vec![
+                catalog_name.to_string(),
+                schema.name.clone(),
+                table.to_string(),
+            ]);
+            let table = schema.tables.get(table).unwrap();
+            self.migrations
+                .operations
+                .push(MigrationOperation::CreateTable {
+                    name: name.clone(),
+                    table,
+                    with_indexes: true,
+                });
+            self.gen_triggers(&name, &[], &table.triggers);
+            self.gen_policies(&name, &[], &table.policies);
+        }
+        Ok(())
+    }
+
+    fn gen_tables(
+        &mut self,
+        catalog_name: &str,
+        previous: &'a Schema,
+        current: &'a Schema,
+    ) -> Result<()> {
+        let previous_tables: BTreeSet<&str> = previous
+            .tables
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !self.is_ignored(&previous.name, k))
+            .collect();
+        let current_tables: BTreeSet<&str> = current
+            .tables
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| !self.is_ignored(&current.name, k))
+            .collect();
+
+        let dropped_tables = previous_tables.difference(&current_tables);
+        let created_tables = current_tables.difference(&previous_tables);
+        let common_tables = previous_tables.intersection(&current_tables);
+
+        for table in dropped_tables {
+            self.migrations
+                .operations
+                .push(MigrationOperation::DropTable {
+                    name: ObjectName(vec![
+                        catalog_name.to_string(),
+                        current.name.clone(),
                         table.to_string(),
                     ]),
-                    table: current.tables.get(*table).unwrap(),
+                    table: previous.tables.get(*table).unwrap(),
+                });
+        }
+        for table in created_tables {
+            let name = ObjectName(vec![
+                catalog_name.to_string(),
+                current.name.clone(),
+                table.to_string(),
+            ]);
+            let table = current.tables.get(*table).unwrap();
+            self.migrations
+                .operations
+                .push(MigrationOperation::CreateTable {
+                    name: name.clone(),
+                    table,
+                    with_indexes: true,
                 });
+            self.gen_triggers(&name, &[], &table.triggers);
+            self.gen_policies(&name, &[], &table.policies);
         }
 
         for table in common_tables {
             let previous_table = previous.tables.get(*table).unwrap();
             let current_table = current.tables.get(*table).unwrap();
 
-            if previous_table != current_table {
+            if !previous_table.semantically_eq(current_table) {
                 self.gen_table(
                     ObjectName(vec![catalog_name.to_string(), current.name.clone()]),
                     previous_table,
@@ -187,9 +1580,107 @@ impl<'a> MigrationGenerator<'a> {
         previous: &'a Table,
         current: &'a Table,
     ) -> Result<()> {
+        let table_name = ObjectName(vec![
+            schema_name.0[0].clone(),
+            schema_name.0[1].clone(),
+            current.name.clone(),
+        ]);
+
+        // Changing the partition key of a table requires recreating it, as
+        // does changing its ON COMMIT action, which no ALTER TABLE sets.
+        let recreated = if previous.partition_by != current.partition_by {
+            Some("partitioning")
+        } else if previous.on_commit != current.on_commit {
+            Some("ON COMMIT action")
+        } else {
+            None
+        };
+        if let Some(change) = recreated {
+            self.migrations.warnings.push(MigrationWarning {
+                message: format!(
+                    "{} of table {} changed, the table will be recreated",
+                    change, table_name
+                ),
+                destructive: true,
+            });
+            self.migrations
+                .operations
+                .push(MigrationOperation::DropTable {
+                    name: table_name.clone(),
+                    table: previous,
+                });
+            self.migrations
+                .operations
+                .push(MigrationOperation::CreateTable {
+                    name: table_name.clone(),
+                    table: current,
+                    with_indexes: true,
+                });
+            self.gen_triggers(&table_name, &[], &current.triggers);
+            self.gen_policies(&table_name, &[], &current.policies);
+            return Ok(());
+        }
+
+        // SQLite's ALTER TABLE can only rename, add and drop columns: it can't
+        // toggle these options, change constraints or columns, nor add a NOT
+        // NULL column without a default.
+        let dialect = self.current.dialect;
+        let column_changed = current.columns.iter().any(|column| {
+            previous.get_column(&column.name).is_ok_and(|previous| {
+                resolved(self.previous, &table_name, previous)
+                    != resolved(self.current, &table_name, column)
+            })
+        });
+        let column_not_addable = current.columns.iter().any(|column| {
+            previous.get_column(&column.name).is_err()
+                && is_not_null(column)
+                && default_value(column).is_none()
+        });
+        if dialect == Dialect::SQLite
+            && (previous.strict != current.strict
+                || previous.without_rowid != current.without_rowid
+                || !same_elements(&previous.constraints, &current.constraints)
+                || column_changed
+                || column_not_addable)
+        {
+            return self.gen_rebuild_table(table_name, previous, current);
+        }
+
+        match self.current.dialect {
+            Dialect::MySql | Dialect::MariaDb => {
+                self.gen_mysql_table_options(&table_name, previous, current)?
+            }
+            Dialect::PostgreSql => self.gen_storage_parameters(&table_name, previous, current)?,
+            Dialect::SQLite => {}
+        }
+
         let previous_columns: Vec<&str> =
             previous.columns.iter().map(|c| c.name.as_str()).collect();
-        let current_columns: Vec<&str> = current.columns.iter().map(|c| c.name.as_str()).collect();
+        let mut current_columns: Vec<&str> =
+            current.columns.iter().map(|c| c.name.as_str()).collect();
+
+        // Columns kept in both versions but in another order are diffed in
+        // their previous order, so that reordering them doesn't drop and add
+        // them. MySQL moves them afterwards, the other dialects can't.
+        let kept_previous: Vec<&str> = previous_columns
+            .iter()
+            .copied()
+            .filter(|name| current_columns.contains(name))
+            .collect();
+        let kept_current: Vec<&str> = current_columns
+            .iter()
+            .copied()
+            .filter(|name| previous_columns.contains(name))
+            .collect();
+        let reordered = kept_previous != kept_current;
+        if reordered {
+            let mut kept = kept_previous.iter();
+            for name in current_columns.iter_mut() {
+                if previous_columns.contains(name) {
+                    *name = kept.next().unwrap();
+                }
+            }
+        }
 
         let columns_diff = diff::slice(&previous_columns, &current_columns);
 
@@ -209,75 +1700,2122 @@ impl<'a> MigrationGenerator<'a> {
                 }
             }
         }
-        if first_added_column.is_some() && last_common_column.is_some() {
-            let first_added_column = first_added_column.unwrap();
-            let last_common_column = last_common_column.unwrap();
-
+        if let (Some(first_added_column), Some(last_common_column)) =
+            (first_added_column, last_common_column)
+        {
             if first_added_column < last_common_column {
                 anyhow::bail!("migi can't add columns in the middle of a table");
             }
         }
 
-        let table_name = ObjectName(vec![
-            schema_name.0[0].clone(),
-            schema_name.0[1].clone(),
-            current.name.clone(),
-        ]);
+        self.gen_drop_primary_key(&table_name, previous, current);
         let mut i = 0;
         let mut j = 0;
         for result in columns_diff {
             match result {
                 diff::Result::Left(_) => {
-                    self.gen_drop_column(&table_name, previous, previous.columns.get(i).unwrap())?;
+                    self.gen_drop_column(&table_name, previous.columns.get(i).unwrap())?;
                     i += 1;
                 }
                 diff::Result::Both(_, _) => {
+                    let column = previous.columns.get(i).unwrap();
                     self.gen_alter_column(
                         &table_name,
-                        previous,
-                        previous.columns.get(i).unwrap(),
-                        current,
-                        current.columns.get(j).unwrap(),
+                        (previous, column),
+                        (current, current.get_column(&column.name)?),
                     )?;
                     i += 1;
                     j += 1;
                 }
                 diff::Result::Right(_) => {
-                    self.gen_add_column(&table_name, current, current.columns.get(j).unwrap())?;
+                    self.gen_add_column(&table_name, current.get_column(current_columns[j])?)?;
                     j += 1;
                 }
             }
         }
 
+        if reordered {
+            self.gen_column_positions(&table_name, &kept_previous, current)?;
+        }
+
+        self.gen_constraints(&table_name, previous, current)?;
+        self.gen_indexes(&table_name, previous, current);
+        self.gen_triggers(&table_name, &previous.triggers, &current.triggers);
+        self.gen_policies(&table_name, &previous.policies, &current.policies);
         Ok(())
     }
 
-    fn gen_alter_column(
+    // Triggers are matched by name. None of the dialects can alter a trigger
+    // in place, so a changed one is dropped and created again.
+    fn gen_triggers(
         &mut self,
         table_name: &ObjectName,
-        previous_table: &'a Table,
-        previous: &'a Column,
-        current_table: &'a Table,
-        current: &'a Column,
+        previous: &'a [Trigger],
+        current: &'a [Trigger],
+    ) {
+        let find = |triggers: &'a [Trigger], name: &str| triggers.iter().find(|t| t.name == name);
+
+        for trigger in previous {
+            if find(current, &trigger.name) != Some(trigger) {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::DropTrigger {
+                        table: table_name.clone(),
+                        trigger,
+                    });
+            }
+        }
+        for trigger in current {
+            if find(previous, &trigger.name) != Some(trigger) {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::CreateTrigger {
+                        table: table_name.clone(),
+                        trigger,
+                    });
+            }
+        }
+    }
+
+    // Policies are matched by name. ALTER POLICY can change the roles and set
+    // the USING and WITH CHECK expressions, any other change drops the policy
+    // and creates it again.
+    fn gen_policies(
+        &mut self,
+        table_name: &ObjectName,
+        previous: &'a [Policy],
+        current: &'a [Policy],
+    ) {
+        let find = |policies: &'a [Policy], name: &str| policies.iter().find(|p| p.name == name);
+        let alterable = |a: &Policy, b: &Policy| {
+            a.permissive == b.permissive
+                && a.command == b.command
+                && (a.using.is_none() || b.using.is_some())
+                && (a.with_check.is_none() || b.with_check.is_some())
+        };
+
+        for policy in previous {
+            match find(current, &policy.name) {
+                Some(current) if current == policy || alterable(policy, current) => {}
+                _ => self
+                    .migrations
+                    .operations
+                    .push(MigrationOperation::DropPolicy {
+                        table: table_name.clone(),
+                        policy,
+                    }),
+            }
+        }
+        for policy in current {
+            match find(previous, &policy.name) {
+                Some(previous) if previous == policy => {}
+                Some(previous) if alterable(previous, policy) => {
+                    self.migrations
+                        .operations
+                        .push(MigrationOperation::AlterPolicy {
+                            table: table_name.clone(),
+                            previous,
+                            current: policy,
+                        })
+                }
+                _ => self
+                    .migrations
+                    .operations
+                    .push(MigrationOperation::CreatePolicy {
+                        table: table_name.clone(),
+                        policy,
+                    }),
+            }
+        }
+    }
+
+    // Indexes are matched by name. One whose columns, their order, ASC/DESC
+    // or NULLS ordering changed is dropped and created again.
+    fn gen_indexes(&mut self, table_name: &ObjectName, previous: &'a Table, current: &'a Table) {
+        let dialect = self.current.dialect;
+        let find = |indexes: &'a [Index], name: &str| indexes.iter().find(|i| i.name == name);
+        let equal =
+            |a: &Index, b: &Index| normalize_index(a, dialect) == normalize_index(b, dialect);
+
+        let mut operations = Vec::new();
+        for index in &previous.indexes {
+            match find(&current.indexes, &index.name) {
+                Some(current_index) if equal(current_index, index) => {}
+                _ => operations.push(AlterTableOperation::DropIndex { index }),
+            }
+        }
+        for index in &current.indexes {
+            match find(&previous.indexes, &index.name) {
+                Some(previous_index) if equal(previous_index, index) => {}
+                _ => operations.push(AlterTableOperation::AddIndex { index }),
+            }
+        }
+
+        for operation in operations {
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation,
+                });
+        }
+    }
+
+    // A constraint whose definition changed is dropped and added again, as
+    // there is no way to alter it in place (e.g. the actions of a foreign key).
+    // A dropped primary key goes before the columns change, as Postgres can't
+    // drop NOT NULL from its columns until then.
+    fn gen_drop_primary_key(
+        &mut self,
+        table_name: &ObjectName,
+        previous: &'a Table,
+        current: &'a Table,
+    ) {
+        for constraint in changed_constraints(&previous.constraints, &current.constraints) {
+            if matches!(constraint, TableConstraint::PrimaryKey { .. }) {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::AlterTable {
+                        name: table_name.clone(),
+                        operation: AlterTableOperation::DropConstraint { constraint },
+                    });
+            }
+        }
+    }
+
+    fn gen_constraints(
+        &mut self,
+        table_name: &ObjectName,
+        previous: &'a Table,
+        current: &'a Table,
+    ) -> Result<()> {
+        let mut operations = Vec::new();
+        for constraint in changed_constraints(&previous.constraints, &current.constraints) {
+            if !matches!(constraint, TableConstraint::PrimaryKey { .. }) {
+                operations.push(AlterTableOperation::DropConstraint { constraint });
+            }
+        }
+        for constraint in changed_constraints(&current.constraints, &previous.constraints) {
+            operations.push(AlterTableOperation::AddConstraint { constraint });
+        }
+
+        // Postgres can't alter an EXCLUDE constraint either, a changed one is
+        // dropped and added again.
+        for constraint in &previous.exclusion_constraints {
+            if !current.exclusion_constraints.contains(constraint) {
+                operations.push(AlterTableOperation::DropExclusionConstraint { constraint });
+            }
+        }
+        for constraint in &current.exclusion_constraints {
+            if !previous.exclusion_constraints.contains(constraint) {
+                operations.push(AlterTableOperation::AddExclusionConstraint { constraint });
+            }
+        }
+
+        for operation in operations {
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation,
+                });
+        }
+        Ok(())
+    }
+
+    // Rebuilds a table the way https://www.sqlite.org/lang_altertable.html
+    // recommends: create the new table, copy the data of the columns both
+    // tables share, drop the old table and rename the new one.
+    fn gen_rebuild_table(
+        &mut self,
+        table_name: ObjectName,
+        previous: &'a Table,
+        current: &'a Table,
+    ) -> Result<()> {
+        let mut new_table_name = table_name.clone();
+        if let Some(name) = new_table_name.0.last_mut() {
+            *name = format!("new_{}", name);
+        }
+
+        let columns = current
+            .columns
+            .iter()
+            .filter(|c| previous.get_column(&c.name).is_ok())
+            .map(|c| c.name.as_str())
+            .collect();
+
+        self.migrations.warnings.push(MigrationWarning {
+            message: format!("table {} will be rebuilt", table_name),
+            destructive: false,
+        });
+        // The rows are copied without the added columns, which have to fill
+        // them on their own.
+        for column in &current.columns {
+            if previous.get_column(&column.name).is_err() {
+                self.warn_unfilled_column(&table_name, column);
+            }
+        }
+        // With foreign keys enforced, dropping a referenced table deletes the
+        // rows referencing it first, or fails.
+        let referenced = self
+            .previous
+            .get_catalog(&table_name.0[0])
+            .and_then(|catalog| catalog.get_schema(&table_name.0[1]))
+            .is_ok_and(|schema| {
+                schema.tables.values().any(|t| {
+                    t.name != previous.name
+                        && t.referenced_tables()
+                            .iter()
+                            .any(|r| r.0.last().map(|n| &n.value) == Some(&previous.name))
+                })
+            });
+        if referenced {
+            self.migrations.warnings.push(MigrationWarning {
+                message: format!(
+                    "table {} is referenced by other tables, run its rebuild with PRAGMA foreign_keys=OFF and check PRAGMA foreign_key_check before turning them back ON",
+                    table_name
+                ),
+                destructive: true,
+            });
+        }
+        self.migrations
+            .operations
+            .push(MigrationOperation::CreateTable {
+                name: new_table_name.clone(),
+                table: current,
+                with_indexes: false,
+            });
+        self.migrations
+            .operations
+            .push(MigrationOperation::CopyTableData {
+                from: table_name.clone(),
+                to: new_table_name.clone(),
+                columns,
+            });
+        self.migrations
+            .operations
+            .push(MigrationOperation::DropTable {
+                name: table_name.clone(),
+                table: previous,
+            });
+        self.migrations
+            .operations
+            .push(MigrationOperation::RenameTable {
+                from: new_table_name,
+                to: table_name.clone(),
+            });
+        // Dropping the old table dropped its indexes and triggers.
+        for index in &current.indexes {
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation: AlterTableOperation::AddIndex { index },
+                });
+        }
+        self.gen_triggers(&table_name, &[], &current.triggers);
+        self.gen_policies(&table_name, &[], &current.policies);
+        Ok(())
+    }
+
+    // Table options can't be unset, so only options present in the current
+    // schema are compared.
+    fn gen_mysql_table_options(
+        &mut self,
+        table_name: &ObjectName,
+        previous: &'a Table,
+        current: &'a Table,
     ) -> Result<()> {
-        todo!()
+        let mut operations = Vec::new();
+        if let Some(engine) = current.engine.as_deref() {
+            if previous.engine.as_deref() != Some(engine) {
+                operations.push(AlterTableOperation::SetEngine { engine });
+            }
+        }
+        if let Some(charset) = current.default_charset.as_deref() {
+            if previous.default_charset.as_deref() != Some(charset) {
+                operations.push(AlterTableOperation::SetDefaultCharset { charset });
+            }
+        }
+        if let Some(collation) = current.collation.as_deref() {
+            if previous.collation.as_deref() != Some(collation) {
+                operations.push(AlterTableOperation::SetCollation { collation });
+            }
+        }
+
+        for operation in operations {
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation,
+                });
+        }
+        Ok(())
     }
 
-    fn gen_drop_column(
+    // Storage parameters are compared by name, so reordering them is a no-op.
+    fn gen_storage_parameters(
         &mut self,
         table_name: &ObjectName,
-        previous_table: &'a Table,
-        previous: &'a Column,
+        previous: &'a Table,
+        current: &'a Table,
     ) -> Result<()> {
-        todo!()
+        let find =
+            |options: &'a [SqlOption], name: &str| options.iter().find(|o| o.name.value == name);
+
+        let set_options: Vec<&SqlOption> = current
+            .with_options
+            .iter()
+            .filter(|o| find(&previous.with_options, &o.name.value) != Some(*o))
+            .collect();
+        let reset_options: Vec<&str> = previous
+            .with_options
+            .iter()
+            .filter(|o| find(&current.with_options, &o.name.value).is_none())
+            .map(|o| o.name.value.as_str())
+            .collect();
+
+        if !set_options.is_empty() {
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation: AlterTableOperation::SetOptions {
+                        options: set_options,
+                    },
+                });
+        }
+        if !reset_options.is_empty() {
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation: AlterTableOperation::ResetOptions {
+                        names: reset_options,
+                    },
+                });
+        }
+        Ok(())
     }
 
-    fn gen_add_column(
+    // Columns are compared with their effective nullability, so that a NOT NULL
+    // implied by the primary key can be spelled out or not.
+    fn gen_alter_column(
         &mut self,
         table_name: &ObjectName,
-        current_table: &'a Table,
-        current: &'a Column,
+        (previous_table, previous): (&'a Table, &'a Column),
+        (current_table, current): (&'a Table, &'a Column),
+    ) -> Result<()> {
+        let dialect = self.current.dialect;
+        let effective = |dbinfo: &Dbinfo, table: &Table, column: &Column| {
+            let mut resolved = resolved(dbinfo, table_name, column);
+            resolved
+                .options
+                .retain(|o| !matches!(o.option, ColumnOption::NotNull));
+            (resolved, column.is_nullable(table))
+        };
+        // A change of default alone is made without redefining the column.
+        let without_default = |dbinfo: &Dbinfo, table: &Table, column: &Column| {
+            let (mut column, nullable) = effective(dbinfo, table, column);
+            column
+                .options
+                .retain(|o| !matches!(o.option, ColumnOption::Default(_)));
+            (column, nullable)
+        };
+        if without_default(self.previous, previous_table, previous)
+            == without_default(self.current, current_table, current)
+            && normalized_default(previous, dialect) != normalized_default(current, dialect)
+        {
+            let column = current.name.as_str();
+            let operation = match normalized_default(current, dialect) {
+                Some(_) => AlterTableOperation::SetColumnDefault {
+                    column,
+                    value: default_value(current).unwrap(),
+                },
+                None => AlterTableOperation::DropColumnDefault { column },
+            };
+            self.migrations
+                .operations
+                .push(MigrationOperation::AlterTable {
+                    name: table_name.clone(),
+                    operation,
+                });
+            return Ok(());
+        }
+        let (previous_nullable, current_nullable) = (
+            previous.is_nullable(previous_table),
+            current.is_nullable(current_table),
+        );
+        // A primary key added or dropped changes the nullability of its
+        // columns on its own.
+        if (previous != current
+            && effective(self.previous, previous_table, previous)
+                != effective(self.current, current_table, current))
+            || previous_nullable != current_nullable
+        {
+            let previous_type = resolved(self.previous, table_name, previous).data_type;
+            let current_type = resolved(self.current, table_name, current).data_type;
+            if previous_type != current_type {
+                if let Some(narrowing) = narrows(&previous_type, &current_type) {
+                    let message = if narrowing {
+                        format!(
+                            "column {}.{} narrows from {} to {}, existing values may not fit",
+                            table_name, current.name, previous.data_type, current.data_type
+                        )
+                    } else {
+                        format!(
+                            "column {}.{} widens from {} to {}",
+                            table_name, current.name, previous.data_type, current.data_type
+                        )
+                    };
+                    self.migrations.warnings.push(MigrationWarning {
+                        message,
+                        destructive: narrowing,
+                    });
+                }
+            }
+            // Postgres starts the new sequence at 1 whatever the existing values.
+            if self.current.dialect == Dialect::PostgreSql
+                && !previous.is_identity()
+                && current.is_identity()
+            {
+                self.migrations.warnings.push(MigrationWarning {
+                    message: format!(
+                        "column {}.{} becomes an identity column, its sequence must be restarted past the existing values",
+                        table_name, current.name
+                    ),
+                    destructive: false,
+                });
+            }
+            if previous.collation != current.collation {
+                let name = |collation: &Option<sqlparser::ast::ObjectName>| {
+                    collation
+                        .as_ref()
+                        .map_or("the default".to_string(), |c| c.to_string())
+                };
+                self.migrations.warnings.push(MigrationWarning {
+                    message: format!(
+                        "collation of column {}.{} changes from {} to {}, the indexes on it will be rebuilt",
+                        table_name,
+                        current.name,
+                        name(&previous.collation),
+                        name(&current.collation)
+                    ),
+                    destructive: false,
+                });
+            }
+            let sets_not_null = previous_nullable && !current_nullable;
+            if sets_not_null && dialect != Dialect::PostgreSql {
+                self.migrations.warnings.push(MigrationWarning {
+                    message: format!(
+                        "column {}.{} becomes NOT NULL, existing NULL values will be rejected or replaced",
+                        table_name, current.name
+                    ),
+                    destructive: true,
+                });
+            }
+            let safe_not_null =
+                sets_not_null && self.safe_migrations && dialect == Dialect::PostgreSql;
+
+            let column = current.name.as_str();
+            let mut operations = Vec::new();
+            if safe_not_null {
+                operations.push(AlterTableOperation::AddNotNullCheck { column });
+                operations.push(AlterTableOperation::ValidateNotNullCheck { column });
+            }
+            operations.push(AlterTableOperation::AlterColumn {
+                previous,
+                current,
+                previous_nullable,
+                current_nullable,
+            });
+            if safe_not_null {
+                operations.push(AlterTableOperation::DropNotNullCheck { column });
+            }
+            for operation in operations {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::AlterTable {
+                        name: table_name.clone(),
+                        operation,
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    // Moves the columns out of their previous order, each after the column
+    // preceding it in the table, so the ones kept in order stay in place.
+    fn gen_column_positions(
+        &mut self,
+        table_name: &ObjectName,
+        previous: &[&str],
+        current: &'a Table,
     ) -> Result<()> {
-        todo!()
+        if !matches!(self.current.dialect, Dialect::MySql | Dialect::MariaDb) {
+            self.migrations.warnings.push(MigrationWarning {
+                message: format!(
+                    "columns of table {} were reordered, {} can't reorder columns so their order is left as is",
+                    table_name, self.current.dialect
+                ),
+                destructive: false,
+            });
+            return Ok(());
+        }
+
+        let kept: Vec<&str> = current
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .filter(|name| previous.contains(name))
+            .collect();
+        let in_place: Vec<&str> = diff::slice(previous, &kept)
+            .into_iter()
+            .filter_map(|result| match result {
+                diff::Result::Both(name, _) => Some(*name),
+                _ => None,
+            })
+            .collect();
+
+        let mut after = None;
+        for column in &current.columns {
+            if previous.contains(&column.name.as_str()) && !in_place.contains(&column.name.as_str())
+            {
+                self.migrations
+                    .operations
+                    .push(MigrationOperation::AlterTable {
+                        name: table_name.clone(),
+                        operation: AlterTableOperation::MoveColumn { column, after },
+                    });
+            }
+            after = Some(column.name.as_str());
+        }
+        Ok(())
+    }
+
+    fn gen_drop_column(&mut self, table_name: &ObjectName, previous: &'a Column) -> Result<()> {
+        self.migrations
+            .operations
+            .push(MigrationOperation::AlterTable {
+                name: table_name.clone(),
+                operation: AlterTableOperation::DropColumn { column: previous },
+            });
+        Ok(())
+    }
+
+    fn warn_unfilled_column(&mut self, table_name: &ObjectName, column: &Column) {
+        if !column.fills_existing_rows() {
+            self.migrations.warnings.push(MigrationWarning {
+                message: format!(
+                    "column {}.{} is added NOT NULL without a default, which fails if the table has rows",
+                    table_name, column.name
+                ),
+                destructive: true,
+            });
+        }
+    }
+
+    fn gen_add_column(&mut self, table_name: &ObjectName, current: &'a Column) -> Result<()> {
+        // MySQL gives the rows the implicit default of the type instead.
+        if !matches!(self.current.dialect, Dialect::MySql | Dialect::MariaDb) {
+            self.warn_unfilled_column(table_name, current);
+        }
+        self.migrations
+            .operations
+            .push(MigrationOperation::AlterTable {
+                name: table_name.clone(),
+                operation: AlterTableOperation::AddColumn { column: current },
+            });
+        Ok(())
+    }
+}
+
+// The constraints of `constraints` missing from `others`, or changed there.
+// CHECK expressions are compared once normalized, so that reformatting them
+// doesn't recreate the constraint.
+fn changed_constraints<'a>(
+    constraints: &'a [TableConstraint],
+    others: &[TableConstraint],
+) -> Vec<&'a TableConstraint> {
+    constraints
+        .iter()
+        .filter(|constraint| {
+            let normalized = normalize_constraint(constraint);
+            let key = constraint_key(&normalized);
+            !others.iter().any(|other| {
+                let other = normalize_constraint(other);
+                constraint_key(&other) == key && other == normalized
+            })
+        })
+        .collect()
+}
+
+// Identifies a constraint across versions of a table: by its name when it has
+// one, otherwise by its definition without the actions of a foreign key.
+fn constraint_key(constraint: &TableConstraint) -> String {
+    if let Some(name) = constraint_name(constraint) {
+        return name.value.clone();
+    }
+    match constraint.clone() {
+        TableConstraint::ForeignKey {
+            name,
+            columns,
+            foreign_table,
+            referred_columns,
+            ..
+        } => TableConstraint::ForeignKey {
+            name,
+            columns,
+            foreign_table,
+            referred_columns,
+            on_delete: None,
+            on_update: None,
+            characteristics: None,
+        }
+        .to_string(),
+        constraint => constraint.to_string(),
+    }
+}
+
+// Sorts the tables so that each comes after the tables it references, given
+// by dependencies[i] for table i. Takes the first table whose dependencies are
+// all sorted, or the first remaining one when the references form a cycle.
+pub(crate) fn dependency_order(dependencies: &[Vec<usize>]) -> Vec<usize> {
+    let mut sorted: Vec<usize> = Vec::with_capacity(dependencies.len());
+    while sorted.len() < dependencies.len() {
+        let remaining = || (0..dependencies.len()).filter(|i| !sorted.contains(i));
+        let next = remaining()
+            .find(|&i| dependencies[i].iter().all(|j| sorted.contains(j)))
+            .or_else(|| remaining().next())
+            .unwrap();
+        sorted.push(next);
+    }
+    sorted
+}
+
+// Resolves a table name as written in a foreign key of the table `referencing`
+// to a fully qualified name.
+pub(crate) fn resolve_table_name(
+    dbinfo: &Dbinfo,
+    referencing: &ObjectName,
+    name: &sqlparser::ast::ObjectName,
+) -> ObjectName {
+    let default_schema = |catalog: &str| {
+        dbinfo
+            .catalogs
+            .get(catalog)
+            .map(|c| c.default_schema.clone())
+            .unwrap_or_else(|| referencing.0[1].clone())
+    };
+
+    let parts: Vec<String> = name.0.iter().map(|ident| ident.value.clone()).collect();
+    let catalog = referencing.0[0].clone();
+    match (parts.as_slice(), dbinfo.dialect) {
+        ([table], _) => ObjectName(vec![
+            catalog.clone(),
+            default_schema(&catalog),
+            table.clone(),
+        ]),
+        ([catalog, table], Dialect::MySql | Dialect::MariaDb) => ObjectName(vec![
+            catalog.clone(),
+            default_schema(catalog),
+            table.clone(),
+        ]),
+        ([schema, table], _) => ObjectName(vec![catalog, schema.clone(), table.clone()]),
+        _ => ObjectName(parts),
+    }
+}
+
+// Whether a type change that only changes the length or precision of a type
+// may not fit the existing values, `None` for other type changes.
+fn narrows(previous: &DataType, current: &DataType) -> Option<bool> {
+    // `None` is unlimited.
+    let narrower = |previous: Option<u64>, current: Option<u64>| match (previous, current) {
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(previous), Some(current)) => current < previous,
+    };
+    let length = |length: &Option<CharacterLength>| match length {
+        Some(CharacterLength::IntegerLength { length, .. }) => Some(*length),
+        Some(CharacterLength::Max) | None => None,
+    };
+    // The integer and fractional digits of a decimal.
+    let digits = |info: &ExactNumberInfo| match *info {
+        ExactNumberInfo::None => (None, None),
+        ExactNumberInfo::Precision(precision) => (Some(precision), Some(0)),
+        ExactNumberInfo::PrecisionAndScale(precision, scale) => {
+            (Some(precision.saturating_sub(scale)), Some(scale))
+        }
+    };
+
+    match (previous, current) {
+        // CHAR without a length is CHAR(1).
+        (DataType::Char(previous), DataType::Char(current)) => Some(narrower(
+            length(previous).or(Some(1)),
+            length(current).or(Some(1)),
+        )),
+        (DataType::Varchar(previous), DataType::Varchar(current))
+        | (DataType::Nvarchar(previous), DataType::Nvarchar(current)) => {
+            Some(narrower(length(previous), length(current)))
+        }
+        (DataType::Binary(previous), DataType::Binary(current))
+        | (DataType::Varbinary(previous), DataType::Varbinary(current)) => {
+            Some(narrower(*previous, *current))
+        }
+        (DataType::Decimal(previous), DataType::Decimal(current)) => {
+            let (previous_integer, previous_fraction) = digits(previous);
+            let (current_integer, current_fraction) = digits(current);
+            Some(
+                narrower(previous_integer, current_integer)
+                    || narrower(previous_fraction, current_fraction),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn get_schema_mut<'d>(dbinfo: &'d mut Dbinfo, name: &ObjectName) -> Result<&'d mut Schema> {
+    dbinfo
+        .get_catalog_mut(&name.0[0])?
+        .get_schema_mut(&name.0[1])
+}
+
+fn insert_table(dbinfo: &mut Dbinfo, name: &ObjectName, table: Table) -> Result<()> {
+    let schema = get_schema_mut(dbinfo, name)?;
+    if schema.tables.contains_key(&name.0[2]) {
+        anyhow::bail!("table already exists");
+    }
+    schema.tables.insert(name.0[2].clone(), table);
+    Ok(())
+}
+
+fn remove_table(dbinfo: &mut Dbinfo, name: &ObjectName) -> Result<Table> {
+    get_schema_mut(dbinfo, name)?
+        .tables
+        .remove(&name.0[2])
+        .ok_or(anyhow::anyhow!("table does not found"))
+}
+
+fn is_not_null(column: &Column) -> bool {
+    column
+        .options
+        .iter()
+        .any(|o| matches!(o.option, ColumnOption::NotNull))
+}
+
+// Whether `name` matches `pattern`, where `*` is any run of characters and `?` any one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+fn default_value(column: &Column) -> Option<&Expr> {
+    column.options.iter().find_map(|o| match &o.option {
+        ColumnOption::Default(value) => Some(value),
+        _ => None,
+    })
+}
+
+fn normalized_default(column: &Column, dialect: Dialect) -> Option<Expr> {
+    default_value(&normalized(column, dialect)).cloned()
+}
+
+// A column of the table `table_name` normalized, with its user-defined type
+// resolved against the default schema.
+fn resolved(dbinfo: &Dbinfo, table_name: &ObjectName, column: &Column) -> Column {
+    let mut column = normalized(column, dbinfo.dialect);
+    if let Some(catalog) = dbinfo.catalogs.get(&table_name.0[0]) {
+        column.data_type =
+            resolve_data_type(&column.data_type, &catalog.name, &catalog.default_schema);
+    }
+    column
+}
+
+// Columns built outside of the inspector may not have normalized options, and
+// the aliases of a data type are the same type. Redundant parentheses are
+// dropped from the default as well.
+fn normalized(column: &Column, dialect: Dialect) -> Column {
+    let mut column = column.clone();
+    column.normalize_options();
+    column.data_type = canonical_data_type(&column.data_type, dialect);
+    for option in &mut column.options {
+        if let ColumnOption::Default(expr) = &option.option {
+            option.option = ColumnOption::Default(normalize_check_expr(expr));
+        }
+    }
+    column
+}
+
+// MySQL replaces any view. Postgres only lets new columns be added at the
+// end, the kept ones must not be renamed (nor change type, which is left
+// to the database to report). SQLite has no CREATE OR REPLACE VIEW.
+fn view_replaceable(dialect: Dialect, previous: &View, current: &View) -> bool {
+    if previous.materialized || current.materialized {
+        return false;
+    }
+    match dialect {
+        Dialect::MySql | Dialect::MariaDb => true,
+        Dialect::PostgreSql => match (previous.column_names(), current.column_names()) {
+            (Some(previous), Some(current)) => current.starts_with(&previous),
+            _ => false,
+        },
+        Dialect::SQLite => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dbinfo::IdentityKind;
+    use crate::inspector::Inspector;
+    use crate::test_support::test_options;
+    use crate::test_support::DbinfoBuilder;
+    use crate::writer::migration_name;
+    use crate::Options;
+
+    use super::*;
+
+    fn inspect(dialect: Dialect, sql: &str) -> Dbinfo {
+        let options = test_options(dialect);
+        let mut dbinfo = Dbinfo::with_options(options);
+        let mut inspector = Inspector::new(&mut dbinfo);
+        inspector.inspect(sql, "test.sql").unwrap();
+        dbinfo
+    }
+
+    #[test]
+    fn test_summary_is_ordered() {
+        let previous = inspect(Dialect::PostgreSql, "CREATE TABLE c (id INT, name TEXT);");
+        let current = inspect(
+            Dialect::PostgreSql,
+            r#"
+                CREATE TABLE c (id INT, email TEXT);
+                CREATE TABLE b (id INT);
+                CREATE TABLE a (id INT);
+            "#,
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "+ CREATE TABLE test.public.a",
+                "+ CREATE TABLE test.public.b",
+                "~ ALTER TABLE test.public.c DROP COLUMN name",
+                "~ ALTER TABLE test.public.c ADD COLUMN email",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_reproduces_current_schema() {
+        let reproduces = |dialect: Dialect, previous: &str, current: &str| {
+            let previous = inspect(dialect, previous);
+            let current = inspect(dialect, current);
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            assert!(!migration.is_empty());
+            let mut applied = previous.clone();
+            migration.apply(&mut applied).unwrap();
+            let remaining = MigrationGenerator::new(&applied, &current)
+                .generate()
+                .unwrap();
+            let summary: Vec<String> = remaining
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect();
+            assert_eq!(summary, Vec::<String>::new());
+        };
+
+        reproduces(
+            Dialect::PostgreSql,
+            r#"
+                CREATE DOMAIN email AS TEXT;
+                CREATE TABLE users (id INT PRIMARY KEY, name TEXT, age INT);
+                CREATE TABLE old (id INT);
+                CREATE INDEX users_name_idx ON users (name);
+            "#,
+            r#"
+                CREATE SCHEMA app;
+                CREATE DOMAIN email AS TEXT CHECK (VALUE LIKE '%@%');
+                CREATE TABLE users (id INT PRIMARY KEY, name TEXT NOT NULL, address email);
+                CREATE TABLE app.posts (id INT, user_id INT REFERENCES users (id));
+                CREATE INDEX users_name_idx ON users (name DESC);
+                CREATE TRIGGER users_touch BEFORE UPDATE ON users
+                    FOR EACH ROW EXECUTE FUNCTION touch();
+            "#,
+        );
+        reproduces(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INT, name TEXT);",
+            "CREATE TABLE t (id INT, name TEXT, UNIQUE (name)) STRICT;",
+        );
+    }
+
+    #[test]
+    fn test_apply_create_and_alter() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE users (id INT, name TEXT);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            r#"
+                CREATE TABLE users (id INT PRIMARY KEY, name TEXT NOT NULL DEFAULT '', email TEXT);
+                CREATE TABLE posts (id INT, user_id INT REFERENCES users (id));
+            "#,
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let mut applied = previous.clone();
+        migration.apply(&mut applied).unwrap();
+        assert_eq!(applied, current);
+
+        // Applying it twice creates the same table again.
+        let err = migration.apply(&mut applied).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot apply + CREATE TABLE test.public.posts: table already exists"
+        );
+        let reverse = MigrationGenerator::new(&current, &previous)
+            .generate()
+            .unwrap();
+        let err = reverse.apply(&mut previous.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot apply - DROP TABLE test.public.posts: table does not found"
+        );
+    }
+
+    #[test]
+    fn test_partition_key_change_recreates_table() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE events (id INT, created_at DATE) PARTITION BY RANGE (created_at);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE events (id INT, created_at DATE) PARTITION BY LIST (id);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "- DROP TABLE test.public.events",
+                "+ CREATE TABLE test.public.events",
+            ]
+        );
+        assert_eq!(migration.warnings.len(), 1);
+        assert!(migration.warnings[0].destructive);
+    }
+
+    #[test]
+    fn test_on_commit_change_recreates_table() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE scratch (id INT) ON COMMIT PRESERVE ROWS;",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE scratch (id INT) ON COMMIT DELETE ROWS;",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "- DROP TABLE test.public.scratch",
+                "+ CREATE TABLE test.public.scratch",
+            ]
+        );
+        assert_eq!(
+            migration.explanations[0],
+            "ON COMMIT action of table test.public.scratch changed from PRESERVE ROWS to DELETE ROWS"
+        );
+        assert_eq!(
+            migration.warnings[0].message,
+            "ON COMMIT action of table test.public.scratch changed, the table will be recreated"
+        );
+        assert!(migration.warnings[0].destructive);
+    }
+
+    #[test]
+    fn test_mysql_engine_change() {
+        let previous = inspect(
+            Dialect::MySql,
+            "CREATE TABLE t (id INT) ENGINE=MyISAM DEFAULT CHARSET=utf8mb4;",
+        );
+        let current = inspect(
+            Dialect::MySql,
+            "CREATE TABLE t (id INT) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(summary, vec!["~ ALTER TABLE test.public.t ENGINE=InnoDB"]);
+    }
+
+    #[test]
+    fn test_foreign_key_action_change() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            r#"
+            CREATE TABLE users (id INT PRIMARY KEY);
+            CREATE TABLE posts (
+                id INT,
+                user_id INT,
+                UNIQUE (id),
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE NO ACTION
+            );
+            "#,
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            r#"
+            CREATE TABLE users (id INT PRIMARY KEY);
+            CREATE TABLE posts (
+                id INT,
+                user_id INT,
+                UNIQUE (id),
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            );
+            "#,
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                r#"~ ALTER TABLE test.public.posts DROP FOREIGN KEY ("user_id") REFERENCES "users"("id") ON DELETE NO ACTION"#,
+                r#"~ ALTER TABLE test.public.posts ADD FOREIGN KEY ("user_id") REFERENCES "users"("id") ON DELETE CASCADE"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equivalent_check_expressions() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            r#"
+            CREATE TABLE t (
+                x INT CHECK (x>0),
+                y INT,
+                CHECK (y >= 0 AND y < 10),
+                CONSTRAINT x_y CHECK (x <> y)
+            );
+            "#,
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            r#"
+            CREATE TABLE t (
+                x INT CHECK ( (x) > 0 ),
+                y INT,
+                CHECK ((0 <= y) AND (y < (10))),
+                CONSTRAINT x_y CHECK (((x <> y)))
+            );
+            "#,
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+
+        let current = inspect(
+            Dialect::PostgreSql,
+            r#"
+            CREATE TABLE t (
+                x INT CHECK (x > 0),
+                y INT,
+                CHECK (y >= 0 AND y < 10),
+                CONSTRAINT x_y CHECK (x < y)
+            );
+            "#,
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "~ ALTER TABLE test.public.t DROP CONSTRAINT x_y",
+                "~ ALTER TABLE test.public.t ADD CONSTRAINT x_y",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_type_aliases_are_equal() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (a INTEGER, b CHARACTER VARYING(255), c BOOL, d NUMERIC(10,2));",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (a INT, b VARCHAR(255), c BOOLEAN, d DECIMAL(10,2));",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.is_empty());
+    }
+
+    #[test]
+    fn test_length_and_precision_changes() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (a VARCHAR(100), b VARCHAR(200), c DECIMAL(10,2), d DECIMAL(10,2));",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (a VARCHAR(200), b VARCHAR(100), c DECIMAL(12,2), d DECIMAL(8,2));",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(migration.operations.len(), 4);
+        let warnings: Vec<(String, bool)> = migration
+            .warnings
+            .iter()
+            .map(|w| (w.message.clone(), w.destructive))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    "column test.public.t.a widens from VARCHAR(100) to VARCHAR(200)".to_string(),
+                    false
+                ),
+                (
+                    "column test.public.t.b narrows from VARCHAR(200) to VARCHAR(100), existing values may not fit".to_string(),
+                    true
+                ),
+                (
+                    "column test.public.t.c widens from DECIMAL(10,2) to DECIMAL(12,2)".to_string(),
+                    false
+                ),
+                (
+                    "column test.public.t.d narrows from DECIMAL(10,2) to DECIMAL(8,2), existing values may not fit".to_string(),
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignored_tables() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app;
+             CREATE TABLE users (id INT);
+             CREATE TABLE jobs_queue (id INT);
+             CREATE TABLE jobs_log (id INT);
+             CREATE TABLE app.settings (id INT);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app;
+             CREATE TABLE users (id INT, name TEXT);
+             CREATE TABLE jobs_log (id BIGINT);
+             CREATE TABLE jobs_lock (id INT);
+             CREATE TABLE app.other (id INT);",
+        );
+        let summary = |ignore: &[&str]| -> Vec<String> {
+            MigrationGenerator::new(&previous, &current)
+                .with_ignore(ignore.iter().map(|p| p.to_string()).collect())
+                .generate()
+                .unwrap()
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            summary(&["jobs_*", "app.*"]),
+            vec!["~ ALTER TABLE test.public.users ADD COLUMN name"]
+        );
+        assert_eq!(
+            summary(&["jobs_lo?", "public.users"]),
+            vec![
+                "- DROP TABLE test.public.jobs_queue",
+                "+ CREATE TABLE test.app.other",
+                "- DROP TABLE test.app.settings",
+                "+ CREATE TABLE test.public.jobs_lock",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignored_tables_keep_their_schema() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA jobs; CREATE TABLE jobs.queue (id INT); CREATE TABLE users (id INT);",
+        );
+        let current = inspect(Dialect::PostgreSql, "CREATE TABLE users (id INT);");
+        let summary = |ignore: &[&str]| -> Vec<String> {
+            MigrationGenerator::new(&previous, &current)
+                .with_ignore(ignore.iter().map(|p| p.to_string()).collect())
+                .generate()
+                .unwrap()
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        };
+
+        assert!(summary(&["jobs.*"]).is_empty());
+        assert_eq!(summary(&[]), vec!["- DROP SCHEMA test.jobs"]);
+    }
+
+    #[test]
+    fn test_created_database_keeps_to_included_schemas() {
+        let previous = inspect(Dialect::PostgreSql, "");
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE DATABASE shop;
+             CREATE SCHEMA shop.app; CREATE SCHEMA shop.jobs;
+             CREATE TABLE shop.public.users (id INT);
+             CREATE TABLE shop.app.settings (id INT);
+             CREATE TABLE shop.jobs.queue (id INT);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .with_include_schemas(vec!["public".to_string(), "app".to_string()])
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "+ CREATE DATABASE shop",
+                "+ CREATE SCHEMA shop.app",
+                "+ CREATE TABLE shop.app.settings",
+                "+ CREATE TABLE shop.public.users",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_managed() {
+        let dbinfo = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app; CREATE SCHEMA jobs;
+             CREATE TABLE users (id INT); CREATE TABLE users_log (id INT);
+             CREATE TABLE app.settings (id INT); CREATE TABLE jobs.queue (id INT);
+             CREATE VIEW recent_log AS SELECT id FROM users_log;",
+        );
+        let managed = MigrationGenerator::new(&dbinfo, &dbinfo)
+            .with_include_schemas(vec!["public".to_string(), "jobs".to_string()])
+            .with_ignore(vec!["*_log".to_string(), "jobs.*".to_string()])
+            .managed(&dbinfo);
+        assert_eq!(
+            managed.to_string(),
+            "catalog test\n  schema jobs\n  schema public\n    table users\n      id INT NULL\n"
+        );
+    }
+
+    #[test]
+    fn test_table_moves() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app; CREATE TABLE users (id INT, name TEXT); CREATE TABLE logs (id INT);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app; CREATE TABLE app.users (id INT, name TEXT); CREATE TABLE app.logs (id BIGINT);",
+        );
+        let summary = |detect_table_moves: bool| -> Vec<String> {
+            MigrationGenerator::new(&previous, &current)
+                .with_detect_table_moves(detect_table_moves)
+                .generate()
+                .unwrap()
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            summary(false),
+            vec![
+                "+ CREATE TABLE test.app.logs",
+                "+ CREATE TABLE test.app.users",
+                "- DROP TABLE test.public.users",
+                "- DROP TABLE test.public.logs",
+            ]
+        );
+        // logs changed, so it can't be moved
+        assert_eq!(
+            summary(true),
+            vec![
+                "+ CREATE TABLE test.app.logs",
+                "~ MOVE TABLE test.public.users TO test.app.users",
+                "- DROP TABLE test.public.logs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_renames() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE users (id INT PRIMARY KEY, name TEXT NOT NULL, email TEXT, created_at DATE);
+             CREATE TABLE logs (id INT);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE accounts (id INT PRIMARY KEY, name TEXT NOT NULL, email TEXT, created_at DATE);
+             CREATE TABLE events (id BIGINT);",
+        );
+        let summary = |detect_table_renames: bool| -> Vec<String> {
+            MigrationGenerator::new(&previous, &current)
+                .with_detect_table_renames(detect_table_renames)
+                .generate()
+                .unwrap()
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            summary(false),
+            vec![
+                "- DROP TABLE test.public.users",
+                "- DROP TABLE test.public.logs",
+                "+ CREATE TABLE test.public.accounts",
+                "+ CREATE TABLE test.public.events",
+            ]
+        );
+        // logs changed, so it isn't renamed
+        assert_eq!(
+            summary(true),
+            vec![
+                "- DROP TABLE test.public.logs",
+                "~ RENAME TABLE test.public.users TO test.public.accounts",
+                "+ CREATE TABLE test.public.events",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_and_table_level_uniques_are_equal() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT PRIMARY KEY, email TEXT UNIQUE, a INT, b INT, UNIQUE (a, b));",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT, email TEXT, a INT, b INT, PRIMARY KEY (id), UNIQUE (email), UNIQUE (a, b));",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.is_empty());
+
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT PRIMARY KEY, email TEXT, a INT UNIQUE, b INT, UNIQUE (a, b));",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                r#"~ ALTER TABLE test.public.t DROP UNIQUE ("email")"#,
+                r#"~ ALTER TABLE test.public.t ADD UNIQUE ("a")"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_adding_not_null_column_without_default_is_destructive() {
+        let previous = inspect(Dialect::PostgreSql, "CREATE TABLE t (id INT);");
+        let warnings = |alter: &str| {
+            let current = inspect(
+                Dialect::PostgreSql,
+                &format!("CREATE TABLE t (id INT); {}", alter),
+            );
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            assert_eq!(migration.operations.len(), 1);
+            migration
+                .warnings
+                .iter()
+                .map(|w| (w.message.clone(), w.destructive))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            warnings("ALTER TABLE t ADD COLUMN x INT NOT NULL DEFAULT 0;"),
+            vec![]
+        );
+        assert_eq!(warnings("ALTER TABLE t ADD COLUMN x INT;"), vec![]);
+        assert_eq!(
+            warnings("ALTER TABLE t ADD COLUMN x INT GENERATED ALWAYS AS IDENTITY NOT NULL;"),
+            vec![]
+        );
+        assert_eq!(
+            warnings("ALTER TABLE t ADD COLUMN x INT NOT NULL;"),
+            vec![(
+                "column test.public.t.x is added NOT NULL without a default, which fails if the table has rows".to_string(),
+                true
+            )]
+        );
+    }
+
+    #[test]
+    fn test_not_null_outside_postgres_is_destructive() {
+        let previous = inspect(Dialect::MySql, "CREATE TABLE t (id INT, email TEXT);");
+        let current = inspect(
+            Dialect::MySql,
+            "CREATE TABLE t (id INT, email TEXT NOT NULL);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .with_safe_migrations(true)
+            .generate()
+            .unwrap();
+        assert_eq!(migration.operations.len(), 1);
+        assert_eq!(migration.warnings.len(), 1);
+        assert!(migration.warnings[0].destructive);
+    }
+
+    #[test]
+    fn test_include_schemas() {
+        let previous = inspect(Dialect::PostgreSql, "CREATE SCHEMA old;");
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app; CREATE TABLE t (id INT); CREATE TABLE app.t (id INT);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .with_include_schemas(vec!["app".to_string()])
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec!["+ CREATE SCHEMA test.app", "+ CREATE TABLE test.app.t"]
+        );
+    }
+
+    #[test]
+    fn test_baseline_creates_default_schema() {
+        let options = |default_schema: &str| Options {
+            default_schema: default_schema.to_string(),
+            ..test_options(Dialect::PostgreSql)
+        };
+        let summary = |default_schema: &str, baseline: bool| {
+            let empty = Dbinfo::with_options(options(default_schema));
+            let mut current = empty.clone();
+            Inspector::new(&mut current)
+                .inspect(
+                    "CREATE TABLE users (id INT); CREATE SCHEMA logs; CREATE TABLE logs.t (id INT);",
+                    "test.sql",
+                )
+                .unwrap();
+            let migration = MigrationGenerator::new(&empty, &current)
+                .with_baseline(baseline)
+                .generate()
+                .unwrap();
+            let summary: Vec<String> = migration
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect();
+            summary
+        };
+
+        assert_eq!(
+            summary("app", true),
+            [
+                "+ CREATE SCHEMA test.app",
+                "+ CREATE SCHEMA test.logs",
+                "+ CREATE TABLE test.logs.t",
+                "+ CREATE TABLE test.app.users",
+            ]
+        );
+        assert_eq!(
+            summary("app", false),
+            [
+                "+ CREATE SCHEMA test.logs",
+                "+ CREATE TABLE test.logs.t",
+                "+ CREATE TABLE test.app.users",
+            ]
+        );
+        // every database comes with public
+        assert_eq!(summary("public", true), summary("public", false));
+    }
+
+    #[test]
+    fn test_equivalent_column_options_are_equal() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT NOT NULL UNIQUE DEFAULT 0, name TEXT NULL);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT DEFAULT 0 UNIQUE NOT NULL NOT NULL, name TEXT DEFAULT NULL);",
+        );
+        assert_eq!(previous, current);
+
+        // UNIQUE is inspected as a table constraint.
+        let constraints = previous.get_catalog("test").unwrap().schemas["public"].tables["t"]
+            .constraints
+            .clone();
+        let current = DbinfoBuilder::new(Dialect::PostgreSql, "test")
+            .table("t")
+            .column("id", DataType::Int(None))
+            .not_null()
+            .option(ColumnOption::Default(Expr::Value(
+                sqlparser::ast::Value::Number("0".into(), false),
+            )))
+            .with_column(|column| column.options.reverse())
+            .column("name", DataType::Text)
+            .with_table(|table| table.constraints = constraints)
+            .build();
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+    }
+
+    #[test]
+    fn test_primary_key_columns_are_not_nullable() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT NOT NULL PRIMARY KEY, name TEXT);
+             CREATE TABLE u (code TEXT NOT NULL, PRIMARY KEY (code));",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT PRIMARY KEY, name TEXT);
+             CREATE TABLE u (code TEXT, PRIMARY KEY (code));",
+        );
+        let schema = &current.get_catalog("test").unwrap().schemas["public"];
+        let t = schema.get_table("t").unwrap();
+        assert!(!t.columns[0].is_nullable(t));
+        assert!(t.columns[1].is_nullable(t));
+        let u = schema.get_table("u").unwrap();
+        assert!(!u.columns[0].is_nullable(u));
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+        assert!(migration.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_storage_parameter_changes() {
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT) WITH (fillfactor = 70, autovacuum_enabled = false);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT) WITH (fillfactor = 90);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "~ ALTER TABLE test.public.t SET (fillfactor=90)",
+                "~ ALTER TABLE test.public.t RESET (autovacuum_enabled)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sqlite_column_changes_rebuild_table() {
+        let previous = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INTEGER, price INTEGER, note TEXT);",
+        );
+        let summary = |sql: &str| -> Vec<String> {
+            let current = inspect(Dialect::SQLite, sql);
+            MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap()
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        };
+        let rebuild = vec![
+            "+ CREATE TABLE test.public.new_t",
+            "~ COPY DATA test.public.t TO test.public.new_t",
+            "- DROP TABLE test.public.t",
+            "~ RENAME TABLE test.public.new_t TO test.public.t",
+        ];
+
+        assert_eq!(
+            summary("CREATE TABLE t (id INTEGER, price REAL, note TEXT);"),
+            rebuild
+        );
+        assert_eq!(
+            summary("CREATE TABLE t (id INTEGER, price INTEGER NOT NULL, note TEXT);"),
+            rebuild
+        );
+        assert_eq!(
+            summary("CREATE TABLE t (id INTEGER, price INTEGER, note TEXT, qty INTEGER NOT NULL);"),
+            rebuild
+        );
+        // the rows are copied without the added column, which can't fill them
+        let warnings = |sql: &str| -> Vec<(String, bool)> {
+            let current = inspect(Dialect::SQLite, sql);
+            MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap()
+                .warnings
+                .iter()
+                .map(|w| (w.message.clone(), w.destructive))
+                .collect()
+        };
+        assert_eq!(
+            warnings("CREATE TABLE t (id INTEGER, price REAL, note TEXT, qty INTEGER NOT NULL);"),
+            vec![
+                ("table test.public.t will be rebuilt".to_string(), false),
+                (
+                    "column test.public.t.qty is added NOT NULL without a default, which fails if the table has rows".to_string(),
+                    true
+                ),
+            ]
+        );
+        assert_eq!(
+            warnings("CREATE TABLE t (id INTEGER, price REAL, note TEXT, qty INTEGER NOT NULL DEFAULT 0);"),
+            vec![("table test.public.t will be rebuilt".to_string(), false)]
+        );
+        // SQLite can add a column and drop one
+        assert_eq!(
+            summary("CREATE TABLE t (id INTEGER, price INTEGER, qty INTEGER NOT NULL DEFAULT 0);"),
+            vec![
+                "~ ALTER TABLE test.public.t DROP COLUMN note",
+                "~ ALTER TABLE test.public.t ADD COLUMN qty",
+            ]
+        );
+
+        // The indexes are created once the old table, and its indexes of the
+        // same names, are gone.
+        let previous = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INTEGER, name TEXT); CREATE INDEX t_name ON t (name);",
+        );
+        let current = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INTEGER, name TEXT, UNIQUE (name)) STRICT;
+             CREATE INDEX t_name ON t (name);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "+ CREATE TABLE test.public.new_t",
+                "~ COPY DATA test.public.t TO test.public.new_t",
+                "- DROP TABLE test.public.t",
+                "~ RENAME TABLE test.public.new_t TO test.public.t",
+                "~ ALTER TABLE test.public.t ADD INDEX t_name",
+            ]
+        );
+        let options = Options::builder()
+            .dialect(Dialect::SQLite)
+            .database("test")
+            .default_schema("public")
+            .build()
+            .unwrap();
+        let sql = crate::render::Renderer::new(&options)
+            .render(&migration)
+            .unwrap();
+        assert_eq!(sql.matches("CREATE INDEX").count(), 1);
+        assert!(sql.find("CREATE INDEX").unwrap() > sql.find("RENAME TO").unwrap());
+        let mut applied = previous.clone();
+        migration.apply(&mut applied).unwrap();
+        assert_eq!(applied.fingerprint(), current.fingerprint());
+
+        // Dropping a table referenced by others deletes or rejects the rows
+        // referencing it while foreign keys are enforced.
+        let previous = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE u (t_id INTEGER REFERENCES t (id));",
+        );
+        let current = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT) STRICT;
+             CREATE TABLE u (t_id INTEGER REFERENCES t (id));",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration
+            .warnings
+            .iter()
+            .any(|w| w.destructive && w.message.contains("PRAGMA foreign_keys=OFF")));
+    }
+
+    #[test]
+    fn test_sqlite_strict_change_rebuilds_table() {
+        let previous = inspect(Dialect::SQLite, "CREATE TABLE t (id INTEGER, name TEXT);");
+        let current = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE t (id INTEGER, name TEXT, age INTEGER) STRICT;",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let summary: Vec<String> = migration
+            .operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "+ CREATE TABLE test.public.new_t",
+                "~ COPY DATA test.public.t TO test.public.new_t",
+                "- DROP TABLE test.public.t",
+                "~ RENAME TABLE test.public.new_t TO test.public.t",
+            ]
+        );
+        match &migration.operations[1] {
+            MigrationOperation::CopyTableData { columns, .. } => {
+                assert_eq!(columns, &vec!["id", "name"])
+            }
+            _ => panic!("expected CopyTableData"),
+        }
+    }
+
+    #[test]
+    fn test_table_options_are_ignored_outside_mysql() {
+        let previous = DbinfoBuilder::new(Dialect::PostgreSql, "test")
+            .table("t")
+            .column("id", DataType::Int(None))
+            .with_table(|table| table.engine = Some("MyISAM".to_string()))
+            .build();
+        let current = inspect(Dialect::PostgreSql, "CREATE TABLE t (id INT);");
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+    }
+
+    #[test]
+    fn test_column_reorder() {
+        let previous_sql = "CREATE TABLE t (id INT, a INT, b TEXT, c INT);";
+        let current_sql = "CREATE TABLE t (id INT, c INT, b TEXT, a INT);";
+
+        // Postgres can't reorder columns, a pure reorder isn't a change
+        let previous = inspect(Dialect::PostgreSql, previous_sql);
+        let current = inspect(Dialect::PostgreSql, current_sql);
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+        assert_eq!(migration.warnings.len(), 1);
+        assert!(!migration.warnings[0].destructive);
+
+        let previous = inspect(Dialect::MySql, previous_sql);
+        let current = inspect(Dialect::MySql, current_sql);
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let operations: Vec<String> = migration.operations.iter().map(|o| o.to_string()).collect();
+        assert_eq!(
+            operations,
+            [
+                "~ ALTER TABLE test.public.t MOVE COLUMN b AFTER c",
+                "~ ALTER TABLE test.public.t MOVE COLUMN a AFTER b",
+            ]
+        );
+        let mut applied = previous.clone();
+        migration.apply(&mut applied).unwrap();
+        assert_eq!(applied, current);
+    }
+
+    #[test]
+    fn test_reordered_constraints_are_not_a_change() {
+        let previous_sql = "CREATE TABLE t (
+                id INT, a INT, b INT,
+                PRIMARY KEY (id), UNIQUE (a), CHECK (b > 0)
+            );
+            CREATE INDEX t_a ON t (a);
+            CREATE INDEX t_b ON t (b);";
+        let current_sql = "CREATE TABLE t (
+                id INT, a INT, b INT,
+                CHECK (b > 0), UNIQUE (a), PRIMARY KEY (id)
+            );
+            CREATE INDEX t_b ON t (b);
+            CREATE INDEX t_a ON t (a);";
+
+        for dialect in [Dialect::PostgreSql, Dialect::MySql, Dialect::SQLite] {
+            let previous = inspect(dialect, previous_sql);
+            let current = inspect(dialect, current_sql);
+            assert_ne!(previous, current);
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            assert!(migration.operations.is_empty(), "{:?}", dialect);
+            assert!(migration.warnings.is_empty(), "{:?}", dialect);
+        }
+    }
+
+    #[test]
+    fn test_serial_is_equivalent_to_identity() {
+        let previous = inspect(Dialect::PostgreSql, "CREATE TABLE t (id SERIAL);");
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE t (id INT GENERATED BY DEFAULT AS IDENTITY);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+    }
+
+    #[test]
+    fn test_column_becomes_identity() {
+        let previous = inspect(Dialect::MySql, "CREATE TABLE t (id INT);");
+        let current = inspect(Dialect::MySql, "CREATE TABLE t (id INT AUTO_INCREMENT);");
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(migration.operations.len(), 1);
+        match &migration.operations[0] {
+            MigrationOperation::AlterTable {
+                operation:
+                    AlterTableOperation::AlterColumn {
+                        previous, current, ..
+                    },
+                ..
+            } => {
+                assert!(!previous.is_identity());
+                assert_eq!(current.identity_kind(), Some(IdentityKind::ByDefault));
+            }
+            _ => panic!("unexpected operation"),
+        }
+    }
+
+    #[test]
+    fn test_qualified_custom_types() {
+        // The enum type `mood` is created outside of the inspected files.
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app;
+             CREATE TABLE users (id INT, mood mood, moods mood[]);
+             CREATE TABLE app.users (id INT, mood public.mood);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app;
+             CREATE TABLE users (id INT, mood public.mood NOT NULL, moods public.mood[]);
+             CREATE TABLE app.users (id INT, mood mood);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(migration.operations.len(), 1);
+        assert_eq!(
+            migration.explanations,
+            vec!["column mood changed: NOT NULL added"]
+        );
+
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app;
+             CREATE TABLE users (id INT, mood app.mood, moods mood[]);
+             CREATE TABLE app.users (id INT, mood public.mood);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(
+            migration.explanations,
+            vec!["column mood changed: type from \"mood\" to \"app\".\"mood\""]
+        );
+    }
+
+    #[test]
+    fn test_explanations() {
+        let previous = inspect(Dialect::SQLite, "CREATE TABLE posts (id INT);");
+        let current = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE posts (id BIGINT);
+             CREATE TABLE tags (id INT);",
+        );
+        let mut migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        migration.add_raw(RawPlacement::Before, "SELECT 1");
+        assert_eq!(
+            migration.explanations,
+            vec![
+                "raw SQL added to the migration",
+                "table test.public.tags is new",
+                "table test.public.posts can't be altered in place, its new version is created",
+                "the data of the columns id of table test.public.posts is kept",
+                "table test.public.posts was copied to its new version",
+                "the new version of table test.public.posts takes its name",
+            ]
+        );
+
+        let previous = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE users (id INT, email TEXT DEFAULT 'a');
+             CREATE INDEX users_email_idx ON users (email);",
+        );
+        let current = inspect(
+            Dialect::PostgreSql,
+            "CREATE TABLE users (id INT, email TEXT DEFAULT 'b');
+             CREATE UNIQUE INDEX users_email_idx ON users (email);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(
+            migration.explanations,
+            vec![
+                "column email changed default from 'a' to 'b'",
+                "index users_email_idx changed, it is dropped to be created again",
+                "index users_email_idx changed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_by_object() {
+        let previous = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE posts (id INT);
+             CREATE TABLE users (id INT);",
+        );
+        let current = inspect(
+            Dialect::SQLite,
+            "CREATE TABLE posts (id BIGINT);
+             CREATE TABLE tags (id INT);
+             CREATE TABLE users (id INT, name TEXT);",
+        );
+        let generate = || {
+            let mut migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            migration.add_raw(RawPlacement::After, "SELECT 1");
+            migration
+        };
+        let operations = |migration: &Migration| -> Vec<String> {
+            migration
+                .operations
+                .iter()
+                .map(|op| op.to_string())
+                .collect()
+        };
+
+        let migration = generate();
+        let parts = generate().split_by_object();
+        assert_eq!(
+            parts.iter().map(migration_name).collect::<Vec<_>>(),
+            vec!["create_tags", "rebuild_posts", "add_users_name", "raw"]
+        );
+        assert_eq!(
+            parts.iter().flat_map(operations).collect::<Vec<_>>(),
+            operations(&migration)
+        );
+        assert_eq!(
+            parts
+                .iter()
+                .flat_map(|p| p.explanations.clone())
+                .collect::<Vec<_>>(),
+            migration.explanations
+        );
     }
 }
@@ -0,0 +1,2715 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlparser::ast::helpers::stmt_create_table::CreateTableBuilder;
+use sqlparser::ast::{
+    self, AlterColumnOperation, ColumnDef, ColumnOption, ColumnOptionDef, CreateTableOptions,
+    DataType, Expr, GeneratedAs, Ident, MySQLColumnPosition, SequenceOptions, Statement,
+    TableConstraint, Value, ViewColumnDef,
+};
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Token, Tokenizer, Word};
+
+use crate::dbinfo::{
+    canonical_data_type, constraint_name, resolve_data_type, Column, IdentityKind, Index, Table,
+    View,
+};
+use crate::inspector::location_offset;
+use crate::migrate::{
+    AlterDomainOperation, AlterTableOperation, Migration, MigrationOperation, ObjectName,
+};
+use crate::{Dialect, Options, Qualify};
+
+mod reserved;
+
+// Renders migrations as SQL statements of the target dialect.
+pub struct Renderer {
+    dialect: Dialect,
+    database: String,
+    default_schema: String,
+    transactional: bool,
+    qualify: Qualify,
+    group_alters: bool,
+    explain: bool,
+    idempotent: bool,
+}
+
+impl Renderer {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            dialect: options.dialect,
+            database: options.database.clone(),
+            default_schema: options.default_schema.clone(),
+            transactional: options.transactional,
+            qualify: options.qualify,
+            group_alters: options.group_alters,
+            explain: false,
+            idempotent: options.idempotent,
+        }
+    }
+
+    pub fn with_transactional(mut self, transactional: bool) -> Self {
+        self.transactional = transactional;
+        self
+    }
+
+    // Merges the consecutive ALTER TABLE statements of a table into one with
+    // several clauses, which MySQL and Postgres apply with a single rewrite of
+    // the table. SQLite only takes one clause per statement.
+    pub fn with_group_alters(mut self, group_alters: bool) -> Self {
+        self.group_alters = group_alters;
+        self
+    }
+
+    // Creates and drops with IF NOT EXISTS and IF EXISTS where the dialect
+    // takes them, so that a migration that failed half way can be run again.
+    pub fn with_idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = idempotent;
+        self
+    }
+
+    // Writes why each operation was generated as a comment above its
+    // statements.
+    pub fn with_explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+
+    // With `transactional`, consecutive statements are wrapped in
+    // BEGIN; ... COMMIT; and those that can't run in a transaction are left
+    // between the blocks. MySQL commits DDL implicitly, so it is never wrapped.
+    pub fn render(&self, migration: &Migration) -> Result<String> {
+        let wrap = self.transactional && !matches!(self.dialect, Dialect::MySql | Dialect::MariaDb);
+        let mut sql = String::new();
+        if self.dialect == Dialect::PostgreSql
+            && self.qualify == Qualify::SearchPath
+            && !migration.is_empty()
+        {
+            sql.push_str(&format!(
+                "SET search_path TO {};\n",
+                self.unquote(&self.ident(&self.default_schema).to_string())
+            ));
+        }
+        let mut in_transaction = false;
+        for (statement, explanations) in self.render_statements(migration)? {
+            let transactional = wrap && self.is_transactional(&statement);
+            if transactional && !in_transaction {
+                sql.push_str("BEGIN;\n");
+            } else if !transactional && in_transaction {
+                sql.push_str("COMMIT;\n");
+            }
+            in_transaction = transactional;
+            for explanation in explanations {
+                sql.push_str(&format!("-- {}\n", explanation.replace('\n', " ")));
+            }
+            sql.push_str(&statement);
+            sql.push_str(";\n");
+        }
+        if in_transaction {
+            sql.push_str("COMMIT;\n");
+        }
+        Ok(sql)
+    }
+
+    // The statements with the explanations of the operations they start, or
+    // of the clauses merged into them.
+    fn render_statements<'m>(
+        &self,
+        migration: &'m Migration,
+    ) -> Result<Vec<(String, Vec<&'m str>)>> {
+        let group_alters = self.group_alters && self.dialect != Dialect::SQLite;
+        let mut statements: Vec<(String, Vec<&str>)> = Vec::new();
+        // the table of the last statement, while clauses can be added to it
+        let mut group: Option<&ObjectName> = None;
+        for (i, operation) in migration.operations.iter().enumerate() {
+            let mut explanation = migration
+                .explanations
+                .get(i)
+                .filter(|_| self.explain)
+                .map(|e| e.as_str());
+            let table = match operation {
+                MigrationOperation::AlterTable { name, operation }
+                    if group_alters && is_groupable(operation) =>
+                {
+                    Some(name)
+                }
+                _ => None,
+            };
+            let prefix =
+                table.map(|name| self.unquote(&format!("ALTER TABLE {} ", self.object_name(name))));
+            for statement in self.render_operation(operation)? {
+                let clause = prefix
+                    .as_deref()
+                    .and_then(|prefix| statement.strip_prefix(prefix));
+                match clause {
+                    Some(clause) if group.is_some() && group == table => {
+                        let (last, explanations) = statements.last_mut().unwrap();
+                        last.push_str(", ");
+                        last.push_str(clause);
+                        explanations.extend(explanation.take());
+                    }
+                    Some(_) => {
+                        group = table;
+                        statements.push((statement, explanation.take().into_iter().collect()));
+                    }
+                    None => {
+                        group = None;
+                        statements.push((statement, explanation.take().into_iter().collect()));
+                    }
+                }
+            }
+        }
+        Ok(statements)
+    }
+
+    fn is_transactional(&self, statement: &str) -> bool {
+        let non_transactional = match self.dialect {
+            Dialect::PostgreSql => &[
+                "CREATE DATABASE ",
+                "DROP DATABASE ",
+                "CREATE INDEX CONCURRENTLY ",
+                "CREATE UNIQUE INDEX CONCURRENTLY ",
+                "DROP INDEX CONCURRENTLY ",
+            ][..],
+            _ => &[][..],
+        };
+        !non_transactional
+            .iter()
+            .any(|prefix| statement.starts_with(prefix))
+    }
+
+    pub fn render_operation(&self, operation: &MigrationOperation) -> Result<Vec<String>> {
+        let statements = match operation {
+            MigrationOperation::CreateDatabase { name } => {
+                if self.dialect == Dialect::SQLite {
+                    anyhow::bail!("SQLite does not support creating database {}", name);
+                }
+                // Postgres has no CREATE DATABASE IF NOT EXISTS.
+                let if_not_exists = match self.dialect {
+                    Dialect::PostgreSql => "",
+                    _ => self.if_not_exists(),
+                };
+                vec![format!(
+                    "CREATE DATABASE {}{}",
+                    if_not_exists,
+                    self.ident(name)
+                )]
+            }
+            MigrationOperation::DropDatabase { name } => {
+                if self.dialect == Dialect::SQLite {
+                    anyhow::bail!("SQLite does not support dropping database {}", name);
+                }
+                vec![format!(
+                    "DROP DATABASE {}{}",
+                    self.if_exists(),
+                    self.ident(name)
+                )]
+            }
+            MigrationOperation::CreateSchema { name } => {
+                if self.dialect != Dialect::PostgreSql {
+                    anyhow::bail!("{} does not support creating schema {}", self.dialect, name);
+                }
+                vec![format!(
+                    "CREATE SCHEMA {}{}",
+                    self.if_not_exists(),
+                    self.ident(&name.0[1])
+                )]
+            }
+            MigrationOperation::DropSchema { name } => {
+                if self.dialect != Dialect::PostgreSql {
+                    anyhow::bail!("{} does not support dropping schema {}", self.dialect, name);
+                }
+                vec![format!(
+                    "DROP SCHEMA {}{}",
+                    self.if_exists(),
+                    self.ident(&name.0[1])
+                )]
+            }
+            MigrationOperation::CreateTable {
+                name,
+                table,
+                with_indexes,
+            } => self.render_create_table(name, table, *with_indexes),
+            MigrationOperation::DropTable { name, .. } => {
+                vec![format!(
+                    "DROP TABLE {}{}",
+                    self.if_exists(),
+                    self.object_name(name)
+                )]
+            }
+            MigrationOperation::AlterTable { name, operation } => {
+                self.render_alter_table(name, operation)?
+            }
+            MigrationOperation::CopyTableData { from, to, columns } => {
+                let columns: Vec<String> =
+                    columns.iter().map(|c| self.ident(c).to_string()).collect();
+                let columns = columns.join(", ");
+                vec![format!(
+                    "INSERT INTO {} ({}) SELECT {} FROM {}",
+                    self.object_name(to),
+                    columns,
+                    columns,
+                    self.object_name(from)
+                )]
+            }
+            MigrationOperation::RenameTable { from, to } => {
+                vec![format!(
+                    "ALTER TABLE {} RENAME TO {}",
+                    self.object_name(from),
+                    self.ident(&to.0[2])
+                )]
+            }
+            MigrationOperation::CreateDomain { name, domain } => {
+                self.expect_domains(name)?;
+                vec![format!(
+                    "CREATE DOMAIN {} {}",
+                    self.object_name(name),
+                    domain
+                )]
+            }
+            MigrationOperation::DropDomain { name, .. } => {
+                self.expect_domains(name)?;
+                vec![format!(
+                    "DROP DOMAIN {}{}",
+                    self.if_exists(),
+                    self.object_name(name)
+                )]
+            }
+            MigrationOperation::AlterDomain { name, operation } => {
+                self.expect_domains(name)?;
+                let operation = match operation {
+                    AlterDomainOperation::DropConstraint { constraint } => {
+                        // Postgres names an unnamed check after its domain.
+                        let constraint_name = match &constraint.name {
+                            Some(constraint_name) => constraint_name.clone(),
+                            None => self.ident(&format!("{}_check", name.0[2])),
+                        };
+                        format!("DROP CONSTRAINT {}", constraint_name)
+                    }
+                    AlterDomainOperation::AddConstraint { constraint } => {
+                        format!("ADD {}", constraint)
+                    }
+                    operation => operation.to_string(),
+                };
+                vec![format!(
+                    "ALTER DOMAIN {} {}",
+                    self.object_name(name),
+                    operation
+                )]
+            }
+            // Postgres has no CREATE TRIGGER IF NOT EXISTS.
+            MigrationOperation::CreateTrigger { table, trigger } => {
+                let if_not_exists = match self.dialect {
+                    Dialect::PostgreSql => "",
+                    _ => self.if_not_exists(),
+                };
+                vec![format!(
+                    "CREATE TRIGGER {}{} {}",
+                    if_not_exists,
+                    self.ident(&trigger.name),
+                    trigger.definition(&self.object_name(table))
+                )]
+            }
+            // Postgres triggers are named per table, the others per schema.
+            MigrationOperation::DropTrigger { table, trigger } => match self.dialect {
+                Dialect::PostgreSql => vec![format!(
+                    "DROP TRIGGER {}{} ON {}",
+                    self.if_exists(),
+                    self.ident(&trigger.name),
+                    self.object_name(table)
+                )],
+                Dialect::MySql | Dialect::MariaDb | Dialect::SQLite => {
+                    let mut name = table.clone();
+                    name.0[2] = trigger.name.clone();
+                    vec![format!(
+                        "DROP TRIGGER {}{}",
+                        self.if_exists(),
+                        self.object_name(&name)
+                    )]
+                }
+            },
+            MigrationOperation::CreatePolicy { table, policy } => {
+                self.expect_policies(table)?;
+                let definition = policy.to_string();
+                vec![format!(
+                    "CREATE POLICY {} ON {}{}{}",
+                    self.ident(&policy.name),
+                    self.object_name(table),
+                    if definition.is_empty() { "" } else { " " },
+                    definition
+                )]
+            }
+            MigrationOperation::DropPolicy { table, policy } => {
+                self.expect_policies(table)?;
+                vec![format!(
+                    "DROP POLICY {}{} ON {}",
+                    self.if_exists(),
+                    self.ident(&policy.name),
+                    self.object_name(table)
+                )]
+            }
+            MigrationOperation::AlterPolicy {
+                table,
+                previous,
+                current,
+            } => {
+                self.expect_policies(table)?;
+                let mut sql = format!(
+                    "ALTER POLICY {} ON {}",
+                    self.ident(&current.name),
+                    self.object_name(table)
+                );
+                if current.roles != previous.roles {
+                    sql.push_str(&format!(" TO {}", current.roles()));
+                }
+                if let Some(using) = current
+                    .using
+                    .as_ref()
+                    .filter(|_| current.using != previous.using)
+                {
+                    sql.push_str(&format!(" USING ({})", using));
+                }
+                if let Some(with_check) = current
+                    .with_check
+                    .as_ref()
+                    .filter(|_| current.with_check != previous.with_check)
+                {
+                    sql.push_str(&format!(" WITH CHECK ({})", with_check));
+                }
+                vec![sql]
+            }
+            MigrationOperation::MoveTable { from, to } => match self.dialect {
+                Dialect::PostgreSql => vec![format!(
+                    "ALTER TABLE {} SET SCHEMA {}",
+                    self.object_name(from),
+                    self.ident(&to.0[1])
+                )],
+                Dialect::MySql | Dialect::MariaDb => vec![format!(
+                    "RENAME TABLE {} TO {}",
+                    self.object_name(from),
+                    self.object_name(to)
+                )],
+                Dialect::SQLite => anyhow::bail!("SQLite does not support moving table {}", from),
+            },
+            MigrationOperation::CreateView { name, view } => {
+                self.render_create_view(name, view, false)?
+            }
+            MigrationOperation::ReplaceView { name, view } => {
+                self.render_create_view(name, view, true)?
+            }
+            MigrationOperation::DropView { name, view } => {
+                self.expect_view(name, view)?;
+                vec![format!(
+                    "DROP {} {}{}",
+                    view.kind(),
+                    self.if_exists(),
+                    self.object_name(name)
+                )]
+            }
+            MigrationOperation::CreateViewIndex { view, index } => {
+                vec![self.create_index(view, index).to_string()]
+            }
+            MigrationOperation::DropViewIndex { view, index } => {
+                vec![self.drop_index(view, index)]
+            }
+            // Left as written, only the last semicolon is added by render().
+            MigrationOperation::Raw { sql } => {
+                return Ok(vec![sql.trim_end().trim_end_matches(';').to_string()]);
+            }
+        };
+        Ok(statements.iter().map(|s| self.unquote(s)).collect())
+    }
+
+    fn render_create_table(
+        &self,
+        name: &ObjectName,
+        table: &Table,
+        with_indexes: bool,
+    ) -> Vec<String> {
+        let mut columns: Vec<ColumnDef> =
+            table.columns.iter().map(|c| self.column_def(c)).collect();
+        let mut constraints = table.constraints.clone();
+        // SQLite only accepts AUTOINCREMENT right after an inline PRIMARY KEY.
+        if self.dialect == Dialect::SQLite {
+            let primary_key = constraints.iter().position(
+                |c| matches!(c, TableConstraint::PrimaryKey { columns, .. } if columns.len() == 1),
+            );
+            if let Some(i) = primary_key {
+                let TableConstraint::PrimaryKey {
+                    name,
+                    columns: key,
+                    characteristics,
+                    ..
+                } = constraints[i].clone()
+                else {
+                    unreachable!()
+                };
+                let identity = table
+                    .columns
+                    .iter()
+                    .position(|c| c.is_identity() && c.name == key[0].value);
+                if let Some(j) = identity {
+                    constraints.remove(i);
+                    let options = &mut columns[j].options;
+                    options.insert(
+                        options.len() - 1,
+                        ColumnOptionDef {
+                            name,
+                            option: ColumnOption::Unique {
+                                is_primary: true,
+                                characteristics,
+                            },
+                        },
+                    );
+                }
+            }
+        }
+
+        let mut builder = CreateTableBuilder::new(self.object_name(name))
+            .columns(columns)
+            .constraints(constraints)
+            .with_options(table.with_options.clone())
+            .without_rowid(table.without_rowid)
+            .engine(table.engine.clone())
+            .auto_increment_offset(table.auto_increment_offset)
+            .default_charset(table.default_charset.clone())
+            .collation(table.collation.clone())
+            .on_commit(table.on_commit)
+            .order_by(table.order_by.clone())
+            .partition_by(table.partition_by.clone())
+            .options(table.options.clone());
+        builder.strict = table.strict;
+        builder.if_not_exists = self.idempotent;
+
+        let mut create_table = builder.build().to_string();
+        // sqlparser doesn't escape table comments and writes them before the
+        // options it parses after them.
+        if let Some(comment) = &table.comment {
+            if matches!(self.dialect, Dialect::MySql | Dialect::MariaDb) {
+                create_table.push_str(&format!(
+                    " COMMENT {}",
+                    ast::Value::SingleQuotedString(comment.clone())
+                ));
+            }
+        }
+
+        let mut statements = vec![create_table];
+        if self.dialect == Dialect::PostgreSql {
+            if table.comment.is_some() {
+                statements.push(self.comment_on_table(name, table));
+            }
+            for column in &table.columns {
+                if column_comment(column).is_some() {
+                    statements.push(self.comment_on_column(name, column));
+                }
+            }
+        }
+        // sqlparser can't render EXCLUDE constraints in a CREATE TABLE
+        for constraint in &table.exclusion_constraints {
+            statements.push(format!(
+                "ALTER TABLE {} ADD {}",
+                self.object_name(name),
+                constraint
+            ));
+        }
+        if with_indexes {
+            for index in &table.indexes {
+                statements.push(self.create_index(name, index).to_string());
+            }
+        }
+        statements
+    }
+
+    fn render_create_view(
+        &self,
+        name: &ObjectName,
+        view: &View,
+        or_replace: bool,
+    ) -> Result<Vec<String>> {
+        self.expect_view(name, view)?;
+        let columns = view
+            .columns
+            .iter()
+            .map(|column| ViewColumnDef {
+                name: self.ident(&column.name),
+                data_type: column.data_type.clone(),
+                options: (!column.options.is_empty()).then(|| column.options.clone()),
+            })
+            .collect();
+        let create_view = Statement::CreateView {
+            or_replace,
+            materialized: view.materialized,
+            name: self.object_name(name),
+            columns,
+            query: view.query.clone(),
+            options: CreateTableOptions::None,
+            cluster_by: vec![],
+            comment: None,
+            with_no_schema_binding: false,
+            // Postgres has no CREATE VIEW IF NOT EXISTS, only for materialized
+            // views.
+            if_not_exists: self.idempotent && view.materialized && !or_replace,
+            temporary: false,
+        };
+
+        let mut statements = vec![create_view.to_string()];
+        for index in &view.indexes {
+            statements.push(self.create_index(name, index).to_string());
+        }
+        Ok(statements)
+    }
+
+    fn render_alter_table(
+        &self,
+        name: &ObjectName,
+        operation: &AlterTableOperation,
+    ) -> Result<Vec<String>> {
+        let table_name = self.object_name(name);
+        let operations = match operation {
+            AlterTableOperation::AddColumn { column } => {
+                let operation = ast::AlterTableOperation::AddColumn {
+                    column_keyword: true,
+                    if_not_exists: self.idempotent_alters(),
+                    column_def: self.column_def(column),
+                    column_position: None,
+                };
+                if self.dialect == Dialect::PostgreSql && column_comment(column).is_some() {
+                    return Ok(vec![
+                        format!("ALTER TABLE {} {}", table_name, operation),
+                        self.comment_on_column(name, column),
+                    ]);
+                }
+                vec![operation]
+            }
+            AlterTableOperation::DropColumn { column } => {
+                vec![ast::AlterTableOperation::DropColumn {
+                    column_name: self.ident(&column.name),
+                    if_exists: self.idempotent_alters(),
+                    cascade: false,
+                }]
+            }
+            AlterTableOperation::AlterColumn {
+                previous,
+                current,
+                previous_nullable,
+                current_nullable,
+            } => {
+                return self.alter_column(
+                    name,
+                    (previous, *previous_nullable),
+                    (current, *current_nullable),
+                );
+            }
+            AlterTableOperation::MoveColumn { column, after } => {
+                if !matches!(self.dialect, Dialect::MySql | Dialect::MariaDb) {
+                    anyhow::bail!("{} can't reorder columns", self.dialect);
+                }
+                let position = match after {
+                    Some(after) => MySQLColumnPosition::After(self.ident(after)),
+                    None => MySQLColumnPosition::First,
+                };
+                return Ok(vec![format!(
+                    "ALTER TABLE {} {}",
+                    table_name,
+                    self.modify_column(column, Some(position))
+                )]);
+            }
+            AlterTableOperation::SetColumnDefault { column, value } => {
+                let value = match (self.dialect, value) {
+                    (Dialect::SQLite, _) => anyhow::bail!(
+                        "SQLite can't alter column {}.{}, the table must be rebuilt",
+                        name,
+                        column
+                    ),
+                    // MySQL only takes an expression other than a literal in
+                    // parentheses.
+                    (Dialect::MySql | Dialect::MariaDb, Expr::Value(_) | Expr::Nested(_))
+                    | (Dialect::PostgreSql, _) => self.requoted(*value),
+                    (Dialect::MySql | Dialect::MariaDb, _) => {
+                        Expr::Nested(Box::new(self.requoted(*value)))
+                    }
+                };
+                vec![ast::AlterTableOperation::AlterColumn {
+                    column_name: self.ident(column),
+                    op: AlterColumnOperation::SetDefault { value },
+                }]
+            }
+            AlterTableOperation::DropColumnDefault { column } => {
+                if self.dialect == Dialect::SQLite {
+                    anyhow::bail!(
+                        "SQLite can't alter column {}.{}, the table must be rebuilt",
+                        name,
+                        column
+                    );
+                }
+                vec![ast::AlterTableOperation::AlterColumn {
+                    column_name: self.ident(column),
+                    op: AlterColumnOperation::DropDefault,
+                }]
+            }
+            AlterTableOperation::AddConstraint { constraint } => {
+                vec![ast::AlterTableOperation::AddConstraint(
+                    (*constraint).clone(),
+                )]
+            }
+            AlterTableOperation::DropConstraint { constraint } => {
+                vec![self.drop_constraint(name, constraint)?]
+            }
+            AlterTableOperation::AddExclusionConstraint { constraint } => {
+                return Ok(vec![format!(
+                    "ALTER TABLE {} ADD {}",
+                    table_name, constraint
+                )]);
+            }
+            AlterTableOperation::DropExclusionConstraint { constraint } => {
+                let constraint_name = match &constraint.name {
+                    Some(name) => self.ident(&name.value),
+                    None => self.ident(&constraint.default_name(&name.0[2])),
+                };
+                vec![ast::AlterTableOperation::DropConstraint {
+                    if_exists: self.idempotent_alters(),
+                    name: constraint_name,
+                    cascade: false,
+                }]
+            }
+            AlterTableOperation::AddIndex { index } => {
+                return Ok(vec![self.create_index(name, index).to_string()]);
+            }
+            AlterTableOperation::DropIndex { index } => {
+                return Ok(vec![self.drop_index(name, index)]);
+            }
+            AlterTableOperation::SetEngine { .. }
+            | AlterTableOperation::SetDefaultCharset { .. }
+            | AlterTableOperation::SetCollation { .. } => {
+                return Ok(vec![format!("ALTER TABLE {} {}", table_name, operation)]);
+            }
+            AlterTableOperation::SetOptions { options } => {
+                let options: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+                return Ok(vec![format!(
+                    "ALTER TABLE {} SET ({})",
+                    table_name,
+                    options.join(", ")
+                )]);
+            }
+            AlterTableOperation::ResetOptions { names } => {
+                let names: Vec<String> = names.iter().map(|n| self.ident(n).to_string()).collect();
+                return Ok(vec![format!(
+                    "ALTER TABLE {} RESET ({})",
+                    table_name,
+                    names.join(", ")
+                )]);
+            }
+            AlterTableOperation::AddNotNullCheck { column } => {
+                return Ok(vec![format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({} IS NOT NULL) NOT VALID",
+                    table_name,
+                    self.not_null_check_name(name, column),
+                    self.ident(column)
+                )]);
+            }
+            AlterTableOperation::ValidateNotNullCheck { column } => {
+                return Ok(vec![format!(
+                    "ALTER TABLE {} VALIDATE CONSTRAINT {}",
+                    table_name,
+                    self.not_null_check_name(name, column)
+                )]);
+            }
+            AlterTableOperation::DropNotNullCheck { column } => {
+                vec![ast::AlterTableOperation::DropConstraint {
+                    if_exists: self.idempotent_alters(),
+                    name: self.not_null_check_name(name, column),
+                    cascade: false,
+                }]
+            }
+        };
+
+        Ok(operations
+            .into_iter()
+            .map(|operation| {
+                Statement::AlterTable {
+                    name: table_name.clone(),
+                    if_exists: false,
+                    only: false,
+                    operations: vec![operation],
+                    location: None,
+                }
+                .to_string()
+            })
+            .collect())
+    }
+
+    fn alter_column(
+        &self,
+        table_name: &ObjectName,
+        (previous, previous_nullable): (&Column, bool),
+        (current, current_nullable): (&Column, bool),
+    ) -> Result<Vec<String>> {
+        let rendered_table_name = self.object_name(table_name);
+        match self.dialect {
+            // MySQL redefines the whole column.
+            Dialect::MySql | Dialect::MariaDb => Ok(vec![format!(
+                "ALTER TABLE {} {}",
+                rendered_table_name,
+                self.modify_column(current, None)
+            )]),
+            Dialect::PostgreSql => {
+                let column_name = self.ident(&current.name);
+                let mut clauses = Vec::new();
+
+                // The collation is given with the type, the database's own
+                // being named "default".
+                let collation_changed = previous.collation != current.collation;
+                if collation_changed {
+                    let collation = match &current.collation {
+                        Some(collation) => collation.to_string(),
+                        None => "\"default\"".to_string(),
+                    };
+                    clauses.push(format!(
+                        "ALTER COLUMN {} TYPE {} COLLATE {}",
+                        column_name, current.data_type, collation
+                    ));
+                }
+
+                let mut alter = |op: AlterColumnOperation| {
+                    clauses.push(format!("ALTER COLUMN {} {}", column_name, op))
+                };
+                let resolve = |data_type: &DataType| {
+                    let data_type = canonical_data_type(data_type, self.dialect);
+                    resolve_data_type(&data_type, &table_name.0[0], &self.default_schema)
+                };
+                if !collation_changed && resolve(&previous.data_type) != resolve(&current.data_type)
+                {
+                    alter(AlterColumnOperation::SetDataType {
+                        data_type: current.data_type.clone(),
+                        using: None,
+                    });
+                }
+                // Identity columns must be NOT NULL beforehand, which their
+                // nullability already says.
+                match (previous_nullable, current_nullable) {
+                    (true, false) => alter(AlterColumnOperation::SetNotNull),
+                    (false, true) => alter(AlterColumnOperation::DropNotNull),
+                    _ => {}
+                }
+                match (default_value(previous), default_value(current)) {
+                    (previous, Some(value)) if previous != Some(value) => {
+                        alter(AlterColumnOperation::SetDefault {
+                            value: self.requoted(value),
+                        })
+                    }
+                    (Some(_), None) => alter(AlterColumnOperation::DropDefault),
+                    _ => {}
+                }
+                match (&previous.identity, &current.identity) {
+                    (None, Some(identity)) => alter(AlterColumnOperation::AddGenerated {
+                        generated_as: Some(generated_as(identity.kind)),
+                        sequence_options: if identity.sequence_options.is_empty() {
+                            None
+                        } else {
+                            Some(identity.sequence_options.clone())
+                        },
+                    }),
+                    (Some(_), None) => {
+                        clauses.push(format!("ALTER COLUMN {} DROP IDENTITY", column_name))
+                    }
+                    (Some(previous_identity), Some(identity)) => {
+                        let mut changes = Vec::new();
+                        if previous_identity.kind != identity.kind {
+                            let kind = match identity.kind {
+                                IdentityKind::Always => "ALWAYS",
+                                IdentityKind::ByDefault => "BY DEFAULT",
+                            };
+                            changes.push(format!("SET GENERATED {}", kind));
+                        }
+                        changes.extend(sequence_option_changes(
+                            &previous_identity.sequence_options,
+                            &identity.sequence_options,
+                        ));
+                        if !changes.is_empty() {
+                            clauses.push(format!(
+                                "ALTER COLUMN {} {}",
+                                column_name,
+                                changes.join(" ")
+                            ));
+                        }
+                    }
+                    (None, None) => {}
+                }
+
+                let mut statements = Vec::new();
+                if column_comment(previous) != column_comment(current) {
+                    statements.push(self.comment_on_column(table_name, current));
+                }
+
+                let other_options = |column: &Column| -> Vec<ColumnOption> {
+                    column
+                        .options
+                        .iter()
+                        .map(|o| o.option.clone())
+                        .filter(|o| {
+                            !matches!(
+                                o,
+                                ColumnOption::Null
+                                    | ColumnOption::NotNull
+                                    | ColumnOption::Default(_)
+                                    | ColumnOption::Comment(_)
+                            )
+                        })
+                        .collect()
+                };
+                if other_options(previous) != other_options(current) {
+                    anyhow::bail!(
+                        "changing the options of column {}.{} is not supported yet",
+                        table_name,
+                        current.name
+                    );
+                }
+                let clauses = clauses
+                    .into_iter()
+                    .map(|clause| format!("ALTER TABLE {} {}", rendered_table_name, clause));
+                Ok(clauses.chain(statements).collect())
+            }
+            Dialect::SQLite => anyhow::bail!(
+                "SQLite can't alter column {}.{}, the table must be rebuilt",
+                table_name,
+                current.name
+            ),
+        }
+    }
+
+    fn expect_domains(&self, name: &ObjectName) -> Result<()> {
+        if self.dialect != Dialect::PostgreSql {
+            anyhow::bail!("{} does not support domain {}", self.dialect, name);
+        }
+        Ok(())
+    }
+
+    fn expect_policies(&self, table: &ObjectName) -> Result<()> {
+        if self.dialect != Dialect::PostgreSql {
+            anyhow::bail!(
+                "{} does not support policies on table {}",
+                self.dialect,
+                table
+            );
+        }
+        Ok(())
+    }
+
+    fn expect_view(&self, name: &ObjectName, view: &View) -> Result<()> {
+        if view.materialized && self.dialect != Dialect::PostgreSql {
+            anyhow::bail!(
+                "{} does not support materialized view {}",
+                self.dialect,
+                name
+            );
+        }
+        Ok(())
+    }
+
+    // Postgres has no inline table comments.
+    fn comment_on_table(&self, table_name: &ObjectName, table: &Table) -> String {
+        let comment = match &table.comment {
+            Some(comment) => ast::Value::SingleQuotedString(comment.clone()).to_string(),
+            None => "NULL".to_string(),
+        };
+        format!(
+            "COMMENT ON TABLE {} IS {}",
+            self.object_name(table_name),
+            comment
+        )
+    }
+
+    // Postgres has no inline column comments.
+    fn comment_on_column(&self, table_name: &ObjectName, column: &Column) -> String {
+        let comment = match column_comment(column) {
+            Some(comment) => ast::Value::SingleQuotedString(comment.clone()).to_string(),
+            None => "NULL".to_string(),
+        };
+        format!(
+            "COMMENT ON COLUMN {}.{} IS {}",
+            self.object_name(table_name),
+            self.ident(&column.name),
+            comment
+        )
+    }
+
+    fn not_null_check_name(&self, table_name: &ObjectName, column: &str) -> Ident {
+        self.ident(&format!("{}_{}_not_null", table_name.0[2], column))
+    }
+
+    fn drop_constraint(
+        &self,
+        table_name: &ObjectName,
+        constraint: &TableConstraint,
+    ) -> Result<ast::AlterTableOperation> {
+        if let Some(name) = constraint_name(constraint) {
+            return Ok(ast::AlterTableOperation::DropConstraint {
+                if_exists: self.idempotent_alters(),
+                name: self.ident(&name.value),
+                cascade: false,
+            });
+        }
+
+        let table = &table_name.0[2];
+        let name = match (self.dialect, constraint) {
+            (Dialect::MySql | Dialect::MariaDb, TableConstraint::PrimaryKey { .. }) => {
+                return Ok(ast::AlterTableOperation::DropPrimaryKey);
+            }
+            // The names Postgres gives to unnamed constraints.
+            (Dialect::PostgreSql, TableConstraint::PrimaryKey { .. }) => format!("{}_pkey", table),
+            (Dialect::PostgreSql, TableConstraint::Unique { columns, .. }) => {
+                format!("{}_{}_key", table, join_idents(columns))
+            }
+            (Dialect::PostgreSql, TableConstraint::ForeignKey { columns, .. }) => {
+                format!("{}_{}_fkey", table, join_idents(columns))
+            }
+            (Dialect::PostgreSql, TableConstraint::Check { .. }) => format!("{}_check", table),
+            _ => anyhow::bail!(
+                "can't drop unnamed constraint {} of table {}",
+                constraint,
+                table_name
+            ),
+        };
+        Ok(ast::AlterTableOperation::DropConstraint {
+            if_exists: self.idempotent_alters(),
+            name: self.ident(&name),
+            cascade: false,
+        })
+    }
+
+    // MySQL's MODIFY COLUMN, redefining the whole column.
+    fn modify_column(&self, column: &Column, position: Option<MySQLColumnPosition>) -> String {
+        match position {
+            Some(position) => format!("MODIFY COLUMN {} {}", self.column_def(column), position),
+            None => format!("MODIFY COLUMN {}", self.column_def(column)),
+        }
+    }
+
+    fn column_def(&self, column: &Column) -> ColumnDef {
+        let mut options = self.requoted(&column.options);
+        if self.dialect == Dialect::PostgreSql {
+            options.retain(|o| !matches!(o.option, ColumnOption::Comment(_)));
+        }
+        if let Some(identity) = &column.identity {
+            match self.dialect {
+                Dialect::PostgreSql => {
+                    let generated_as = generated_as(identity.kind);
+                    let sequence_options = if identity.sequence_options.is_empty() {
+                        None
+                    } else {
+                        Some(identity.sequence_options.clone())
+                    };
+                    options.insert(
+                        0,
+                        ColumnOptionDef {
+                            name: None,
+                            option: ColumnOption::Generated {
+                                generated_as,
+                                sequence_options,
+                                generation_expr: None,
+                                generation_expr_mode: None,
+                                generated_keyword: true,
+                            },
+                        },
+                    );
+                }
+                // SQLite only accepts AUTOINCREMENT after PRIMARY KEY.
+                Dialect::MySql | Dialect::MariaDb | Dialect::SQLite => {
+                    let keyword = match self.dialect {
+                        Dialect::MySql | Dialect::MariaDb => "AUTO_INCREMENT",
+                        _ => "AUTOINCREMENT",
+                    };
+                    options.push(ColumnOptionDef {
+                        name: None,
+                        option: ColumnOption::DialectSpecific(vec![Token::Word(Word {
+                            value: keyword.to_string(),
+                            quote_style: None,
+                            keyword: Keyword::NoKeyword,
+                        })]),
+                    });
+                }
+            }
+        }
+
+        ColumnDef {
+            name: self.ident(&column.name),
+            data_type: column.data_type.clone(),
+            collation: column.collation.clone(),
+            options,
+        }
+    }
+
+    fn create_index(&self, table_name: &ObjectName, index: &Index) -> Statement {
+        // Postgres indexes always live in the schema of their table.
+        let name = ast::ObjectName(vec![self.ident(&index.name)]);
+        Statement::CreateIndex {
+            name: Some(name),
+            table_name: self.object_name(table_name),
+            using: index.using.clone(),
+            columns: index.columns.clone(),
+            unique: index.unique,
+            concurrently: index.concurrently,
+            // MySQL has no CREATE INDEX IF NOT EXISTS.
+            if_not_exists: self.idempotent && self.dialect != Dialect::MySql,
+            include: index.include.clone(),
+            nulls_distinct: index.nulls_distinct,
+            predicate: index.predicate.clone(),
+        }
+    }
+
+    fn drop_index(&self, table_name: &ObjectName, index: &Index) -> String {
+        match self.dialect {
+            // Postgres indexes live in the schema of their table.
+            Dialect::PostgreSql => {
+                let mut name = table_name.clone();
+                name.0[2] = index.name.clone();
+                let concurrently = if index.concurrently {
+                    "CONCURRENTLY "
+                } else {
+                    ""
+                };
+                format!(
+                    "DROP INDEX {}{}{}",
+                    concurrently,
+                    self.if_exists(),
+                    self.object_name(&name)
+                )
+            }
+            // MySQL has no DROP INDEX IF EXISTS.
+            Dialect::MySql => format!(
+                "DROP INDEX {} ON {}",
+                self.ident(&index.name),
+                self.object_name(table_name)
+            ),
+            Dialect::MariaDb => format!(
+                "DROP INDEX {}{} ON {}",
+                self.if_exists(),
+                self.ident(&index.name),
+                self.object_name(table_name)
+            ),
+            Dialect::SQLite => {
+                format!("DROP INDEX {}{}", self.if_exists(), self.ident(&index.name))
+            }
+        }
+    }
+
+    fn if_exists(&self) -> &'static str {
+        if self.idempotent {
+            "IF EXISTS "
+        } else {
+            ""
+        }
+    }
+
+    fn if_not_exists(&self) -> &'static str {
+        if self.idempotent {
+            "IF NOT EXISTS "
+        } else {
+            ""
+        }
+    }
+
+    // Whether columns and constraints are added and dropped with IF [NOT]
+    // EXISTS, which only Postgres and MariaDB take.
+    fn idempotent_alters(&self) -> bool {
+        self.idempotent && matches!(self.dialect, Dialect::PostgreSql | Dialect::MariaDb)
+    }
+
+    // Renders a fully qualified name, leaving out the default database and
+    // schema.
+    fn object_name(&self, name: &ObjectName) -> ast::ObjectName {
+        let parts = match (self.dialect, name.0.as_slice()) {
+            (Dialect::PostgreSql, [_, schema, object])
+                if *schema != self.default_schema || self.qualify == Qualify::Always =>
+            {
+                vec![schema, object]
+            }
+            (Dialect::MySql | Dialect::MariaDb, [database, _, object])
+                if *database != self.database =>
+            {
+                vec![database, object]
+            }
+            (_, [.., object]) => vec![object],
+            (_, []) => vec![],
+        };
+        ast::ObjectName(parts.into_iter().map(|part| self.ident(part)).collect())
+    }
+
+    // Identifiers are quoted while rendering, and the quotes are dropped
+    // afterwards from those which are neither reserved words nor need them
+    // for their characters, or their case in Postgres which folds bare
+    // identifiers to lower case.
+    fn unquote(&self, statement: &str) -> String {
+        let dialect = self.dialect.sqlparser_dialect();
+        let Ok(tokens) = Tokenizer::new(&*dialect, statement).tokenize_with_location() else {
+            return statement.to_string();
+        };
+        let quote = self.dialect.quote_char();
+        let mut unquoted = String::with_capacity(statement.len());
+        let mut end = 0;
+        for (i, tok) in tokens.iter().enumerate() {
+            let Token::Word(w) = &tok.token else {
+                continue;
+            };
+            if w.quote_style != Some(quote) || self.needs_quotes(&w.value) {
+                continue;
+            }
+            let start = location_offset(statement, tok.location);
+            let next = tokens
+                .get(i + 1)
+                .map_or(statement.len(), |t| location_offset(statement, t.location));
+            if statement[start..next] == format!("{}{}{}", quote, w.value, quote) {
+                unquoted.push_str(&statement[end..start]);
+                unquoted.push_str(&w.value);
+                end = next;
+            }
+        }
+        unquoted.push_str(&statement[end..]);
+        unquoted
+    }
+
+    // Identifiers inspected from a file of another dialect, given with a
+    // `-- migi:dialect=` directive, keep its quotes: they are quoted the target
+    // dialect's way so that expressions such as defaults render as written,
+    // e.g. `gen_id`() as gen_id() for Postgres.
+    fn requoted<T: Clone + Serialize + DeserializeOwned>(&self, value: &T) -> T {
+        requote(value, self.dialect)
+    }
+
+    fn needs_quotes(&self, value: &str) -> bool {
+        let mut chars = value.chars();
+        let bare = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        !bare
+            || (self.dialect == Dialect::PostgreSql
+                && value.chars().any(|c| c.is_ascii_uppercase()))
+            || reserved::is_reserved(self.dialect, value)
+    }
+
+    fn ident(&self, value: &str) -> Ident {
+        Ident::with_quote(self.dialect.quote_char(), value)
+    }
+}
+
+// The SET clauses turning the options of an identity sequence into others,
+// the options no longer given going back to their defaults.
+fn sequence_option_changes(
+    previous: &[SequenceOptions],
+    current: &[SequenceOptions],
+) -> Vec<String> {
+    let kind = |option: &SequenceOptions| std::mem::discriminant(option);
+    let mut changes: Vec<String> = current
+        .iter()
+        .filter(|option| !previous.contains(option))
+        .map(|option| format!("SET{}", option))
+        .collect();
+    for option in previous {
+        if current.iter().any(|o| kind(o) == kind(option)) {
+            continue;
+        }
+        let one = || Expr::Value(Value::Number("1".to_string(), false));
+        let default = match option {
+            SequenceOptions::IncrementBy(..) => SequenceOptions::IncrementBy(one(), true),
+            SequenceOptions::MinValue(_) => SequenceOptions::MinValue(None),
+            SequenceOptions::MaxValue(_) => SequenceOptions::MaxValue(None),
+            SequenceOptions::StartWith(..) => SequenceOptions::StartWith(one(), true),
+            SequenceOptions::Cache(_) => SequenceOptions::Cache(one()),
+            SequenceOptions::Cycle(_) => SequenceOptions::Cycle(true),
+        };
+        changes.push(format!("SET{}", default));
+    }
+    changes
+}
+
+// Quotes the identifiers of a value inspected in one dialect the way of
+// another, going through its json to reach every sqlparser Ident in it.
+pub(crate) fn requote<T: Clone + Serialize + DeserializeOwned>(value: &T, dialect: Dialect) -> T {
+    fn requote(value: &mut serde_json::Value, quote: &str) {
+        match value {
+            serde_json::Value::Object(map) => {
+                let ident = map.len() == 2 && map.contains_key("value");
+                match map.get_mut("quote_style") {
+                    Some(serde_json::Value::String(style))
+                        if ident && (style == "\"" || style == "`") =>
+                    {
+                        *style = quote.to_string();
+                    }
+                    _ => {}
+                }
+                map.values_mut().for_each(|v| requote(v, quote));
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| requote(v, quote)),
+            _ => {}
+        }
+    }
+    let Ok(mut json) = serde_json::to_value(value) else {
+        return value.clone();
+    };
+    requote(&mut json, &dialect.quote_char().to_string());
+    serde_json::from_value(json).unwrap_or_else(|_| value.clone())
+}
+
+fn generated_as(kind: IdentityKind) -> GeneratedAs {
+    match kind {
+        IdentityKind::Always => GeneratedAs::Always,
+        IdentityKind::ByDefault => GeneratedAs::ByDefault,
+    }
+}
+
+fn join_idents(idents: &[Ident]) -> String {
+    let values: Vec<&str> = idents.iter().map(|ident| ident.value.as_str()).collect();
+    values.join("_")
+}
+
+// The steps of a safe SET NOT NULL stay apart: the check is validated without
+// locking the table and Postgres would drop it before setting NOT NULL.
+fn is_groupable(operation: &AlterTableOperation) -> bool {
+    !matches!(
+        operation,
+        AlterTableOperation::AddNotNullCheck { .. }
+            | AlterTableOperation::ValidateNotNullCheck { .. }
+            | AlterTableOperation::DropNotNullCheck { .. }
+    )
+}
+
+fn column_comment(column: &Column) -> Option<&String> {
+    column.options.iter().find_map(|o| match &o.option {
+        ColumnOption::Comment(comment) => Some(comment),
+        _ => None,
+    })
+}
+
+fn default_value(column: &Column) -> Option<&Expr> {
+    column.options.iter().find_map(|o| match &o.option {
+        ColumnOption::Default(value) => Some(value),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dbinfo::Dbinfo;
+    use crate::inspector::Inspector;
+    use crate::migrate::{MigrationGenerator, RawPlacement};
+    use crate::test_support::test_options;
+    use crate::writer::migration_name;
+    use crate::Qualify;
+
+    use super::*;
+
+    #[test]
+    fn test_baseline_creates_referenced_tables_first() {
+        let options = test_options(Dialect::PostgreSql);
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE TABLE app.comments (id INT, post_id INT REFERENCES posts (id));
+            CREATE TABLE posts (
+                id INT PRIMARY KEY,
+                user_id INT,
+                FOREIGN KEY (user_id) REFERENCES users (id)
+            );
+            CREATE TABLE users (id SERIAL PRIMARY KEY, name TEXT NOT NULL);
+            CREATE INDEX ON users (name);
+        "#;
+
+        let empty = Dbinfo::with_options(options.clone());
+        let mut current = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut current)
+            .inspect(sql, "test.sql")
+            .unwrap();
+
+        let migration = MigrationGenerator::new(&empty, &current)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            [
+                r#"CREATE SCHEMA app;"#,
+                r#"CREATE TABLE users (id INT GENERATED BY DEFAULT AS IDENTITY, name TEXT NOT NULL, PRIMARY KEY (id));"#,
+                r#"CREATE INDEX users_name_idx ON users(name);"#,
+                r#"CREATE TABLE posts (id INT, user_id INT, PRIMARY KEY (id), FOREIGN KEY (user_id) REFERENCES users(id));"#,
+                r#"CREATE TABLE app.comments (id INT, post_id INT, FOREIGN KEY (post_id) REFERENCES posts(id));"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_unnamed_foreign_key_is_dropped_by_its_default_name() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect(
+            "CREATE TABLE users (id INT PRIMARY KEY); CREATE TABLE posts (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id));",
+        );
+        let current = inspect(
+            "CREATE TABLE users (id INT PRIMARY KEY); CREATE TABLE posts (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            [
+                r#"ALTER TABLE posts DROP CONSTRAINT posts_user_id_fkey;"#,
+                r#"ALTER TABLE posts ADD FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_column_becomes_identity() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect("CREATE TABLE t (id INT, n INT GENERATED ALWAYS AS IDENTITY);");
+        let current = inspect(
+            "CREATE TABLE t (id INT GENERATED BY DEFAULT AS IDENTITY, n INT GENERATED BY DEFAULT AS IDENTITY);",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            [
+                r#"ALTER TABLE t ALTER COLUMN id SET NOT NULL;"#,
+                r#"ALTER TABLE t ALTER COLUMN id ADD GENERATED BY DEFAULT AS IDENTITY;"#,
+                r#"ALTER TABLE t ALTER COLUMN n SET GENERATED BY DEFAULT;"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(migration.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_primary_key_changes_nullability() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let keyed = inspect("CREATE TABLE t (id INT PRIMARY KEY, name TEXT);");
+        let unkeyed = inspect("CREATE TABLE t (id INT, name TEXT);");
+
+        // Postgres keeps the column NOT NULL once its primary key is dropped.
+        let migration = MigrationGenerator::new(&keyed, &unkeyed)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            [
+                r#"ALTER TABLE t DROP CONSTRAINT t_pkey;"#,
+                r#"ALTER TABLE t ALTER COLUMN id DROP NOT NULL;"#,
+                "",
+            ]
+            .join("\n")
+        );
+        let mut applied = keyed.clone();
+        migration.apply(&mut applied).unwrap();
+        assert_eq!(applied.fingerprint(), unkeyed.fingerprint());
+
+        let migration = MigrationGenerator::new(&unkeyed, &keyed)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert!(
+            sql.contains("ALTER TABLE t ALTER COLUMN id SET NOT NULL;"),
+            "{}",
+            sql
+        );
+    }
+
+    #[test]
+    fn test_collation_changes() {
+        let render = |dialect: Dialect, previous: &str, current: &str| {
+            let options = test_options(dialect);
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            assert_eq!(migration.warnings.len(), 1);
+            assert!(!migration.warnings[0].destructive);
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                "CREATE TABLE t (name TEXT NOT NULL);",
+                r#"CREATE TABLE t (name TEXT COLLATE "C" NOT NULL);"#
+            ),
+            "ALTER TABLE t ALTER COLUMN name TYPE TEXT COLLATE \"C\";\n"
+        );
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                r#"CREATE TABLE t (name TEXT COLLATE "C");"#,
+                "CREATE TABLE t (name TEXT);"
+            ),
+            "ALTER TABLE t ALTER COLUMN name TYPE TEXT COLLATE \"default\";\n"
+        );
+        assert_eq!(
+            render(
+                Dialect::MySql,
+                "CREATE TABLE t (name VARCHAR(100) NOT NULL);",
+                "CREATE TABLE t (name VARCHAR(100) COLLATE utf8mb4_bin NOT NULL);"
+            ),
+            "ALTER TABLE t MODIFY COLUMN name VARCHAR(100) COLLATE utf8mb4_bin NOT NULL;\n"
+        );
+    }
+
+    #[test]
+    fn test_group_alters() {
+        let render = |dialect: Dialect, previous: &str, current: &str, group_alters: bool| {
+            let options = test_options(dialect);
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .with_safe_migrations(true)
+                .generate()
+                .unwrap();
+            Renderer::new(&options)
+                .with_group_alters(group_alters)
+                .render(&migration)
+                .unwrap()
+        };
+
+        let previous = "CREATE TABLE users (id INT, name TEXT, age INT);
+                        CREATE TABLE posts (id INT);";
+        let current = "CREATE TABLE users (id INT, name VARCHAR(100), email TEXT);
+                       CREATE TABLE posts (id INT, title TEXT);";
+        assert_eq!(
+            render(Dialect::MySql, previous, current, false),
+            [
+                "ALTER TABLE posts ADD COLUMN title TEXT;",
+                "ALTER TABLE users MODIFY COLUMN name VARCHAR(100);",
+                "ALTER TABLE users DROP COLUMN age;",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(Dialect::MySql, previous, current, true),
+            [
+                "ALTER TABLE posts ADD COLUMN title TEXT;",
+                "ALTER TABLE users MODIFY COLUMN name VARCHAR(100), DROP COLUMN age, ADD COLUMN email TEXT;",
+                "",
+            ]
+            .join("\n")
+        );
+
+        // the NOT NULL check is validated and dropped on its own
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                "CREATE TABLE users (id INT, age INT);",
+                "CREATE TABLE users (id INT, age INT NOT NULL, name TEXT, email TEXT);",
+                true
+            ),
+            [
+                "ALTER TABLE users ADD CONSTRAINT users_age_not_null CHECK (age IS NOT NULL) NOT VALID;",
+                "ALTER TABLE users VALIDATE CONSTRAINT users_age_not_null;",
+                "ALTER TABLE users ALTER COLUMN age SET NOT NULL;",
+                "ALTER TABLE users DROP CONSTRAINT users_age_not_null;",
+                "ALTER TABLE users ADD COLUMN name TEXT, ADD COLUMN email TEXT;",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(
+                Dialect::SQLite,
+                "CREATE TABLE users (id INT);",
+                "CREATE TABLE users (id INT, name TEXT, email TEXT);",
+                true
+            ),
+            [
+                "ALTER TABLE users ADD COLUMN name TEXT;",
+                "ALTER TABLE users ADD COLUMN email TEXT;",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_identity_sequence_changes() {
+        let options = test_options(Dialect::PostgreSql);
+        let render = |previous: &str, current: &str| {
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        let previous =
+            "CREATE TABLE t (id BIGINT GENERATED ALWAYS AS IDENTITY (INCREMENT BY 1 START WITH 1));";
+        assert_eq!(
+            render(
+                previous,
+                "CREATE TABLE t (id BIGINT GENERATED ALWAYS AS IDENTITY (INCREMENT BY 50 START WITH 1));"
+            ),
+            "ALTER TABLE t ALTER COLUMN id SET INCREMENT BY 50;\n"
+        );
+        assert_eq!(
+            render(
+                previous,
+                "CREATE TABLE t (id BIGINT GENERATED BY DEFAULT AS IDENTITY (INCREMENT 1 START 1 CACHE 20));"
+            ),
+            "ALTER TABLE t ALTER COLUMN id SET GENERATED BY DEFAULT SET CACHE 20;\n"
+        );
+        assert_eq!(
+            render(
+                previous,
+                "CREATE TABLE t (id BIGINT GENERATED ALWAYS AS IDENTITY (START WITH 1));"
+            ),
+            "ALTER TABLE t ALTER COLUMN id SET INCREMENT BY 1;\n"
+        );
+    }
+
+    #[test]
+    fn test_qualify() {
+        let render = |qualify: Qualify| {
+            let options = Options {
+                default_schema: "app".to_string(),
+                qualify,
+                ..test_options(Dialect::PostgreSql)
+            };
+            let previous = Dbinfo::with_options(options.clone());
+            let mut current = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut current)
+                .inspect(
+                    "CREATE TABLE users (id INT); CREATE INDEX users_id ON users (id);",
+                    "test.sql",
+                )
+                .unwrap();
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        assert_eq!(
+            render(Qualify::Auto),
+            "CREATE TABLE users (id INT);
+CREATE INDEX users_id ON users(id);
+"
+        );
+        assert_eq!(
+            render(Qualify::Always),
+            "CREATE TABLE app.users (id INT);
+CREATE INDEX users_id ON app.users(id);
+"
+        );
+        assert_eq!(
+            render(Qualify::SearchPath),
+            "SET search_path TO app;
+CREATE TABLE users (id INT);
+CREATE INDEX users_id ON users(id);
+"
+        );
+    }
+
+    #[test]
+    fn test_move_table() {
+        for (dialect, expected) in [
+            (Dialect::PostgreSql, r#"ALTER TABLE users SET SCHEMA app;"#),
+            (Dialect::MySql, "RENAME TABLE users TO app.users;"),
+        ] {
+            let options = test_options(dialect);
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let previous = inspect("CREATE SCHEMA app; CREATE TABLE users (id INT);");
+            let current = inspect("CREATE SCHEMA app; CREATE TABLE app.users (id INT);");
+
+            let migration = MigrationGenerator::new(&previous, &current)
+                .with_detect_table_moves(true)
+                .generate()
+                .unwrap();
+            let sql = Renderer::new(&options).render(&migration).unwrap();
+            assert_eq!(sql, format!("{}\n", expected));
+        }
+    }
+
+    #[test]
+    fn test_sqlite_autoincrement_primary_key_stays_inline() {
+        let options = Options::builder()
+            .dialect(Dialect::SQLite)
+            .database("test")
+            .build()
+            .unwrap();
+        let mut current = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut current)
+            .inspect(
+                "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT, email TEXT UNIQUE);",
+                "test.sql",
+            )
+            .unwrap();
+        let previous = Dbinfo::with_options(options.clone());
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT, email TEXT, UNIQUE (email));\n"
+        );
+    }
+
+    #[test]
+    fn test_safe_not_null() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect("CREATE TABLE users (id INT, email TEXT);");
+        let current = inspect("CREATE TABLE users (id INT, email TEXT NOT NULL);");
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .with_safe_migrations(true)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            [
+                r#"ALTER TABLE users ADD CONSTRAINT users_email_not_null CHECK (email IS NOT NULL) NOT VALID;"#,
+                r#"ALTER TABLE users VALIDATE CONSTRAINT users_email_not_null;"#,
+                r#"ALTER TABLE users ALTER COLUMN email SET NOT NULL;"#,
+                r#"ALTER TABLE users DROP CONSTRAINT users_email_not_null;"#,
+                "",
+            ]
+            .join("\n")
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(sql, "ALTER TABLE users ALTER COLUMN email SET NOT NULL;\n");
+    }
+
+    #[test]
+    fn test_explain() {
+        let options = Options {
+            transactional: true,
+            ..test_options(Dialect::PostgreSql)
+        };
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect("CREATE TABLE users (id INT, email VARCHAR(100));");
+        let current = inspect(
+            "CREATE TABLE users (id INT, email VARCHAR(255) NOT NULL, name TEXT DEFAULT 'x');",
+        );
+
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options)
+            .with_explain(true)
+            .render(&migration)
+            .unwrap();
+        assert_eq!(
+            sql,
+            [
+                "BEGIN;",
+                "-- column email changed: type from VARCHAR(100) to VARCHAR(255), NOT NULL added",
+                "ALTER TABLE users ALTER COLUMN email SET DATA TYPE VARCHAR(255);",
+                "ALTER TABLE users ALTER COLUMN email SET NOT NULL;",
+                "-- column name is new",
+                "ALTER TABLE users ADD COLUMN name TEXT DEFAULT 'x';",
+                "COMMIT;",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let sql = Renderer::new(&options)
+            .with_explain(true)
+            .with_group_alters(true)
+            .render(&migration)
+            .unwrap();
+        assert_eq!(
+            sql,
+            [
+                "BEGIN;",
+                "-- column email changed: type from VARCHAR(100) to VARCHAR(255), NOT NULL added",
+                "-- column name is new",
+                "ALTER TABLE users ALTER COLUMN email SET DATA TYPE VARCHAR(255), ALTER COLUMN email SET NOT NULL, ADD COLUMN name TEXT DEFAULT 'x';",
+                "COMMIT;",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert!(!sql.contains("--"));
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let options = Options {
+            idempotent: true,
+            ..test_options(Dialect::PostgreSql)
+        };
+        let inspect = |options: &Options, sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect(
+            &options,
+            "CREATE TABLE users (id INT, email TEXT);
+             CREATE TABLE posts (id INT);
+             CREATE INDEX posts_id_idx ON posts (id);",
+        );
+        let current = inspect(
+            &options,
+            "CREATE TABLE users (id INT, name TEXT);
+             CREATE TABLE tags (id INT);
+             CREATE INDEX tags_id_idx ON tags (id);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(
+            Renderer::new(&options).render(&migration).unwrap(),
+            [
+                "DROP TABLE IF EXISTS posts;",
+                "CREATE TABLE IF NOT EXISTS tags (id INT);",
+                "CREATE INDEX IF NOT EXISTS tags_id_idx ON tags(id);",
+                "ALTER TABLE users DROP COLUMN IF EXISTS email;",
+                "ALTER TABLE users ADD COLUMN IF NOT EXISTS name TEXT;",
+                "",
+            ]
+            .join("\n")
+        );
+        let sql = Renderer::new(&options)
+            .with_idempotent(false)
+            .render(&migration)
+            .unwrap();
+        assert!(!sql.contains("EXISTS"));
+
+        // MySQL has no IF [NOT] EXISTS for indexes and columns.
+        let options = Options {
+            dialect: Dialect::MySql,
+            ..options
+        };
+        let previous = inspect(
+            &options,
+            "CREATE TABLE posts (id INT, INDEX posts_id_idx (id));",
+        );
+        let current = inspect(
+            &options,
+            "CREATE TABLE tags (id INT); CREATE INDEX tags_id_idx ON tags (id);",
+        );
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        assert_eq!(
+            Renderer::new(&options).render(&migration).unwrap(),
+            [
+                "DROP TABLE IF EXISTS posts;",
+                "CREATE TABLE IF NOT EXISTS tags (id INT);",
+                "CREATE INDEX tags_id_idx ON tags(id);",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_views() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(
+                    &format!(
+                        "CREATE TABLE users (id INT, name TEXT, active BOOLEAN); {}",
+                        sql
+                    ),
+                    "test.sql",
+                )
+                .unwrap();
+            dbinfo
+        };
+        let render = |previous: &Dbinfo, current: &Dbinfo| {
+            let migration = MigrationGenerator::new(previous, current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+        let previous =
+            inspect("CREATE VIEW active_users AS SELECT id, name FROM users WHERE active;");
+
+        let current = inspect(
+            "CREATE VIEW active_users AS
+                 SELECT id,   name
+                 FROM users WHERE active;",
+        );
+        assert_eq!(render(&previous, &current), "");
+
+        let current = inspect(
+            "CREATE VIEW active_users AS SELECT users.id, name FROM users WHERE active AND id > 0;",
+        );
+        assert_eq!(
+            render(&previous, &current),
+            "CREATE OR REPLACE VIEW active_users AS SELECT users.id, name FROM users WHERE active AND id > 0;\n"
+        );
+
+        let current = inspect(
+            "CREATE VIEW active_users AS SELECT id, name, upper(name) AS upper_name FROM users WHERE active;",
+        );
+        assert_eq!(
+            render(&previous, &current),
+            "CREATE OR REPLACE VIEW active_users AS SELECT id, name, upper(name) AS upper_name FROM users WHERE active;\n"
+        );
+
+        let current = inspect("CREATE VIEW active_users AS SELECT id FROM users WHERE active;");
+        assert_eq!(
+            render(&previous, &current),
+            [
+                "DROP VIEW active_users;",
+                "CREATE VIEW active_users AS SELECT id FROM users WHERE active;",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_materialized_views() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let render = |previous: &Dbinfo, current: &Dbinfo| {
+            let migration = MigrationGenerator::new(previous, current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+        let empty = inspect("");
+        let previous = inspect(
+            "CREATE TABLE users (id INT, active BOOLEAN);
+             CREATE MATERIALIZED VIEW active_users AS SELECT id FROM users WHERE active;
+             CREATE UNIQUE INDEX active_users_id_idx ON active_users (id);",
+        );
+
+        assert_eq!(
+            render(&empty, &previous),
+            [
+                "CREATE TABLE users (id INT, active BOOLEAN);",
+                "CREATE MATERIALIZED VIEW active_users AS SELECT id FROM users WHERE active;",
+                "CREATE UNIQUE INDEX active_users_id_idx ON active_users(id);",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let current = inspect(
+            "CREATE TABLE users (id INT, active BOOLEAN);
+             CREATE MATERIALIZED VIEW active_users AS SELECT id FROM users WHERE active AND id > 0;
+             CREATE UNIQUE INDEX active_users_id_idx ON active_users (id);",
+        );
+        assert_eq!(
+            render(&previous, &current),
+            [
+                "DROP MATERIALIZED VIEW active_users;",
+                "CREATE MATERIALIZED VIEW active_users AS SELECT id FROM users WHERE active AND id > 0;",
+                "CREATE UNIQUE INDEX active_users_id_idx ON active_users(id);",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let current = inspect(
+            "CREATE TABLE users (id INT, active BOOLEAN);
+             CREATE MATERIALIZED VIEW active_users AS SELECT id FROM users WHERE active;
+             CREATE INDEX active_users_id_idx ON active_users (id);",
+        );
+        assert_eq!(
+            render(&previous, &current),
+            [
+                "DROP INDEX active_users_id_idx;",
+                "CREATE INDEX active_users_id_idx ON active_users(id);",
+                "",
+            ]
+            .join("\n")
+        );
+
+        assert_eq!(
+            render(&previous, &empty),
+            "DROP MATERIALIZED VIEW active_users;\nDROP TABLE users;\n"
+        );
+    }
+
+    #[test]
+    fn test_transactional() {
+        let options = Options {
+            transactional: true,
+            ..test_options(Dialect::PostgreSql)
+        };
+        let sql = r#"
+            CREATE TABLE users (id INT, email TEXT);
+            CREATE UNIQUE INDEX CONCURRENTLY users_email_idx ON users (email);
+            CREATE TABLE posts (id INT);
+        "#;
+
+        let empty = Dbinfo::with_options(options.clone());
+        let mut current = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut current)
+            .inspect(sql, "test.sql")
+            .unwrap();
+        let migration = MigrationGenerator::new(&empty, &current)
+            .generate()
+            .unwrap();
+
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert_eq!(
+            sql,
+            [
+                "BEGIN;",
+                r#"CREATE TABLE posts (id INT);"#,
+                r#"CREATE TABLE users (id INT, email TEXT);"#,
+                "COMMIT;",
+                r#"CREATE UNIQUE INDEX CONCURRENTLY users_email_idx ON users(email);"#,
+                "",
+            ]
+            .join("\n")
+        );
+
+        let sql = Renderer::new(&options)
+            .with_transactional(false)
+            .render(&migration)
+            .unwrap();
+        assert!(!sql.contains("BEGIN;"));
+
+        let options = Options {
+            dialect: Dialect::MySql,
+            ..options
+        };
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        assert!(!sql.contains("BEGIN;"));
+    }
+
+    #[test]
+    fn test_column_comment_changes() {
+        let render = |dialect: Dialect, previous: &str, current: &str| {
+            let options = test_options(dialect);
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        assert_eq!(
+            render(
+                Dialect::MySql,
+                "CREATE TABLE users (id INT, email TEXT COMMENT 'login');",
+                "CREATE TABLE users (id INT, email TEXT COMMENT 'contact address');",
+            ),
+            "ALTER TABLE users MODIFY COLUMN email TEXT COMMENT 'contact address';\n"
+        );
+
+        let previous = "CREATE TABLE users (id INT, email TEXT);
+                        COMMENT ON COLUMN users.email IS 'login';";
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                previous,
+                "CREATE TABLE users (id INT, email TEXT);
+                 COMMENT ON COLUMN users.email IS 'user''s address';",
+            ),
+            "COMMENT ON COLUMN users.email IS 'user''s address';\n"
+        );
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                previous,
+                "CREATE TABLE users (id INT, email TEXT);"
+            ),
+            "COMMENT ON COLUMN users.email IS NULL;\n"
+        );
+        assert_eq!(
+            render(Dialect::PostgreSql, "", previous),
+            [
+                r#"CREATE TABLE users (id INT, email TEXT);"#,
+                r#"COMMENT ON COLUMN users.email IS 'login';"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_comments_round_trip() {
+        let round_trip = |dialect: Dialect, sql: &str| {
+            let options = test_options(dialect);
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (empty, inspected) = (inspect(""), inspect(sql));
+            let migration = MigrationGenerator::new(&empty, &inspected)
+                .generate()
+                .unwrap();
+            let rendered = Renderer::new(&options).render(&migration).unwrap();
+            assert_eq!(inspect(&rendered), inspected, "{}", rendered);
+            rendered
+        };
+
+        assert_eq!(
+            round_trip(
+                Dialect::PostgreSql,
+                "CREATE TABLE users (id INT, email TEXT);
+                 COMMENT ON TABLE users IS 'people''s accounts';
+                 COMMENT ON COLUMN users.email IS 'login';"
+            ),
+            [
+                "CREATE TABLE users (id INT, email TEXT);",
+                "COMMENT ON TABLE users IS 'people''s accounts';",
+                "COMMENT ON COLUMN users.email IS 'login';",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            round_trip(
+                Dialect::MySql,
+                "CREATE TABLE users (id INT, email TEXT COMMENT 'login') ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin COMMENT='people''s accounts';"
+            ),
+            "CREATE TABLE users (id INT, email TEXT COMMENT 'login') ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin COMMENT 'people''s accounts';\n"
+        );
+    }
+
+    #[test]
+    fn test_domains() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let render = |previous: &Dbinfo, current: &Dbinfo| {
+            let migration = MigrationGenerator::new(previous, current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        let empty = inspect("");
+        let previous = inspect(
+            "CREATE DOMAIN email AS TEXT CHECK (VALUE LIKE '%@%');
+             CREATE DOMAIN code AS TEXT;
+             CREATE TABLE users (id INT, email email);",
+        );
+        assert_eq!(
+            render(&empty, &previous),
+            [
+                r#"CREATE DOMAIN code AS TEXT;"#,
+                r#"CREATE DOMAIN email AS TEXT CHECK (VALUE LIKE '%@%');"#,
+                r#"CREATE TABLE users (id INT, email email);"#,
+                "",
+            ]
+            .join("\n")
+        );
+
+        let current = inspect(
+            "CREATE DOMAIN email AS TEXT NOT NULL DEFAULT '' CONSTRAINT email_at CHECK (VALUE LIKE '%@%');
+             CREATE TABLE users (id INT);",
+        );
+        assert_eq!(
+            render(&previous, &current),
+            [
+                r#"ALTER DOMAIN email SET DEFAULT '';"#,
+                r#"ALTER DOMAIN email SET NOT NULL;"#,
+                r#"ALTER DOMAIN email DROP CONSTRAINT email_check;"#,
+                r#"ALTER DOMAIN email ADD CONSTRAINT email_at CHECK (VALUE LIKE '%@%');"#,
+                r#"ALTER TABLE users DROP COLUMN email;"#,
+                r#"DROP DOMAIN code;"#,
+                "",
+            ]
+            .join("\n")
+        );
+
+        let changed = inspect("CREATE DOMAIN email AS VARCHAR(255);");
+        let err = MigrationGenerator::new(&current, &changed)
+            .generate()
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "domain test.public.email changes its type from TEXT to VARCHAR(255), which can't be altered"
+        );
+    }
+
+    #[test]
+    fn test_index_column_order() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |index: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(
+                    &format!("CREATE TABLE t (a INT, b INT); {}", index),
+                    "test.sql",
+                )
+                .unwrap();
+            dbinfo
+        };
+        let render = |previous: &str, current: &str| {
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        let index = "CREATE INDEX t_idx ON t (a, b);";
+        assert_eq!(
+            render(index, "CREATE INDEX t_idx ON t (b, a);"),
+            [
+                r#"DROP INDEX t_idx;"#,
+                r#"CREATE INDEX t_idx ON t(b,a);"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(index, "CREATE INDEX t_idx ON t (a, b DESC);"),
+            [
+                r#"DROP INDEX t_idx;"#,
+                r#"CREATE INDEX t_idx ON t(a,b DESC);"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(index, "CREATE INDEX t_idx ON t (a, b NULLS FIRST);"),
+            [
+                r#"DROP INDEX t_idx;"#,
+                r#"CREATE INDEX t_idx ON t(a,b NULLS FIRST);"#,
+                "",
+            ]
+            .join("\n")
+        );
+        // the defaults spelled out
+        assert_eq!(
+            render(
+                index,
+                "CREATE INDEX CONCURRENTLY t_idx ON t (a ASC NULLS LAST, b);"
+            ),
+            ""
+        );
+        assert_eq!(
+            render(
+                "CREATE INDEX t_idx ON t (a DESC);",
+                "CREATE INDEX t_idx ON t (a DESC NULLS FIRST);"
+            ),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_add_trigger_to_existing_table() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |triggers: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(
+                    &format!(
+                        "CREATE TABLE users (id INT, updated_at TIMESTAMP); {}",
+                        triggers
+                    ),
+                    "test.sql",
+                )
+                .unwrap();
+            dbinfo
+        };
+        let render = |previous: &str, current: &str| {
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        let trigger = "CREATE TRIGGER users_touch BEFORE UPDATE ON users
+            FOR EACH ROW EXECUTE FUNCTION touch();";
+        assert_eq!(
+            render("", trigger),
+            [
+                r#"CREATE TRIGGER users_touch BEFORE UPDATE ON users FOR EACH ROW EXECUTE FUNCTION touch();"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(render(trigger, trigger), "");
+        assert_eq!(
+            render(
+                trigger,
+                "CREATE TRIGGER users_touch BEFORE INSERT OR UPDATE ON users
+                    FOR EACH ROW WHEN (NEW.id > 0) EXECUTE FUNCTION touch();"
+            ),
+            [
+                r#"DROP TRIGGER users_touch ON users;"#,
+                r#"CREATE TRIGGER users_touch BEFORE INSERT OR UPDATE ON users FOR EACH ROW WHEN (NEW.id > 0) EXECUTE FUNCTION touch();"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(trigger, ""),
+            [r#"DROP TRIGGER users_touch ON users;"#, ""].join("\n")
+        );
+    }
+
+    #[test]
+    fn test_add_policy_to_existing_table() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |policies: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(
+                    &format!("CREATE TABLE documents (id INT, owner TEXT); {}", policies),
+                    "test.sql",
+                )
+                .unwrap();
+            dbinfo
+        };
+        let render = |previous: &str, current: &str| {
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        let policy = "CREATE POLICY documents_owner ON documents FOR SELECT TO app
+            USING (owner = current_user);";
+        assert_eq!(
+            render("", policy),
+            [
+                r#"CREATE POLICY documents_owner ON documents FOR SELECT TO app USING (owner = current_user);"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(render(policy, policy), "");
+        assert_eq!(
+            render(
+                policy,
+                "CREATE POLICY documents_owner ON documents FOR SELECT TO app, auditor
+                    USING (owner = current_user OR id < 100);"
+            ),
+            [
+                r#"ALTER POLICY documents_owner ON documents TO app, auditor USING (owner = current_user OR id < 100);"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(
+                policy,
+                "CREATE POLICY documents_owner ON documents AS RESTRICTIVE FOR SELECT TO app
+                    USING (owner = current_user);"
+            ),
+            [
+                r#"DROP POLICY documents_owner ON documents;"#,
+                r#"CREATE POLICY documents_owner ON documents AS RESTRICTIVE FOR SELECT TO app USING (owner = current_user);"#,
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(policy, ""),
+            [r#"DROP POLICY documents_owner ON documents;"#, ""].join("\n")
+        );
+    }
+
+    #[test]
+    fn test_default_expressions() {
+        let options = test_options(Dialect::PostgreSql);
+        let render = |previous: &str, current: &str| {
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        assert_eq!(
+            render(
+                "CREATE TABLE t (id INT);",
+                "CREATE TABLE t (id INT, uid UUID DEFAULT gen_random_uuid(), at TIMESTAMP DEFAULT now() AT TIME ZONE 'utc');",
+            ),
+            [
+                "ALTER TABLE t ADD COLUMN uid UUID DEFAULT gen_random_uuid();",
+                "ALTER TABLE t ADD COLUMN at TIMESTAMP DEFAULT now() AT TIME ZONE 'utc';",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            render(
+                "CREATE TABLE t (id INT, uid UUID, at TIMESTAMP);",
+                "CREATE TABLE t (id INT, uid UUID DEFAULT gen_random_uuid(), at TIMESTAMP DEFAULT now() AT TIME ZONE 'utc');",
+            ),
+            [
+                "ALTER TABLE t ALTER COLUMN uid SET DEFAULT gen_random_uuid();",
+                "ALTER TABLE t ALTER COLUMN at SET DEFAULT now() AT TIME ZONE 'utc';",
+                "",
+            ]
+            .join("\n")
+        );
+        // a file of another dialect quotes the names of its defaults its way
+        assert_eq!(
+            render(
+                "CREATE TABLE t (id INT);",
+                "-- migi:dialect=mysql\nCREATE TABLE t (id INT, n INT DEFAULT (next_id(`orders`)));",
+            ),
+            "ALTER TABLE t ADD COLUMN n INT DEFAULT (next_id(orders));\n"
+        );
+    }
+
+    #[test]
+    fn test_default_only_change() {
+        let render = |dialect: Dialect, previous: &str, current: &str| {
+            let options = test_options(dialect);
+            let inspect = |sql: &str| {
+                let mut dbinfo = Dbinfo::with_options(options.clone());
+                Inspector::new(&mut dbinfo)
+                    .inspect(sql, "test.sql")
+                    .unwrap();
+                dbinfo
+            };
+            let (previous, current) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        let previous = "CREATE TABLE t (id INT, n INT NOT NULL DEFAULT 0);";
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                previous,
+                "CREATE TABLE t (id INT, n INT NOT NULL DEFAULT 1);"
+            ),
+            "ALTER TABLE t ALTER COLUMN n SET DEFAULT 1;\n"
+        );
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                previous,
+                "CREATE TABLE t (id INT, n INT NOT NULL);"
+            ),
+            "ALTER TABLE t ALTER COLUMN n DROP DEFAULT;\n"
+        );
+        // redundant parentheses aren't a change
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                previous,
+                "CREATE TABLE t (id INT, n INT NOT NULL DEFAULT (0));"
+            ),
+            ""
+        );
+        assert_eq!(
+            render(
+                Dialect::MySql,
+                previous,
+                "CREATE TABLE t (id INT, n INT NOT NULL DEFAULT 1);"
+            ),
+            "ALTER TABLE t ALTER COLUMN n SET DEFAULT 1;\n"
+        );
+        assert_eq!(
+            render(
+                Dialect::MySql,
+                previous,
+                "CREATE TABLE t (id INT, n INT NOT NULL DEFAULT (id + 1));"
+            ),
+            "ALTER TABLE t ALTER COLUMN n SET DEFAULT (id + 1);\n"
+        );
+        assert!(render(
+            Dialect::SQLite,
+            previous,
+            "CREATE TABLE t (id INT, n INT NOT NULL DEFAULT 1);"
+        )
+        .contains("INSERT INTO new_t"));
+    }
+
+    #[test]
+    fn test_raw_sql_placement() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect("CREATE TABLE users (id INT, name TEXT);");
+        let current = inspect("CREATE TABLE users (id INT, name TEXT NOT NULL);");
+        let mut migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+        migration.add_raw(RawPlacement::After, "ANALYZE users;\n");
+        migration.add_raw(
+            RawPlacement::Before,
+            "UPDATE users SET name = '' WHERE name IS NULL;",
+        );
+        migration.add_raw(RawPlacement::Before, "SELECT \"Refresh\"()");
+
+        assert_eq!(
+            Renderer::new(&options).render(&migration).unwrap(),
+            "UPDATE users SET name = '' WHERE name IS NULL;
+SELECT \"Refresh\"();
+ALTER TABLE users ALTER COLUMN name SET NOT NULL;
+ANALYZE users;
+"
+        );
+        assert_eq!(migration_name(&migration), "alter_users_name");
+    }
+
+    #[test]
+    fn test_sqlite_type_change_rebuilds_table() {
+        let options = Options::builder()
+            .dialect(Dialect::SQLite)
+            .database("test")
+            .build()
+            .unwrap();
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect("CREATE TABLE t (id INTEGER, price INTEGER, note TEXT);");
+        let current = inspect("CREATE TABLE t (id INTEGER, price REAL NOT NULL);");
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+
+        assert_eq!(
+            Renderer::new(&options).render(&migration).unwrap(),
+            "CREATE TABLE new_t (id INTEGER, price REAL NOT NULL);
+INSERT INTO new_t (id, price) SELECT id, price FROM t;
+DROP TABLE t;
+ALTER TABLE new_t RENAME TO t;
+"
+        );
+        assert_eq!(migration_name(&migration), "rebuild_t");
+    }
+
+    #[test]
+    fn test_mysql_column_reorder() {
+        let options = test_options(Dialect::MySql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        let previous = inspect("CREATE TABLE t (id INT, name TEXT NOT NULL, age INT);");
+        let current = inspect("CREATE TABLE t (age INT, id INT, name TEXT NOT NULL);");
+        let migration = MigrationGenerator::new(&previous, &current)
+            .generate()
+            .unwrap();
+
+        assert_eq!(
+            Renderer::new(&options).render(&migration).unwrap(),
+            "ALTER TABLE t MODIFY COLUMN age INT FIRST;\n"
+        );
+    }
+
+    #[test]
+    fn test_identifiers_are_quoted_only_when_necessary() {
+        let render = |dialect: Dialect, sql: &str| {
+            let options = test_options(dialect);
+            let empty = Dbinfo::with_options(options.clone());
+            let mut current = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut current)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            let migration = MigrationGenerator::new(&empty, &current)
+                .generate()
+                .unwrap();
+            Renderer::new(&options).render(&migration).unwrap()
+        };
+
+        assert_eq!(
+            render(
+                Dialect::PostgreSql,
+                r#"CREATE TABLE "order" (id INT, "user" TEXT, "createdAt" DATE, "first name" TEXT, note TEXT DEFAULT 'say "hi"');"#
+            ),
+            r#"CREATE TABLE "order" (id INT, "user" TEXT, "createdAt" DATE, "first name" TEXT, note TEXT DEFAULT 'say "hi"');"#.to_string()
+                + "\n"
+        );
+        assert_eq!(
+            render(Dialect::PostgreSql, "CREATE TABLE users (id INT);"),
+            "CREATE TABLE users (id INT);\n"
+        );
+        // MySQL doesn't fold the case of identifiers
+        assert_eq!(
+            render(
+                Dialect::MySql,
+                "CREATE TABLE `order` (id INT, createdAt DATE, `key` TEXT);"
+            ),
+            "CREATE TABLE `order` (id INT, createdAt DATE, `key` TEXT);\n"
+        );
+    }
+
+    #[test]
+    fn test_exclusion_constraints() {
+        let options = test_options(Dialect::PostgreSql);
+        let inspect = |sql: &str| {
+            let mut dbinfo = Dbinfo::with_options(options.clone());
+            Inspector::new(&mut dbinfo)
+                .inspect(sql, "test.sql")
+                .unwrap();
+            dbinfo
+        };
+        // the rendered migration applied to the previous schema gives the current one
+        let render = |previous: &str, current: &str| {
+            let (previous_dbinfo, current_dbinfo) = (inspect(previous), inspect(current));
+            let migration = MigrationGenerator::new(&previous_dbinfo, &current_dbinfo)
+                .generate()
+                .unwrap();
+            let rendered = Renderer::new(&options).render(&migration).unwrap();
+            assert_eq!(
+                inspect(&format!("{}\n{}", previous, rendered)),
+                current_dbinfo,
+                "{}",
+                rendered
+            );
+            rendered
+        };
+
+        let previous = "CREATE TABLE bookings (id INT, room INT, during TSRANGE);";
+        let current = "CREATE TABLE bookings (
+                           id INT,
+                           room INT,
+                           during TSRANGE,
+                           CONSTRAINT no_overlap EXCLUDE USING gist (room WITH =, during WITH &&)
+                       );";
+        assert_eq!(
+            render(previous, current),
+            "ALTER TABLE bookings ADD CONSTRAINT no_overlap EXCLUDE USING gist (room WITH =, during WITH &&);\n"
+        );
+        assert_eq!(
+            render(current, previous),
+            "ALTER TABLE bookings DROP CONSTRAINT no_overlap;\n"
+        );
+
+        // unnamed, declared after the table and with a predicate
+        let previous = "CREATE TABLE bookings (id INT, room INT);";
+        let current = "CREATE TABLE bookings (id INT, room INT);
+                       ALTER TABLE bookings ADD EXCLUDE (room WITH =) WHERE (id > 0);";
+        assert_eq!(
+            render(current, previous),
+            "ALTER TABLE bookings DROP CONSTRAINT bookings_room_excl;\n"
+        );
+        assert_eq!(
+            render("", current),
+            [
+                "CREATE TABLE bookings (id INT, room INT);",
+                "ALTER TABLE bookings ADD EXCLUDE (room WITH =) WHERE (id > 0);",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::Result;
+
+use crate::dbinfo::Dbinfo;
+use crate::inspector::Inspector;
+use crate::migrate::MigrationGenerator;
+use crate::render::Renderer;
+use crate::Options;
+
+// The outcome of inspecting a schema, rendering it back to SQL and inspecting
+// the rendered SQL again. Any difference between the two inspected schemas is
+// a fidelity bug of either the inspector or the renderer.
+pub struct RoundTrip {
+    pub inspected: Dbinfo,
+    pub sql: String,
+    pub reinspected: Dbinfo,
+}
+
+impl RoundTrip {
+    // Schemas are compared by their normalized json, so that only differences
+    // which matter to the database are reported.
+    pub fn is_faithful(&self) -> bool {
+        self.inspected.differing_paths(&self.reinspected).is_empty()
+    }
+
+    // The lines of the inspected schema missing from the reinspected one
+    // prefixed with `-`, the added ones with `+`. Falls back to their json
+    // when the schemas only differ by something their tree doesn't show.
+    pub fn diff(&self) -> String {
+        let (inspected, reinspected) = (self.inspected.to_string(), self.reinspected.to_string());
+        let (inspected, reinspected) = if inspected == reinspected {
+            (
+                serde_json::to_string_pretty(&self.inspected.normalized_json()).unwrap(),
+                serde_json::to_string_pretty(&self.reinspected.normalized_json()).unwrap(),
+            )
+        } else {
+            (inspected, reinspected)
+        };
+
+        let mut lines = vec![];
+        for line in diff::lines(&inspected, &reinspected) {
+            match line {
+                diff::Result::Left(l) => lines.push(format!("-{}", l)),
+                diff::Result::Right(r) => lines.push(format!("+{}", r)),
+                diff::Result::Both(..) => {}
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn inspect(options: &Options, sql: &str, filename: &str) -> Result<Dbinfo> {
+    let mut dbinfo = Dbinfo::with_options(options.clone());
+    let mut inspector = Inspector::from_options(&mut dbinfo, options);
+    inspector.inspect(sql, filename)?;
+    for unsupported in inspector.unsupported_statements() {
+        log::warn!("{}", unsupported);
+    }
+    Ok(dbinfo)
+}
+
+// Inspects `sql`, renders the baseline creating the inspected schema and
+// inspects that baseline again.
+pub fn round_trip(options: &Options, sql: &str, filename: &str) -> Result<RoundTrip> {
+    let inspected = inspect(options, sql, filename)?;
+
+    let empty = Dbinfo::with_options(options.clone());
+    let migration = MigrationGenerator::new(&empty, &inspected)
+        .with_include_schemas(options.include_schemas.clone())
+        .with_baseline(true)
+        .generate()?;
+    let sql = Renderer::new(options).render(&migration)?;
+
+    let reinspected = inspect(options, &sql, &format!("{} (rendered)", filename))
+        .map_err(|e| anyhow::anyhow!("the rendered schema can't be inspected: {}", e))?;
+    Ok(RoundTrip {
+        inspected,
+        sql,
+        reinspected,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbinfo::Table;
+    use crate::test_support::test_options;
+    use crate::Dialect;
+
+    fn options(dialect: Dialect) -> Options {
+        Options {
+            strict: true,
+            ..test_options(dialect)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sql = r#"
+            CREATE SCHEMA app;
+            CREATE DOMAIN app.email AS TEXT CHECK (VALUE LIKE '%@%');
+            CREATE TABLE app.users (
+                id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+                email app.email NOT NULL UNIQUE,
+                created_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            CREATE TABLE app.posts (
+                id SERIAL PRIMARY KEY,
+                author_id BIGINT NOT NULL REFERENCES app.users (id) ON DELETE CASCADE,
+                title VARCHAR(200) NOT NULL,
+                body TEXT
+            );
+            CREATE INDEX posts_author ON app.posts (author_id, id DESC);
+            COMMENT ON TABLE app.posts IS 'Blog posts';
+            CREATE VIEW app.titles AS SELECT id, title FROM app.posts;
+            CREATE POLICY posts_author ON app.posts FOR UPDATE TO app_user
+                USING (author_id = 1);
+        "#;
+        let result = round_trip(&options(Dialect::PostgreSql), sql, "schema.sql").unwrap();
+        assert!(result.is_faithful(), "{}", result.diff());
+        assert_eq!(result.diff(), "");
+        assert_eq!(result.inspected.stats().tables, 2);
+
+        let sql = "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);";
+        let result = round_trip(&options(Dialect::SQLite), sql, "schema.sql").unwrap();
+        assert!(result.is_faithful(), "{}", result.diff());
+    }
+
+    #[test]
+    fn test_diff() {
+        let sql = "CREATE TABLE users (id INT);";
+        let mut result = round_trip(&options(Dialect::PostgreSql), sql, "schema.sql").unwrap();
+        result
+            .reinspected
+            .get_catalog_mut("test")
+            .unwrap()
+            .schemas
+            .get_mut("public")
+            .unwrap()
+            .tables
+            .insert("accounts".to_string(), Table::new("accounts"));
+        assert!(!result.is_faithful());
+        assert_eq!(result.diff(), "+    table accounts");
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_derive::Serialize;
+use serde_json::Value;
+
+use crate::dbinfo::Dbinfo;
+use crate::Options;
+
+pub const DEFAULT_PATH: &str = ".migi/snapshot.json";
+
+// The version of the snapshot format written by this migi. Version 1 is a bare
+// Dbinfo, later versions wrap it as `{ "version": N, "dbinfo": ... }`.
+pub const VERSION: u64 = 2;
+
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    version: u64,
+    dbinfo: &'a Dbinfo,
+}
+
+// Loads the schema the last migration was generated from, which is empty until
+// a migration has been written.
+pub fn load(path: &Path, options: &Options) -> Result<Dbinfo> {
+    if !path.exists() {
+        return Ok(Dbinfo::with_options(options.clone()));
+    }
+
+    let json = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("cannot read snapshot {}: {}", path.display(), e))?;
+    let invalid = |e: String| {
+        anyhow::anyhow!(
+            "invalid snapshot {}: {}, restore it from version control or remove it to diff against an empty schema",
+            path.display(),
+            e
+        )
+    };
+
+    let (version, dbinfo) = match serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))? {
+        Value::Object(mut snapshot) if snapshot.contains_key("version") => {
+            let version = snapshot
+                .get("version")
+                .and_then(Value::as_u64)
+                .filter(|version| *version >= 1)
+                .ok_or_else(|| invalid("version is not a positive number".to_string()))?;
+            let dbinfo = snapshot.remove("dbinfo").unwrap_or(Value::Null);
+            (version, dbinfo)
+        }
+        dbinfo => (1, dbinfo),
+    };
+    if version > VERSION {
+        anyhow::bail!(
+            "snapshot {} has version {} but migi {} only reads up to version {}, upgrade migi to use it",
+            path.display(),
+            version,
+            env!("CARGO_PKG_VERSION"),
+            VERSION
+        );
+    }
+
+    serde_json::from_value(upgrade(version, dbinfo))
+        .map_err(|e| invalid(format!("version {}, {}", version, e)))
+}
+
+// Brings the Dbinfo of an older snapshot to the current format, one version at
+// a time.
+fn upgrade(mut version: u64, dbinfo: Value) -> Value {
+    while version < VERSION {
+        match version {
+            // Only the envelope changed, fields added to Dbinfo since then
+            // default when missing.
+            1 => {}
+            _ => unreachable!("unknown snapshot version {}", version),
+        }
+        version += 1;
+    }
+    dbinfo
+}
+
+pub fn save(path: &Path, dbinfo: &Dbinfo) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let snapshot = Snapshot {
+        version: VERSION,
+        dbinfo,
+    };
+    fs::write(path, serde_json::to_string_pretty(&snapshot)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspector::Inspector;
+    use crate::test_support::test_options;
+    use crate::Dialect;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let options = test_options(Dialect::PostgreSql);
+        let dir = std::env::temp_dir().join(format!("migi-snapshot-{}", std::process::id()));
+        let path = dir.join("snapshot.json");
+
+        let empty = load(&path, &options).unwrap();
+        assert_eq!(empty, Dbinfo::with_options(options.clone()));
+
+        let mut dbinfo = Dbinfo::with_options(options.clone());
+        Inspector::new(&mut dbinfo)
+            .inspect(
+                "CREATE TABLE users (id SERIAL PRIMARY KEY, email TEXT NOT NULL DEFAULT '');",
+                "test.sql",
+            )
+            .unwrap();
+        save(&path, &dbinfo).unwrap();
+        let loaded = load(&path, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded, dbinfo);
+    }
+
+    #[test]
+    fn test_load_version_1_snapshot() {
+        let options = test_options(Dialect::PostgreSql);
+        let dir = std::env::temp_dir().join(format!("migi-v1-{}", std::process::id()));
+        let path = dir.join("snapshot.json");
+        fs::create_dir_all(&dir).unwrap();
+        // a bare Dbinfo, from before tables had triggers
+        fs::write(
+            &path,
+            r#"{
+  "dialect": "postgresql",
+  "default_catalog": "test",
+  "catalogs": {
+    "test": {
+      "name": "test",
+      "default_schema": "public",
+      "schemas": {
+        "public": {
+          "name": "public",
+          "tables": {
+            "t": {
+              "name": "t",
+              "columns": [
+                {
+                  "name": "id",
+                  "data_type": { "Int": null },
+                  "collation": null,
+                  "options": [],
+                  "identity": null
+                }
+              ],
+              "constraints": [],
+              "indexes": [],
+              "with_options": [],
+              "without_rowid": false,
+              "engine": null,
+              "comment": null,
+              "auto_increment_offset": null,
+              "default_charset": null,
+              "collation": null,
+              "on_commit": null,
+              "order_by": null,
+              "partition_by": null,
+              "options": null,
+              "strict": false
+            }
+          },
+          "views": {},
+          "domains": {},
+          "routines": {}
+        }
+      }
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        let loaded = load(&path, &options).unwrap();
+        save(&path, &loaded).unwrap();
+        let resaved = fs::read_to_string(&path).unwrap();
+        let reloaded = load(&path, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut expected = Dbinfo::with_options(options);
+        Inspector::new(&mut expected)
+            .inspect("CREATE TABLE t (id INT);", "test.sql")
+            .unwrap();
+        assert_eq!(loaded, expected);
+        assert!(resaved.starts_with("{\n  \"version\": 2,\n  \"dbinfo\": {"));
+        assert_eq!(reloaded, expected);
+    }
+
+    #[test]
+    fn test_load_corrupt_snapshot() {
+        let options = test_options(Dialect::PostgreSql);
+        let dir = std::env::temp_dir().join(format!("migi-corrupt-{}", std::process::id()));
+        let path = dir.join("nested").join("snapshot.json");
+        save(&path, &Dbinfo::with_options(options.clone())).unwrap();
+        fs::write(&path, r#"{"dialect": "postgresql", "catalogs": "#).unwrap();
+        let corrupt = load(&path, &options).unwrap_err().to_string();
+        fs::write(&path, r#"{"version": 99, "dbinfo": {}}"#).unwrap();
+        let newer = load(&path, &options).unwrap_err().to_string();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(corrupt.starts_with(&format!("invalid snapshot {}: EOF", path.display())));
+        assert_eq!(
+            newer,
+            format!(
+                "snapshot {} has version 99 but migi {} only reads up to version 2, upgrade migi to use it",
+                path.display(),
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
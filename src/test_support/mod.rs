@@ -0,0 +1,187 @@
+use sqlparser::ast::{ColumnOption, ColumnOptionDef, DataType, TableConstraint};
+
+use crate::dbinfo::{Catalog, Column, Dbinfo, Identity, IdentityKind, Schema, Table};
+use crate::{Dialect, Options};
+
+// The options of the tests: a `test` database whose tables belong to `public`
+// whatever the dialect, so that expected names read the same across dialects.
+pub fn test_options(dialect: Dialect) -> Options {
+    Options::builder()
+        .dialect(dialect)
+        .database("test")
+        .default_schema("public")
+        .build()
+        .unwrap()
+}
+
+// Builds a Dbinfo without writing SQL, e.g.
+// DbinfoBuilder::new(Dialect::PostgreSql, "test").schema("app").table("users").column("id", DataType::Int(None)).build()
+//
+// Tables are added to the last given schema (the default schema at first) and
+// columns, constraints and options to the last given table.
+pub struct DbinfoBuilder {
+    dbinfo: Dbinfo,
+    schema: String,
+    table: Option<String>,
+}
+
+impl DbinfoBuilder {
+    pub fn new(dialect: Dialect, database: &str) -> Self {
+        let default_schema = dialect.default_schema(database);
+        Self {
+            dbinfo: Dbinfo {
+                dialect,
+                default_catalog: database.into(),
+                catalogs: [(database.into(), Catalog::new(database, &default_schema))].into(),
+            },
+            schema: default_schema,
+            table: None,
+        }
+    }
+
+    pub fn schema(mut self, name: &str) -> Self {
+        self.default_catalog_mut()
+            .schemas
+            .entry(name.into())
+            .or_insert_with(|| Schema::new(name));
+        self.schema = name.into();
+        self.table = None;
+        self
+    }
+
+    pub fn table(mut self, name: &str) -> Self {
+        let schema = self.schema_mut();
+        schema.tables.insert(name.into(), Table::new(name));
+        self.table = Some(name.into());
+        self
+    }
+
+    pub fn column(self, name: &str, data_type: DataType) -> Self {
+        self.with_table(|table| {
+            table.columns.push(Column {
+                name: name.into(),
+                data_type,
+                collation: None,
+                options: vec![],
+                identity: None,
+            })
+        })
+    }
+
+    // Adds an option to the last column.
+    pub fn option(self, option: ColumnOption) -> Self {
+        self.with_column(|column| {
+            column.options.push(ColumnOptionDef { name: None, option });
+            column.normalize_options();
+        })
+    }
+
+    pub fn not_null(self) -> Self {
+        self.option(ColumnOption::NotNull)
+    }
+
+    pub fn identity(self, kind: IdentityKind) -> Self {
+        self.with_column(|column| {
+            column.identity = Some(Identity {
+                kind,
+                sequence_options: vec![],
+            })
+        })
+    }
+
+    pub fn constraint(self, constraint: TableConstraint) -> Self {
+        self.with_table(|table| table.constraints.push(constraint))
+    }
+
+    // Gives access to the fields of the last table the builder has no method for.
+    pub fn with_table(mut self, f: impl FnOnce(&mut Table)) -> Self {
+        let name = self
+            .table
+            .clone()
+            .expect("table() must be called before adding to a table");
+        f(self.schema_mut().tables.get_mut(&name).unwrap());
+        self
+    }
+
+    pub fn with_column(self, f: impl FnOnce(&mut Column)) -> Self {
+        self.with_table(|table| {
+            f(table
+                .columns
+                .last_mut()
+                .expect("column() must be called before adding to a column"))
+        })
+    }
+
+    pub fn build(self) -> Dbinfo {
+        self.dbinfo
+    }
+
+    fn default_catalog_mut(&mut self) -> &mut Catalog {
+        self.dbinfo
+            .catalogs
+            .get_mut(&self.dbinfo.default_catalog)
+            .unwrap()
+    }
+
+    fn schema_mut(&mut self) -> &mut Schema {
+        let schema = self.schema.clone();
+        self.default_catalog_mut().schemas.get_mut(&schema).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inspector::Inspector;
+    use crate::migrate::MigrationGenerator;
+    use crate::Options;
+
+    use super::*;
+
+    fn inspect(dialect: Dialect, sql: &str) -> Dbinfo {
+        let options = Options::builder()
+            .dialect(dialect)
+            .database("test")
+            .build()
+            .unwrap();
+        let mut dbinfo = Dbinfo::with_options(options);
+        Inspector::new(&mut dbinfo)
+            .inspect(sql, "test.sql")
+            .unwrap();
+        dbinfo
+    }
+
+    #[test]
+    fn test_builder_matches_inspected_schema() {
+        let inspected = inspect(
+            Dialect::PostgreSql,
+            "CREATE SCHEMA app;
+             CREATE TABLE app.users (id INT GENERATED ALWAYS AS IDENTITY NOT NULL, name TEXT);",
+        );
+        let built = DbinfoBuilder::new(Dialect::PostgreSql, "test")
+            .schema("app")
+            .table("users")
+            .column("id", DataType::Int(None))
+            .not_null()
+            .identity(IdentityKind::Always)
+            .column("name", DataType::Text)
+            .build();
+
+        assert_eq!(built, inspected);
+        let migration = MigrationGenerator::new(&inspected, &built)
+            .generate()
+            .unwrap();
+        assert!(migration.operations.is_empty());
+
+        // Tables go to the database in MySQL and to `main` in SQLite.
+        for dialect in [Dialect::MySql, Dialect::SQLite] {
+            let inspected = inspect(dialect, "CREATE TABLE users (id INT NOT NULL, name TEXT);");
+            let built = DbinfoBuilder::new(dialect, "test")
+                .table("users")
+                .column("id", DataType::Int(None))
+                .not_null()
+                .column("name", DataType::Text)
+                .build();
+            assert_eq!(built, inspected, "{:?}", dialect);
+        }
+    }
+}
@@ -0,0 +1,662 @@
+use sqlparser::ast::{
+    CharacterLength, ColumnOption, ColumnOptionDef, DataType, ExactNumberInfo, Expr, Function,
+    FunctionArguments, ObjectName, TableConstraint, TimezoneInfo,
+};
+
+use crate::dbinfo::{Catalog, Column, Dbinfo, Domain, Schema, Table};
+use crate::render::requote;
+use crate::{Dialect, Options};
+
+// Rewrites a schema inspected in one dialect for another, e.g. a Postgres
+// schema also deployed to SQLite for local development. The translation is
+// best effort: what the target can't express is approximated or dropped, with
+// a warning for each. `options` are those of the target.
+pub fn translate(dbinfo: &Dbinfo, options: &Options) -> (Dbinfo, Vec<String>) {
+    let target = options.dialect;
+    let mut translated = dbinfo.clone();
+    translated.dialect = target;
+    let mut warnings = vec![];
+    if dbinfo.dialect == target {
+        return (translated, warnings);
+    }
+
+    let source = dbinfo.dialect;
+    let mut catalogs: Vec<&mut Catalog> = translated.catalogs.values_mut().collect();
+    catalogs.sort_by(|a, b| a.name.cmp(&b.name));
+    for catalog in catalogs {
+        if target != Dialect::PostgreSql {
+            fold_schemas(catalog, target, &mut warnings);
+        }
+        let mut schemas: Vec<&mut Schema> = catalog.schemas.values_mut().collect();
+        schemas.sort_by(|a, b| a.name.cmp(&b.name));
+        for schema in schemas {
+            translate_schema(schema, source, target, &mut warnings);
+        }
+        let default_schema = if catalog.name == dbinfo.default_catalog {
+            options.default_schema.clone()
+        } else {
+            target.default_schema(&catalog.name)
+        };
+        rename_default_schema(catalog, &default_schema, &mut warnings);
+    }
+    (requote(&translated, target), warnings)
+}
+
+// Only Postgres has schemas besides the default one, in MySQL they would be
+// other databases. Their objects are moved to the default schema, unless one
+// of the same name is already there.
+fn fold_schemas(catalog: &mut Catalog, target: Dialect, warnings: &mut Vec<String>) {
+    let mut names: Vec<String> = catalog
+        .schemas
+        .keys()
+        .filter(|name| **name != catalog.default_schema)
+        .cloned()
+        .collect();
+    names.sort();
+    for name in names {
+        let schema = catalog.schemas.remove(&name).unwrap();
+        let default_name = catalog.default_schema.clone();
+        let default = catalog
+            .schemas
+            .entry(default_name.clone())
+            .or_insert_with(|| Schema::new(&default_name));
+        warnings.push(format!(
+            "{} has no schemas, the objects of schema {} are created in {}",
+            target, name, default_name
+        ));
+
+        let mut dropped = |kind: &str, object: &str| {
+            warnings.push(format!(
+                "{} {}.{} is dropped, another object is named {} in {}",
+                kind, name, object, object, default_name
+            ))
+        };
+        let mut tables: Vec<(String, Table)> = schema.tables.into_iter().collect();
+        tables.sort_by(|a, b| a.0.cmp(&b.0));
+        for (table_name, table) in tables {
+            if default.tables.contains_key(&table_name) || default.views.contains_key(&table_name) {
+                dropped("table", &table_name);
+                continue;
+            }
+            default.tables.insert(table_name, table);
+        }
+        let mut views: Vec<_> = schema.views.into_iter().collect();
+        views.sort_by(|a, b| a.0.cmp(&b.0));
+        for (view_name, view) in views {
+            if default.tables.contains_key(&view_name) || default.views.contains_key(&view_name) {
+                dropped("view", &view_name);
+                continue;
+            }
+            default.views.insert(view_name, view);
+        }
+        let mut domains: Vec<_> = schema.domains.into_iter().collect();
+        domains.sort_by(|a, b| a.0.cmp(&b.0));
+        for (domain_name, domain) in domains {
+            if default.domains.contains_key(&domain_name) {
+                dropped("domain", &domain_name);
+                continue;
+            }
+            default.domains.insert(domain_name, domain);
+        }
+        let mut routines: Vec<_> = schema.routines.into_iter().collect();
+        routines.sort_by(|a, b| a.0.cmp(&b.0));
+        for (signature, routine) in routines {
+            if default.routines.contains_key(&signature) {
+                dropped("function", &signature);
+                continue;
+            }
+            default.routines.insert(signature, routine);
+        }
+    }
+}
+
+// The default schema of the source, e.g. `public`, is renamed after the one of
+// the target, e.g. the database in MySQL.
+fn rename_default_schema(catalog: &mut Catalog, name: &str, warnings: &mut Vec<String>) {
+    if catalog.default_schema == name {
+        return;
+    }
+    if catalog.schemas.contains_key(name) {
+        warnings.push(format!(
+            "schema {} is kept as the default schema of {}, {} already exists",
+            catalog.default_schema, catalog.name, name
+        ));
+        return;
+    }
+    if let Some(mut schema) = catalog.schemas.remove(&catalog.default_schema) {
+        schema.name = name.to_string();
+        catalog.schemas.insert(name.to_string(), schema);
+    }
+    catalog.default_schema = name.to_string();
+}
+
+fn translate_schema(
+    schema: &mut Schema,
+    source: Dialect,
+    target: Dialect,
+    warnings: &mut Vec<String>,
+) {
+    let domains = if target == Dialect::PostgreSql {
+        vec![]
+    } else {
+        let mut domains: Vec<Domain> = schema.domains.drain().map(|(_, d)| d).collect();
+        domains.sort_by(|a, b| a.name.cmp(&b.name));
+        for domain in &domains {
+            warnings.push(format!(
+                "{} has no domains, the columns of domain {}.{} use its base type {}",
+                target, schema.name, domain.name, domain.data_type
+            ));
+        }
+        domains
+    };
+
+    // The bodies of functions are written in the language of the source.
+    let mut routines: Vec<&String> = schema.routines.keys().collect();
+    routines.sort();
+    for routine in routines {
+        warnings.push(format!(
+            "function {}.{} is written for {} and is not translated",
+            schema.name, routine, source
+        ));
+    }
+    schema.routines.clear();
+
+    if target != Dialect::PostgreSql {
+        let mut materialized: Vec<String> = schema
+            .views
+            .iter()
+            .filter(|(_, view)| view.materialized)
+            .map(|(name, _)| name.clone())
+            .collect();
+        materialized.sort();
+        for name in materialized {
+            warnings.push(format!(
+                "{} has no materialized views, {}.{} is dropped",
+                target, schema.name, name
+            ));
+            schema.views.remove(&name);
+        }
+    }
+
+    let mut tables: Vec<&mut Table> = schema.tables.values_mut().collect();
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    for table in tables {
+        let name = format!("{}.{}", schema.name, table.name);
+        translate_table(&name, table, &domains, source, target, warnings);
+    }
+}
+
+fn translate_table(
+    name: &str,
+    table: &mut Table,
+    domains: &[Domain],
+    source: Dialect,
+    target: Dialect,
+    warnings: &mut Vec<String>,
+) {
+    if !table.triggers.is_empty() {
+        warnings.push(format!(
+            "triggers of table {} are written for {} and are not translated",
+            name, source
+        ));
+        table.triggers.clear();
+    }
+    if target != Dialect::PostgreSql {
+        if !table.policies.is_empty() {
+            warnings.push(format!(
+                "{} has no row-level security, the policies of table {} are dropped",
+                target, name
+            ));
+            table.policies.clear();
+        }
+        if !table.exclusion_constraints.is_empty() {
+            warnings.push(format!(
+                "{} has no EXCLUDE constraints, those of table {} are dropped",
+                target, name
+            ));
+            table.exclusion_constraints.clear();
+        }
+    }
+
+    // Storage parameters, engines, partitioning and the like only mean
+    // something to the source.
+    let defaults = Table::new(&table.name);
+    let options_of = |t: &Table| {
+        (
+            t.with_options.clone(),
+            t.engine.clone(),
+            t.default_charset.clone(),
+            t.collation.clone(),
+            t.auto_increment_offset,
+            t.on_commit,
+            t.order_by.clone(),
+            t.partition_by.clone(),
+            t.options.clone(),
+        )
+    };
+    if options_of(table) != options_of(&defaults)
+        || (target != Dialect::SQLite && (table.strict || table.without_rowid))
+    {
+        warnings.push(format!(
+            "options of table {} are specific to {} and are dropped",
+            name, source
+        ));
+        table.with_options = defaults.with_options;
+        table.engine = defaults.engine;
+        table.default_charset = defaults.default_charset;
+        table.collation = defaults.collation;
+        table.auto_increment_offset = defaults.auto_increment_offset;
+        table.on_commit = defaults.on_commit;
+        table.order_by = defaults.order_by;
+        table.partition_by = defaults.partition_by;
+        table.options = defaults.options;
+        if target != Dialect::SQLite {
+            table.strict = false;
+            table.without_rowid = false;
+        }
+    }
+
+    let primary_key = single_primary_key(table);
+    for column in &mut table.columns {
+        translate_column(
+            name,
+            column,
+            primary_key.as_deref(),
+            domains,
+            source,
+            target,
+            warnings,
+        );
+    }
+
+    // The schemas were folded into the default one.
+    if target != Dialect::PostgreSql {
+        for constraint in &mut table.constraints {
+            if let TableConstraint::ForeignKey { foreign_table, .. } = constraint {
+                unqualify(foreign_table);
+            }
+        }
+        for column in &mut table.columns {
+            for option in &mut column.options {
+                if let ColumnOption::ForeignKey { foreign_table, .. } = &mut option.option {
+                    unqualify(foreign_table);
+                }
+            }
+        }
+    }
+}
+
+fn translate_column(
+    table_name: &str,
+    column: &mut Column,
+    primary_key: Option<&str>,
+    domains: &[Domain],
+    source: Dialect,
+    target: Dialect,
+    warnings: &mut Vec<String>,
+) {
+    let has_option =
+        |column: &Column, f: fn(&ColumnOption) -> bool| column.options.iter().any(|o| f(&o.option));
+
+    // A column of a domain gets the domain's type, NOT NULL and default.
+    let domain = match &column.data_type {
+        DataType::Custom(name, modifiers) if modifiers.is_empty() => name
+            .0
+            .last()
+            .and_then(|n| domains.iter().find(|d| d.name == n.value)),
+        _ => None,
+    };
+    if let Some(domain) = domain {
+        column.data_type = domain.data_type.clone();
+        if domain.not_null && !has_option(column, |o| matches!(o, ColumnOption::NotNull)) {
+            column.options.push(ColumnOptionDef {
+                name: None,
+                option: ColumnOption::NotNull,
+            });
+        }
+        if let Some(default) = &domain.default {
+            if !has_option(column, |o| matches!(o, ColumnOption::Default(_))) {
+                column.options.push(ColumnOptionDef {
+                    name: None,
+                    option: ColumnOption::Default(default.clone()),
+                });
+            }
+        }
+    }
+
+    match translate_data_type(&column.data_type, source, target) {
+        Some(data_type) => column.data_type = data_type,
+        None => {
+            warnings.push(format!(
+                "type {} of column {}.{} has no equivalent in {}, TEXT is used",
+                column.data_type, table_name, column.name, target
+            ));
+            column.data_type = DataType::Text;
+        }
+    }
+
+    if let Some(collation) = column.collation.take() {
+        warnings.push(format!(
+            "collation {} of column {}.{} is specific to {} and is dropped",
+            collation, table_name, column.name, source
+        ));
+    }
+
+    for option in &mut column.options {
+        if let ColumnOption::Default(expr) = &mut option.option {
+            translate_default(expr, target);
+        }
+    }
+
+    if let Some(identity) = &mut column.identity {
+        if target != Dialect::PostgreSql && !identity.sequence_options.is_empty() {
+            warnings.push(format!(
+                "{} has no sequences, the sequence options of column {}.{} are dropped",
+                target, table_name, column.name
+            ));
+            identity.sequence_options.clear();
+        }
+        // Only a rowid alias can be AUTOINCREMENT.
+        if target == Dialect::SQLite {
+            if primary_key == Some(column.name.as_str()) {
+                column.data_type = DataType::Integer(None);
+            } else {
+                warnings.push(format!(
+                    "SQLite only generates values for an INTEGER PRIMARY KEY, column {}.{} is no longer generated",
+                    table_name, column.name
+                ));
+                column.identity = None;
+            }
+        }
+    }
+
+    column.normalize_options();
+}
+
+// The type closest to `data_type` in the target dialect, None when there is
+// none worth the name.
+pub fn translate_data_type(
+    data_type: &DataType,
+    source: Dialect,
+    target: Dialect,
+) -> Option<DataType> {
+    use DataType::*;
+
+    let custom = match data_type {
+        Custom(name, _) => name.0.last().map(|n| n.value.to_lowercase()),
+        _ => None,
+    };
+    let translated = match (target, data_type) {
+        (_, Custom(..)) => match (target, custom.as_deref()) {
+            (_, Some("citext" | "tinytext" | "mediumtext" | "longtext")) => Text,
+            (Dialect::PostgreSql, Some("tinyblob" | "mediumblob" | "longblob")) => Bytea,
+            (_, Some("tinyblob" | "mediumblob" | "longblob")) => Blob(None),
+            _ => return None,
+        },
+        (_, Unspecified | Array(_) | Struct(_) | Interval | Regclass) => return None,
+
+        // SQLite only has INTEGER, REAL, TEXT, BLOB and NUMERIC affinities.
+        (
+            Dialect::SQLite,
+            TinyInt(_) | UnsignedTinyInt(_) | Int2(_) | UnsignedInt2(_) | SmallInt(_)
+            | UnsignedSmallInt(_) | MediumInt(_) | UnsignedMediumInt(_) | Int(_) | Int4(_) | Int64
+            | Integer(_) | UnsignedInt(_) | UnsignedInt4(_) | UnsignedInteger(_) | BigInt(_)
+            | UnsignedBigInt(_) | Int8(_) | UnsignedInt8(_) | Bool | Boolean,
+        ) => Integer(None),
+        (
+            Dialect::SQLite,
+            Float(_) | Float4 | Float64 | Real | Float8 | Double | DoublePrecision,
+        ) => Real,
+        (
+            Dialect::SQLite,
+            Uuid
+            | CharacterLargeObject(_)
+            | CharLargeObject(_)
+            | Clob(_)
+            | String(_)
+            | JSON
+            | JSONB
+            | Date
+            | Time(..)
+            | Datetime(_)
+            | Timestamp(..),
+        ) => Text,
+        (Dialect::SQLite, Binary(_) | Varbinary(_) | Bytes(_) | Bytea) => Blob(None),
+        (Dialect::SQLite, Enum(_) | Set(_)) => return None,
+
+        (Dialect::MySql | Dialect::MariaDb, Int2(width)) => SmallInt(*width),
+        (Dialect::MySql | Dialect::MariaDb, Int4(width) | Integer(width)) => Int(*width),
+        (Dialect::MySql | Dialect::MariaDb, Int8(width)) => BigInt(*width),
+        (Dialect::MySql | Dialect::MariaDb, Int64) => BigInt(None),
+        (Dialect::MySql | Dialect::MariaDb, Float4) => Float(None),
+        (Dialect::MySql | Dialect::MariaDb, Float8 | Float64 | DoublePrecision) => Double,
+        // VARCHAR needs a length in MySQL.
+        (
+            Dialect::MySql | Dialect::MariaDb,
+            Varchar(None) | CharacterVarying(None) | CharVarying(None),
+        ) => Text,
+        (Dialect::MySql | Dialect::MariaDb, Uuid) => Char(Some(CharacterLength::IntegerLength {
+            length: 36,
+            unit: None,
+        })),
+        (Dialect::MySql | Dialect::MariaDb, JSONB) => JSON,
+        (Dialect::MySql | Dialect::MariaDb, Bytea | Bytes(_)) => Blob(None),
+        // A Postgres TIMESTAMP has no time zone, like a DATETIME. MySQL's
+        // TIMESTAMP is converted to UTC, like a TIMESTAMPTZ.
+        (Dialect::MySql | Dialect::MariaDb, Timestamp(precision, TimezoneInfo::None))
+            if source == Dialect::PostgreSql =>
+        {
+            Datetime(*precision)
+        }
+        (
+            Dialect::MySql | Dialect::MariaDb,
+            Timestamp(precision, TimezoneInfo::WithoutTimeZone),
+        ) => Datetime(*precision),
+        (
+            Dialect::MySql | Dialect::MariaDb,
+            Timestamp(precision, TimezoneInfo::WithTimeZone | TimezoneInfo::Tz),
+        ) => Timestamp(*precision, TimezoneInfo::None),
+        (Dialect::MySql | Dialect::MariaDb, Time(precision, _)) => {
+            Time(*precision, TimezoneInfo::None)
+        }
+
+        (Dialect::PostgreSql, TinyInt(_) | UnsignedTinyInt(_)) => SmallInt(None),
+        (
+            Dialect::PostgreSql,
+            MediumInt(_) | UnsignedSmallInt(_) | UnsignedInt2(_) | UnsignedMediumInt(_),
+        ) => Int(None),
+        (Dialect::PostgreSql, UnsignedInt(_) | UnsignedInt4(_) | UnsignedInteger(_) | Int64) => {
+            BigInt(None)
+        }
+        (Dialect::PostgreSql, UnsignedBigInt(_) | UnsignedInt8(_)) => {
+            Numeric(ExactNumberInfo::Precision(20))
+        }
+        (Dialect::PostgreSql, Double | Float64) => DoublePrecision,
+        (Dialect::PostgreSql, Datetime(precision)) => Timestamp(*precision, TimezoneInfo::None),
+        (Dialect::PostgreSql, Blob(_) | Binary(_) | Varbinary(_) | Bytes(_)) => Bytea,
+        (Dialect::PostgreSql, Clob(_) | CharacterLargeObject(_) | CharLargeObject(_)) => Text,
+        (Dialect::PostgreSql, Enum(_) | Set(_)) => return None,
+
+        (_, data_type) => data_type.clone(),
+    };
+    Some(translated)
+}
+
+// `now()` is CURRENT_TIMESTAMP outside of Postgres and MySQL.
+fn translate_default(expr: &mut Expr, target: Dialect) {
+    let Expr::Function(function) = expr else {
+        return;
+    };
+    let is_now = function
+        .name
+        .0
+        .last()
+        .is_some_and(|n| n.value.eq_ignore_ascii_case("now"));
+    if target == Dialect::SQLite && is_now {
+        *expr = Expr::Function(Function {
+            name: ObjectName(vec!["CURRENT_TIMESTAMP".into()]),
+            args: FunctionArguments::None,
+            filter: None,
+            null_treatment: None,
+            over: None,
+            within_group: vec![],
+        });
+    }
+}
+
+// The column making up the primary key of the table on its own, if any.
+fn single_primary_key(table: &Table) -> Option<String> {
+    for constraint in &table.constraints {
+        if let TableConstraint::PrimaryKey { columns, .. } = constraint {
+            return match columns.as_slice() {
+                [column] => Some(column.value.clone()),
+                _ => None,
+            };
+        }
+    }
+    table
+        .columns
+        .iter()
+        .find(|c| {
+            c.options.iter().any(|o| {
+                matches!(
+                    o.option,
+                    ColumnOption::Unique {
+                        is_primary: true,
+                        ..
+                    }
+                )
+            })
+        })
+        .map(|c| c.name.clone())
+}
+
+fn unqualify(name: &mut ObjectName) {
+    if name.0.len() > 1 {
+        name.0.drain(..name.0.len() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspector::Inspector;
+    use crate::migrate::MigrationGenerator;
+    use crate::render::Renderer;
+    use crate::test_support::test_options;
+
+    // Renders the baseline of the Postgres schema in the target dialect, whose
+    // default schema is `main` in SQLite and the database in MySQL.
+    fn render(sql: &str, target: Dialect) -> (String, Vec<String>) {
+        let mut dbinfo = Dbinfo::with_options(test_options(Dialect::PostgreSql));
+        Inspector::new(&mut dbinfo)
+            .inspect(sql, "test.sql")
+            .unwrap();
+        let options = Options::builder()
+            .dialect(target)
+            .database("test")
+            .build()
+            .unwrap();
+        let (translated, warnings) = translate(&dbinfo, &options);
+        assert_eq!(
+            translated.get_catalog("test").unwrap().default_schema,
+            target.default_schema("test")
+        );
+        let empty = Dbinfo::with_options(options.clone());
+        let migration = MigrationGenerator::new(&empty, &translated)
+            .with_baseline(true)
+            .generate()
+            .unwrap();
+        let sql = Renderer::new(&options).render(&migration).unwrap();
+        (sql, warnings)
+    }
+
+    #[test]
+    fn test_postgres_to_sqlite() {
+        let (sql, warnings) = render(
+            r#"
+            CREATE TABLE users (
+                id SERIAL PRIMARY KEY,
+                email VARCHAR(200) NOT NULL,
+                admin BOOLEAN NOT NULL DEFAULT false,
+                settings JSONB,
+                tags TEXT[],
+                created_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            "#,
+            Dialect::SQLite,
+        );
+        assert_eq!(
+            sql,
+            [
+                "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, email VARCHAR(200) NOT NULL, admin INTEGER NOT NULL DEFAULT false, settings TEXT, tags TEXT, created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP);",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            warnings,
+            ["type TEXT[] of column public.users.tags has no equivalent in sqlite, TEXT is used"]
+        );
+    }
+
+    #[test]
+    fn test_postgres_to_mysql() {
+        let (sql, warnings) = render(
+            r#"
+            CREATE TABLE events (
+                id UUID PRIMARY KEY,
+                name VARCHAR NOT NULL,
+                payload JSONB,
+                happened_at TIMESTAMP,
+                recorded_at TIMESTAMPTZ
+            );
+            "#,
+            Dialect::MySql,
+        );
+        assert_eq!(
+            sql,
+            [
+                "CREATE TABLE events (id CHAR(36), name TEXT NOT NULL, payload JSON, happened_at DATETIME, recorded_at TIMESTAMP, PRIMARY KEY (id));",
+                "",
+            ]
+            .join("\n")
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_schemas_are_folded_for_mysql() {
+        let (sql, warnings) = render(
+            r#"
+            CREATE SCHEMA app;
+            CREATE DOMAIN code AS VARCHAR(10);
+            CREATE DOMAIN app.code AS TEXT;
+            CREATE TABLE app.users (id INT PRIMARY KEY, code code);
+            CREATE TABLE app.comments (id INT, author_id INT REFERENCES app.users (id));
+            CREATE TABLE app.posts (id INT);
+            CREATE TABLE posts (id INT);
+            "#,
+            Dialect::MySql,
+        );
+        assert_eq!(
+            sql,
+            [
+                "CREATE TABLE posts (id INT);",
+                "CREATE TABLE users (id INT, code VARCHAR(10), PRIMARY KEY (id));",
+                "CREATE TABLE comments (id INT, author_id INT, FOREIGN KEY (author_id) REFERENCES users(id));",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            warnings,
+            [
+                "mysql has no schemas, the objects of schema app are created in public",
+                "table app.posts is dropped, another object is named posts in public",
+                "domain app.code is dropped, another object is named code in public",
+                "mysql has no domains, the columns of domain public.code use its base type VARCHAR(10)",
+            ]
+        );
+    }
+}
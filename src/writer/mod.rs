@@ -0,0 +1,368 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use crate::migrate::{AlterTableOperation, Migration, MigrationOperation};
+use crate::{Format, Naming};
+
+// Writes migrations as SQL files named `0001_create_users.sql`, or
+// `20240115123000_create_users.sql` with the timestamp naming.
+pub struct MigrationWriter {
+    dir: PathBuf,
+    naming: Naming,
+    format: Format,
+}
+
+impl MigrationWriter {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            naming: Naming::Sequential,
+            format: Format::Sql,
+        }
+    }
+
+    pub fn with_naming(mut self, naming: Naming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    // Flyway migrations are named `V0001__create_users.sql`, Liquibase ones
+    // are formatted SQL changelogs holding a single changeset.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn next_path(&self, name: &str) -> Result<PathBuf> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.next_path_at(name, now)
+    }
+
+    // The migration is numbered after the last one in the directory, so
+    // numbers are never reused even if earlier migrations are deleted. A
+    // timestamp is moved past the last migration generated within the same
+    // second.
+    fn next_path_at(&self, name: &str, now: u64) -> Result<PathBuf> {
+        let mut last = None;
+        if self.dir.exists() {
+            for entry in fs::read_dir(&self.dir)? {
+                let filename = entry?.file_name();
+                if let Some(version) = migration_version(&filename.to_string_lossy(), self.format) {
+                    let version = match self.naming {
+                        Naming::Sequential if version.len() < 14 => version.parse().ok(),
+                        Naming::Timestamp => parse_timestamp(version),
+                        _ => None,
+                    };
+                    last = last.max(version);
+                }
+            }
+        }
+
+        let version = match self.naming {
+            Naming::Sequential => format!("{:04}", last.unwrap_or(0) + 1),
+            Naming::Timestamp => {
+                let next = last.map_or(now, |last| now.max(last + 1));
+                format_timestamp(next)
+            }
+        };
+        let filename = match self.format {
+            Format::Sql | Format::Liquibase => format!("{}_{}.sql", version, slugify(name)),
+            Format::Flyway => format!("V{}__{}.sql", version, slugify(name)),
+        };
+        Ok(self.dir.join(filename))
+    }
+
+    pub fn write(&self, name: &str, sql: &str) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.next_path(name)?;
+        if path.exists() {
+            anyhow::bail!("migration already exists: {}", path.display());
+        }
+        fs::write(&path, self.contents(&path, sql))?;
+        Ok(path)
+    }
+
+    // Writes the parts of a split migration as numbered migrations, and a
+    // manifest named after the first listing their files in the order to
+    // apply them, e.g. `0004_create_users.manifest`.
+    pub fn write_split(&self, parts: &[(String, String)]) -> Result<(Vec<PathBuf>, PathBuf)> {
+        let mut paths = Vec::with_capacity(parts.len());
+        for (name, sql) in parts {
+            paths.push(self.write(name, sql)?);
+        }
+        let Some(first) = paths.first() else {
+            anyhow::bail!("no migration to write");
+        };
+        let manifest = first.with_extension("manifest");
+        let mut contents = String::new();
+        for path in &paths {
+            contents.push_str(&path.file_name().unwrap_or_default().to_string_lossy());
+            contents.push('\n');
+        }
+        fs::write(&manifest, contents)?;
+        Ok((paths, manifest))
+    }
+
+    // Liquibase identifies a changeset by its author and id, the id being the
+    // file name without its extension.
+    fn contents(&self, path: &Path, sql: &str) -> String {
+        match self.format {
+            Format::Sql | Format::Flyway => sql.to_string(),
+            Format::Liquibase => {
+                let id = path.file_stem().unwrap_or_default().to_string_lossy();
+                format!(
+                    "-- liquibase formatted sql\n\n-- changeset migi:{}\n{}",
+                    id, sql
+                )
+            }
+        }
+    }
+}
+
+// Names a migration after its first generated operation, e.g.
+// `add_orders_total`.
+pub fn migration_name(migration: &Migration) -> String {
+    let Some(operation) = migration
+        .operations
+        .iter()
+        .find(|op| !matches!(op, MigrationOperation::Raw { .. }))
+    else {
+        return if migration.is_empty() { "empty" } else { "raw" }.to_string();
+    };
+    let last = |name: &crate::migrate::ObjectName| name.0.last().cloned().unwrap_or_default();
+
+    match operation {
+        MigrationOperation::CreateDatabase { name } => format!("create_database_{}", name),
+        MigrationOperation::DropDatabase { name } => format!("drop_database_{}", name),
+        MigrationOperation::CreateSchema { name } => format!("create_schema_{}", last(name)),
+        MigrationOperation::DropSchema { name } => format!("drop_schema_{}", last(name)),
+        // A rebuilt table is created under a temporary name and filled next.
+        MigrationOperation::CreateTable { name, .. } => match migration.operations.get(1) {
+            Some(MigrationOperation::CopyTableData { from, to, .. }) if to == name => {
+                format!("rebuild_{}", last(from))
+            }
+            _ => format!("create_{}", last(name)),
+        },
+        MigrationOperation::DropTable { name, .. } => format!("drop_{}", last(name)),
+        MigrationOperation::AlterTable { name, operation } => match operation {
+            AlterTableOperation::AddColumn { column } => {
+                format!("add_{}_{}", last(name), column.name)
+            }
+            AlterTableOperation::DropColumn { column } => {
+                format!("drop_{}_{}", last(name), column.name)
+            }
+            AlterTableOperation::AlterColumn { current, .. } => {
+                format!("alter_{}_{}", last(name), current.name)
+            }
+            _ => format!("alter_{}", last(name)),
+        },
+        MigrationOperation::CopyTableData { from, .. } => format!("rebuild_{}", last(from)),
+        MigrationOperation::RenameTable { from, .. } => format!("rename_{}", last(from)),
+        MigrationOperation::MoveTable { from, .. } => format!("move_{}", last(from)),
+        MigrationOperation::CreateDomain { name, .. } => format!("create_domain_{}", last(name)),
+        MigrationOperation::DropDomain { name, .. } => format!("drop_domain_{}", last(name)),
+        MigrationOperation::AlterDomain { name, .. } => format!("alter_domain_{}", last(name)),
+        MigrationOperation::CreateTrigger { trigger, .. } => {
+            format!("create_trigger_{}", trigger.name)
+        }
+        MigrationOperation::DropTrigger { trigger, .. } => format!("drop_trigger_{}", trigger.name),
+        MigrationOperation::CreatePolicy { policy, .. } => format!("create_policy_{}", policy.name),
+        MigrationOperation::DropPolicy { policy, .. } => format!("drop_policy_{}", policy.name),
+        MigrationOperation::AlterPolicy { current, .. } => {
+            format!("alter_policy_{}", current.name)
+        }
+        MigrationOperation::CreateView { name, .. } => format!("create_view_{}", last(name)),
+        MigrationOperation::ReplaceView { name, .. } => format!("replace_view_{}", last(name)),
+        // A view whose columns changed is dropped and created again.
+        MigrationOperation::DropView { name, .. } => {
+            let recreated = migration.operations.iter().any(
+                |op| matches!(op, MigrationOperation::CreateView { name: created, .. } if created == name),
+            );
+            if recreated {
+                format!("recreate_view_{}", last(name))
+            } else {
+                format!("drop_view_{}", last(name))
+            }
+        }
+        MigrationOperation::CreateViewIndex { index, .. } => format!("create_index_{}", index.name),
+        MigrationOperation::DropViewIndex { index, .. } => format!("drop_index_{}", index.name),
+        MigrationOperation::Raw { .. } => unreachable!(),
+    }
+}
+
+// `0001_create_users.sql`, or `V0001__create_users.sql` for Flyway -> `0001`
+fn migration_version(filename: &str, format: Format) -> Option<&str> {
+    let stem = filename.strip_suffix(".sql")?;
+    let (version, _) = match format {
+        Format::Sql | Format::Liquibase => stem.split_once('_')?,
+        Format::Flyway => stem.strip_prefix('V')?.split_once("__")?,
+    };
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(version)
+}
+
+// Seconds since the Unix epoch to `YYYYMMDDHHMMSS` in UTC.
+fn format_timestamp(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+// `YYYYMMDDHHMMSS` in UTC to seconds since the Unix epoch.
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    if timestamp.len() != 14 {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| timestamp[range].parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_path() {
+        let dir = std::env::temp_dir().join(format!("migi-writer-{}", std::process::id()));
+        let writer = MigrationWriter::new(&dir);
+
+        let first = writer
+            .write("create_users", "CREATE TABLE users (id INT);\n")
+            .unwrap();
+        fs::write(dir.join("0007_manual.sql"), "").unwrap();
+        fs::write(dir.join("README.md"), "").unwrap();
+        let next = writer.next_path("Add orders.total").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, dir.join("0001_create_users.sql"));
+        assert_eq!(next, dir.join("0008_add_orders_total.sql"));
+    }
+
+    #[test]
+    fn test_write_split() {
+        let dir = std::env::temp_dir().join(format!("migi-split-{}", std::process::id()));
+        let writer = MigrationWriter::new(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("0003_manual.sql"), "").unwrap();
+
+        let (paths, manifest) = writer
+            .write_split(&[
+                (
+                    "create_users".into(),
+                    "CREATE TABLE users (id INT);\n".into(),
+                ),
+                (
+                    "create_orders".into(),
+                    "CREATE TABLE orders (id INT);\n".into(),
+                ),
+            ])
+            .unwrap();
+        let contents = fs::read_to_string(&manifest).unwrap();
+        let next = writer.next_path("add_orders_total").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("0004_create_users.sql"),
+                dir.join("0005_create_orders.sql")
+            ]
+        );
+        assert_eq!(manifest, dir.join("0004_create_users.manifest"));
+        assert_eq!(contents, "0004_create_users.sql\n0005_create_orders.sql\n");
+        assert_eq!(next, dir.join("0006_add_orders_total.sql"));
+    }
+
+    #[test]
+    fn test_timestamp_naming() {
+        let dir = std::env::temp_dir().join(format!("migi-timestamp-{}", std::process::id()));
+        let writer = MigrationWriter::new(&dir).with_naming(Naming::Timestamp);
+        // 2024-01-15 12:30:00 UTC
+        let now = 1705321800;
+        assert_eq!(parse_timestamp("20240115123000"), Some(now));
+
+        let first = writer.next_path_at("create_users", now).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&first, "").unwrap();
+        let second = writer.next_path_at("create_orders", now).unwrap();
+        fs::write(&second, "").unwrap();
+        let later = writer.next_path_at("create_items", now + 60).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, dir.join("20240115123000_create_users.sql"));
+        assert_eq!(second, dir.join("20240115123001_create_orders.sql"));
+        assert_eq!(later, dir.join("20240115123100_create_items.sql"));
+    }
+
+    #[test]
+    fn test_formats() {
+        let dir = std::env::temp_dir().join(format!("migi-formats-{}", std::process::id()));
+        let sql = "CREATE TABLE users (id INT);\n";
+
+        let flyway = MigrationWriter::new(dir.join("flyway")).with_format(Format::Flyway);
+        let first = flyway.write("create_users", sql).unwrap();
+        let second = flyway.next_path("Add orders").unwrap();
+        let flyway_contents = fs::read_to_string(&first).unwrap();
+
+        let liquibase = MigrationWriter::new(dir.join("liquibase")).with_format(Format::Liquibase);
+        let changelog = liquibase.write("create_users", sql).unwrap();
+        let liquibase_contents = fs::read_to_string(&changelog).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, dir.join("flyway/V0001__create_users.sql"));
+        assert_eq!(second, dir.join("flyway/V0002__add_orders.sql"));
+        assert_eq!(flyway_contents, sql);
+        assert_eq!(changelog, dir.join("liquibase/0001_create_users.sql"));
+        assert_eq!(
+            liquibase_contents,
+            "-- liquibase formatted sql\n\n-- changeset migi:0001_create_users\nCREATE TABLE users (id INT);\n"
+        );
+    }
+}